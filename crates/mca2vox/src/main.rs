@@ -9,12 +9,13 @@ use std::{
 use clap::Parser;
 use dot_vox::{Color, DotVoxData, Model, SceneNode, ShapeModel, Voxel};
 use fastanvil::Region;
-use image::{io::Reader as ImageReader, Pixel, RgbImage};
+use image::{imageops, io::Reader as ImageReader, Pixel, RgbImage, Rgba, RgbaImage};
 use itertools::iproduct;
 use ndarray::Array3;
 use palette::{
     color_difference::EuclideanDistance, convert::FromColorUnclamped, FromColor, IntoColor,
 };
+use wender::cli::{Backend, View};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -62,8 +63,21 @@ struct Args {
     /// 1 voxel = 1/16 minecraft block
     #[arg(long)]
     tiny: bool,
+
+    /// Also write the block textures of the palette to this .png, for `detail_textures` in the
+    /// scene metadata
+    #[arg(long)]
+    detail_atlas: Option<PathBuf>,
+
+    /// Only convert the chunk of the start block and render it to this .png, leaving the output
+    /// file untouched. Quick to iterate on the block textures and the palette
+    #[arg(long)]
+    preview: Option<PathBuf>,
 }
 
+/// width and height of the `--preview` image, in pixels.
+const PREVIEW_SIZE: u32 = 256;
+
 static IGNORE_BLOCKS: [&str; 17] = [
     "air",
     "short_grass",
@@ -126,7 +140,52 @@ fn block_colors(block_textures: &Path, name: &str) -> Option<Vec<Color>> {
     Some(vec)
 }
 
-fn run(args: &Args) -> (Array3<u32>, Vec<[u8; 4]>) {
+/// size of the tiles of the detail atlas, the resolution of vanilla block textures.
+const DETAIL_TILE: u32 = 16;
+
+/// texture suffixes tried for the top, side and bottom faces of a block, in order.
+static DETAIL_FACES: [&[&str]; 3] = [&["_top", ""], &["_side", ""], &["_bottom", "_top", ""]];
+
+fn block_texture(block_textures: &Path, name: &str) -> Option<RgbaImage> {
+    let mut img_path = block_textures.to_path_buf();
+    img_path.push(format!("{}.png", name));
+    let img = ImageReader::open(img_path).ok()?.decode().ok()?.to_rgba8();
+    // animated textures are vertical strips of frames, keep the first one.
+    let size = img.width().min(img.height());
+    let img = imageops::crop_imm(&img, 0, 0, size, size).to_image();
+    Some(imageops::resize(
+        &img,
+        DETAIL_TILE,
+        DETAIL_TILE,
+        imageops::FilterType::Nearest,
+    ))
+}
+
+/// one row of top, side and bottom tiles per palette entry, see `src/detail.rs` in wender.
+fn write_detail_atlas(args: &Args, path: &Path, names: &[String], colors: &[[u8; 4]]) {
+    let mut atlas = RgbaImage::new(DETAIL_TILE * 3, DETAIL_TILE * names.len().max(1) as u32);
+    for (row, (name, color)) in names.iter().zip(colors).enumerate() {
+        for (column, suffixes) in DETAIL_FACES.iter().enumerate() {
+            let tile = suffixes
+                .iter()
+                .find_map(|suffix| block_texture(&args.block_textures, &format!("{name}{suffix}")))
+                .unwrap_or_else(|| RgbaImage::from_pixel(DETAIL_TILE, DETAIL_TILE, Rgba(*color)));
+            imageops::replace(
+                &mut atlas,
+                &tile,
+                (column as u32 * DETAIL_TILE) as i64,
+                (row as u32 * DETAIL_TILE) as i64,
+            );
+        }
+    }
+    atlas.save(path).expect("failed to write the detail atlas");
+    println!(
+        "wrote the detail atlas, set `detail_textures = {:?}` in the `.meta.toml` of the scene",
+        path.file_name().unwrap_or_default()
+    );
+}
+
+fn run(args: &Args) -> (Array3<u32>, Vec<[u8; 4]>, Vec<String>) {
     let mut voxels = Array3::zeros((
         (args.e_x - args.s_x + 1) as usize,
         (args.e_y - args.s_y + 1) as usize,
@@ -134,6 +193,7 @@ fn run(args: &Args) -> (Array3<u32>, Vec<[u8; 4]>) {
     ));
     let mut palette = HashMap::new();
     let mut colors = Vec::new();
+    let mut names = Vec::new();
 
     let s_rx = args.s_x.div_euclid(16 * 32);
     let s_rz = args.s_z.div_euclid(16 * 32);
@@ -178,6 +238,7 @@ fn run(args: &Args) -> (Array3<u32>, Vec<[u8; 4]>) {
                             println!("{:20}\t{:?}", name, color);
                             let i = palette.len() as u32;
                             colors.push([color.r, color.g, color.b, color.a]);
+                            names.push(name.to_string());
                             palette.insert(name.to_string(), i);
                             Some(i)
                         });
@@ -196,7 +257,32 @@ fn run(args: &Args) -> (Array3<u32>, Vec<[u8; 4]>) {
         }
     }
 
-    (voxels, colors)
+    (voxels, colors, names)
+}
+
+/// render the converted chunk with the thumbnail renderer of wender, from an orthographic view.
+fn preview(path: &Path, voxels: &Array3<u32>, palette: &[[u8; 4]]) {
+    // named after the process, concurrent conversions do not overwrite each other's scene.
+    let name = format!("mca2vox_preview_{}.wvox", std::process::id());
+    let scene = std::env::temp_dir().join(name);
+    let file = File::create(&scene).expect("failed to create the preview scene");
+    let mut file = BufWriter::new(file);
+    bincode::serialize_into(&mut file, &(voxels, palette))
+        .expect("failed to serialize / write data");
+    file.flush().unwrap();
+    drop(file);
+
+    let ok = wender::thumbnail(
+        &scene,
+        path,
+        PREVIEW_SIZE,
+        View::Orthographic,
+        Backend::Auto,
+    );
+    std::fs::remove_file(&scene).ok();
+    if !ok {
+        std::process::exit(1);
+    }
 }
 
 fn main() {
@@ -210,15 +296,29 @@ fn main() {
     args.s_x = s_x;
     args.s_y = s_y;
     args.s_z = s_z;
+    if args.preview.is_some() {
+        // the whole column of the chunk, within the y range.
+        args.s_x = args.s_x.div_euclid(16) * 16;
+        args.s_z = args.s_z.div_euclid(16) * 16;
+        args.e_x = args.s_x + 15;
+        args.e_z = args.s_z + 15;
+    }
     println!(
         "parsing a minecraft region of size ({}, {}, {})",
         args.e_x - args.s_x + 1,
         args.e_y - args.s_y + 1,
         args.e_z - args.s_z + 1
     );
+    let (voxels, palette, names) = run(&args);
+    if let Some(path) = &args.detail_atlas {
+        write_detail_atlas(&args, path, &names, &palette);
+    }
+    if let Some(path) = &args.preview {
+        preview(path, &voxels, &palette);
+        return;
+    }
     let out_file = File::create(&args.output_file).expect("failed to create output file");
     let mut out_file = BufWriter::new(out_file);
-    let (voxels, palette) = run(&args);
     println!("writing to file");
     bincode::serialize_into(&mut out_file, &(voxels, palette))
         .expect("failed to serialize / write data");
@@ -0,0 +1,45 @@
+/* the c abi of wender, built with `cargo build --release --features ffi`. see `src/ffi.rs`. */
+
+#ifndef WENDER_H
+#define WENDER_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define WENDER_WINDOW_XLIB 0    /* display: Display*, window: Window */
+#define WENDER_WINDOW_WAYLAND 1 /* display: wl_display*, window: wl_surface* */
+#define WENDER_WINDOW_WIN32 2   /* display: HINSTANCE or NULL, window: HWND */
+#define WENDER_WINDOW_APPKIT 3  /* window: NSView* */
+
+typedef struct WenderRenderer WenderRenderer;
+
+typedef struct {
+    uint32_t kind;
+    void *display;
+    void *window;
+} WenderWindow;
+
+typedef struct {
+    bool hit;
+    float pos[3];
+    uint32_t voxel[3];
+    float normal[3];
+    float distance;
+} WenderPick;
+
+/* returns NULL on failure. */
+WenderRenderer *wender_create(const WenderWindow *window, uint32_t width, uint32_t height);
+void wender_destroy(WenderRenderer *renderer);
+/* the message of the last failed call of this thread, or NULL. */
+const char *wender_last_error(void);
+
+bool wender_load_scene(WenderRenderer *renderer, const char *path);
+bool wender_load_scene_bytes(WenderRenderer *renderer, const uint8_t *bytes, size_t len);
+bool wender_set_camera(WenderRenderer *renderer, const float pos[3], const float target[3],
+                       float fov_y);
+void wender_resize(WenderRenderer *renderer, uint32_t width, uint32_t height);
+bool wender_render(WenderRenderer *renderer);
+bool wender_pick(const WenderRenderer *renderer, float x, float y, WenderPick *out);
+
+#endif
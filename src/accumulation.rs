@@ -0,0 +1,75 @@
+use crate::{camera::CameraUniform, lights::LightsUniform};
+
+// progressive rendering of the path traced mode (`ShaderConstants::render_mode` 1): every frame
+// traces one new path per pixel, and the shader averages it with the previous frames kept in the
+// history buffer (see `pathtrace.wgsl`). the average restarts whenever anything it depends on
+// changes: the camera, the lights, the environment or the settings. the time and the exposure are
+// left out, the animations are blurred instead and the exposure is applied after the average.
+// the frame seed is left out too, the paths of each frame are drawn from it (see `random.rs`).
+
+/// frames averaged at most, later frames replace the oldest ones exponentially so the history
+/// stays within the precision of its f16 channels.
+pub const MAX_FRAMES: u32 = 1024;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AccumulationUniform {
+    /// frames already averaged in the history, 0 to restart.
+    pub frame: u32,
+    /// of the history, in pixels.
+    pub width: u32,
+    _pad: [u32; 2],
+}
+
+pub struct Accumulation {
+    pub uniform: AccumulationUniform,
+    /// the inputs of the average, see `update`.
+    inputs: Vec<u8>,
+}
+
+impl Accumulation {
+    pub fn new() -> Self {
+        Self {
+            uniform: AccumulationUniform::default(),
+            inputs: Vec::new(),
+        }
+    }
+
+    /// count a new frame, restarting the average if the inputs changed. `others` are the other
+    /// buffers of the render pipeline the image depends on.
+    pub fn update(&mut self, camera: &CameraUniform, lights: &LightsUniform, others: &[&[u8]]) {
+        let mut camera = *camera;
+        camera.time = 0.0;
+        camera.seed = 0;
+        let mut lights = *lights;
+        lights.exposure = 0.0;
+        let inputs = [bytemuck::bytes_of(&camera), bytemuck::bytes_of(&lights)]
+            .into_iter()
+            .chain(others.iter().copied())
+            .collect::<Vec<_>>()
+            .concat();
+
+        let uniform = &mut self.uniform;
+        uniform.width = camera.size.x as u32;
+        if inputs != self.inputs {
+            uniform.frame = 0;
+            self.inputs = inputs;
+        } else {
+            uniform.frame = (uniform.frame + 1).min(MAX_FRAMES - 1);
+        }
+    }
+
+    /// restart the average on the next frame, e.g. after the history was drawn over.
+    pub fn reset(&mut self) {
+        self.inputs.clear();
+    }
+
+    /// frames averaged in the displayed image.
+    pub fn frames(&self) -> u32 {
+        self.uniform.frame + 1
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.uniform)
+    }
+}
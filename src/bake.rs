@@ -0,0 +1,88 @@
+use itertools::iproduct;
+use nalgebra_glm as glm;
+use ndarray::{Array3, Zip};
+
+use crate::voxels::Voxels;
+
+// cpu light baking: sun visibility and ambient occlusion per voxel, two bytes per voxel.
+// channel 0 is the sun visibility (255 = lit), channel 1 the ambient occlusion (255 = occluded).
+// the sun direction is fixed at bake time, moving the sun afterwards requires baking again.
+
+pub type Lightmap = Array3<[u8; 2]>;
+
+/// length of the ambient occlusion rays, in voxels.
+const AO_DIST: f32 = 8.0;
+
+/// half the diagonal of a voxel: rays start there so the voxel does not occlude itself.
+const START_DIST: f32 = 0.87;
+
+fn neighbor_dirs() -> Vec<glm::IVec3> {
+    iproduct!(-1..=1, -1..=1, -1..=1)
+        .map(|(x, y, z)| glm::IVec3::new(x, y, z))
+        .filter(|d| *d != glm::IVec3::zeros())
+        .collect()
+}
+
+#[tracing::instrument(skip_all)]
+pub fn bake(voxels: &Voxels, sun_dir: &glm::Vec3) -> Lightmap {
+    let dirs = neighbor_dirs();
+    let faces = [
+        glm::IVec3::x(),
+        -glm::IVec3::x(),
+        glm::IVec3::y(),
+        -glm::IVec3::y(),
+        glm::IVec3::z(),
+        -glm::IVec3::z(),
+    ];
+
+    println!("baking lighting...");
+
+    let lightmap = Zip::indexed(voxels.voxels()).par_map_collect(|(i, j, k), v| {
+        if *v == 0 {
+            return [0, 0];
+        }
+
+        // world x and z are swapped relative to the array axes.
+        let cell = glm::IVec3::new(k as i32, j as i32, i as i32);
+
+        // hidden voxels are never displayed, skip them.
+        let open_faces = faces
+            .iter()
+            .filter(|f| !voxels.is_solid(cell + *f))
+            .collect::<Vec<_>>();
+        if open_faces.is_empty() {
+            return [0, 0];
+        }
+
+        let center = cell.cast::<f32>() + glm::vec3(0.5, 0.5, 0.5);
+        let normal = open_faces
+            .iter()
+            .fold(glm::IVec3::zeros(), |acc, f| acc + *f);
+        let normal = normal.cast::<f32>();
+
+        let sun = voxels
+            .raycast(&(center + sun_dir * START_DIST), sun_dir, f32::INFINITY)
+            .is_none();
+
+        // occlusion over the hemisphere on the open side of the voxel.
+        let mut rays = 0;
+        let mut occluded = 0;
+        for dir in &dirs {
+            let dir = glm::normalize(&dir.cast::<f32>());
+            if normal != glm::Vec3::zeros() && glm::dot(&dir, &normal) <= 0.0 {
+                continue;
+            }
+            rays += 1;
+            let start = center + dir * START_DIST;
+            if voxels.raycast(&start, &dir, AO_DIST).is_some() {
+                occluded += 1;
+            }
+        }
+
+        let ao = if rays == 0 { 0 } else { occluded * 255 / rays };
+        [if sun { 255 } else { 0 }, ao as u8]
+    });
+
+    println!("baked lighting");
+    lightmap
+}
@@ -0,0 +1,55 @@
+#import "octree.wgsl"::{ raycast_beam }
+#import "ray.wgsl"::{ render_cam, camera_ray_dir }
+
+// the beam pre-pass of the deferred renderer (laine & karras 2010): before the primary pass,
+// one ray per corner of the 8x8 pixel tiles of the target traces the dvo down to the octants
+// as large as the beam between the corners. any voxel seen by a pixel of a tile is inside such
+// an octant crossed by a corner ray of the tile, so the nearest corner distance (minus the
+// diagonal of the octants) is in front of the first hit of every ray of the tile. the primary
+// pass starts its rays there and skips the empty space in front of the scene, see `primary.wgsl`.
+// the texel (x, y) is the corner of the pixel (8x, 8y), see `WgpuState::draw_beams`. the rays
+// leave the render camera, see `Frustum::render_camera`.
+//
+// this module "requires":
+// (the constants required by the imported modules)
+
+struct VertexInput {
+    @location(0) pos: vec2f,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_pos: vec4f,
+    @location(0) pos: vec2f,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.pos = in.pos;
+    out.clip_pos = vec4f(out.pos, 0.0, 1.0);
+
+    return out;
+}
+
+// the distance to start the rays of the tiles around this corner at, 0 when unknown.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) f32 {
+    // the width of the beam per unit of distance, from the screen distance between the corners.
+    let tan_y = tan(render_cam.fov_y / 2.0);
+    let step = abs(vec2f(dpdx(in.pos.x), dpdy(in.pos.y)));
+    let beam = max(step.x * tan_y * render_cam.aspect, step.y * tan_y);
+
+    // the leaves are smaller than `4 * beam * t_exit`, and `t_exit` is at most a diagonal
+    // farther than `res.t`.
+    let margin = sqrt(3.0) * 4.0 * beam;
+    if margin >= 0.5 {
+        return 0.0;
+    }
+    let ray_pos = render_cam.pos - render_cam.volume_pos;
+    let res = raycast_beam(ray_pos, camera_ray_dir(render_cam, in.pos), beam);
+    if !res.hit {
+        return 0.0;
+    }
+    return res.t * (1.0 - margin / (1.0 - margin));
+}
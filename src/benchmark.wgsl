@@ -0,0 +1,17 @@
+// a tiny alu-bound compute workload, used by `--diagnose` to compare gpus.
+// each invocation iterates a hash and writes the result, so the loop is not optimized out.
+
+@group(0) @binding(0)
+var<storage, read_write> out: array<u32>;
+
+const ITERATIONS: u32 = 4096u;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+    var h = id.x;
+    for (var i = 0u; i < ITERATIONS; i++) {
+        h = h * 747796405u + 2891336453u;
+        h = ((h >> ((h >> 28u) + 4u)) ^ h) * 277803737u;
+    }
+    out[id.x] = h;
+}
@@ -10,3 +10,38 @@ var linear_sampler: sampler;
 @group(1) @binding(3)
 var nearest_sampler: sampler;
 
+
+@group(1) @binding(4)
+var lightmap: texture_3d<f32>;
+
+@group(1) @binding(5)
+var probes_tex: texture_cube_array<f32>;
+
+@group(1) @binding(6)
+var sdf: texture_3d<u32>;
+
+@group(1) @binding(7)
+var contours: texture_3d<f32>;
+
+@group(1) @binding(8)
+var brick_index: texture_3d<u32>;
+
+@group(1) @binding(9)
+var brick_atlas: texture_3d<u32>;
+
+@group(1) @binding(10)
+var<storage, read> contree: array<vec4u>;
+
+@group(1) @binding(11)
+var detail_atlas: texture_2d<f32>;
+
+@group(1) @binding(12)
+var voxel_ids: texture_3d<u32>;
+
+// the bricks in the camera frustum, see `culling.wgsl`.
+@group(1) @binding(14)
+var<storage, read> visible_bricks: array<u32>;
+
+// the local lights of each cluster of the view frustum, see `clusters.wgsl`.
+@group(1) @binding(15)
+var<storage, read> cluster_lights: array<u32>;
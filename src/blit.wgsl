@@ -0,0 +1,29 @@
+// upscales the scene, rendered at a lower internal resolution, to the window.
+// uses the fullscreen quad of the render pipeline.
+
+@group(0) @binding(0) var scene_tex: texture_2d<f32>;
+@group(0) @binding(1) var scene_sampler: sampler;
+
+struct VertexInput {
+    @location(0) pos: vec2f,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_pos: vec4f,
+    @location(0) uv: vec2f,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.uv = vec2f(in.pos.x + 1.0, 1.0 - in.pos.y) * 0.5;
+    out.clip_pos = vec4f(in.pos, 0.0, 1.0);
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return textureSample(scene_tex, scene_sampler, in.uv);
+}
@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+
+use nalgebra_glm as glm;
+use ndarray::s;
+
+use crate::{
+    pvs::Pvs,
+    voxels::{Voxels, VoxelsFormat},
+};
+
+// the brick map: the volume cut in bricks of 8^3 voxels, of which only the occupied bricks near
+// the camera are resident in an atlas texture. a coarse index texture gives the atlas slot of
+// each brick, 0 being empty or not resident. it is traversed by `brickmap.wgsl`
+// (`TRAVERSAL_BRICKS`), bricks farther than `radius` are not drawn, like the render distance of
+// minecraft.
+//
+// the dense volume textures are still allocated, the shading and the other passes use them.
+//
+// with the visibility of the scene computed offline (see `pvs.rs`), the bricks not visible from
+// the region of the camera are not streamed either. editing the scene drops the visibility.
+
+/// side of a brick, in voxels.
+pub const BRICK: u32 = 8;
+/// bricks per axis of the atlas texture.
+pub const ATLAS_BRICKS: u32 = 32;
+/// bricks uploaded per frame at most, the nearest first.
+const MAX_UPLOADS: usize = 256;
+
+/// residency changes to apply to the gpu textures, see `WgpuState::write_bricks`.
+pub struct BrickUpdate {
+    /// bricks no longer resident, in brick coordinates.
+    pub evicted: Vec<glm::UVec3>,
+    /// bricks to upload, with their atlas slot and their voxels in array axes.
+    pub uploads: Vec<(glm::UVec3, u32, Vec<VoxelsFormat>)>,
+}
+
+/// the voxels of a brick in array axes, tightly packed like the gpu textures.
+fn brick_voxels(voxels: &Voxels, brick: &glm::UVec3) -> Vec<VoxelsFormat> {
+    let lo = brick.map(|c| (c * BRICK) as usize);
+    let n = BRICK as usize;
+    voxels
+        .voxels()
+        .slice(s![lo.z..lo.z + n, lo.y..lo.y + n, lo.x..lo.x + n])
+        .iter()
+        .copied()
+        .collect()
+}
+
+pub struct BrickMap {
+    occupied: HashSet<glm::UVec3>,
+    resident: HashMap<glm::UVec3, u32>,
+    free: Vec<u32>,
+    /// bricks edited since their upload, evicted at the next update.
+    stale: Vec<glm::UVec3>,
+    /// camera brick, radius and culling at the last update, the residency is recomputed when they
+    /// change.
+    focus: Option<(glm::UVec3, f32, bool)>,
+    /// render distance, in voxels.
+    pub radius: f32,
+    pvs: Option<Pvs>,
+    /// skip the bricks not visible from the camera region, with a visibility.
+    pub culling: bool,
+}
+
+impl BrickMap {
+    pub fn new(voxels: &Voxels) -> Self {
+        let occupied = voxels
+            .occupancy(BRICK as usize)
+            .indexed_iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|((z, y, x), _)| glm::vec3(x as u32, y as u32, z as u32))
+            .collect();
+        Self {
+            occupied,
+            resident: HashMap::new(),
+            free: (0..ATLAS_BRICKS.pow(3)).rev().collect(),
+            stale: Vec::new(),
+            focus: None,
+            radius: 512.0,
+            pvs: Pvs::load(voxels),
+            culling: true,
+        }
+    }
+
+    pub fn resident_count(&self) -> usize {
+        self.resident.len()
+    }
+
+    pub fn has_pvs(&self) -> bool {
+        self.pvs.is_some()
+    }
+
+    /// the edited box `min..max` of world coordinates is uploaded again at the next update.
+    pub fn invalidate(&mut self, voxels: &Voxels, min: glm::UVec3, max: glm::UVec3) {
+        let bricks = voxels.dim() / BRICK;
+        let (lo, hi) = (min / BRICK, max.map(|c| c.div_ceil(BRICK).min(bricks)));
+        for z in lo.z..hi.z {
+            for y in lo.y..hi.y {
+                for x in lo.x..hi.x {
+                    let brick = glm::vec3(x, y, z);
+                    if brick_voxels(voxels, &brick).iter().any(|v| *v != 0) {
+                        self.occupied.insert(brick);
+                    } else {
+                        self.occupied.remove(&brick);
+                    }
+                    self.stale.push(brick);
+                }
+            }
+        }
+        // the edit may have opened a wall.
+        self.pvs = None;
+        self.focus = None;
+    }
+
+    /// make the occupied bricks nearest to the camera at `pos` resident, in world coordinates.
+    pub fn update(&mut self, voxels: &Voxels, pos: &glm::Vec3) -> BrickUpdate {
+        let mut evicted = Vec::new();
+        for brick in std::mem::take(&mut self.stale) {
+            if let Some(slot) = self.resident.remove(&brick) {
+                self.free.push(slot);
+                evicted.push(brick);
+            }
+        }
+
+        let focus = (
+            pos.map(|c| c.max(0.0) as u32) / BRICK,
+            self.radius,
+            self.culling,
+        );
+        if self.focus == Some(focus) {
+            return BrickUpdate {
+                evicted,
+                uploads: Vec::new(),
+            };
+        }
+
+        let center = |brick: &glm::UVec3| {
+            (brick.map(|c| c as f32) + glm::vec3(0.5, 0.5, 0.5)) * BRICK as f32
+        };
+        let mut wanted = self
+            .occupied
+            .iter()
+            .map(|brick| (glm::distance(&center(brick), pos), *brick))
+            .filter(|(dist, _)| *dist <= self.radius)
+            .filter(|(dist, brick)| match (&self.pvs, self.culling) {
+                (Some(pvs), true) => pvs.is_visible(pos, brick, *dist),
+                _ => true,
+            })
+            .collect::<Vec<_>>();
+        wanted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        wanted.truncate(ATLAS_BRICKS.pow(3) as usize);
+        let wanted_set = wanted
+            .iter()
+            .map(|(_, brick)| *brick)
+            .collect::<HashSet<_>>();
+
+        let far = self
+            .resident
+            .keys()
+            .filter(|brick| !wanted_set.contains(brick))
+            .copied()
+            .collect::<Vec<_>>();
+        for brick in far {
+            self.free.push(self.resident.remove(&brick).unwrap());
+            evicted.push(brick);
+        }
+
+        let missing = wanted
+            .iter()
+            .filter(|(_, brick)| !self.resident.contains_key(brick))
+            .collect::<Vec<_>>();
+        // the remaining bricks are uploaded in the next frames.
+        if missing.len() <= MAX_UPLOADS {
+            self.focus = Some(focus);
+        }
+        let uploads = missing
+            .into_iter()
+            .take(MAX_UPLOADS)
+            .map(|(_, brick)| {
+                let slot = self.free.pop().unwrap();
+                self.resident.insert(*brick, slot);
+                (*brick, slot, brick_voxels(voxels, brick))
+            })
+            .collect();
+
+        BrickUpdate { evicted, uploads }
+    }
+}
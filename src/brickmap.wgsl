@@ -0,0 +1,76 @@
+#import "util.wgsl"::{ vmin, vmax, cmpmin, cmpmax }
+#import "octree.wgsl"::{ CastResult }
+#import "bindings.wgsl"::{ brick_index, brick_atlas }
+
+// this shader is a "module" supposed to be included.
+// an alternative to the dvo descent of octree.wgsl: a walk through the brick map of brickmap.rs.
+// bricks that are empty or not resident are skipped whole, the voxels of the resident bricks are
+// walked one by one in the atlas.
+//
+// this module "exports":
+// fn raycast_bricks(ray_pos: vec3f, ray_dir: vec3f) -> CastResult
+//
+// this module "requires":
+// const #OCTREE_DEPTH: u32;
+// const #OCTREE_MAX_ITER: u32 // max number of steps per ray.
+
+const BRICK_LOG2: u32 = 3u; // 8^3 voxels per brick
+
+fn bricks_no_hit(iter: u32) -> CastResult {
+    return CastResult(vec3f(0.1), vec3f(0.0), vec3u(0u), iter, 0.0, false);
+}
+
+// texel of the atlas of a voxel of a resident brick, `entry` being its value in the index.
+fn atlas_texel(entry: u32, voxel: vec3u) -> vec3u {
+    let slot = entry - 1u;
+    let bricks = textureDimensions(brick_atlas).x >> BRICK_LOG2;
+    let origin = vec3u(slot % bricks, (slot / bricks) % bricks, slot / (bricks * bricks));
+    return (origin << vec3u(BRICK_LOG2)) + (voxel & vec3u((1u << BRICK_LOG2) - 1u));
+}
+
+fn raycast_bricks(ray_pos: vec3f, ray_dir: vec3f) -> CastResult {
+    let size = f32(2u << #OCTREE_DEPTH);
+    let inv_dir = 1.0 / ray_dir;
+
+    // clip the ray to the volume
+    let t_a = (vec3f(0.0) - ray_pos) * inv_dir;
+    let t_b = (vec3f(size) - ray_pos) * inv_dir;
+    let t_in = max(vmax(min(t_a, t_b)), 0.0);
+    let t_out = vmin(max(t_a, t_b));
+    if t_in > t_out {
+        return bricks_no_hit(0u);
+    }
+
+    var t = t_in;
+    var normal = vec3f(cmpmax(min(t_a, t_b))) * -sign(ray_dir);
+
+    for (var i = 0u; i < #OCTREE_MAX_ITER; i++) {
+        if t > t_out {
+            return bricks_no_hit(i);
+        }
+
+        // nudge the sample inside the voxel the ray is entering.
+        let sample_pos = clamp(ray_pos + ray_dir * (t + 1e-3), vec3f(0.0), vec3f(size - 1.0));
+        let voxel = vec3u(sample_pos);
+
+        let entry = textureLoad(brick_index, voxel >> vec3u(BRICK_LOG2), 0).r;
+        var cell = vec3f(voxel);
+        var cell_size = 1.0;
+        if entry == 0u {
+            // skip the whole brick
+            cell_size = f32(1u << BRICK_LOG2);
+            cell = vec3f((voxel >> vec3u(BRICK_LOG2)) << vec3u(BRICK_LOG2));
+        }
+        else if textureLoad(brick_atlas, atlas_texel(entry, voxel), 0).r != 0u {
+            return CastResult(ray_pos + ray_dir * t, normal, voxel, i, t, true);
+        }
+
+        // step to the next cell boundary
+        let next_t = (cell + step(vec3f(0.0), ray_dir) * cell_size - ray_pos) * inv_dir;
+        let mask = cmpmin(next_t);
+        t = vmin(next_t);
+        normal = vec3f(mask) * -sign(ray_dir);
+    }
+
+    return bricks_no_hit(#OCTREE_MAX_ITER);
+}
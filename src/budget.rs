@@ -0,0 +1,131 @@
+use pollster::FutureExt;
+use wgpu::{
+    Device, ErrorFilter, Extent3d, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages,
+};
+
+use crate::{
+    voxels::Voxels,
+    wgpu_util::{COLORS_FORMAT, OCTREE_FORMAT},
+};
+
+// gpu memory fallbacks. wgpu does not expose how much memory the adapter has, so a scene that
+// does not fit is detected by a trial allocation of its largest textures (and by the 3d texture
+// size limit), and loaded again one step down this ladder:
+// 1. colors without mipmaps, 2. volume downsampled by 2, 4, 8...
+// byte voxels are a compile time choice (cargo feature `byte_voxels`), not part of the ladder.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fallback {
+    /// the colors are stored without mipmaps, cone traced shadows and ao sample the full
+    /// resolution colors.
+    NoColorMips,
+    /// the volume is downsampled by 2^n, and has no color mipmaps.
+    Downsample(u32),
+}
+
+impl Fallback {
+    pub fn next(fallback: Option<Self>) -> Self {
+        match fallback {
+            None => Self::NoColorMips,
+            Some(Self::NoColorMips) => Self::Downsample(1),
+            Some(Self::Downsample(n)) => Self::Downsample(n + 1),
+        }
+    }
+
+    pub fn color_mips(fallback: Option<Self>) -> bool {
+        fallback.is_none()
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::NoColorMips => "the colors are loaded without mipmaps".to_owned(),
+            Self::Downsample(n) => format!("the scene is downsampled {}x", 1 << n),
+        }
+    }
+}
+
+/// bytes of the largest gpu textures of a `dim`^3 scene: voxels, colors and octree.
+pub fn scene_bytes(dim: u32, color_mips: bool) -> u64 {
+    let voxels = dim as u64 * dim as u64 * dim as u64;
+    let texel_bytes = |format: TextureFormat| format.block_copy_size(None).unwrap_or(4) as u64;
+    // a full mip chain adds 1/7 of the base level.
+    let mips = |bytes: u64| bytes + bytes / 7;
+    let colors = voxels * texel_bytes(COLORS_FORMAT);
+    let colors = if color_mips { mips(colors) } else { colors };
+    voxels * texel_bytes(OCTREE_FORMAT) + colors + mips(voxels / 8 * texel_bytes(OCTREE_FORMAT))
+}
+
+/// apply the fallback ladder until the scene fits on the device. returns the scene to upload and
+/// the fallback used, if any.
+#[tracing::instrument(skip_all)]
+pub fn fit(device: &Device, voxels: Voxels) -> (Voxels, Option<Fallback>) {
+    let max_dim = device.limits().max_texture_dimension_3d;
+    let mut voxels = voxels;
+    let mut fallback = None;
+
+    if voxels.dim() > max_dim {
+        let n = (voxels.dim() / max_dim).ilog2();
+        voxels = voxels.downsample(n);
+        fallback = Some(Fallback::Downsample(n));
+    }
+
+    while !allocates(device, voxels.dim(), Fallback::color_mips(fallback)) {
+        if voxels.dim() <= 2 {
+            break;
+        }
+        let next = Fallback::next(fallback);
+        eprintln!(
+            "the scene does not fit in gpu memory (~{} MiB), {}",
+            scene_bytes(voxels.dim(), Fallback::color_mips(fallback)) / 1024 / 1024,
+            next.describe()
+        );
+        if let Fallback::Downsample(_) = next {
+            voxels = voxels.downsample(1);
+        }
+        fallback = Some(next);
+    }
+
+    (voxels, fallback)
+}
+
+/// whether the largest textures of a `dim`^3 scene can be allocated. they are freed right away.
+fn allocates(device: &Device, dim: u32, color_mips: bool) -> bool {
+    let texture = |label, dim: u32, mip_level_count, format| TextureDescriptor {
+        label: Some(label),
+        size: Extent3d {
+            width: dim,
+            height: dim,
+            depth_or_array_layers: dim,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: TextureDimension::D3,
+        format,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
+        view_formats: &[],
+    };
+    let color_mip_levels = if color_mips { dim.ilog2() } else { 1 };
+
+    device.push_error_scope(ErrorFilter::OutOfMemory);
+    let textures = [
+        device.create_texture(&texture("trial voxels", dim, 1, OCTREE_FORMAT)),
+        // the colors are only storage textures with `MipPath::Storage`, the size is the same.
+        device.create_texture(&TextureDescriptor {
+            usage: TextureUsages::TEXTURE_BINDING,
+            ..texture("trial colors", dim, color_mip_levels, COLORS_FORMAT)
+        }),
+        device.create_texture(&texture(
+            "trial octree",
+            dim / 2,
+            dim.ilog2(),
+            OCTREE_FORMAT,
+        )),
+    ];
+    let err = device.pop_error_scope().block_on();
+    for texture in textures {
+        texture.destroy();
+    }
+
+    err.is_none()
+}
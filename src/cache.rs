@@ -0,0 +1,74 @@
+use std::{
+    fs::File,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dvo::Dvo,
+    voxels::Voxels,
+    wgpu_util::{ShaderConstants, COLORS_FORMAT, OCTREE_FORMAT},
+};
+
+// the octree and color mipmaps computed on the first load of a scene are saved in a sidecar
+// file, `scene.wvox` -> `scene.wcache`, and uploaded directly on the next loads instead of running
+// the compute passes. the cache is keyed by a hash of the voxels, the palette and everything that
+// changes the computed textures, a stale cache is simply rebuilt.
+
+#[derive(Serialize, Deserialize)]
+pub struct SceneCache {
+    key: u64,
+    pub dvo: Dvo,
+    /// color mip levels 1.., tightly packed. empty without color mipmaps.
+    pub color_mips: Vec<Vec<u8>>,
+}
+
+impl SceneCache {
+    pub fn path(scene: &Path) -> PathBuf {
+        scene.with_extension("wcache")
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub fn key(voxels: &Voxels, constants: &ShaderConstants, color_mips: bool) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        voxels.voxels_bytes().hash(&mut hasher);
+        voxels.palette().hash(&mut hasher);
+        constants.octree_depth.hash(&mut hasher);
+        constants.mip_filter.hash(&mut hasher);
+        color_mips.hash(&mut hasher);
+        format!("{OCTREE_FORMAT:?} {COLORS_FORMAT:?}").hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn new(key: u64, dvo: Dvo, color_mips: Vec<Vec<u8>>) -> Self {
+        Self {
+            key,
+            dvo,
+            color_mips,
+        }
+    }
+
+    /// the cache of a scene, if it was built with `key`.
+    #[tracing::instrument(skip_all, fields(scene = %scene.display()))]
+    pub fn load(scene: &Path, key: u64) -> Option<Self> {
+        let path = Self::path(scene);
+        let file = BufReader::new(File::open(&path).ok()?);
+        let cache: Self = bincode::deserialize_from(file).ok()?;
+        (cache.key == key).then(|| {
+            println!("loaded cache `{}`", path.display());
+            cache
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(scene = %scene.display()))]
+    pub fn save(&self, scene: &Path) -> bincode::Result<()> {
+        let path = Self::path(scene);
+        let mut file = BufWriter::new(File::create(&path)?);
+        bincode::serialize_into(&mut file, self)?;
+        println!("wrote cache `{}`", path.display());
+        Ok(())
+    }
+}
@@ -1,37 +1,131 @@
-use nalgebra_glm as glm;
+use std::f32::consts::FRAC_PI_2;
 
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
 use winit::{
     event::*,
     keyboard::{KeyCode, PhysicalKey},
 };
 
+// camera-relative rendering, for worlds too large for f32 positions: the camera position is kept
+// relative to an origin snapped to a grid of `REBASE_STEP` voxels, in double precision on the cpu.
+// the movement is integrated in the small local position. the shaders work relative to the same
+// origin (render space, see `ray.wgsl`): the rays start at `uniform.pos`, and the volume is moved
+// to `uniform.volume_pos` instead. use `Camera::pos` and `Camera::set_pos` for the world position
+// rather than `uniform.pos`.
+
 // !! careful with the alignments! add padding fields if necessary.
 // see https://www.w3.org/TR/WGSL/#alignment-and-size
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct CameraUniform {
+    /// relative to `origin`, see `Camera::rebase`.
     pub pos: glm::Vec3,
     pub fov_y: f32,
     pub size: glm::Vec2,
     pub aspect: f32,
-    _pad: [f32; 1], // padding to ensure correct alignment
+    pub time: f32, // seconds of scene time, see `clock.rs`
+    /// the world origin relative to the origin of `pos`, see `Camera::rebase`.
+    pub volume_pos: glm::Vec3,
+    pub seed: u32, // changes every frame, see `random.rs`
     pub view_mat_inv: glm::Mat4x4,
 }
 
+impl CameraUniform {
+    /// world position of the camera.
+    pub fn world_pos(&self) -> glm::Vec3 {
+        self.pos - self.volume_pos
+    }
+}
+
+/// the camera origin moves by steps of this many voxels, see `Camera::rebase`.
+const REBASE_STEP: f64 = 1024.0;
+
 pub struct Camera {
     pub uniform: CameraUniform,
     pub quat: glm::Quat,
+    origin: glm::DVec3,
+}
+
+/// the world axis pointing up, to fly through datasets that are not y-up.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpAxis {
+    X,
+    #[default]
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    pub const ALL: [Self; 3] = [Self::X, Self::Y, Self::Z];
+
+    pub fn vector(self) -> glm::Vec3 {
+        match self {
+            UpAxis::X => glm::Vec3::x(),
+            UpAxis::Y => glm::Vec3::y(),
+            UpAxis::Z => glm::Vec3::z(),
+        }
+    }
+
+    /// rotation from the y-up frame of the controller to the world.
+    fn rotation(self) -> glm::Quat {
+        match self {
+            UpAxis::X => glm::quat_angle_axis(-FRAC_PI_2, &glm::Vec3::z()),
+            UpAxis::Y => glm::quat_identity(),
+            UpAxis::Z => glm::quat_angle_axis(FRAC_PI_2, &glm::Vec3::x()),
+        }
+    }
+}
+
+/// how the input moves the camera.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CameraMode {
+    /// first person flight, the mouse looks around when the cursor is grabbed.
+    Fly,
+    /// turns around a focus point: middle drag rotates, shift + middle drag pans and the wheel
+    /// zooms.
+    Orbit,
 }
 
 pub struct Controller {
     pub speed: f32,
-    sensitivity: f64,
+    /// mouse look, in radians per pixel. changing it turns the camera.
+    pub sensitivity: f64,
+    pub up: UpAxis,
+    /// roll the camera with q and e.
+    pub free_flight: bool,
+    pub mode: CameraMode,
+    /// the point the orbit camera turns around, in world coordinates.
+    pub focus: glm::Vec3,
+    /// distance from the orbit camera to `focus`, in voxels.
+    pub distance: f32,
+    /// the middle mouse button is held.
+    dragging: bool,
+    is_shift: bool,
     is_forward: bool,
     is_back: bool,
     is_left: bool,
     is_right: bool,
     is_up: bool,
     is_down: bool,
+    is_roll_left: bool,
+    is_roll_right: bool,
+    mouse_pos: (f64, f64),
+    /// mouse movement not applied to the view yet, see `MOUSE_SMOOTHING`.
+    mouse_delta: (f64, f64),
+    /// mouse movement not applied to the orbit focus yet.
+    pan_delta: (f64, f64),
+    /// radians, around the view direction.
+    roll: f32,
+    keyboard_enabled: bool,
+    pointer_enabled: bool,
+    fly: Option<Fly>,
+}
+
+/// a smooth camera move towards a target position and orientation.
+struct Fly {
+    pos: glm::Vec3,
     mouse_pos: (f64, f64),
 }
 
@@ -43,11 +137,77 @@ impl Camera {
                 fov_y: 70.0 / 180.0 * glm::pi::<f32>(),
                 aspect: 1.0,
                 size,
-                _pad: Default::default(),
+                time: 0.0,
+                volume_pos: glm::Vec3::zeros(),
+                seed: 0,
                 view_mat_inv: Default::default(),
             },
             quat: Default::default(),
+            origin: glm::DVec3::zeros(),
+        }
+    }
+
+    fn pos_f64(&self) -> glm::DVec3 {
+        self.origin + self.uniform.pos.cast::<f64>()
+    }
+
+    /// world position of the camera.
+    pub fn pos(&self) -> glm::Vec3 {
+        self.pos_f64().cast()
+    }
+
+    pub fn set_pos(&mut self, pos: &glm::Vec3) {
+        self.uniform.pos = (pos.cast::<f64>() - self.origin).cast();
+        self.rebase();
+    }
+
+    /// move the origin to the grid cell containing the camera, once the camera left the cell of
+    /// the current origin.
+    pub fn rebase(&mut self) {
+        let world = self.pos_f64();
+        let origin = world.map(|c| (c / REBASE_STEP).floor() * REBASE_STEP);
+        if origin != self.origin {
+            self.origin = origin;
+            self.uniform.pos = (world - origin).cast();
+            self.uniform.volume_pos = (-origin).cast();
+        }
+    }
+
+    /// orient the camera towards a world position, keeping +y up.
+    pub fn look_at(&mut self, target: &glm::Vec3) {
+        let forward = glm::normalize(&(target - self.pos()));
+        let right = glm::normalize(&glm::cross(&glm::Vec3::y(), &forward));
+        let up = glm::cross(&forward, &right);
+        self.quat = glm::mat3_to_quat(&glm::Mat3::from_columns(&[right, up, forward]));
+        self.uniform.view_mat_inv = glm::quat_cast(&self.quat);
+    }
+
+    /// world direction of the ray through a point in normalized device coordinates, like
+    /// `cam_ray_dir` in the shader.
+    pub fn ray_dir(&self, ndc: &glm::Vec2) -> glm::Vec3 {
+        let tan = (self.uniform.fov_y / 2.0).tan();
+        let dir = glm::normalize(&glm::vec4(
+            ndc.x * tan * self.uniform.aspect,
+            ndc.y * tan,
+            1.0,
+            0.0,
+        ));
+        (self.uniform.view_mat_inv * dir).xyz()
+    }
+
+    /// normalized device coordinates of a world position, the inverse of `ray_dir`. `None`
+    /// behind the camera.
+    pub fn project(&self, pos: &glm::Vec3) -> Option<glm::Vec2> {
+        let rel = pos - self.pos();
+        let view = glm::transpose(&self.uniform.view_mat_inv) * glm::vec4(rel.x, rel.y, rel.z, 0.0);
+        if view.z <= 0.0 {
+            return None;
         }
+        let tan = (self.uniform.fov_y / 2.0).tan();
+        Some(glm::vec2(
+            view.x / (view.z * tan * self.uniform.aspect),
+            view.y / (view.z * tan),
+        ))
     }
 
     pub fn as_bytes(&self) -> &[u8] {
@@ -56,23 +216,189 @@ impl Camera {
 }
 
 impl Controller {
+    /// default speed in meters per second.
+    pub const DEFAULT_SPEED: f32 = 6.0;
+    /// height of the camera above the ground when spawning, in meters.
+    pub const EYE_HEIGHT: f32 = 1.8;
+    /// distance kept between the camera and solid voxels when collisions are enabled, in meters.
+    pub const COLLISION_RADIUS: f32 = 0.25;
+    /// the view stops short of straight up and down, where yaw and roll become the same.
+    const MAX_PITCH: f64 = 89.0;
+    /// degrees per second.
+    const ROLL_SPEED: f32 = 90.0;
+    /// rate of the smooth moves of `fly_to`, per second.
+    const FLY_RATE: f32 = 10.0;
+    /// time constant of the mouse smoothing, in seconds. the mouse movement is spread over the
+    /// next frames, so mice sending many small events per frame turn like the others.
+    const MOUSE_SMOOTHING: f32 = 0.015;
+    /// longest frame accounted for, a stall does not send the camera through walls.
+    const MAX_DT: f32 = 0.1;
+
     pub fn new() -> Self {
         Self {
-            speed: 0.1,
+            speed: Self::DEFAULT_SPEED,
             sensitivity: 0.005,
+            up: UpAxis::Y,
+            free_flight: false,
+            mode: CameraMode::Fly,
+            focus: glm::Vec3::zeros(),
+            distance: 64.0,
+            dragging: false,
+            is_shift: false,
             is_forward: false,
             is_back: false,
             is_left: false,
             is_right: false,
             is_up: false,
             is_down: false,
+            is_roll_left: false,
+            is_roll_right: false,
             mouse_pos: (0.0, 0.0),
+            mouse_delta: (0.0, 0.0),
+            pan_delta: (0.0, 0.0),
+            roll: 0.0,
+            keyboard_enabled: true,
+            pointer_enabled: true,
+            fly: None,
         }
     }
 
+    /// a world direction in the y-up frame of the controller.
+    fn to_local(&self, dir: &glm::Vec3) -> glm::Vec3 {
+        glm::quat_rotate_vec3(&glm::quat_inverse(&self.up.rotation()), dir)
+    }
+
+    fn clamp_pitch(&mut self) {
+        let max = Self::MAX_PITCH.to_radians() / self.sensitivity;
+        self.mouse_pos.1 = self.mouse_pos.1.clamp(-max, max);
+    }
+
+    /// mouse position that orients the camera along `dir`. the inverse of `update_camera`.
+    fn mouse_pos_towards(&self, dir: &glm::Vec3) -> (f64, f64) {
+        let dir = self.to_local(dir);
+        let yaw = (dir.x.atan2(dir.z) - 45.0_f32.to_radians()) as f64;
+        let pitch = (-dir.y.asin()) as f64;
+
+        // pick the turn closest to the current yaw, to avoid spinning around.
+        let cur_yaw = self.mouse_pos.0 * self.sensitivity;
+        let turns = ((cur_yaw - yaw) / std::f64::consts::TAU).round();
+        let yaw = yaw + turns * std::f64::consts::TAU;
+
+        let max = Self::MAX_PITCH.to_radians();
+        (
+            yaw / self.sensitivity,
+            pitch.clamp(-max, max) / self.sensitivity,
+        )
+    }
+
+    /// orient the camera towards `target`, keeping the view above a steep downwards angle.
+    pub fn look_at(&mut self, cam: &Camera, target: &glm::Vec3) {
+        let offset = self.to_local(&(target - cam.pos()));
+        if glm::length(&offset.xz()) < 1.0 {
+            return;
+        }
+        let mut dir = glm::normalize(&offset);
+        dir.y = dir.y.max(-0.7);
+        let dir = glm::quat_rotate_vec3(&self.up.rotation(), &glm::normalize(&dir));
+        self.mouse_pos = self.mouse_pos_towards(&dir);
+        self.mouse_delta = (0.0, 0.0);
+    }
+
+    /// smoothly move the camera to face `target`, stopping at a distance proportional to the
+    /// current one. the speed is adjusted to the remaining distance.
+    pub fn fly_to(&mut self, cam: &Camera, target: &glm::Vec3) {
+        let offset = target - cam.pos();
+        let dist = glm::length(&offset);
+        if dist < 1e-3 {
+            return;
+        }
+        let dir = offset / dist;
+        let standoff = (dist * 0.25).clamp(2.0_f32.min(dist), dist);
+
+        if self.mode == CameraMode::Orbit {
+            self.focus = *target;
+            self.distance = self.distance.min(dist);
+            return;
+        }
+
+        self.fly = Some(Fly {
+            pos: target - dir * standoff,
+            mouse_pos: self.mouse_pos_towards(&dir),
+        });
+        self.mouse_delta = (0.0, 0.0);
+        self.speed = standoff * 1.2;
+    }
+
+    /// switch to the orbit camera, turning around `focus` from the current position.
+    pub fn orbit_around(&mut self, cam: &Camera, focus: &glm::Vec3) {
+        let offset = focus - cam.pos();
+        self.distance = glm::length(&offset).max(1.0);
+        self.focus = *focus;
+        if glm::length(&offset) > 1e-3 {
+            self.mouse_pos = self.mouse_pos_towards(&glm::normalize(&offset));
+        }
+        self.mouse_delta = (0.0, 0.0);
+        self.fly = None;
+        self.mode = CameraMode::Orbit;
+    }
+
+    /// the middle mouse button was pressed or released.
+    pub fn set_dragging(&mut self, dragging: bool) {
+        self.dragging = dragging && self.pointer_enabled;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    /// the mouse wheel changes the speed, or the distance of the orbit camera.
+    pub fn scroll(&mut self, lines: f32) {
+        match self.mode {
+            CameraMode::Fly => self.speed *= 2f32.powf(-lines),
+            CameraMode::Orbit => {
+                self.distance = (self.distance * 2f32.powf(-lines * 0.25)).max(1.0);
+            }
+        }
+    }
+
+    /// yaw and pitch of the camera, in mouse units.
+    pub fn view(&self) -> (f64, f64) {
+        self.mouse_pos
+    }
+
+    pub fn set_view(&mut self, view: (f64, f64)) {
+        self.mouse_pos = view;
+        self.mouse_delta = (0.0, 0.0);
+        self.clamp_pitch();
+        self.fly = None;
+    }
+
+    /// enable or disable camera input, e.g. when the ui has focus.
+    /// keys held when the keyboard gets disabled are released, so the camera stops moving.
+    pub fn set_input_enabled(&mut self, keyboard: bool, pointer: bool) {
+        if self.keyboard_enabled && !keyboard {
+            self.is_forward = false;
+            self.is_back = false;
+            self.is_left = false;
+            self.is_right = false;
+            self.is_up = false;
+            self.is_down = false;
+            self.is_roll_left = false;
+            self.is_roll_right = false;
+            self.is_shift = false;
+        }
+        self.keyboard_enabled = keyboard;
+        self.pointer_enabled = pointer;
+    }
+
     pub fn process_keyboard(&mut self, input: &KeyEvent) {
         let pressed = input.state == ElementState::Pressed;
 
+        // releases always go through, otherwise keys could get stuck.
+        if pressed && !self.keyboard_enabled {
+            return;
+        }
+
         match input.physical_key {
             PhysicalKey::Code(KeyCode::KeyW) => {
                 self.is_forward = pressed;
@@ -91,56 +417,143 @@ impl Controller {
             }
             PhysicalKey::Code(KeyCode::ShiftLeft) => {
                 self.is_down = pressed;
+                self.is_shift = pressed;
+            }
+            PhysicalKey::Code(KeyCode::ShiftRight) => {
+                self.is_shift = pressed;
+            }
+            PhysicalKey::Code(KeyCode::KeyQ) => {
+                self.is_roll_left = pressed;
+            }
+            PhysicalKey::Code(KeyCode::KeyE) => {
+                self.is_roll_right = pressed;
             }
             _ => {}
         }
     }
 
     pub fn process_mouse(&mut self, delta: (f64, f64)) {
-        self.mouse_pos.0 += delta.0;
-        self.mouse_pos.1 += delta.1;
+        if !self.pointer_enabled {
+            return;
+        }
+        match (self.mode, self.dragging, self.is_shift) {
+            (CameraMode::Orbit, false, _) => {}
+            (CameraMode::Orbit, true, true) => {
+                self.pan_delta.0 += delta.0;
+                self.pan_delta.1 += delta.1;
+            }
+            _ => {
+                self.mouse_delta.0 += delta.0;
+                self.mouse_delta.1 += delta.1;
+            }
+        }
     }
 
-    pub fn update_camera(&mut self, cam: &mut Camera) {
+    /// move the camera for a frame of `dt` seconds.
+    pub fn update_camera(&mut self, cam: &mut Camera, dt: f32) {
+        let dt = dt.min(Self::MAX_DT);
+
+        let k = (1.0 - (-dt / Self::MOUSE_SMOOTHING).exp()) as f64;
+        let step = (self.mouse_delta.0 * k, self.mouse_delta.1 * k);
+        self.mouse_pos.0 += step.0;
+        self.mouse_pos.1 += step.1;
+        self.mouse_delta.0 -= step.0;
+        self.mouse_delta.1 -= step.1;
+        self.clamp_pitch();
+
+        let moving = self.is_forward
+            || self.is_back
+            || self.is_left
+            || self.is_right
+            || self.is_up
+            || self.is_down;
+        if moving {
+            self.fly = None;
+        }
+
+        if let Some(fly) = &self.fly {
+            let t = 1.0 - (-dt * Self::FLY_RATE).exp();
+            cam.set_pos(&glm::lerp(&cam.pos(), &fly.pos, t));
+            self.mouse_pos.0 += (fly.mouse_pos.0 - self.mouse_pos.0) * t as f64;
+            self.mouse_pos.1 += (fly.mouse_pos.1 - self.mouse_pos.1) * t as f64;
+
+            let arrived = glm::distance(&cam.pos(), &fly.pos) < 1e-2
+                && (fly.mouse_pos.0 - self.mouse_pos.0).abs() < 1.0
+                && (fly.mouse_pos.1 - self.mouse_pos.1).abs() < 1.0;
+            if arrived {
+                cam.set_pos(&fly.pos);
+                self.mouse_pos = fly.mouse_pos;
+                self.fly = None;
+            }
+        }
+
+        if !self.free_flight {
+            self.roll = 0.0;
+        } else if self.is_roll_left != self.is_roll_right {
+            let sign = if self.is_roll_left { 1.0 } else { -1.0 };
+            self.roll += sign * Self::ROLL_SPEED.to_radians() * dt;
+        }
+
         {
             let half_y = 45.0_f32.to_radians() * 0.5;
             let half_x = -0.0_f32.to_radians() * 0.5;
-            cam.quat = glm::Quat::new(half_y.cos(), 0.0, half_y.sin(), 0.0)
+            cam.quat = self.up.rotation()
+                * glm::Quat::new(half_y.cos(), 0.0, half_y.sin(), 0.0)
                 * glm::Quat::new(half_x.cos(), half_x.sin(), 0.0, 0.0);
         }
 
         let half_angle_x = (self.mouse_pos.1 * self.sensitivity * 0.5) as f32;
         let half_angle_y = (self.mouse_pos.0 * self.sensitivity * 0.5) as f32;
+        let half_roll = self.roll * 0.5;
         cam.quat *= glm::Quat::new(half_angle_y.cos(), 0.0, half_angle_y.sin(), 0.0)
-            * glm::Quat::new(half_angle_x.cos(), half_angle_x.sin(), 0.0, 0.0);
+            * glm::Quat::new(half_angle_x.cos(), half_angle_x.sin(), 0.0, 0.0)
+            * glm::Quat::new(half_roll.cos(), 0.0, 0.0, half_roll.sin());
 
+        let dist = self.speed * dt;
+        let moved_from = cam.uniform.pos;
         if self.is_forward {
             let dir = glm::quat_cast(&cam.quat) * glm::vec4(0.0, 0.0, 1.0, 0.0);
-            cam.uniform.pos += dir.xyz() * self.speed;
+            cam.uniform.pos += dir.xyz() * dist;
         }
         if self.is_back {
             let dir = glm::quat_cast(&cam.quat) * glm::vec4(0.0, 0.0, 1.0, 0.0);
-            cam.uniform.pos -= dir.xyz() * self.speed;
+            cam.uniform.pos -= dir.xyz() * dist;
         }
         if self.is_left {
             let dir = glm::quat_cast(&cam.quat) * glm::vec4(1.0, 0.0, 0.0, 0.0);
-            cam.uniform.pos -= dir.xyz() * self.speed;
+            cam.uniform.pos -= dir.xyz() * dist;
             // let half_angle = -self.speed.to_radians() * 2.0;
             // cam.quat *= glm::Quat::new(half_angle.cos(), 0.0, half_angle.sin(), 0.0)
         }
         if self.is_right {
             let dir = glm::quat_cast(&cam.quat) * glm::vec4(1.0, 0.0, 0.0, 0.0);
-            cam.uniform.pos += dir.xyz() * self.speed;
+            cam.uniform.pos += dir.xyz() * dist;
             // let half_angle = self.speed.to_radians() * 2.0;
             // cam.quat *= glm::Quat::new(half_angle.cos(), 0.0, half_angle.sin(), 0.0)
         }
         if self.is_up {
-            cam.uniform.pos.y += self.speed;
+            cam.uniform.pos += self.up.vector() * dist;
         }
-        if self.is_down {
-            cam.uniform.pos.y -= self.speed;
+        // shift pans the orbit camera.
+        if self.is_down && self.mode == CameraMode::Fly {
+            cam.uniform.pos -= self.up.vector() * dist;
+        }
+
+        if self.mode == CameraMode::Orbit {
+            // the keys move the focus, the camera stays at `distance` behind it.
+            let rot = glm::quat_cast(&cam.quat);
+            let forward = (rot * glm::vec4(0.0, 0.0, 1.0, 0.0)).xyz();
+            let right = (rot * glm::vec4(1.0, 0.0, 0.0, 0.0)).xyz();
+            let up = (rot * glm::vec4(0.0, 1.0, 0.0, 0.0)).xyz();
+            // a pixel of pan moves the focus by the same angle as a pixel of rotation.
+            let k = self.distance * self.sensitivity as f32;
+            self.focus += cam.uniform.pos - moved_from;
+            self.focus += (up * self.pan_delta.1 as f32 - right * self.pan_delta.0 as f32) * k;
+            self.pan_delta = (0.0, 0.0);
+            cam.set_pos(&(self.focus - forward * self.distance));
         }
 
+        cam.rebase();
         cam.uniform.view_mat_inv = glm::quat_cast(&cam.quat);
     }
 }
@@ -0,0 +1,214 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::FilterType,
+    Delay, Frame, ImageResult, RgbaImage,
+};
+use web_time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use wgpu::*;
+
+// readback of rendered frames to the cpu.
+// buffer rows must be aligned to COPY_BYTES_PER_ROW_ALIGNMENT, the padding is stripped on read.
+
+pub(crate) struct Readback {
+    buffer: Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    format: TextureFormat,
+}
+
+/// a file name like `{prefix}-{unix time}.{ext}` in the working directory.
+pub(crate) fn timestamped_path(prefix: &str, ext: &str) -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    PathBuf::from(format!("{prefix}-{secs}.{ext}"))
+}
+
+/// an offscreen color target that can be rendered to and copied back.
+pub(crate) fn create_render_target(
+    device: &Device,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: Some("offscreen render target"),
+        size: Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// record a copy of a 2d rgba8/bgra8 texture into a mappable buffer.
+pub(crate) fn copy_texture(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    texture: &Texture,
+) -> Readback {
+    let width = texture.width();
+    let height = texture.height();
+    let format = texture.format();
+    let bytes_per_pixel = format.block_copy_size(None).unwrap();
+    assert_eq!(bytes_per_pixel, 4, "unsupported readback format {format:?}");
+
+    let padded_bytes_per_row = (width * bytes_per_pixel).div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT)
+        * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("readback buffer"),
+        size: (padded_bytes_per_row * height) as BufferAddress,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    Readback {
+        buffer,
+        width,
+        height,
+        padded_bytes_per_row,
+        format,
+    }
+}
+
+impl Readback {
+    /// wait for the copy to complete and return the image. the copy must have been submitted.
+    pub(crate) fn read(self, device: &Device) -> RgbaImage {
+        let slice = self.buffer.slice(..);
+        slice.map_async(MapMode::Read, |res| {
+            res.expect("failed to map readback buffer")
+        });
+        device.poll(Maintain::Wait);
+
+        let row_size = (self.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(row_size * self.height as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..row_size]);
+            }
+        }
+        self.buffer.unmap();
+
+        if matches!(
+            self.format,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        RgbaImage::from_raw(self.width, self.height, pixels).unwrap()
+    }
+}
+
+/// a ring buffer of the last seconds of rendered frames, downscaled, for quick gif exports.
+pub(crate) struct FrameHistory {
+    pub enabled: bool,
+    pub seconds: f32,
+    pub fps: u32,
+    pub max_width: u32,
+    frames: VecDeque<(Instant, RgbaImage)>,
+    last_capture: Instant,
+}
+
+impl FrameHistory {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            seconds: 10.0,
+            fps: 10,
+            max_width: 480,
+            frames: VecDeque::new(),
+            last_capture: Instant::now(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn should_capture(&self) -> bool {
+        self.enabled && self.last_capture.elapsed().as_secs_f32() >= 1.0 / self.fps as f32
+    }
+
+    pub fn push(&mut self, image: RgbaImage) {
+        let now = Instant::now();
+        self.last_capture = now;
+
+        let image = if image.width() > self.max_width {
+            let height = image.height() * self.max_width / image.width();
+            image::imageops::resize(&image, self.max_width, height, FilterType::Triangle)
+        } else {
+            image
+        };
+        self.frames.push_back((now, image));
+
+        let max_age = Duration::from_secs_f32(self.seconds);
+        while let Some((time, _)) = self.frames.front() {
+            if now.duration_since(*time) > max_age {
+                self.frames.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn export_gif(&self, path: &Path) -> ImageResult<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = GifEncoder::new_with_speed(file, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        // keep the original pacing, in case frames were captured irregularly.
+        let delays = self
+            .frames
+            .iter()
+            .zip(self.frames.iter().skip(1))
+            .map(|((t1, _), (t2, _))| t2.duration_since(*t1))
+            .chain(std::iter::once(Duration::from_secs_f32(
+                1.0 / self.fps as f32,
+            )));
+
+        encoder.encode_frames(self.frames.iter().zip(delays).map(|((_, image), delay)| {
+            let delay = Delay::from_numer_denom_ms(delay.as_millis() as u32, 1);
+            Frame::from_parts(image.clone(), 0, 0, delay)
+        }))?;
+
+        println!("wrote {} frames to `{}`", self.frames.len(), path.display());
+        Ok(())
+    }
+}
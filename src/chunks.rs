@@ -0,0 +1,380 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use ndarray::{s, Array3};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    loading::{LoadProgress, Stage},
+    voxels::Voxels,
+};
+
+// the chunked scene format (.wchunks), made for streaming over http.
+//
+// layout: the magic bytes, the length of the header (u64, little endian), the bincode header,
+// then the compressed chunks. the header lists the byte range of each chunk relative to the end
+// of the header, so a client can fetch the header first and then each chunk with a range request.
+// chunks are `CHUNK_SIZE`^3 blocks of palette indices (u32, array axes), deflate compressed.
+// empty chunks are omitted.
+
+pub const MAGIC: &[u8; 8] = b"WCHUNKS1";
+pub const CHUNK_SIZE: usize = 64;
+/// length of the magic bytes and the header length.
+pub const PREFIX_LEN: usize = MAGIC.len() + 8;
+/// larger header lengths are rejected before allocating them, the file is corrupted.
+pub const MAX_HEADER_LEN: u64 = 256 << 20;
+/// larger chunk sizes in a header are rejected, the file is corrupted.
+pub const MAX_CHUNK_SIZE: usize = 256;
+/// larger scene shapes in a header are rejected before allocating them, per axis and in total.
+pub const MAX_SHAPE: usize = 1 << 14;
+pub const MAX_VOXELS: u64 = 1 << 32;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("i/o error: {0}")]
+    IOError(#[from] io::Error),
+    #[error("not a .wchunks file")]
+    BadMagic,
+    #[error("invalid header: {0}")]
+    HeaderError(#[from] bincode::Error),
+    #[error("invalid header length of {0} bytes")]
+    HeaderLen(u64),
+    #[error("invalid chunk size of {0}")]
+    ChunkSize(usize),
+    #[error("invalid scene shape {0:?}")]
+    Shape((usize, usize, usize)),
+    #[error("invalid chunk at {0:?}")]
+    ChunkError([u32; 3]),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChunkEntry {
+    /// position of the chunk in the grid of chunks, array axes.
+    pub pos: [u32; 3],
+    pub offset: u64,
+    pub len: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Header {
+    /// shape of the scene before padding, array axes.
+    pub shape: (usize, usize, usize),
+    pub chunk_size: usize,
+    pub palette: Vec<[u8; 4]>,
+    pub chunks: Vec<ChunkEntry>,
+}
+
+impl ChunkEntry {
+    /// position of the first voxel of the chunk, array axes.
+    pub fn origin(&self, chunk_size: usize) -> [usize; 3] {
+        self.pos.map(|p| p as usize * chunk_size)
+    }
+}
+
+fn compress(chunk: &Array3<u32>) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    for v in chunk.iter() {
+        encoder.write_all(&v.to_le_bytes())?;
+    }
+    encoder.finish()
+}
+
+/// decompress the data of a chunk, a `chunk_size`^3 block of palette indices. the header must have
+/// been validated by `read_header`, which bounds `chunk_size`.
+pub fn decompress(
+    entry: &ChunkEntry,
+    data: &[u8],
+    chunk_size: usize,
+) -> Result<Array3<u32>, Error> {
+    let len = chunk_size.pow(3) * 4;
+    let mut bytes = Vec::with_capacity(len);
+    // one byte more than expected to detect oversized chunks without inflating all of them.
+    DeflateDecoder::new(data)
+        .take(len as u64 + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|_| Error::ChunkError(entry.pos))?;
+    if bytes.len() != len {
+        return Err(Error::ChunkError(entry.pos));
+    }
+    let values = bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    Array3::from_shape_vec((chunk_size, chunk_size, chunk_size), values)
+        .map_err(|_| Error::ChunkError(entry.pos))
+}
+
+/// length of the header, read from the first `PREFIX_LEN` bytes of the file.
+pub fn read_header_len(prefix: &[u8]) -> Result<usize, Error> {
+    match prefix.get(..PREFIX_LEN) {
+        Some(prefix) if prefix.starts_with(MAGIC) => {
+            let mut len = [0u8; 8];
+            len.copy_from_slice(&prefix[MAGIC.len()..]);
+            match u64::from_le_bytes(len) {
+                len if len > MAX_HEADER_LEN => Err(Error::HeaderLen(len)),
+                len => Ok(len as usize),
+            }
+        }
+        _ => Err(Error::BadMagic),
+    }
+}
+
+/// check that the palette indices of a decompressed chunk are in the palette, 0 being empty.
+pub fn check_indices(
+    entry: &ChunkEntry,
+    chunk: &Array3<u32>,
+    palette_len: usize,
+) -> Result<(), Error> {
+    match chunk.iter().all(|i| *i as usize <= palette_len) {
+        true => Ok(()),
+        false => Err(Error::ChunkError(entry.pos)),
+    }
+}
+
+/// deserialize and validate the header: the chunk size and the shape are bounded, and every chunk
+/// lies in the scene with a byte range that does not overflow.
+pub fn read_header(bytes: &[u8]) -> Result<Header, Error> {
+    let header: Header = bincode::deserialize(bytes)?;
+
+    if header.chunk_size == 0 || header.chunk_size > MAX_CHUNK_SIZE {
+        return Err(Error::ChunkSize(header.chunk_size));
+    }
+    let (sz, sy, sx) = header.shape;
+    let shape = [sz, sy, sx];
+    // checked per axis first, so that the product does not overflow.
+    if shape.iter().any(|s| *s == 0 || *s > MAX_SHAPE)
+        || shape.iter().map(|s| *s as u64).product::<u64>() > MAX_VOXELS
+    {
+        return Err(Error::Shape(header.shape));
+    }
+
+    for entry in &header.chunks {
+        let inside = entry.pos.iter().zip(shape).all(|(p, s)| {
+            (*p as usize)
+                .checked_mul(header.chunk_size)
+                .is_some_and(|o| o < s)
+        });
+        if !inside || entry.offset.checked_add(entry.len).is_none() {
+            return Err(Error::ChunkError(entry.pos));
+        }
+    }
+
+    Ok(header)
+}
+
+/// read a whole chunked scene, as the unpadded array of palette indices and the palette. the
+/// chunks are decompressed in parallel, a batch at a time to bound the memory, and the remaining
+/// ones are skipped once `progress` is cancelled.
+pub fn read(path: &Path, progress: &LoadProgress) -> Result<(Array3<u32>, Vec<[u8; 4]>), Error> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    let header_len = read_header_len(&bytes)?;
+    let data_start = PREFIX_LEN + header_len;
+    let header = read_header(bytes.get(PREFIX_LEN..data_start).ok_or(Error::BadMagic)?)?;
+
+    let (sz, sy, sx) = header.shape;
+    let mut vox = Array3::zeros(header.shape);
+    progress.begin(Stage::Decompress, header.chunks.len());
+    let batch = rayon::current_num_threads() * 4;
+    for entries in header.chunks.chunks(batch) {
+        if progress.is_cancelled() {
+            break;
+        }
+        let chunks = entries
+            .par_iter()
+            .map(|entry| {
+                let range = (data_start as u64)
+                    .checked_add(entry.offset)
+                    .and_then(|start| {
+                        let end = start.checked_add(entry.len)?;
+                        Some(usize::try_from(start).ok()?..usize::try_from(end).ok()?)
+                    });
+                let data = range
+                    .and_then(|range| bytes.get(range))
+                    .ok_or(Error::ChunkError(entry.pos))?;
+                let chunk = decompress(entry, data, header.chunk_size)?;
+                check_indices(entry, &chunk, header.palette.len())?;
+                progress.step();
+                Ok(chunk)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        for (entry, chunk) in entries.iter().zip(&chunks) {
+            let [i0, j0, k0] = entry.origin(header.chunk_size);
+            let (i1, j1, k1) = (
+                (i0 + header.chunk_size).min(sz),
+                (j0 + header.chunk_size).min(sy),
+                (k0 + header.chunk_size).min(sx),
+            );
+            vox.slice_mut(s![i0..i1, j0..j1, k0..k1])
+                .assign(&chunk.slice(s![..i1 - i0, ..j1 - j0, ..k1 - k0]));
+        }
+    }
+
+    Ok((vox, header.palette))
+}
+
+/// write the scene in the chunked format. returns the number of non-empty chunks.
+pub fn write(voxels: &Voxels, path: &Path) -> Result<usize, Error> {
+    let padded = voxels.voxels();
+    let (sz, sy, sx) = voxels.shape();
+    let count = |n: usize| n.div_ceil(CHUNK_SIZE);
+
+    let mut chunks = Vec::new();
+    let mut data = Vec::new();
+    for i in 0..count(sz) {
+        for j in 0..count(sy) {
+            for k in 0..count(sx) {
+                let (i0, j0, k0) = (i * CHUNK_SIZE, j * CHUNK_SIZE, k * CHUNK_SIZE);
+                let view = padded.slice(s![
+                    i0..(i0 + CHUNK_SIZE).min(padded.dim().0),
+                    j0..(j0 + CHUNK_SIZE).min(padded.dim().1),
+                    k0..(k0 + CHUNK_SIZE).min(padded.dim().2)
+                ]);
+                if view.iter().all(|v| *v == 0) {
+                    continue;
+                }
+
+                // chunks at the border are padded with empty voxels.
+                let mut chunk = Array3::zeros((CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE));
+                let (di, dj, dk) = view.dim();
+                chunk
+                    .slice_mut(s![..di, ..dj, ..dk])
+                    .assign(&view.mapv(|v| v as u32));
+
+                let compressed = compress(&chunk)?;
+                chunks.push(ChunkEntry {
+                    pos: [i as u32, j as u32, k as u32],
+                    offset: data.len() as u64,
+                    len: compressed.len() as u64,
+                });
+                data.extend_from_slice(&compressed);
+            }
+        }
+    }
+
+    let header = Header {
+        shape: voxels.shape(),
+        chunk_size: CHUNK_SIZE,
+        palette: voxels.palette().to_vec(),
+        chunks,
+    };
+    let header_bytes = bincode::serialize(&header)?;
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    file.write_all(&(header_bytes.len() as u64).to_le_bytes())?;
+    file.write_all(&header_bytes)?;
+    file.write_all(&data)?;
+    file.flush()?;
+
+    Ok(header.chunks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(shape: (usize, usize, usize), chunk_size: usize, index: u32) -> (Header, Vec<u8>) {
+        let data = compress(&Array3::from_elem(
+            (chunk_size, chunk_size, chunk_size),
+            index,
+        ))
+        .unwrap();
+        let header = Header {
+            shape,
+            chunk_size,
+            palette: vec![[255, 0, 0, 255]],
+            chunks: vec![ChunkEntry {
+                pos: [0, 0, 0],
+                offset: 0,
+                len: data.len() as u64,
+            }],
+        };
+        (header, data)
+    }
+
+    fn to_bytes(header: &Header, data: &[u8]) -> Vec<u8> {
+        let header_bytes = bincode::serialize(header).unwrap();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(header_bytes.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&header_bytes);
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn read_bytes(name: &str, bytes: &[u8]) -> Result<(Array3<u32>, Vec<[u8; 4]>), Error> {
+        let path =
+            std::env::temp_dir().join(format!("wender_{name}_{}.wchunks", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        let res = read(&path, &LoadProgress::new());
+        std::fs::remove_file(&path).unwrap();
+        res
+    }
+
+    #[test]
+    fn reads_valid_file() {
+        let (header, data) = scene((4, 4, 3), 4, 1);
+        let (vox, palette) = read_bytes("valid", &to_bytes(&header, &data)).unwrap();
+        assert_eq!(vox.dim(), (4, 4, 3));
+        assert!(vox.iter().all(|v| *v == 1));
+        assert_eq!(palette.len(), 1);
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let (header, data) = scene((4, 4, 4), 4, 1);
+        let bytes = to_bytes(&header, &data);
+        for len in [4, PREFIX_LEN + 2, bytes.len() - data.len() / 2] {
+            assert!(
+                read_bytes("truncated", &bytes[..len]).is_err(),
+                "length {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_corrupted_header() {
+        let (mut header, data) = scene((4, 4, 4), 4, 1);
+        header.chunks[0].pos = [1, 0, 0];
+        let res = read_bytes("outside", &to_bytes(&header, &data));
+        assert!(matches!(res, Err(Error::ChunkError([1, 0, 0]))));
+
+        let (mut header, data) = scene((4, 4, 4), 4, 1);
+        header.chunks[0].offset = u64::MAX;
+        let res = read_bytes("offset", &to_bytes(&header, &data));
+        assert!(matches!(res, Err(Error::ChunkError(_))));
+
+        let (mut header, data) = scene((4, 4, 4), 4, 1);
+        header.chunk_size = 0;
+        let res = read_bytes("chunk_size", &to_bytes(&header, &data));
+        assert!(matches!(res, Err(Error::ChunkSize(0))));
+
+        let (mut header, data) = scene((4, 4, 4), 4, 1);
+        header.shape = (MAX_SHAPE, MAX_SHAPE, MAX_SHAPE);
+        let res = read_bytes("shape", &to_bytes(&header, &data));
+        assert!(matches!(res, Err(Error::Shape(_))));
+    }
+
+    #[test]
+    fn rejects_corrupted_chunk() {
+        // an index past the palette.
+        let (header, data) = scene((4, 4, 4), 4, 2);
+        let res = read_bytes("index", &to_bytes(&header, &data));
+        assert!(matches!(res, Err(Error::ChunkError(_))));
+
+        // a chunk of the wrong size.
+        let (mut header, _) = scene((4, 4, 4), 4, 1);
+        let (_, data) = scene((4, 4, 4), 2, 1);
+        header.chunks[0].len = data.len() as u64;
+        let res = read_bytes("size", &to_bytes(&header, &data));
+        assert!(matches!(res, Err(Error::ChunkError(_))));
+    }
+}
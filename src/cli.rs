@@ -0,0 +1,193 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Realtime voxel raymarcher",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Args {
+    /// Path to the .wvox (or MagicaVoxel .vox) scene to open, the `scene` of `wender.toml` by
+    /// default
+    pub scene: Option<PathBuf>,
+
+    /// Compile every shader module on its own and exit
+    #[arg(long)]
+    pub check_shaders: bool,
+
+    /// Print a report of the gpu capabilities, write it to a file and exit
+    #[arg(long)]
+    pub diagnose: bool,
+
+    /// Restore the last session (`.wender-session`) instead of opening a scene
+    #[arg(long = "continue")]
+    pub continue_session: bool,
+
+    /// Graphics api, picked from `WGPU_BACKEND` or among the native ones by default
+    #[arg(long, value_enum, default_value_t = Backend::Auto)]
+    pub backend: Backend,
+
+    /// Record a chrome://tracing compatible trace, written on exit
+    #[arg(long)]
+    pub trace: bool,
+
+    /// Seed of the random numbers of the renderer, runs with the same seed render the same
+    /// images
+    #[arg(long, default_value_t = 0)]
+    pub seed: u32,
+
+    /// Render frames offscreen along a camera path without a window, write the images and the
+    /// frame times and exit
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Number of frames rendered by `--headless`
+    #[arg(long, default_value_t = 300, requires = "headless")]
+    pub frames: u32,
+
+    /// Width and height of the `--headless` frames, in pixels
+    #[arg(long, default_value_t = 1280, requires = "headless")]
+    pub width: u32,
+    #[arg(long, default_value_t = 720, requires = "headless")]
+    pub height: u32,
+
+    /// Route file (.json or .csv) followed by the `--headless` camera, an orbit by default
+    #[arg(long, requires = "headless")]
+    pub camera_path: Option<PathBuf>,
+
+    /// Save one `--headless` frame every this many frames, 0 for none
+    #[arg(long, default_value_t = 30, requires = "headless")]
+    pub image_every: u32,
+
+    /// Write the hashes of the `--image-every` frames to `hashes.json` instead of the images
+    #[arg(long, requires = "headless")]
+    pub hash: bool,
+
+    /// `hashes.json` of a previous `--hash` run, exit with an error if a frame differs from it
+    #[arg(long, requires = "hash")]
+    pub compare_hashes: Option<PathBuf>,
+
+    /// Mean luma difference from 0 to 1 tolerated by `--compare-hashes` when the exact hashes
+    /// differ
+    #[arg(long, default_value_t = 0.01, requires = "compare_hashes")]
+    pub tolerance: f32,
+
+    /// Output directory of `--headless`, created if missing
+    #[arg(long, default_value = "headless", requires = "headless")]
+    pub out_dir: PathBuf,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Render a preview image of a scene and exit
+    Thumbnail {
+        /// Path to the .wvox scene
+        scene: PathBuf,
+
+        /// Path to the output image, the format is picked from the extension
+        output: PathBuf,
+
+        /// Width and height of the image, in pixels
+        #[arg(long, default_value_t = 512)]
+        size: u32,
+
+        /// How the camera frames the scene
+        #[arg(long, value_enum, default_value_t = View::Perspective)]
+        view: View,
+
+        /// Graphics api, picked from `WGPU_BACKEND` or among the native ones by default
+        #[arg(long, value_enum, default_value_t = Backend::Auto)]
+        backend: Backend,
+    },
+
+    /// Export a scene as a self-contained web page
+    ExportWeb {
+        /// Path to the .wvox scene
+        scene: PathBuf,
+
+        /// Output directory of the bundle, created if missing
+        out_dir: PathBuf,
+
+        /// Directory of the web viewer build, see `web/index.html`
+        #[arg(long, default_value = "pkg")]
+        pkg: PathBuf,
+
+        /// Downsample the scene until it is at most this many voxels wide, browsers have less
+        /// gpu memory available
+        #[arg(long, default_value_t = 512)]
+        max_dim: u32,
+    },
+
+    /// Export voxel counts per palette entry, height histograms and a height map of a scene
+    Stats {
+        /// Path to the .wvox (or MagicaVoxel .vox) scene
+        scene: PathBuf,
+
+        /// Output directory of the csv files and the height map, created if missing
+        out_dir: PathBuf,
+    },
+
+    /// Precompute which bricks are visible from each region of a scene, for the brick map
+    Pvs {
+        /// Path to the .wvox scene
+        scene: PathBuf,
+
+        /// Length of the visibility rays, in voxels. Bricks farther away are always streamed
+        #[arg(long, default_value_t = 512.0)]
+        distance: f32,
+    },
+
+    /// Check a scene for out-of-range palette indices, mismatched baked lighting and invalid
+    /// metadata, and optionally repair it
+    Validate {
+        /// Path to the .wvox scene
+        scene: PathBuf,
+
+        /// Repair the scene, out-of-range voxels are clamped to the last palette entry or cleared
+        #[arg(long, value_enum)]
+        repair: Option<Repair>,
+
+        /// Path of the repaired scene, the scene itself by default
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Repair {
+    Clamp,
+    Clear,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum View {
+    /// Three-quarter view from above
+    Perspective,
+    /// Same view from far away with a narrow field of view, the faces keep the same size
+    Orthographic,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Backend {
+    Auto,
+    Vulkan,
+    Metal,
+    Dx12,
+    Gl,
+}
+
+impl Backend {
+    pub fn backends(self) -> wgpu::Backends {
+        match self {
+            Backend::Auto => wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::PRIMARY),
+            Backend::Vulkan => wgpu::Backends::VULKAN,
+            Backend::Metal => wgpu::Backends::METAL,
+            Backend::Dx12 => wgpu::Backends::DX12,
+            Backend::Gl => wgpu::Backends::GL,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use image::RgbaImage;
+use thiserror::Error;
+
+// copy of rendered frames to the system clipboard, to paste screenshots straight into chats and
+// issue reports. the clipboard is opened once and kept: on linux the copied image is served by
+// it, and lost if it is closed before a clipboard manager took it. the web viewer has no access
+// to the clipboard images.
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("failed to copy to the clipboard: {0}")]
+    ClipboardError(#[from] arboard::Error),
+    #[error("the clipboard is not available on the web")]
+    Unsupported,
+}
+
+pub struct ImageClipboard {
+    #[cfg(not(target_arch = "wasm32"))]
+    clipboard: Option<arboard::Clipboard>,
+}
+
+impl ImageClipboard {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            clipboard: None,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn copy(&mut self, image: &RgbaImage) -> Result<(), Error> {
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => self.clipboard.insert(arboard::Clipboard::new()?),
+        };
+        clipboard.set_image(arboard::ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: image.as_raw().into(),
+        })?;
+        Ok(())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn copy(&mut self, _image: &RgbaImage) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+}
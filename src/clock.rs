@@ -0,0 +1,60 @@
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+// the scene time, which drives the animations (sun rotation, water foam). it runs at `scale`
+// times the real time, to slow down animated effects while debugging them or for cinematic
+// captures. the offscreen renders (thumbnail, headless) have no clock and stay at time 0.
+
+pub const MAX_SCALE: f32 = 4.0;
+/// slowest speed reached with the keyboard.
+const MIN_KEY_SCALE: f32 = 1.0 / 16.0;
+
+pub struct Clock {
+    /// speed of the scene time relative to the real time.
+    pub scale: f32,
+    pub paused: bool,
+    /// seconds of scene time since the start.
+    pub time: f32,
+    /// real seconds between the last two ticks, for the camera which ignores the scale.
+    pub frame_time: f32,
+    last_tick: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            scale: 1.0,
+            paused: false,
+            time: 0.0,
+            frame_time: 0.0,
+            last_tick: None,
+        }
+    }
+
+    /// advance the scene time. returns the seconds of scene time since the last tick.
+    pub fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        let real = self
+            .last_tick
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+        self.last_tick = Some(now);
+        self.frame_time = real;
+        let dt = match self.paused {
+            true => 0.0,
+            false => real * self.scale,
+        };
+        self.time += dt;
+        dt
+    }
+
+    /// p pauses, - and = halve and double the speed. returns whether the key was used.
+    pub fn process_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::KeyP => self.paused = !self.paused,
+            KeyCode::Minus => self.scale = (self.scale * 0.5).max(MIN_KEY_SCALE),
+            KeyCode::Equal => self.scale = (self.scale * 2.0).min(MAX_SCALE),
+            _ => return false,
+        }
+        true
+    }
+}
@@ -0,0 +1,68 @@
+#import "ray.wgsl"::{ render_cam, render_view_pos }
+
+// this shader is a "module" supposed to be included.
+// clustered shading of the local lights: the view frustum of the render camera (see
+// `Frustum::render_camera`) is split in clusters, CLUSTER_TILES^2 tiles of the screen by
+// CLUSTER_SLICES slices of the view depth, exponential from CLUSTER_NEAR to CLUSTER_FAR. before the
+// primary pass, a compute pass (`compute_light_culling.wgsl`) lists the local lights reaching each
+// cluster, and the shading of a hit only loops over the lights of its cluster (see `local_lighting`
+// in shading.wgsl). a cluster keeps MAX_CLUSTER_LIGHTS lights at most, the next ones are dropped.
+// the hits outside the frustum, e.g. the path traced bounces, loop over every light.
+//
+// this module "exports":
+// const CLUSTER_TILES: u32
+// const CLUSTER_SLICES: u32
+// const MAX_CLUSTER_LIGHTS: u32
+// const CLUSTER_STRIDE: u32
+// const NO_CLUSTER: u32
+// fn cluster_index(cluster: vec3u) -> u32
+// fn slice_bounds(slice: u32) -> vec2f
+// fn cluster_of(pos: vec3f) -> u32
+//
+// CLUSTER_TILES, CLUSTER_SLICES and MAX_CLUSTER_LIGHTS must match wgpu_util.rs.
+
+// tiles per axis of the screen.
+const CLUSTER_TILES: u32 = 16u;
+// slices of the view depth.
+const CLUSTER_SLICES: u32 = 24u;
+const MAX_CLUSTER_LIGHTS: u32 = 32u;
+// u32 per cluster in the lists: the number of lights, then their indices in `local_lights`.
+const CLUSTER_STRIDE: u32 = 33u;
+// the view depths between which the slices are exponential, in voxels. the first slice starts
+// at the camera and the last one goes to infinity.
+const CLUSTER_NEAR: f32 = 1.0;
+const CLUSTER_FAR: f32 = 4096.0;
+const NO_CLUSTER: u32 = 0xffffffffu;
+
+// index of `cluster` (tile x, tile y, slice) in the lists.
+fn cluster_index(cluster: vec3u) -> u32 {
+    return (cluster.z * CLUSTER_TILES + cluster.y) * CLUSTER_TILES + cluster.x;
+}
+
+// the view depths at the start and at the end of `slice`.
+fn slice_bounds(slice: u32) -> vec2f {
+    let ratio = CLUSTER_FAR / CLUSTER_NEAR;
+    let near = CLUSTER_NEAR * pow(ratio, f32(slice) / f32(CLUSTER_SLICES));
+    let far = CLUSTER_NEAR * pow(ratio, f32(slice + 1u) / f32(CLUSTER_SLICES));
+    return vec2f(
+        select(near, 0.0, slice == 0u),
+        select(far, 1e9, slice == CLUSTER_SLICES - 1u),
+    );
+}
+
+// the cluster containing the render space position `pos`, NO_CLUSTER outside the frustum of the
+// render camera.
+fn cluster_of(pos: vec3f) -> u32 {
+    let view = render_view_pos(pos);
+    if view.z <= 0.0 {
+        return NO_CLUSTER;
+    }
+    let tan_y = tan(render_cam.fov_y / 2.0);
+    let screen = view.xy / (view.z * vec2f(tan_y * render_cam.aspect, tan_y));
+    if any(abs(screen) > vec2f(1.0)) {
+        return NO_CLUSTER;
+    }
+    let tile = min(vec2u((screen * 0.5 + 0.5) * f32(CLUSTER_TILES)), vec2u(CLUSTER_TILES - 1u));
+    let slice = log(view.z / CLUSTER_NEAR) / log(CLUSTER_FAR / CLUSTER_NEAR) * f32(CLUSTER_SLICES);
+    return cluster_index(vec3u(tile, u32(clamp(slice, 0.0, f32(CLUSTER_SLICES - 1u)))));
+}
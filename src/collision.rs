@@ -0,0 +1,110 @@
+use nalgebra_glm as glm;
+use ndarray::{Array3, Zip};
+
+use crate::voxels::{raycast_grid, Voxels, VoxelsFormat};
+
+// camera collisions. the gpu holds the volume, so a compact copy of the solid voxels is kept on
+// the cpu: one bit per voxel, 16MiB for a 512^3 scene.
+
+pub struct Collider {
+    dim: u32,
+    bits: Vec<u64>,
+}
+
+impl Collider {
+    #[tracing::instrument(skip_all)]
+    pub fn new(voxels: &Voxels) -> Self {
+        let dim = voxels.dim();
+        let mut collider = Self {
+            dim,
+            bits: vec![0u64; (dim as usize).pow(3).div_ceil(64)],
+        };
+        collider.write_region([0, 0, 0], voxels.voxels());
+        collider
+    }
+
+    /// overwrite a box of the volume, e.g. a streamed chunk. `origin` is in array axes.
+    pub fn write_region(&mut self, origin: [usize; 3], voxels: &Array3<VoxelsFormat>) {
+        let [i0, j0, k0] = origin;
+        Zip::indexed(voxels).for_each(|(i, j, k), v| {
+            // world x and z are swapped relative to the array axes.
+            let idx = Self::index(self.dim, k0 + k, j0 + j, i0 + i);
+            if *v != 0 {
+                self.bits[idx / 64] |= 1 << (idx % 64);
+            } else {
+                self.bits[idx / 64] &= !(1 << (idx % 64));
+            }
+        });
+    }
+
+    fn index(dim: u32, x: usize, y: usize, z: usize) -> usize {
+        let dim = dim as usize;
+        (z * dim + y) * dim + x
+    }
+
+    /// whether the voxel at world coordinates `cell` is solid. out of bounds voxels are empty.
+    pub fn is_solid(&self, cell: glm::IVec3) -> bool {
+        let dim = self.dim as i32;
+        if cell.iter().any(|c| *c < 0 || *c >= dim) {
+            return false;
+        }
+        let idx = Self::index(self.dim, cell.x as usize, cell.y as usize, cell.z as usize);
+        self.bits[idx / 64] & (1 << (idx % 64)) != 0
+    }
+
+    pub fn raycast(
+        &self,
+        pos: &glm::Vec3,
+        dir: &glm::Vec3,
+        max_dist: f32,
+    ) -> Option<(glm::IVec3, f32)> {
+        raycast_grid(self.dim, pos, dir, max_dist, |cell| self.is_solid(cell))
+    }
+
+    /// move a sphere of `radius` from `from` towards `to`, stopping it in front of solid voxels.
+    /// the move is done one axis at a time, so the sphere slides along walls instead of
+    /// sticking to them. a sphere already inside a solid voxel moves freely, so it can get out.
+    pub fn sweep_sphere(&self, from: &glm::Vec3, to: &glm::Vec3, radius: f32) -> glm::Vec3 {
+        let inside = |p: &glm::Vec3| self.is_solid(glm::floor(p).map(|x| x as i32));
+        if inside(from) {
+            return *to;
+        }
+
+        let mut pos = *from;
+        for a in 0..3 {
+            let delta = to[a] - pos[a];
+            if delta == 0.0 {
+                continue;
+            }
+            let mut dir = glm::Vec3::zeros();
+            dir[a] = delta.signum();
+
+            // the sphere cross-section is approximated by its center and 4 points on its rim.
+            let (b, c) = ((a + 1) % 3, (a + 2) % 3);
+            let rim = radius * std::f32::consts::FRAC_1_SQRT_2;
+            let offsets = [(0.0, 0.0), (rim, 0.0), (-rim, 0.0), (0.0, rim), (0.0, -rim)];
+
+            let max_dist = delta.abs() + radius;
+            let hit = offsets
+                .iter()
+                .map(|(ob, oc)| {
+                    let mut start = pos;
+                    start[b] += ob;
+                    start[c] += oc;
+                    start
+                })
+                .filter(|start| !inside(start))
+                .filter_map(|start| self.raycast(&start, &dir, max_dist))
+                .map(|(_, t)| t)
+                .min_by(f32::total_cmp);
+
+            let allowed = match hit {
+                Some(t) => (t - radius).clamp(0.0, delta.abs()),
+                None => delta.abs(),
+            };
+            pos[a] += allowed * dir[a];
+        }
+
+        pos
+    }
+}
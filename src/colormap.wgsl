@@ -0,0 +1,30 @@
+// this shader is a "module" supposed to be included.
+// perceptually uniform color maps for the debug displays, readable with color vision
+// deficiencies. 5 stops of the matplotlib maps, interpolated in srgb and returned linear.
+//
+// this module "exports":
+// fn colormap(t: f32, palette: u32) -> vec3f // palette 1: viridis, 2: cividis
+
+fn srgb_to_linear(c: vec3f) -> vec3f {
+    return pow(c, vec3f(2.2));
+}
+
+fn hex(c: u32) -> vec3f {
+    return vec3f(f32((c >> 16u) & 0xffu), f32((c >> 8u) & 0xffu), f32(c & 0xffu)) / 255.0;
+}
+
+fn stops(t: f32, c0: u32, c1: u32, c2: u32, c3: u32, c4: u32) -> vec3f {
+    let x = saturate(t) * 4.0;
+    var c = mix(hex(c0), hex(c1), saturate(x));
+    c = mix(c, hex(c2), saturate(x - 1.0));
+    c = mix(c, hex(c3), saturate(x - 2.0));
+    c = mix(c, hex(c4), saturate(x - 3.0));
+    return srgb_to_linear(c);
+}
+
+fn colormap(t: f32, palette: u32) -> vec3f {
+    if palette == 2u {
+        return stops(t, 0x00204du, 0x414d6bu, 0x7c7b78u, 0xbcaf6fu, 0xffea46u);
+    }
+    return stops(t, 0x440154u, 0x3b528bu, 0x21918cu, 0x5ec962u, 0xfde725u);
+}
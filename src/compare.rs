@@ -0,0 +1,41 @@
+use crate::{settings::Settings, wgpu_util::ShaderConstants};
+
+// split-screen a/b comparison of render settings, to judge the quality and cost of ao, shadows or
+// lod settings side by side. the left of the divider is rendered with the current settings, the
+// right with the profile b: its own feature switches and shader constants. both halves are drawn
+// in the same pass with scissor rects, see `WgpuState::set_compare`.
+
+pub struct Compare {
+    pub enabled: bool,
+    /// position of the divider, from 0 (left) to 1 (right) of the view.
+    pub split: f32,
+    pub settings: Settings,
+    pub constants: ShaderConstants,
+}
+
+impl Compare {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            split: 0.5,
+            settings: Settings::new(),
+            constants: Default::default(),
+        }
+    }
+
+    /// start comparing, the profile b begins as a copy of the current settings.
+    pub fn start(&mut self, settings: &Settings, constants: &ShaderConstants) {
+        self.enabled = true;
+        self.settings.uniform = settings.uniform;
+        self.constants = constants.clone();
+    }
+
+    /// the constants of the profile b for the scene built with `scene`. the traversal is the one
+    /// of the scene, its structures are only allocated for it.
+    pub fn constants_for(&self, scene: &ShaderConstants) -> ShaderConstants {
+        ShaderConstants {
+            traversal: scene.traversal,
+            ..self.constants.with_scene(scene)
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// contours of the surface voxels, like efficient sparse voxel octrees (laine & karras 2010).
+// each surface voxel gets a plane fitted to its 3^3 neighborhood: the voxel is clipped to the
+// half-space dot(normal, pos - center) <= offset, which chamfers the edges and corners of the
+// volume and smooths the silhouettes at low resolutions.
+// the texture stores (normal, offset). a zero normal means no contour: the voxel is a full cube.
+
+@group(0) @binding(0)
+var colors: texture_3d<f32>;
+
+@group(0) @binding(1)
+var contours_out: texture_storage_3d<rgba8snorm, write>;
+
+// a voxel on a flat surface has 17 solid neighbors: its contour must be its face.
+const FLAT_NEIGHBORS: f32 = 17.0;
+
+fn is_solid(coord: vec3i) -> bool {
+    let dim = vec3i(textureDimensions(colors));
+    if any(coord < vec3i(0)) || any(coord >= dim) {
+        return false;
+    }
+    return any(textureLoad(colors, coord, 0) != vec4f(0.0));
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn cs_contours(@builtin(global_invocation_id) index: vec3u) {
+    if any(index >= textureDimensions(contours_out)) {
+        return;
+    }
+    let coord = vec3i(index);
+    if !is_solid(coord) {
+        textureStore(contours_out, index, vec4f(0.0));
+        return;
+    }
+
+    // the normal points away from the solid neighbors.
+    var inside = vec3f(0.0);
+    var count = 0.0;
+    for (var z = -1; z <= 1; z++) {
+        for (var y = -1; y <= 1; y++) {
+            for (var x = -1; x <= 1; x++) {
+                let offset = vec3i(x, y, z);
+                if all(offset == vec3i(0)) || !is_solid(coord + offset) {
+                    continue;
+                }
+                inside += vec3f(offset);
+                count += 1.0;
+            }
+        }
+    }
+
+    if length(inside) < 1e-3 {
+        textureStore(contours_out, index, vec4f(0.0));
+        return;
+    }
+
+    let normal = -normalize(inside);
+    // half extent of the voxel along the normal: the plane at this offset touches the voxel.
+    let extent = 0.5 * dot(abs(normal), vec3f(1.0));
+    let offset = extent * saturate(count / FLAT_NEIGHBORS * 2.0 - 1.0);
+    textureStore(contours_out, index, vec4f(normal, offset));
+}
@@ -0,0 +1,77 @@
+#import "contree.wgsl"::{ contree_levels, child_bit, has_child, child_index }
+
+// builds the sparse 64-tree of contree.wgsl top-down, one dispatch per level, after the dvo. each
+// node of the level finds itself by descending from the root, reads the occupancy of its 64
+// children from the dvo mip chain, and allocates its occupied children contiguously.
+// nodes that do not fit in the buffer are dropped, their regions look empty.
+
+@group(0) @binding(0)
+var voxels: texture_3d<u32>;
+
+@group(0) @binding(1)
+var dvo: texture_3d<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> nodes: array<vec4u>;
+
+// number of allocated nodes besides the root, cleared before the first level.
+@group(0) @binding(3)
+var<storage, read_write> node_count: atomic<u32>;
+
+// level of the dispatched nodes in x, 0 being the root.
+@group(0) @binding(4)
+var<uniform> level: vec4u;
+
+// whether the box of 2^log_size voxels at `pos` has any solid voxel. a dvo texel of mip m covers
+// 2^(m + 1) voxels.
+fn is_region_solid(pos: vec3u, log_size: u32) -> bool {
+    if any(pos >= textureDimensions(voxels)) {
+        return false;
+    }
+    if log_size == 0u {
+        return textureLoad(voxels, pos, 0).r != 0u;
+    }
+    return textureLoad(dvo, pos >> vec3u(log_size), i32(log_size - 1u)).r != 0u;
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn cs_contree(@builtin(global_invocation_id) id: vec3u) {
+    let depth = level.x;
+    let levels = contree_levels();
+    let node_log = 2u * (levels - depth);
+    let child_log = node_log - 2u;
+    let origin = id << vec3u(node_log);
+    if any(origin >= textureDimensions(voxels)) {
+        return;
+    }
+
+    // descend from the root, the node exists if all its ancestors have it as a child.
+    var index = 0u;
+    for (var k = 0u; k < depth; k++) {
+        let node = nodes[index];
+        let bit = child_bit((id >> vec3u(2u * (depth - k - 1u))) & vec3u(3u));
+        if !has_child(node, bit) {
+            return;
+        }
+        index = child_index(node, bit);
+    }
+
+    var mask = vec2u(0u);
+    for (var c = 0u; c < 64u; c++) {
+        let child = vec3u(c & 3u, (c >> 2u) & 3u, c >> 4u);
+        if is_region_solid(origin + (child << vec3u(child_log)), child_log) {
+            mask[c / 32u] |= 1u << (c % 32u);
+        }
+    }
+
+    var first = 0u;
+    let count = countOneBits(mask.x) + countOneBits(mask.y);
+    if depth + 1u < levels && count != 0u {
+        first = atomicAdd(&node_count, count) + 1u;
+        if first + count > arrayLength(&nodes) {
+            mask = vec2u(0u);
+            first = 0u;
+        }
+    }
+    nodes[index] = vec4u(mask, first, 0u);
+}
@@ -0,0 +1,55 @@
+#import "ray.wgsl"::{ cam, cam_pos, from_volume }
+#import "culling.wgsl"::{ CULL_DIM, brick_index }
+
+// marks the top-level bricks of the dvo that intersect the camera frustum, see `culling.wgsl`.
+// runs before the primary pass of every frame. `cam` is bound to the render camera, see
+// `Frustum::render_camera`.
+
+@group(0) @binding(1)
+var<storage, read_write> visible_bricks_out: array<u32>;
+
+// the frustum is widened by this many pixels, for the msaa and the jittered rays.
+const MARGIN_PIXELS: f32 = 2.0;
+
+// the 4 side planes of the camera frustum, as (normal, offset) with the normals pointing inwards.
+// the near plane is the camera position, where the side planes meet.
+fn frustum_planes() -> array<vec4f, 4> {
+    let tan_y = tan(cam.fov_y / 2.0) * (1.0 + 2.0 * MARGIN_PIXELS / cam.size.y);
+    let tan_x = tan(cam.fov_y / 2.0) * cam.aspect * (1.0 + 2.0 * MARGIN_PIXELS / cam.size.x);
+    // in view space, looking towards +z.
+    var normals = array<vec3f, 4>(
+        vec3f(1.0, 0.0, tan_x),
+        vec3f(-1.0, 0.0, tan_x),
+        vec3f(0.0, 1.0, tan_y),
+        vec3f(0.0, -1.0, tan_y),
+    );
+    var planes: array<vec4f, 4>;
+    for (var i = 0u; i < 4u; i++) {
+        let normal = normalize((cam.view_mat_inv * vec4f(normals[i], 0.0)).xyz);
+        planes[i] = vec4f(normal, -dot(normal, cam_pos()));
+    }
+    return planes;
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn cs_culling(@builtin(global_invocation_id) brick: vec3u) {
+    if any(brick >= vec3u(CULL_DIM)) {
+        return;
+    }
+    let brick_size = f32(2u << #OCTREE_DEPTH) / f32(CULL_DIM);
+    // the planes are in render space, the bricks in world coordinates.
+    let center = from_volume((vec3f(brick) + 0.5) * brick_size);
+    // one more voxel around the brick, for the rounding of the traversal.
+    let half_size = vec3f(brick_size / 2.0 + 1.0);
+
+    var planes = frustum_planes();
+    var visible = true;
+    for (var i = 0u; i < 4u; i++) {
+        // the corner of the box the farthest along the normal.
+        let plane = planes[i];
+        if dot(plane.xyz, center) + dot(abs(plane.xyz), half_size) + plane.w < 0.0 {
+            visible = false;
+        }
+    }
+    visible_bricks_out[brick_index(brick)] = u32(visible);
+}
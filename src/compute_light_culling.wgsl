@@ -0,0 +1,55 @@
+#import "ray.wgsl"::{ cam, view_pos, from_volume }
+#import "lights.wgsl"::{ lights, local_lights, LIGHT_DIRECTIONAL }
+#import "clusters.wgsl"::{ CLUSTER_TILES, CLUSTER_SLICES, MAX_CLUSTER_LIGHTS, CLUSTER_STRIDE, cluster_index, slice_bounds }
+
+// lists the local lights reaching each cluster of the view frustum, see `clusters.wgsl`. runs
+// before the primary pass of every frame, with the lights of the frame. `cam` is bound to the
+// render camera, see `Frustum::render_camera`.
+
+@group(0) @binding(2)
+var<storage, read_write> cluster_lights_out: array<u32>;
+
+// the 4 side planes of the screen tile `tile`, which all pass through the camera. as normals
+// pointing inwards, in view space.
+fn tile_planes(tile: vec2u) -> array<vec3f, 4> {
+    let tan_y = tan(cam.fov_y / 2.0);
+    let tan_xy = vec2f(tan_y * cam.aspect, tan_y);
+    // the slopes of the sides of the tile.
+    let lo = (vec2f(tile) / f32(CLUSTER_TILES) * 2.0 - 1.0) * tan_xy;
+    let hi = (vec2f(tile + 1u) / f32(CLUSTER_TILES) * 2.0 - 1.0) * tan_xy;
+    return array<vec3f, 4>(
+        normalize(vec3f(1.0, 0.0, -lo.x)),
+        normalize(vec3f(-1.0, 0.0, hi.x)),
+        normalize(vec3f(0.0, 1.0, -lo.y)),
+        normalize(vec3f(0.0, -1.0, hi.y)),
+    );
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn cs_light_culling(@builtin(global_invocation_id) cluster: vec3u) {
+    if any(cluster >= vec3u(CLUSTER_TILES, CLUSTER_TILES, CLUSTER_SLICES)) {
+        return;
+    }
+    var planes = tile_planes(cluster.xy);
+    let depth = slice_bounds(cluster.z);
+    let base = cluster_index(cluster) * CLUSTER_STRIDE;
+
+    var count = 0u;
+    for (var i = 0u; i < lights.local_count && count < MAX_CLUSTER_LIGHTS; i++) {
+        let light = local_lights[i];
+        // the directional lights reach every cluster, the others a sphere of their radius.
+        var reaches = true;
+        if light.kind != LIGHT_DIRECTIONAL {
+            let center = view_pos(from_volume(light.pos));
+            reaches = center.z + light.radius > depth.x && center.z - light.radius < depth.y;
+            for (var j = 0u; j < 4u; j++) {
+                reaches = reaches && dot(planes[j], center) > -light.radius;
+            }
+        }
+        if reaches {
+            cluster_lights_out[base + 1u + count] = i;
+            count++;
+        }
+    }
+    cluster_lights_out[base] = count;
+}
@@ -4,6 +4,10 @@ var voxels: texture_storage_3d<r#{OCTREE_FORMAT}int, read>;
 @group(0) @binding(1)
 var dvo: texture_storage_3d<r#{OCTREE_FORMAT}int, write>;
 
+// first node of the dispatched box, the level is recomputed only there after an edit.
+@group(0) @binding(2)
+var<uniform> origin: vec4u;
+
 fn pack_octants(octants: array<bool, 8>) -> u32 {
     return
         u32(octants[0]) << 0u |
@@ -18,7 +22,8 @@ fn pack_octants(octants: array<bool, 8>) -> u32 {
 
 // shader must be run dvo_depth times, for each depth level.
 @compute @workgroup_size(1)
-fn cs_main( @builtin(global_invocation_id) index: vec3u ) {
+fn cs_main( @builtin(global_invocation_id) id: vec3u ) {
+    let index = id + origin.xyz;
     let i2 = index * 2u;
     let octants = array(
         textureLoad(voxels, i2 + vec3(0u, 0u, 0u)).r != 0u,
@@ -0,0 +1,86 @@
+// distance field of the scene at the brick level (4^3 voxels), by jump flooding:
+// 1. cs_seed: every occupied brick is its own nearest seed, read from the octree mip of the bricks.
+// 2. cs_jump: run for step = n/2, n/4, ..., 1. each brick takes the nearest seed among its 26
+//    neighbors at `step` bricks away, ping-ponging between two seed textures.
+// 3. cs_distance: turn the nearest seed into a conservative distance, in bricks.
+// jump flooding is approximate, a few bricks may get a slightly too far seed.
+
+const SDF_BRICK_LEVEL: i32 = 1; // octree mip level of the bricks, 4^3 voxels per texel
+const NO_SEED: u32 = 0xffffu;
+
+struct JumpParams {
+    step: u32,
+}
+
+@group(0) @binding(0)
+var dvo: texture_3d<u32>;
+
+@group(0) @binding(1)
+var seeds_out: texture_storage_3d<rgba16uint, write>;
+
+@group(0) @binding(2)
+var seeds_in: texture_3d<u32>;
+
+@group(0) @binding(3)
+var<uniform> params: JumpParams;
+
+@group(0) @binding(4)
+var sdf_out: texture_storage_3d<r32uint, write>;
+
+@compute @workgroup_size(4, 4, 4)
+fn cs_seed(@builtin(global_invocation_id) index: vec3u) {
+    if any(index >= textureDimensions(seeds_out)) {
+        return;
+    }
+    let occupied = textureLoad(dvo, index, SDF_BRICK_LEVEL).r != 0u;
+    let seed = select(vec3u(NO_SEED), index, occupied);
+    textureStore(seeds_out, index, vec4u(seed, 0u));
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn cs_jump(@builtin(global_invocation_id) index: vec3u) {
+    let dim = textureDimensions(seeds_in);
+    if any(index >= dim) {
+        return;
+    }
+
+    var best = textureLoad(seeds_in, index, 0).xyz;
+    var best_dist = select(1e20, distance(vec3f(index), vec3f(best)), best.x != NO_SEED);
+
+    for (var z = -1; z <= 1; z++) {
+        for (var y = -1; y <= 1; y++) {
+            for (var x = -1; x <= 1; x++) {
+                let coord = vec3i(index) + vec3i(x, y, z) * i32(params.step);
+                if any(coord < vec3i(0)) || any(coord >= vec3i(dim)) {
+                    continue;
+                }
+                let seed = textureLoad(seeds_in, coord, 0).xyz;
+                if seed.x == NO_SEED {
+                    continue;
+                }
+                let dist = distance(vec3f(index), vec3f(seed));
+                if dist < best_dist {
+                    best = seed;
+                    best_dist = dist;
+                }
+            }
+        }
+    }
+
+    textureStore(seeds_out, index, vec4u(best, 0u));
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn cs_distance(@builtin(global_invocation_id) index: vec3u) {
+    if any(index >= textureDimensions(sdf_out)) {
+        return;
+    }
+    let seed = textureLoad(seeds_in, index, 0).xyz;
+    var dist = 255u;
+    if seed.x != NO_SEED {
+        // any point of this brick is at least this far from any point of the seed brick.
+        let center_dist = distance(vec3f(index), vec3f(seed));
+        dist = u32(max(center_dist - sqrt(3.0), 0.0));
+    }
+    textureStore(sdf_out, index, vec4u(dist, 0u, 0u, 0u));
+}
@@ -1,19 +1,24 @@
 #import "bindings.wgsl"::{ colors, linear_sampler, nearest_sampler }
 
-fn conetrace(ray_pos: vec3f, ray_dir: vec3f, tan_angle: f32, start_dist: f32, max_dist: f32) -> vec4f {
+// max_iter is further capped by SHADOW_MAX_ITER.
+fn conetrace(ray_pos: vec3f, ray_dir: vec3f, tan_angle: f32, start_dist: f32, max_dist: f32, max_iter: u32) -> vec4f {
     var res = vec4f(0.0);
 
     let dist_incr = 0.5;
     var dist = start_dist;
     let size = vec3f(textureDimensions(colors, 0u));
 
-    for (var i = 0u; i < #SHADOW_MAX_ITER && dist <= max_dist; i++) {
+    let iters = min(max_iter, #SHADOW_MAX_ITER);
+    for (var i = 0u; i < iters && dist <= max_dist; i++) {
         let pos = ray_pos + ray_dir * dist;
         let radius = tan_angle * dist;
-        let sample = textureSampleLevel(colors, linear_sampler, pos / size, log2(radius));
-        // let sample = textureSampleLevel(colors, colors_sampler, pos / size, 0.0);
-        // this integration is incorrect because it does not take step size into account
-        res = res + (1.0 - res.a) * sample;
+        // samples outside the volume would be clamped to the border voxels.
+        if all(pos >= vec3f(0.0)) && all(pos <= size) {
+            let sample = textureSampleLevel(colors, linear_sampler, pos / size, log2(radius));
+            // let sample = textureSampleLevel(colors, colors_sampler, pos / size, 0.0);
+            // this integration is incorrect because it does not take step size into account
+            res = res + (1.0 - res.a) * sample;
+        }
         // dist += dist_incr;
         dist += radius + dist_incr;
 
@@ -29,16 +34,21 @@ fn cone_spread(cone_angle: f32) -> f32 {
     return 2.0 * tan(cone_angle / 2.0 / 180.0 * 3.1415);
 }
 
-fn trace_shadow(ray_pos: vec3f, ray_dir: vec3f, start_dist: f32) -> f32 {
+// the cone stops at `max_dist`, or 1000 voxels.
+fn trace_shadow(ray_pos: vec3f, ray_dir: vec3f, start_dist: f32, max_dist: f32, max_iter: u32) -> f32 {
     let shadow_spread = cone_spread(f32(#SHADOW_CONE_ANGLE));
-    let max_dist = 1000.0;
-    let sample = conetrace(ray_pos, ray_dir, shadow_spread, start_dist, max_dist);
+    let max_dist = min(max_dist, 1000.0);
+    let sample = conetrace(ray_pos, ray_dir, shadow_spread, start_dist, max_dist, max_iter);
     return sample.a;
 }
 
-fn trace_ao(hit_pos: vec3f, hit_normal: vec3f) -> f32 {
-    let pos = hit_pos + hit_normal * 0.5;
+// the occupancy of the mip `level` just above the surface: coarser levels darken larger creases.
+fn trace_ao(hit_pos: vec3f, hit_normal: vec3f, level: u32) -> f32 {
+    let pos = hit_pos + hit_normal * 0.5 * exp2(f32(level));
     let size = vec3f(textureDimensions(colors, 0u));
-    let sample = textureSampleLevel(colors, linear_sampler, pos / size, 0.0);
+    if any(pos < vec3f(0.0)) || any(pos > size) {
+        return 0.0;
+    }
+    let sample = textureSampleLevel(colors, linear_sampler, pos / size, f32(level));
     return sample.a;
 }
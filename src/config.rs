@@ -0,0 +1,112 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{camera::UpAxis, wgpu_util::ShaderConstants, State};
+
+// startup settings, read from `wender.toml` in the working directory. missing keys take their
+// default value, and the whole file is optional. written by "save current settings" in the ui.
+// the shader constants that describe the scene (depth, baked lighting, noise seed, contours,
+// detail textures) always come from the scene itself.
+
+pub const CONFIG_FILE: &str = "wender.toml";
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Vsync {
+    On,
+    Off,
+}
+
+impl Vsync {
+    pub fn present_mode(self) -> wgpu::PresentMode {
+        match self {
+            Vsync::On => wgpu::PresentMode::AutoVsync,
+            Vsync::Off => wgpu::PresentMode::AutoNoVsync,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// logical size of the window.
+    pub window_size: (u32, u32),
+    pub vsync: Vsync,
+    /// scene opened when none is given on the command line.
+    pub scene: PathBuf,
+    /// camera position at startup, in voxels. the spawn point of the scene if unset.
+    pub spawn: Option<[f32; 3]>,
+    /// mouse look sensitivity, in radians per pixel.
+    pub sensitivity: f64,
+    /// world axis pointing up, for datasets that are not y-up.
+    pub up_axis: UpAxis,
+    pub constants: ShaderConstants,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            window_size: (800, 800),
+            vsync: Vsync::On,
+            scene: PathBuf::from("assets/minecraft_511.wvox"),
+            spawn: None,
+            sensitivity: 0.005,
+            up_axis: UpAxis::Y,
+            constants: Default::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> &'static Path {
+        Path::new(CONFIG_FILE)
+    }
+
+    /// load the config file, or the defaults if there is none.
+    pub fn load() -> Self {
+        let path = Self::path();
+        let Ok(source) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+
+        toml::from_str(&source).unwrap_or_else(|err| {
+            eprintln!("ignoring invalid config `{}`: {}", path.display(), err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        let source = toml::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, source)?;
+        println!("wrote `{}`", path.display());
+        Ok(())
+    }
+
+    /// the current settings, to start with them next time.
+    pub fn capture(state: &State) -> Self {
+        let size = state.size.to_logical::<u32>(state.window.scale_factor());
+        Self {
+            window_size: (size.width, size.height),
+            vsync: match state.config.present_mode {
+                wgpu::PresentMode::AutoNoVsync | wgpu::PresentMode::Immediate => Vsync::Off,
+                _ => Vsync::On,
+            },
+            scene: state.scene_path.clone(),
+            spawn: Some(state.camera.pos().into()),
+            sensitivity: state.controller.sensitivity,
+            up_axis: state.controller.up,
+            constants: state.constants.clone(),
+        }
+    }
+}
+
+/// the scene given on the command line, or the one of the config file.
+pub fn scene_or_default(scene: Option<PathBuf>) -> PathBuf {
+    scene.unwrap_or_else(|| Config::load().scene)
+}
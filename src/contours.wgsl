@@ -0,0 +1,56 @@
+#import "util.wgsl"::{ vmin, vmax, cmpmax }
+#import "bindings.wgsl"::{ contours }
+
+// this shader is a "module" supposed to be included.
+// clips the voxels hit by a ray with their contour, computed by compute_contours.wgsl.
+//
+// this module "exports":
+// fn clip_contour(voxel: vec3u, ray_pos: vec3f, ray_dir: vec3f) -> ContourClip
+
+struct ContourClip {
+    t_in: f32,
+    t_out: f32, // the ray misses the clipped voxel if t_in > t_out.
+    t_exit: f32, // time the ray exits the voxel cube.
+    normal: vec3f,
+}
+
+fn clip_contour(voxel: vec3u, ray_pos: vec3f, ray_dir: vec3f) -> ContourClip {
+    let inv_dir = 1.0 / ray_dir;
+    let t_a = (vec3f(voxel) - ray_pos) * inv_dir;
+    let t_b = (vec3f(voxel + 1u) - ray_pos) * inv_dir;
+    let t_min = min(t_a, t_b);
+    let t_exit = vmin(max(t_a, t_b));
+
+    var res = ContourClip(vmax(t_min), t_exit, t_exit, vec3f(cmpmax(t_min)) * -sign(ray_dir));
+
+    let contour = textureLoad(contours, voxel, 0);
+    if all(contour.xyz == vec3f(0.0)) {
+        return res;
+    }
+
+    // the ray is inside the contour when dist + t * speed <= 0.
+    let normal = normalize(contour.xyz);
+    let center = vec3f(voxel) + 0.5;
+    let dist = dot(normal, ray_pos - center) - contour.w;
+    let speed = dot(normal, ray_dir);
+
+    if abs(speed) < 1e-6 {
+        if dist > 0.0 {
+            res.t_in = res.t_out + 1.0;
+        }
+    }
+    else {
+        let t_plane = -dist / speed;
+        if speed < 0.0 {
+            if t_plane > res.t_in {
+                res.t_in = t_plane;
+                res.normal = normal;
+            }
+        }
+        else {
+            res.t_out = min(res.t_out, t_plane);
+        }
+    }
+
+    return res;
+}
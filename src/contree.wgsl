@@ -0,0 +1,117 @@
+#import "util.wgsl"::{ vmin, vmax, cmpmin, cmpmax }
+#import "octree.wgsl"::{ CastResult }
+#import "bindings.wgsl"::{ contree }
+
+// this shader is a "module" supposed to be included.
+// an alternative to the dvo descent of octree.wgsl: a sparse 64-tree, 4x4x4 children per node,
+// built by compute_contree.wgsl. the wide nodes skip large empty regions in few steps.
+//
+// a node is a vec4u: the 64 bits mask of its occupied children in xy, and in z the index of its
+// first child, the children are contiguous in the order of the mask. the children of the last
+// level are the voxels. the root is the node 0, it covers 4^levels voxels.
+//
+// this module "exports":
+// fn contree_levels() -> u32
+// fn child_bit(child: vec3u) -> u32
+// fn has_child(node: vec4u, bit: u32) -> bool
+// fn child_index(node: vec4u, bit: u32) -> u32
+// fn raycast_contree(ray_pos: vec3f, ray_dir: vec3f) -> CastResult
+//
+// this module "requires":
+// const #OCTREE_DEPTH: u32;
+// const #OCTREE_MAX_ITER: u32 // max number of steps per ray.
+
+const CONTREE_MAX_LEVELS: u32 = 8u;
+
+// the volume is 2^(#OCTREE_DEPTH + 1) voxels wide.
+fn contree_levels() -> u32 {
+    return (#OCTREE_DEPTH + 2u) / 2u;
+}
+
+// bit of a child in the mask, `child` being in 0..4.
+fn child_bit(child: vec3u) -> u32 {
+    return child.x + child.y * 4u + child.z * 16u;
+}
+
+fn has_child(node: vec4u, bit: u32) -> bool {
+    if bit < 32u {
+        return extractBits(node.x, bit, 1u) != 0u;
+    }
+    return extractBits(node.y, bit - 32u, 1u) != 0u;
+}
+
+// index in the node buffer of an occupied child.
+fn child_index(node: vec4u, bit: u32) -> u32 {
+    if bit < 32u {
+        return node.z + countOneBits(extractBits(node.x, 0u, bit));
+    }
+    return node.z + countOneBits(node.x) + countOneBits(extractBits(node.y, 0u, bit - 32u));
+}
+
+fn contree_no_hit(iter: u32) -> CastResult {
+    return CastResult(vec3f(0.1), vec3f(0.0), vec3u(0u), iter, 0.0, false);
+}
+
+fn raycast_contree(ray_pos: vec3f, ray_dir: vec3f) -> CastResult {
+    let levels = contree_levels();
+    let size = f32(2u << #OCTREE_DEPTH);
+    let inv_dir = 1.0 / ray_dir;
+
+    // clip the ray to the volume
+    let t_a = (vec3f(0.0) - ray_pos) * inv_dir;
+    let t_b = (vec3f(size) - ray_pos) * inv_dir;
+    let t_in = max(vmax(min(t_a, t_b)), 0.0);
+    let t_out = vmin(max(t_a, t_b));
+    if t_in > t_out {
+        return contree_no_hit(0u);
+    }
+
+    var t = t_in;
+    var normal = vec3f(cmpmax(min(t_a, t_b))) * -sign(ray_dir);
+
+    // nodes from the root to the current one.
+    var stack: array<u32, CONTREE_MAX_LEVELS>;
+    stack[0] = 0u;
+    var level = 0u;
+    // nudge the sample inside the cell the ray is entering.
+    var voxel = vec3u(clamp(ray_pos + ray_dir * (t + 1e-3), vec3f(0.0), vec3f(size - 1.0)));
+
+    for (var i = 0u; i < #OCTREE_MAX_ITER; i++) {
+        let node = contree[stack[level]];
+        let child_log = 2u * (levels - level - 1u);
+        let bit = child_bit((voxel >> vec3u(child_log)) & vec3u(3u));
+
+        if has_child(node, bit) {
+            if level == levels - 1u {
+                return CastResult(ray_pos + ray_dir * t, normal, voxel, i, t, true);
+            }
+            level += 1u;
+            stack[level] = child_index(node, bit);
+            continue;
+        }
+
+        // step over the empty child
+        let cell_size = f32(1u << child_log);
+        let cell = vec3f((voxel >> vec3u(child_log)) << vec3u(child_log));
+        let next_t = (cell + step(vec3f(0.0), ray_dir) * cell_size - ray_pos) * inv_dir;
+        let mask = cmpmin(next_t);
+        t = vmin(next_t);
+        normal = vec3f(mask) * -sign(ray_dir);
+        if t > t_out {
+            return contree_no_hit(i);
+        }
+
+        // go up to the first node containing the new voxel.
+        let next_voxel = vec3u(clamp(ray_pos + ray_dir * (t + 1e-3), vec3f(0.0), vec3f(size - 1.0)));
+        while level > 0u {
+            let node_log = vec3u(2u * (levels - level));
+            if all((voxel >> node_log) == (next_voxel >> node_log)) {
+                break;
+            }
+            level -= 1u;
+        }
+        voxel = next_voxel;
+    }
+
+    return contree_no_hit(#OCTREE_MAX_ITER);
+}
@@ -0,0 +1,23 @@
+// this shader is a "module" supposed to be included.
+// frustum culling of the top-level bricks of the dvo: before the primary pass, a compute pass
+// (`compute_culling.wgsl`) marks the bricks that intersect the camera frustum in a visibility
+// buffer, and the dvo descent of the primary rays skips the others (see `raycast_culled` in
+// octree.wgsl). the bricks are the octants at depth CULL_DEPTH. the shadow, ao and path traced
+// bounces leave the frustum and are never culled.
+//
+// this module "exports":
+// const CULL_DEPTH: u32
+// const CULL_DIM: u32
+// fn brick_index(brick: vec3u) -> u32
+//
+// CULL_DIM must match `CULL_DIM` in wgpu_util.rs.
+
+// depth of the octants culled, 0 for the 2^3 octants of the root.
+const CULL_DEPTH: u32 = 3u;
+// bricks per axis, 2^(CULL_DEPTH + 1).
+const CULL_DIM: u32 = 16u;
+
+// index of `brick` in the visibility buffer.
+fn brick_index(brick: vec3u) -> u32 {
+    return (brick.z * CULL_DIM + brick.y) * CULL_DIM + brick.x;
+}
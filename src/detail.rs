@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use image::RgbaImage;
+
+use crate::voxels::Voxels;
+
+// detail textures: scenes converted at one voxel per minecraft block can map the original block
+// textures onto the faces of the voxels near the camera, see `detail.wgsl`. the atlas is written
+// by `mca2vox --detail-atlas`: one row of square tiles per palette entry, for the top, side and
+// bottom faces. the tiles are divided by their average color here, so that they modulate the
+// palette color instead of replacing it (e.g. the grayscale grass of minecraft, tinted by biome).
+//
+// detail noise: any scene can give palette entries a noise amplitude in its metadata (see
+// `materials.rs`), which perturbs the albedo and the normal of their voxels with a hash noise, to
+// break up large flat surfaces like stone cliffs.
+
+/// tiles per palette entry in the atlas: top, side, bottom.
+pub const ATLAS_COLUMNS: u32 = 3;
+
+/// the atlas named in the metadata of the scene, if any, normalized around 0.5.
+pub fn load_atlas(voxels: &Voxels) -> Option<RgbaImage> {
+    let name = voxels.meta.detail_textures.as_ref()?;
+    let path = voxels.path.parent().unwrap_or(Path::new("")).join(name);
+    let mut atlas = match image::open(&path) {
+        Ok(image) => image.to_rgba8(),
+        Err(err) => {
+            eprintln!("ignoring the detail textures `{}`: {err}", path.display());
+            return None;
+        }
+    };
+
+    let tile = atlas.width() / ATLAS_COLUMNS;
+    if tile == 0 || atlas.width() % ATLAS_COLUMNS != 0 || atlas.height() % tile != 0 {
+        eprintln!(
+            "ignoring the detail textures `{}`: expected rows of {ATLAS_COLUMNS} square tiles",
+            path.display()
+        );
+        return None;
+    }
+    for row in 0..atlas.height() / tile {
+        for column in 0..ATLAS_COLUMNS {
+            normalize_tile(&mut atlas, column * tile, row * tile, tile);
+        }
+    }
+    Some(atlas)
+}
+
+/// divide the texels of a tile by its average color, which becomes 0.5.
+fn normalize_tile(atlas: &mut RgbaImage, x0: u32, y0: u32, tile: u32) {
+    let texels = || (y0..y0 + tile).flat_map(move |y| (x0..x0 + tile).map(move |x| (x, y)));
+    let mut sum = [0u32; 3];
+    for (x, y) in texels() {
+        let texel = atlas.get_pixel(x, y);
+        for c in 0..3 {
+            sum[c] += texel[c] as u32;
+        }
+    }
+    let average = sum.map(|s| (s / (tile * tile)).max(1) as f32);
+    for (x, y) in texels() {
+        let texel = atlas.get_pixel_mut(x, y);
+        for c in 0..3 {
+            texel[c] = (texel[c] as f32 / average[c] * 127.5).min(255.0) as u8;
+        }
+    }
+}
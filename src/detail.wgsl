@@ -0,0 +1,76 @@
+#import "bindings.wgsl"::{ detail_atlas, voxel_ids }
+#import "noise.wgsl"::{ value_noise }
+
+// this shader is a "module" supposed to be included.
+// the block textures of the palette entries mapped on the voxel faces near the camera, see
+// `detail.rs` for the atlas layout.
+//
+// this module "exports":
+// fn apply_detail(albedo: vec4f, voxel: vec3u, hit_pos: vec3f, hit_normal: vec3f, dist: f32) -> vec4f
+// fn noisy_albedo(albedo: vec4f, hit_pos: vec3f, amplitude: f32) -> vec4f
+// fn noisy_normal(hit_normal: vec3f, hit_pos: vec3f, amplitude: f32) -> vec3f
+//
+// this module "requires":
+// const DETAIL_TEXTURES: u32; // 1 if the scene has an atlas
+// const DETAIL_DISTANCE: u32; // distance where the textures have faded out, in voxels
+// const NOISE_SEED: u32;
+
+const ATLAS_COLUMNS: u32 = 3u; // top, side, bottom
+// noise cells per voxel.
+const DETAIL_NOISE_FREQ: f32 = 4.0;
+
+// modulate the albedo of a hit voxel with its texture, fading out with the distance `dist`.
+fn apply_detail(albedo: vec4f, voxel: vec3u, hit_pos: vec3f, hit_normal: vec3f, dist: f32) -> vec4f {
+    let max_dist = f32(#DETAIL_DISTANCE);
+    if #DETAIL_TEXTURES == 0u || dist >= max_dist {
+        return albedo;
+    }
+    let size = textureDimensions(detail_atlas);
+    let tile = size.x / ATLAS_COLUMNS;
+    let material = textureLoad(voxel_ids, voxel, 0).r;
+    if tile == 0u || material == 0u || material > size.y / tile {
+        return albedo;
+    }
+
+    // the texture of a face is seen from outside, upright on the sides.
+    let local = saturate(hit_pos - vec3f(voxel));
+    var uv = vec2f(local.x, 1.0 - local.y);
+    var column = 1u;
+    if abs(hit_normal.y) > 0.5 {
+        uv = local.xz;
+        column = select(2u, 0u, hit_normal.y > 0.0);
+    }
+    else if abs(hit_normal.x) > 0.5 {
+        uv = vec2f(local.z, 1.0 - local.y);
+    }
+
+    let texel = min(vec2u(uv * f32(tile)), vec2u(tile - 1u)) + vec2u(column, material - 1u) * tile;
+    let detail = textureLoad(detail_atlas, texel, 0).rgb * 2.0;
+    let fade = 1.0 - smoothstep(max_dist * 0.5, max_dist, dist);
+    return vec4f(albedo.rgb * mix(vec3f(1.0), detail, fade), albedo.a);
+}
+
+// darken or lighten the albedo by up to `amplitude`, with two octaves of value noise.
+fn noisy_albedo(albedo: vec4f, hit_pos: vec3f, amplitude: f32) -> vec4f {
+    if amplitude == 0.0 {
+        return albedo;
+    }
+    let p = hit_pos * DETAIL_NOISE_FREQ;
+    let n = value_noise(p, #NOISE_SEED) * 0.67 + value_noise(p * 2.0, #NOISE_SEED + 1u) * 0.33;
+    return vec4f(albedo.rgb * (1.0 + amplitude * (n * 2.0 - 1.0)), albedo.a);
+}
+
+// tilt the normal along the surface by up to `amplitude` / 2, with a noise per axis.
+fn noisy_normal(hit_normal: vec3f, hit_pos: vec3f, amplitude: f32) -> vec3f {
+    if amplitude == 0.0 {
+        return hit_normal;
+    }
+    let p = hit_pos * DETAIL_NOISE_FREQ;
+    let offset = vec3f(
+        value_noise(p, #NOISE_SEED + 2u),
+        value_noise(p, #NOISE_SEED + 3u),
+        value_noise(p, #NOISE_SEED + 4u),
+    ) - 0.5;
+    let tangent = offset - hit_normal * dot(offset, hit_normal);
+    return normalize(hit_normal + tangent * amplitude);
+}
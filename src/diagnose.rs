@@ -0,0 +1,226 @@
+use std::{
+    borrow::Cow,
+    fmt::Write,
+    fs,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use wgpu::*;
+
+use crate::{
+    capture::timestamped_path,
+    cli::Backend,
+    features,
+    preproc::{self, preprocess_shader},
+    wgpu_util::{ShaderConstants, COLORS_FORMAT, OCTREE_FORMAT},
+};
+
+// `--diagnose`: a self-test of the gpu, printed and written to a report file. users can attach
+// the report to performance complaints.
+
+/// size of the buffer uploaded to measure the bandwidth.
+const UPLOAD_SIZE: u64 = 64 << 20;
+const UPLOAD_RUNS: usize = 4;
+/// number of invocations of the compute benchmark.
+const COMPUTE_INVOCATIONS: u32 = 1 << 20;
+const COMPUTE_RUNS: usize = 4;
+
+/// formats used by the renderer, and what they are used for.
+fn formats() -> [(TextureFormat, &'static str); 4] {
+    [
+        (OCTREE_FORMAT, "octree"),
+        (COLORS_FORMAT, "colors"),
+        (TextureFormat::Rg8Unorm, "lightmap"),
+        (TextureFormat::Rgba8UnormSrgb, "captures"),
+    ]
+}
+
+fn best_of(runs: usize, mut f: impl FnMut() -> Duration) -> Duration {
+    (0..runs).map(|_| f()).min().unwrap_or_default()
+}
+
+fn measure_upload(device: &Device, queue: &Queue) -> Duration {
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("diagnose upload buffer"),
+        size: UPLOAD_SIZE,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let data = vec![0xA5u8; UPLOAD_SIZE as usize];
+
+    best_of(UPLOAD_RUNS, || {
+        let start = Instant::now();
+        queue.write_buffer(&buffer, 0, &data);
+        queue.submit(None);
+        device.poll(Maintain::Wait);
+        start.elapsed()
+    })
+}
+
+fn measure_compute(device: &Device, queue: &Queue) -> Option<Duration> {
+    let constants = ShaderConstants::default().to_hashmap();
+    let preproc_ctx = preproc::Context {
+        main: &PathBuf::from_str("src/benchmark.wgsl").unwrap(),
+        constants: &constants,
+    };
+    let module = match preprocess_shader(&preproc_ctx) {
+        Ok(module) => module,
+        Err(err) => {
+            eprintln!("preproc error: {}", err);
+            return None;
+        }
+    };
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("benchmark"),
+        source: ShaderSource::Naga(Cow::Owned(module)),
+    });
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("benchmark pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: "cs_main",
+        compilation_options: Default::default(),
+    });
+
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("benchmark buffer"),
+        size: COMPUTE_INVOCATIONS as u64 * 4,
+        usage: BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("benchmark bind group"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+
+    let dispatch = || {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("benchmark encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("benchmark pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(COMPUTE_INVOCATIONS / 64, 1, 1);
+        }
+        let start = Instant::now();
+        queue.submit(Some(encoder.finish()));
+        device.poll(Maintain::Wait);
+        start.elapsed()
+    };
+
+    // the first dispatch includes the pipeline compilation by the driver.
+    dispatch();
+    Some(best_of(COMPUTE_RUNS, dispatch))
+}
+
+async fn report(backend: Backend) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    let instance = Instance::new(InstanceDescriptor {
+        backends: backend.backends(),
+        ..Default::default()
+    });
+
+    writeln!(out, "wender {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(out, "{}", features::describe())?;
+
+    let Some(adapter) = instance
+        .request_adapter(&RequestAdapterOptions::default())
+        .await
+    else {
+        writeln!(
+            out,
+            "no compatible adapter found for the backends {:?}",
+            backend.backends()
+        )?;
+        return Ok(out);
+    };
+
+    writeln!(out, "\n# adapter\n{:#?}", adapter.get_info())?;
+    writeln!(out, "\n# limits\n{:#?}", adapter.limits())?;
+    writeln!(out, "\n# features\n{:?}", adapter.features())?;
+
+    writeln!(out, "\n# formats")?;
+    for (format, usage) in formats() {
+        let support = adapter.get_texture_format_features(format);
+        writeln!(
+            out,
+            "{format:?} ({usage}): usages {:?}, flags {:?}",
+            support.allowed_usages, support.flags
+        )?;
+    }
+    match features::validate_adapter(&adapter) {
+        Ok(()) => writeln!(out, "the adapter supports the enabled features")?,
+        Err(err) => writeln!(out, "UNSUPPORTED: {err}")?,
+    }
+    // the viewer requests the adapter specific format features when there are some.
+    let mip_path = features::mip_path(&adapter, adapter.features());
+    writeln!(out, "color mipmaps: {mip_path:?}")?;
+
+    let device = adapter
+        .request_device(
+            &DeviceDescriptor {
+                label: Some("diagnose device"),
+                required_features: Features::empty(),
+                required_limits: adapter.limits(),
+            },
+            None,
+        )
+        .await;
+    let (device, queue) = match device {
+        Ok(device) => device,
+        Err(err) => {
+            writeln!(out, "\nfailed to create a device: {err}")?;
+            return Ok(out);
+        }
+    };
+
+    writeln!(out, "\n# benchmarks")?;
+    let upload = measure_upload(&device, &queue);
+    let mib = (UPLOAD_SIZE >> 20) as f64;
+    writeln!(
+        out,
+        "upload: {mib} MiB in {:.2?} ({:.0} MiB/s)",
+        upload,
+        mib / upload.as_secs_f64()
+    )?;
+    match measure_compute(&device, &queue) {
+        Some(time) => writeln!(
+            out,
+            "compute: {COMPUTE_INVOCATIONS} invocations in {:.2?}",
+            time
+        )?,
+        None => writeln!(out, "compute: failed to build the benchmark shader")?,
+    }
+
+    Ok(out)
+}
+
+/// print the diagnostics report and write it to a file. returns whether the report was written.
+pub fn diagnose(backend: Backend) -> bool {
+    let report = pollster::block_on(report(backend)).expect("formatting to a string never fails");
+    println!("{report}");
+
+    let path = timestamped_path("diagnose", "txt");
+    match fs::write(&path, report) {
+        Ok(()) => {
+            println!("wrote `{}`", path.display());
+            true
+        }
+        Err(err) => {
+            eprintln!("failed to write `{}`: {}", path.display(), err);
+            false
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use ndarray::{Array3, ArrayView2, Axis, Zip};
+use serde::{Deserialize, Serialize};
+
+use crate::voxels::VoxelsFormat;
+
+// the dvo (dense voxel octree) is built on the gpu by `compute_octree.wgsl`. it can be read back,
+// saved next to the scene (`scene.wvox` -> `scene.dvo`) and compared against the cpu reference
+// builder below. the startup cache of the octree is in `cache.rs`.
+//
+// level 0 is the finest, a node per 2^3 voxels. each bit of a node tells whether an octant is
+// solid, the octant (x, y, z) in texture axes being bit x * 4 + y * 2 + z. levels are stored in
+// array axes (z, y, x), like the gpu textures.
+
+#[derive(Serialize, Deserialize)]
+pub struct Dvo {
+    pub levels: Vec<Array3<VoxelsFormat>>,
+}
+
+fn build_level(children: &Array3<VoxelsFormat>) -> Array3<VoxelsFormat> {
+    Zip::from(children.exact_chunks((2, 2, 2))).par_map_collect(|chunk| {
+        let mut node = 0;
+        for ((z, y, x), child) in chunk.indexed_iter() {
+            if *child != 0 {
+                node |= 1 << (x * 4 + y * 2 + z);
+            }
+        }
+        node
+    })
+}
+
+impl Dvo {
+    pub fn path(scene: &Path) -> PathBuf {
+        scene.with_extension("dvo")
+    }
+
+    /// build the octree on the cpu, the same way as the gpu.
+    #[tracing::instrument(skip_all)]
+    pub fn reference(voxels: &Array3<VoxelsFormat>) -> Self {
+        let mut levels = vec![build_level(voxels)];
+        while levels.last().unwrap().dim().0 > 1 {
+            levels.push(build_level(levels.last().unwrap()));
+        }
+        Self { levels }
+    }
+
+    /// number of nodes that differ, per level.
+    pub fn mismatches(&self, other: &Self) -> Vec<usize> {
+        self.levels
+            .iter()
+            .zip(&other.levels)
+            .map(|(a, b)| {
+                if a.dim() != b.dim() {
+                    return a.len().max(b.len());
+                }
+                Zip::from(a).and(b).fold(0, |n, a, b| n + (a != b) as usize)
+            })
+            .collect()
+    }
+
+    /// a z slice of a level.
+    pub fn slice(&self, level: usize, z: usize) -> ArrayView2<VoxelsFormat> {
+        self.levels[level].index_axis(Axis(0), z)
+    }
+
+    #[tracing::instrument(skip_all, fields(path = %path.display()))]
+    pub fn save(&self, path: &Path) -> bincode::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(&mut file, self)?;
+        println!("wrote `{}`", path.display());
+        Ok(())
+    }
+}
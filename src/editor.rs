@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+use nalgebra_glm as glm;
+
+use crate::palette_file::Remap;
+
+// builder mode: the selected palette entry, brush and grid snapping of the voxel editor, and the
+// voxel under the crosshair. the hud is drawn in ui.rs.
+
+/// grid snapping steps, in voxels.
+pub const SNAPS: [u32; 4] = [1, 2, 4, 8];
+/// number of recently used palette entries in the hotbar, bound to the keys 1 to 9.
+pub const HOTBAR_LEN: usize = 9;
+pub const MAX_BRUSH: u32 = 16;
+
+pub struct Editor {
+    pub enabled: bool,
+    /// palette of the scene, see `Voxels::palette`.
+    pub palette: Vec<[u8; 4]>,
+    /// selected palette entry, 1-based like the voxels.
+    pub material: u32,
+    /// side of the brush cube, in voxels.
+    pub brush: u32,
+    pub snap: u32,
+    /// voxel under the crosshair, snapped to the grid.
+    pub target: Option<glm::UVec3>,
+    /// normal of the targeted face, new voxels are placed on this side.
+    pub normal: glm::IVec3,
+    /// recently used palette entries, most recent first.
+    pub recent: VecDeque<u32>,
+    /// how imported palettes recolor the scene.
+    pub remap: Remap,
+}
+
+impl Editor {
+    pub fn new(palette: &[[u8; 4]]) -> Self {
+        let mut editor = Self {
+            enabled: false,
+            palette: Vec::new(),
+            material: 1,
+            brush: 1,
+            snap: 1,
+            target: None,
+            normal: glm::IVec3::zeros(),
+            recent: VecDeque::new(),
+            remap: Remap::Nearest,
+        };
+        editor.set_palette(palette);
+        editor.select(1);
+        editor
+    }
+
+    /// the palette of a new scene. the selection is kept when it still exists.
+    pub fn set_palette(&mut self, palette: &[[u8; 4]]) {
+        let len = palette.len() as u32;
+        self.palette = palette.to_vec();
+        self.recent.retain(|i| *i <= len);
+        if self.material > len {
+            self.material = 1;
+        }
+    }
+
+    pub fn select(&mut self, material: u32) {
+        if material == 0 || material as usize > self.palette.len() {
+            return;
+        }
+        self.material = material;
+        self.recent.retain(|i| *i != material);
+        self.recent.push_front(material);
+        self.recent.truncate(HOTBAR_LEN);
+    }
+
+    /// select the nth entry of the hotbar, 0-based.
+    pub fn select_recent(&mut self, n: usize) {
+        if let Some(material) = self.recent.get(n).copied() {
+            self.select(material);
+        }
+    }
+
+    /// color of a palette entry, 1-based.
+    pub fn color(&self, material: u32) -> Option<[u8; 4]> {
+        self.palette
+            .get((material as usize).checked_sub(1)?)
+            .copied()
+    }
+
+    pub fn cycle_snap(&mut self) {
+        let i = SNAPS.iter().position(|s| *s == self.snap).unwrap_or(0);
+        self.snap = SNAPS[(i + 1) % SNAPS.len()];
+    }
+
+    pub fn grow_brush(&mut self, delta: i32) {
+        self.brush = self.brush.saturating_add_signed(delta).clamp(1, MAX_BRUSH);
+    }
+
+    /// the box covered by the brush at the target, `min..max` in world coordinates.
+    pub fn brush_box(&self) -> Option<(glm::UVec3, glm::UVec3)> {
+        let min = self.target?;
+        Some((min, min + glm::UVec3::repeat(self.brush)))
+    }
+
+    /// the box against the targeted face, where the brush is placed.
+    pub fn place_box(&self) -> Option<(glm::UVec3, glm::UVec3)> {
+        let step = self.brush.max(self.snap) as i32;
+        let min = self.target?.map(|c| c as i32) + self.normal * step;
+        if min.iter().any(|c| *c < 0) {
+            return None;
+        }
+        let min = min.map(|c| c as u32);
+        Some((min, min + glm::UVec3::repeat(self.brush)))
+    }
+
+    /// the voxel `cell`, snapped down to the grid.
+    pub fn snapped(&self, cell: glm::UVec3) -> glm::UVec3 {
+        cell.map(|c| c / self.snap * self.snap)
+    }
+}
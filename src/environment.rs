@@ -0,0 +1,120 @@
+use nalgebra_glm as glm;
+
+use crate::scene::SceneMeta;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum GroundMode {
+    None = 0,
+    Flat = 1,
+    Checker = 2,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum BackgroundMode {
+    Solid = 0,
+    Gradient = 1,
+    Sky = 2,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BackgroundPreset {
+    StudioGrey,
+    Black,
+    White,
+}
+
+impl BackgroundPreset {
+    pub const ALL: [Self; 3] = [Self::StudioGrey, Self::Black, Self::White];
+}
+
+// !! careful with the alignments! add padding fields if necessary.
+// see https://www.w3.org/TR/WGSL/#alignment-and-size
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EnvironmentUniform {
+    pub ground_color: glm::Vec3,
+    pub ground_mode: u32,
+    pub checker_color: glm::Vec3,
+    pub checker_size: f32, // voxels
+    pub fog_color: glm::Vec3,
+    pub fog_distance: f32, // voxels
+    pub ground_height: f32,
+    pub fog_enabled: u32,
+    pub background_mode: u32,
+    _pad: f32,                       // padding to ensure correct alignment
+    pub background_color: glm::Vec3, // solid color, or gradient zenith
+    _pad1: f32,
+    pub background_horizon: glm::Vec3, // gradient horizon
+    _pad2: f32,
+}
+
+/// what rays see when they miss the voxel volume: background, ground plane and horizon fog.
+pub struct Environment {
+    pub uniform: EnvironmentUniform,
+    pub background_mode: BackgroundMode,
+    pub ground_mode: GroundMode,
+    pub fog_enabled: bool,
+    pub fog_distance: f32, // meters
+    pub checker_size: f32, // meters
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self {
+            uniform: EnvironmentUniform {
+                ground_color: glm::vec3(0.35, 0.33, 0.3),
+                ground_mode: GroundMode::None as u32,
+                checker_color: glm::vec3(0.25, 0.24, 0.22),
+                checker_size: 8.0,
+                fog_color: glm::vec3(0.6, 0.65, 0.7),
+                fog_distance: 1000.0,
+                ground_height: 0.0,
+                fog_enabled: 0,
+                background_mode: BackgroundMode::Solid as u32,
+                _pad: Default::default(),
+                background_color: glm::vec3(0.1, 0.1, 0.1),
+                _pad1: Default::default(),
+                background_horizon: glm::vec3(0.3, 0.3, 0.3),
+                _pad2: Default::default(),
+            },
+            background_mode: BackgroundMode::Solid,
+            ground_mode: GroundMode::None,
+            fog_enabled: false,
+            fog_distance: 1000.0,
+            checker_size: 8.0,
+        }
+    }
+
+    pub fn apply_preset(&mut self, preset: BackgroundPreset) {
+        let (mode, color, horizon) = match preset {
+            BackgroundPreset::StudioGrey => (
+                BackgroundMode::Gradient,
+                glm::vec3(0.18, 0.18, 0.18),
+                glm::vec3(0.45, 0.45, 0.45),
+            ),
+            BackgroundPreset::Black => (BackgroundMode::Solid, glm::zero(), glm::zero()),
+            BackgroundPreset::White => (
+                BackgroundMode::Solid,
+                glm::vec3(1.0, 1.0, 1.0),
+                glm::vec3(1.0, 1.0, 1.0),
+            ),
+        };
+        self.background_mode = mode;
+        self.uniform.background_color = color;
+        self.uniform.background_horizon = horizon;
+    }
+
+    pub fn update(&mut self, meta: &SceneMeta) {
+        self.uniform.background_mode = self.background_mode as u32;
+        self.uniform.ground_mode = self.ground_mode as u32;
+        self.uniform.fog_enabled = self.fog_enabled as u32;
+        self.uniform.fog_distance = meta.to_voxels(self.fog_distance);
+        self.uniform.checker_size = meta.to_voxels(self.checker_size);
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.uniform)
+    }
+}
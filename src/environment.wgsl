@@ -0,0 +1,48 @@
+#import "settings.wgsl"::{ feature_enabled, FEATURE_GROUND }
+#import "ray.wgsl"::{ to_volume, from_volume }
+
+// this shader is a "module" supposed to be included.
+//
+// this module "exports":
+// var<uniform> env: Environment
+// fn ground_t(ray_pos: vec3f, ray_dir: vec3f) -> f32
+// fn ground_albedo(pos: vec3f) -> vec3f
+
+struct Environment {
+    ground_color: vec3f,
+    ground_mode: u32, // 0: none, 1: flat, 2: checker
+    checker_color: vec3f,
+    checker_size: f32,
+    fog_color: vec3f,
+    fog_distance: f32,
+    ground_height: f32,
+    fog_enabled: u32,
+    background_mode: u32, // 0: solid, 1: gradient, 2: sky
+    background_color: vec3f,
+    background_horizon: vec3f,
+}
+
+@group(0) @binding(4)
+var<uniform> env: Environment;
+
+// distance to the infinite ground plane, or -1.0 if the ray does not hit it. `ray_pos` is in
+// render space.
+fn ground_t(ray_pos: vec3f, ray_dir: vec3f) -> f32 {
+    if env.ground_mode == 0u || !feature_enabled(FEATURE_GROUND) || ray_dir.y >= 0.0 {
+        return -1.0;
+    }
+    let height = from_volume(vec3f(0.0, env.ground_height, 0.0)).y;
+    let t = (height - ray_pos.y) / ray_dir.y;
+    return select(-1.0, t, t > 0.0);
+}
+
+// the checker is anchored to the world, `pos` is in render space.
+fn ground_albedo(pos: vec3f) -> vec3f {
+    if env.ground_mode == 2u {
+        let cell = vec2i(floor(to_volume(pos).xz / env.checker_size));
+        if ((cell.x + cell.y) & 1) != 0 {
+            return env.checker_color;
+        }
+    }
+    return env.ground_color;
+}
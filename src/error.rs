@@ -0,0 +1,123 @@
+use std::{io, path::PathBuf};
+
+use thiserror::Error;
+
+use crate::{features, palette_file, route, session, stream, voxels};
+
+// errors surfaced to the user, with a hint on how to fix them. startup errors are shown in a
+// native message box since there is no ui yet, runtime errors in an egui window.
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to load the scene: {0}")]
+    SceneError(#[from] voxels::Error),
+    #[error("failed to stream the scene: {0}")]
+    StreamError(#[from] stream::Error),
+    #[error("failed to restore the session: {0}")]
+    SessionError(#[from] session::Error),
+    #[error("failed to create the window: {0}")]
+    WindowError(#[from] winit::error::OsError),
+    #[error("failed to create the event loop: {0}")]
+    EventLoopError(#[from] winit::error::EventLoopError),
+    #[error("failed to create the window surface: {0}")]
+    SurfaceError(#[from] wgpu::CreateSurfaceError),
+    #[error("no compatible gpu adapter found")]
+    NoAdapter,
+    #[error("{0}")]
+    FeatureError(#[from] features::Error),
+    #[error("failed to create the gpu device: {0}")]
+    DeviceError(#[from] wgpu::RequestDeviceError),
+    #[error("the scene is {dim}^3 voxels, but this gpu supports 3d textures up to {max}^3")]
+    SceneTooLarge { dim: u32, max: u32 },
+    #[error("failed to compile the shaders, see the log for details")]
+    ShaderError,
+    #[error("failed to write the image: {0}")]
+    ImageError(#[from] image::ImageError),
+    #[error("failed to import the palette: {0}")]
+    PaletteError(#[from] palette_file::Error),
+    #[error("failed to load the camera path: {0}")]
+    RouteError(#[from] route::Error),
+    #[error("the camera path `{0}` has no points")]
+    EmptyCameraPath(PathBuf),
+    #[error("failed to write `{0}`: {1}")]
+    IOError(PathBuf, io::Error),
+    #[error("failed to read `{0}`: {1}")]
+    ReadError(PathBuf, io::Error),
+    #[error("invalid frame hashes `{0}`: {1}")]
+    HashesError(PathBuf, serde_json::Error),
+}
+
+impl Error {
+    /// what the user can do about it.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Error::SceneError(voxels::Error::IOError(..)) => {
+                "check the scene path given as first argument."
+            }
+            Error::SceneError(voxels::Error::FeatureError(_)) | Error::FeatureError(_) => {
+                "rebuild with different cargo features, see `--diagnose`."
+            }
+            Error::SceneError(voxels::Error::VoxError(..)) => {
+                "the file is not a valid MagicaVoxel .vox scene, save it again from MagicaVoxel."
+            }
+            Error::SceneError(_) => "the file is not a valid .wvox scene, convert it again.",
+            Error::StreamError(stream::Error::FetchError(..)) => {
+                "check the scene url and the network. the server must allow range requests."
+            }
+            Error::StreamError(stream::Error::SceneError(_)) => {
+                "the scene is not supported, see `--diagnose`."
+            }
+            Error::StreamError(_) => "the file is not a valid .wchunks scene, export it again.",
+            Error::SessionError(session::Error::SceneError(_)) => {
+                "the scene of the session was moved or changed, open it directly."
+            }
+            Error::SessionError(_) => "delete `.wender-session` to start from scratch.",
+            Error::WindowError(_) | Error::EventLoopError(_) => {
+                "a graphical session (x11, wayland, windows or macos) is required."
+            }
+            Error::SurfaceError(_) | Error::NoAdapter | Error::DeviceError(_) => {
+                "a gpu with vulkan, metal, dx12 or opengl support is required: update the \
+                 graphics drivers, try another `--backend`, and run `--diagnose` for details."
+            }
+            Error::SceneTooLarge { .. } => {
+                "scene too large for this gpu: downscale it, or crop it when converting."
+            }
+            Error::ShaderError => "run `--check-shaders` to locate the error.",
+            Error::ImageError(_) => {
+                "check the output path, the format is picked from its extension."
+            }
+            Error::PaletteError(_) => {
+                "use a png strip, a JASC .pal or a lospec .hex file, the format is picked from \
+                 its extension."
+            }
+            Error::RouteError(_) | Error::EmptyCameraPath(_) => {
+                "use a json array of `[x, y, z]` points, or a csv file of `x,y,z` lines."
+            }
+            Error::IOError(..) => "check that the output directory is writable.",
+            Error::ReadError(..) | Error::HashesError(..) => {
+                "pass the `hashes.json` written by a previous `--hash` run."
+            }
+        }
+    }
+}
+
+/// report an error that prevents the program from starting.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn show_fatal(err: &Error) {
+    eprintln!("error: {err}\nhint: {}", err.hint());
+
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title("Wender")
+        .set_description(format!("{err}\n\n{}", err.hint()))
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn show_fatal(err: &Error) {
+    log::error!("{err}\nhint: {}", err.hint());
+    if let Some(window) = web_sys::window() {
+        let _ = window.alert_with_message(&format!("{err}\n\n{}", err.hint()));
+    }
+}
@@ -0,0 +1,101 @@
+use web_time::Instant;
+
+// automatic exposure (eye adaptation): the fragment shader counts the log2 luminance of the
+// shaded pixels before exposure in a histogram (see `exposure.wgsl`), which is read back every
+// few frames. the exposure then moves smoothly towards the one mapping the average luminance to
+// middle gray, so walking from bright terrain into a cave brightens the view over a second or so.
+
+pub const LUMA_HISTOGRAM_BINS: usize = 64;
+/// log2 luminance range of the histogram, darker and brighter pixels land in the end bins.
+const MIN_LOG_LUMA: f32 = -10.0;
+const MAX_LOG_LUMA: f32 = 6.0;
+/// luminance the average pixel is exposed to.
+const MIDDLE_GRAY: f32 = 0.18;
+
+pub struct AutoExposure {
+    /// record the histogram and drive `LightsUniform::exposure`. changing it requires reloading
+    /// the shaders.
+    pub enabled: bool,
+    /// bounds of the exposure, in EV (log2 of the exposure multiplier).
+    pub min_ev: f32,
+    pub max_ev: f32,
+    /// adaptation rate, in 1/s: the exposure closes 63% of the gap to the target in 1/speed
+    /// seconds.
+    pub speed: f32,
+    /// the exposure the adaptation converges to, in EV.
+    pub target_ev: Option<f32>,
+    frame: u32,
+    last_adapt: Option<Instant>,
+}
+
+impl AutoExposure {
+    /// frames accumulated in the histogram between two readbacks.
+    pub const INTERVAL: u32 = 8;
+    /// fractions of the darkest and brightest pixels left out of the average, e.g. the sun disc.
+    const LOW_CUT: f32 = 0.1;
+    const HIGH_CUT: f32 = 0.05;
+
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            min_ev: -3.0,
+            max_ev: 3.0,
+            speed: 1.5,
+            target_ev: None,
+            frame: 0,
+            last_adapt: None,
+        }
+    }
+
+    /// count a rendered frame, returns whether the histogram should be read back.
+    pub fn tick(&mut self) -> bool {
+        if !self.enabled {
+            self.last_adapt = None;
+            return false;
+        }
+        self.frame += 1;
+        self.frame % Self::INTERVAL == 0
+    }
+
+    /// average log2 luminance of the histogram, without the cut ends.
+    fn average_log_luma(histogram: &[u32]) -> Option<f32> {
+        let total = histogram.iter().map(|&n| n as u64).sum::<u64>();
+        let lo = (total as f32 * Self::LOW_CUT) as u64;
+        let hi = (total as f32 * (1.0 - Self::HIGH_CUT)) as u64;
+        let bin_width = (MAX_LOG_LUMA - MIN_LOG_LUMA) / (histogram.len() - 1) as f32;
+
+        let (mut count, mut sum, mut weight) = (0, 0.0, 0);
+        for (bin, &n) in histogram.iter().enumerate() {
+            let n = n as u64;
+            // the part of the bin between the cuts.
+            let kept = (count + n).min(hi).saturating_sub(count.max(lo));
+            sum += kept as f32 * (MIN_LOG_LUMA + (bin as f32 + 0.5) * bin_width);
+            weight += kept;
+            count += n;
+        }
+        (weight > 0).then(|| sum / weight as f32)
+    }
+
+    /// update the target exposure from a histogram read back from the gpu.
+    pub fn measure(&mut self, histogram: &[u32]) {
+        if let Some(log_luma) = Self::average_log_luma(histogram) {
+            let ev = MIDDLE_GRAY.log2() - log_luma;
+            self.target_ev = Some(ev.clamp(self.min_ev, self.max_ev.max(self.min_ev)));
+        }
+    }
+
+    /// move `exposure` towards the target by the time elapsed since the last call.
+    pub fn adapt(&mut self, exposure: &mut f32) {
+        let now = Instant::now();
+        let dt = self
+            .last_adapt
+            .map_or(0.0, |last| (now - last).as_secs_f32());
+        self.last_adapt = Some(now);
+        let Some(target) = self.target_ev else {
+            return;
+        };
+        let ev = exposure.max(1e-3).log2();
+        let ev = ev + (target - ev) * (1.0 - (-dt * self.speed).exp());
+        *exposure = ev.exp2();
+    }
+}
@@ -0,0 +1,24 @@
+// this shader is a "module" supposed to be included.
+// histogram of the log2 luminance of the shaded pixels before exposure, read back by `exposure.rs`.
+//
+// this module "exports":
+// fn record_luminance(color: vec3f)
+//
+// this module "requires":
+// const AUTO_EXPOSURE: u32; // 1 to record the histogram
+
+const LUMA_HISTOGRAM_BINS: u32 = 64u; // see exposure.rs
+const MIN_LOG_LUMA: f32 = -10.0;
+const MAX_LOG_LUMA: f32 = 6.0;
+
+@group(0) @binding(11)
+var<storage, read_write> luma_histogram: array<atomic<u32>, LUMA_HISTOGRAM_BINS>;
+
+fn record_luminance(color: vec3f) {
+    if #AUTO_EXPOSURE == 1u {
+        let luma = dot(color, vec3f(0.2126, 0.7152, 0.0722));
+        let x = (log2(max(luma, 1e-6)) - MIN_LOG_LUMA) / (MAX_LOG_LUMA - MIN_LOG_LUMA);
+        let bin = u32(saturate(x) * f32(LUMA_HISTOGRAM_BINS - 1u));
+        atomicAdd(&luma_histogram[bin], 1u);
+    }
+}
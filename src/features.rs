@@ -0,0 +1,77 @@
+use thiserror::Error;
+use wgpu::{Adapter, Features, TextureFormat, TextureFormatFeatureFlags, TextureUsages};
+
+use crate::{
+    voxels::VoxelsFormat,
+    wgpu_util::{MipPath, COLORS_FORMAT, OCTREE_FORMAT},
+};
+
+// cargo features change the gpu texture formats at compile time. they are validated at startup
+// against the adapter and the scene, to fail with a clear message instead of corrupt visuals.
+// the colors mip chain adapts instead: without storage textures of the colors format, the
+// mipmaps are computed through a buffer, see `MipPath`.
+
+pub const FEATURES: &[(&str, bool)] = &[
+    ("byte_voxels", cfg!(feature = "byte_voxels")),
+    ("f16_colors", cfg!(feature = "f16_colors")),
+];
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("the scene palette has {0} colors, but voxels are stored on {1} bits (feature `byte_voxels`): rebuild without it")]
+    PaletteTooLarge(usize, u32),
+    #[error("the adapter does not support {0:?} as a read-write storage texture, needed for the {1} (see feature `{2}`)")]
+    UnsupportedFormat(TextureFormat, &'static str, &'static str),
+}
+
+/// a one-line summary of the enabled features and the formats they select.
+pub fn describe() -> String {
+    let features = FEATURES
+        .iter()
+        .map(|(name, enabled)| format!("{name}={}", if *enabled { "on" } else { "off" }))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("features: {features} (octree: {OCTREE_FORMAT:?}, colors: {COLORS_FORMAT:?})")
+}
+
+/// whether `format` can be bound as a read-write storage texture.
+fn supports_storage(adapter: &Adapter, format: TextureFormat) -> bool {
+    let support = adapter.get_texture_format_features(format);
+    support
+        .allowed_usages
+        .contains(TextureUsages::STORAGE_BINDING)
+        && support
+            .flags
+            .contains(TextureFormatFeatureFlags::STORAGE_READ_WRITE)
+}
+
+pub fn validate_adapter(adapter: &Adapter) -> Result<(), Error> {
+    if !supports_storage(adapter, OCTREE_FORMAT) {
+        return Err(Error::UnsupportedFormat(
+            OCTREE_FORMAT,
+            "octree",
+            "byte_voxels",
+        ));
+    }
+    Ok(())
+}
+
+/// how the color mipmaps are computed with a device of `device_features` on `adapter`. the
+/// read-only storage textures of the colors format need the adapter specific format features.
+pub(crate) fn mip_path(adapter: &Adapter, device_features: Features) -> MipPath {
+    if device_features.contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+        && supports_storage(adapter, COLORS_FORMAT)
+    {
+        MipPath::Storage
+    } else {
+        MipPath::Copy
+    }
+}
+
+/// voxels store palette indices, which must fit in `VoxelsFormat`.
+pub fn validate_palette(palette_len: usize) -> Result<(), Error> {
+    if palette_len > VoxelsFormat::MAX as usize {
+        return Err(Error::PaletteTooLarge(palette_len, VoxelsFormat::BITS));
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+// iteration feedback: the fragment shader counts the traversal iterations of the primary rays in
+// a histogram (see `feedback.wgsl`), which is read back every few frames. the 99th percentile
+// tells how close the rays get to `*_max_iter`, and the auto mode tunes the constant from it.
+
+pub const ITER_HISTOGRAM_BINS: usize = 256;
+/// iterations per histogram bin: the histogram covers 0..1024 iterations.
+pub const ITER_BIN_WIDTH: u32 = 4;
+
+pub struct IterFeedback {
+    /// record the histogram. changing it requires reloading the shaders.
+    pub enabled: bool,
+    /// tune the max iter constant of the current traversal from the percentile.
+    pub auto: bool,
+    /// the last measured 99th percentile, in iterations.
+    pub p99: Option<u32>,
+    frame: u32,
+}
+
+impl IterFeedback {
+    /// frames accumulated in the histogram between two readbacks.
+    pub const INTERVAL: u32 = 30;
+    const MIN_MAX_ITER: u32 = 32;
+    const MAX_MAX_ITER: u32 = 1000;
+
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            auto: false,
+            p99: None,
+            frame: 0,
+        }
+    }
+
+    /// count a rendered frame, returns whether the histogram should be read back.
+    pub fn tick(&mut self) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        self.frame += 1;
+        self.frame % Self::INTERVAL == 0
+    }
+
+    /// upper bound, in iterations, of the bin containing the `p` quantile.
+    pub fn percentile(histogram: &[u32], p: f32) -> Option<u32> {
+        let total = histogram.iter().map(|&n| n as u64).sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+        let target = (total as f32 * p).ceil() as u64;
+        let mut count = 0;
+        for (bin, &n) in histogram.iter().enumerate() {
+            count += n as u64;
+            if count >= target {
+                return Some((bin as u32 + 1) * ITER_BIN_WIDTH);
+            }
+        }
+        Some(histogram.len() as u32 * ITER_BIN_WIDTH)
+    }
+
+    /// the max iter the auto mode wants, or `None` to keep `max_iter`. rays cut off at the max
+    /// report exactly `max_iter`, so a percentile reaching it grows the limit; otherwise it
+    /// shrinks to leave 50% of headroom above the percentile. small changes are ignored, each
+    /// change reloads the shaders.
+    pub fn tune(&self, max_iter: u32) -> Option<u32> {
+        let p99 = self.p99?;
+        let target = (p99 + p99 / 2).next_multiple_of(16);
+        let target = target.clamp(Self::MIN_MAX_ITER, Self::MAX_MAX_ITER);
+        let delta = target.abs_diff(max_iter);
+        (delta > max_iter / 4).then_some(target)
+    }
+}
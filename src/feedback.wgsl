@@ -0,0 +1,21 @@
+// this shader is a "module" supposed to be included.
+// histogram of the traversal iterations of the primary rays, read back by `feedback.rs`.
+//
+// this module "exports":
+// fn record_iter(iter: u32)
+//
+// this module "requires":
+// const ITER_FEEDBACK: u32; // 1 to record the histogram
+
+const ITER_HISTOGRAM_BINS: u32 = 256u; // see feedback.rs
+const ITER_BIN_WIDTH: u32 = 4u;
+
+@group(0) @binding(10)
+var<storage, read_write> iter_histogram: array<atomic<u32>, ITER_HISTOGRAM_BINS>;
+
+fn record_iter(iter: u32) {
+    if #ITER_FEEDBACK == 1u {
+        let bin = min(iter / ITER_BIN_WIDTH, ITER_HISTOGRAM_BINS - 1u);
+        atomicAdd(&iter_histogram[bin], 1u);
+    }
+}
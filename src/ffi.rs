@@ -0,0 +1,449 @@
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_void, CStr, CString},
+    iter,
+    num::NonZeroIsize,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    ptr::{self, NonNull},
+    slice,
+};
+
+use nalgebra_glm as glm;
+use raw_window_handle::{
+    AppKitDisplayHandle, AppKitWindowHandle, RawDisplayHandle, RawWindowHandle,
+    WaylandDisplayHandle, WaylandWindowHandle, Win32WindowHandle, WindowsDisplayHandle,
+    XlibDisplayHandle, XlibWindowHandle,
+};
+
+use crate::{
+    camera::Camera,
+    error::Error,
+    features,
+    thumbnail::scene_state,
+    voxels::Voxels,
+    wgpu_util::{MipPath, WgpuState, PICK_SAMPLES},
+};
+
+// the c abi of the renderer, built with the `ffi` feature, so engines in other languages and python
+// (through ctypes or cffi) can drive it: create a renderer on a native window, load a scene, place
+// the camera, render frames and pick voxels. there is no ui and no event loop, the host calls
+// `wender_render` when it wants a frame. the functions returning a bool report their errors through
+// `wender_last_error`, panics included: they do not unwind into the host. a renderer must only be
+// used from the thread that created it.
+
+/// `WenderWindow::kind` of an x11 window, `display` is the `Display*` and `window` the `Window`.
+pub const WENDER_WINDOW_XLIB: u32 = 0;
+/// a wayland surface, `display` is the `wl_display*` and `window` the `wl_surface*`.
+pub const WENDER_WINDOW_WAYLAND: u32 = 1;
+/// a windows window, `window` is the `HWND` and `display` the `HINSTANCE` or null.
+pub const WENDER_WINDOW_WIN32: u32 = 2;
+/// a macos view, `window` is the `NSView*` backed by a `CAMetalLayer`.
+pub const WENDER_WINDOW_APPKIT: u32 = 3;
+
+/// the native window to render to.
+#[repr(C)]
+pub struct WenderWindow {
+    pub kind: u32,
+    pub display: *mut c_void,
+    pub window: *mut c_void,
+}
+
+#[repr(C)]
+pub struct WenderPick {
+    pub hit: bool,
+    /// in world coordinates, in voxels.
+    pub pos: [f32; 3],
+    pub voxel: [u32; 3],
+    /// normal of the face hit.
+    pub normal: [f32; 3],
+    /// from the camera, in voxels.
+    pub distance: f32,
+}
+
+/// a renderer drawing to a window of the host.
+pub struct WenderRenderer {
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    mip_path: MipPath,
+    camera: Camera,
+    /// the render state of the loaded scene.
+    scene: Option<WgpuState>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// remember `err` for `wender_last_error`, and return false.
+fn fail(err: impl ToString) -> bool {
+    let message = err.to_string().replace('\0', " ");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+    false
+}
+
+/// run `f`, or report its panic like an error and return `on_panic`, since unwinding through the
+/// c abi is undefined behavior.
+fn guard<T>(on_panic: T, f: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(payload) => {
+            let message = match payload.downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .unwrap_or_default(),
+            };
+            fail(format!("panicked: {message}"));
+            on_panic
+        }
+    }
+}
+
+fn raw_handles(window: &WenderWindow) -> Option<(RawDisplayHandle, RawWindowHandle)> {
+    Some(match window.kind {
+        WENDER_WINDOW_XLIB => (
+            XlibDisplayHandle::new(NonNull::new(window.display), 0).into(),
+            XlibWindowHandle::new(window.window as _).into(),
+        ),
+        WENDER_WINDOW_WAYLAND => (
+            WaylandDisplayHandle::new(NonNull::new(window.display)?).into(),
+            WaylandWindowHandle::new(NonNull::new(window.window)?).into(),
+        ),
+        WENDER_WINDOW_WIN32 => {
+            let mut handle = Win32WindowHandle::new(NonZeroIsize::new(window.window as isize)?);
+            handle.hinstance = NonZeroIsize::new(window.display as isize);
+            (WindowsDisplayHandle::new().into(), handle.into())
+        }
+        WENDER_WINDOW_APPKIT => (
+            AppKitDisplayHandle::new().into(),
+            AppKitWindowHandle::new(NonNull::new(window.window)?).into(),
+        ),
+        _ => return None,
+    })
+}
+
+impl WenderRenderer {
+    /// # Safety
+    /// the handles of `window` must stay valid as long as the renderer.
+    async unsafe fn new(window: &WenderWindow, width: u32, height: u32) -> Result<Self, String> {
+        let (raw_display_handle, raw_window_handle) =
+            raw_handles(window).ok_or("invalid window handle")?;
+        let instance = wgpu::Instance::default();
+        let surface = instance
+            .create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
+                raw_display_handle,
+                raw_window_handle,
+            })
+            .map_err(Error::from)
+            .map_err(|e| e.to_string())?;
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: Some(&surface),
+                ..Default::default()
+            })
+            .await
+            .ok_or_else(|| Error::NoAdapter.to_string())?;
+        features::validate_adapter(&adapter).map_err(|e| e.to_string())?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("ffi device"),
+                    required_features: adapter.features()
+                        & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                    required_limits: adapter.limits(),
+                },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        let mip_path = features::mip_path(&adapter, device.features());
+
+        let caps = surface.get_capabilities(&adapter);
+        let format = caps
+            .formats
+            .iter()
+            .copied()
+            .find(|f| f.is_srgb())
+            .ok_or("the window cannot be rendered to")?;
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+        surface.configure(&device, &config);
+
+        let mut camera = Camera::new(glm::vec2(config.width as f32, config.height as f32));
+        camera.uniform.aspect = config.width as f32 / config.height as f32;
+        Ok(Self {
+            surface,
+            device,
+            queue,
+            config,
+            mip_path,
+            camera,
+            scene: None,
+        })
+    }
+
+    fn set_scene(&mut self, voxels: Result<Voxels, crate::voxels::Error>) -> bool {
+        let state = voxels.map_err(Error::from).and_then(|voxels| {
+            scene_state(
+                &self.device,
+                &self.queue,
+                &voxels,
+                &self.camera,
+                &self.config,
+                self.mip_path,
+            )
+        });
+        match state {
+            Ok(state) => {
+                self.scene = Some(state);
+                true
+            }
+            Err(err) => fail(err),
+        }
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.config.width = width.max(1);
+        self.config.height = height.max(1);
+        self.surface.configure(&self.device, &self.config);
+        let size = glm::vec2(self.config.width as f32, self.config.height as f32);
+        self.camera.uniform.size = size;
+        self.camera.uniform.aspect = size.x / size.y;
+        if let Some(scene) = &mut self.scene {
+            scene.set_render_size(&self.device, &self.config, None);
+        }
+    }
+
+    fn render(&mut self) -> bool {
+        let Some(scene) = &self.scene else {
+            return fail("no scene is loaded");
+        };
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(err) => return fail(err),
+        };
+        let view = output.texture.create_view(&Default::default());
+        scene.write_cameras(&self.queue, &self.camera.uniform, &self.camera.uniform);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("ffi encoder"),
+            });
+        let size = (self.config.width, self.config.height);
+        scene.draw(&view, size, &mut encoder);
+        self.queue.submit(iter::once(encoder.finish()));
+        output.present();
+        true
+    }
+
+    fn pick(&self, x: f32, y: f32) -> Option<WenderPick> {
+        let scene = self.scene.as_ref()?;
+        let ndc = glm::vec2(
+            2.0 * x / self.config.width as f32 - 1.0,
+            1.0 - 2.0 * y / self.config.height as f32,
+        );
+        let dir = self.camera.ray_dir(&ndc);
+        let results = scene.pick(
+            &self.device,
+            &self.queue,
+            &self.camera.pos(),
+            &[dir; PICK_SAMPLES],
+        );
+        let hit = &results[0];
+        Some(WenderPick {
+            hit: hit.hit != 0,
+            pos: hit.pos.into(),
+            voxel: hit.voxel.into(),
+            normal: hit.normal.into(),
+            distance: hit.t,
+        })
+    }
+}
+
+/// the path of a nul-terminated utf-8 string.
+///
+/// # Safety
+/// `path` must be null or point to a nul-terminated string.
+unsafe fn path_of(path: *const c_char) -> Option<PathBuf> {
+    if path.is_null() {
+        return None;
+    }
+    CStr::from_ptr(path).to_str().ok().map(PathBuf::from)
+}
+
+/// create a renderer drawing to `window` of `width`x`height` pixels, or return null.
+///
+/// # Safety
+/// `window` must point to a `WenderWindow` whose handles stay valid until `wender_destroy`.
+#[no_mangle]
+pub unsafe extern "C" fn wender_create(
+    window: *const WenderWindow,
+    width: u32,
+    height: u32,
+) -> *mut WenderRenderer {
+    guard(ptr::null_mut(), || {
+        let Some(window) = window.as_ref() else {
+            fail("the window is null");
+            return ptr::null_mut();
+        };
+        match pollster::block_on(WenderRenderer::new(window, width, height)) {
+            Ok(renderer) => Box::into_raw(Box::new(renderer)),
+            Err(err) => {
+                fail(err);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// # Safety
+/// `renderer` must come from `wender_create` and not be used afterwards, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn wender_destroy(renderer: *mut WenderRenderer) {
+    guard((), || {
+        if !renderer.is_null() {
+            drop(Box::from_raw(renderer));
+        }
+    })
+}
+
+/// the message of the last error of this thread, valid until the next call failing. null if no
+/// call failed yet.
+#[no_mangle]
+pub extern "C" fn wender_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |err| err.as_ptr())
+    })
+}
+
+/// load the scene file at `path` (.wvox, .vox or .wchunks), replacing the current one.
+///
+/// # Safety
+/// `renderer` must come from `wender_create`, `path` must be a nul-terminated utf-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn wender_load_scene(
+    renderer: *mut WenderRenderer,
+    path: *const c_char,
+) -> bool {
+    guard(false, || {
+        let (Some(renderer), Some(path)) = (renderer.as_mut(), path_of(path)) else {
+            return fail("invalid renderer or path");
+        };
+        renderer.set_scene(Voxels::from_path(&path))
+    })
+}
+
+/// load a .wvox scene from the `len` bytes at `bytes`, replacing the current one.
+///
+/// # Safety
+/// `renderer` must come from `wender_create`, `bytes` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wender_load_scene_bytes(
+    renderer: *mut WenderRenderer,
+    bytes: *const u8,
+    len: usize,
+) -> bool {
+    guard(false, || {
+        let Some(renderer) = renderer.as_mut() else {
+            return fail("invalid renderer");
+        };
+        if bytes.is_null() {
+            return fail("the scene bytes are null");
+        }
+        let bytes = slice::from_raw_parts(bytes, len);
+        renderer.set_scene(Voxels::from_bytes(bytes, Path::new("memory.wvox")))
+    })
+}
+
+/// place the camera at `pos` looking at `target`, in voxels, with a vertical field of view of
+/// `fov_y` degrees.
+///
+/// # Safety
+/// `renderer` must come from `wender_create`, `pos` and `target` must point to 3 floats.
+#[no_mangle]
+pub unsafe extern "C" fn wender_set_camera(
+    renderer: *mut WenderRenderer,
+    pos: *const [f32; 3],
+    target: *const [f32; 3],
+    fov_y: f32,
+) -> bool {
+    guard(false, || {
+        let (Some(renderer), Some(pos), Some(target)) =
+            (renderer.as_mut(), pos.as_ref(), target.as_ref())
+        else {
+            return fail("invalid renderer or camera");
+        };
+        let camera = &mut renderer.camera;
+        camera.uniform.fov_y = fov_y.to_radians();
+        camera.set_pos(&glm::Vec3::from(*pos));
+        camera.look_at(&glm::Vec3::from(*target));
+        true
+    })
+}
+
+/// resize the window target after the host window was resized.
+///
+/// # Safety
+/// `renderer` must come from `wender_create`.
+#[no_mangle]
+pub unsafe extern "C" fn wender_resize(renderer: *mut WenderRenderer, width: u32, height: u32) {
+    guard((), || {
+        if let Some(renderer) = renderer.as_mut() {
+            renderer.resize(width, height);
+        }
+    })
+}
+
+/// render a frame of the scene and present it to the window.
+///
+/// # Safety
+/// `renderer` must come from `wender_create`.
+#[no_mangle]
+pub unsafe extern "C" fn wender_render(renderer: *mut WenderRenderer) -> bool {
+    guard(false, || match renderer.as_mut() {
+        Some(renderer) => renderer.render(),
+        None => fail("invalid renderer"),
+    })
+}
+
+/// cast a ray from the camera through the pixel (`x`, `y`) from the top left of the window, and
+/// write what it hit to `out`. blocks until the gpu answers.
+///
+/// # Safety
+/// `renderer` must come from `wender_create`, `out` must point to a writable `WenderPick`.
+#[no_mangle]
+pub unsafe extern "C" fn wender_pick(
+    renderer: *const WenderRenderer,
+    x: f32,
+    y: f32,
+    out: *mut WenderPick,
+) -> bool {
+    guard(false, || {
+        let (Some(renderer), Some(out)) = (renderer.as_ref(), out.as_mut()) else {
+            return fail("invalid renderer or output");
+        };
+        match renderer.pick(x, y) {
+            Some(pick) => {
+                *out = pick;
+                true
+            }
+            None => fail("no scene is loaded"),
+        }
+    })
+}
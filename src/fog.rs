@@ -0,0 +1,82 @@
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+use crate::scene::SceneMeta;
+
+// local fog volumes: boxes of homogeneous fog with their own color and density, e.g. a misty
+// valley or a dusty cave, independent of the global horizon fog. they are stored in the scene
+// metadata. the shader integrates the fog analytically along the ray segment inside each box.
+
+/// capacity of the fog volumes uniform. must match `MAX_FOG_VOLUMES` in `post.wgsl`.
+pub const MAX_FOG_VOLUMES: usize = 8;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FogVolume {
+    /// corners of the box, in voxels.
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+    pub color: [f32; 3],
+    /// extinction per meter.
+    pub density: f32,
+}
+
+impl FogVolume {
+    /// a box of `half_size` voxels around `center`.
+    pub fn around(center: &glm::Vec3, half_size: f32) -> Self {
+        Self {
+            min: (center - glm::Vec3::repeat(half_size)).into(),
+            max: (center + glm::Vec3::repeat(half_size)).into(),
+            color: [0.7, 0.72, 0.75],
+            density: 0.1,
+        }
+    }
+}
+
+// !! careful with the alignments! add padding fields if necessary.
+// see https://www.w3.org/TR/WGSL/#alignment-and-size
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FogVolumeUniform {
+    pub min: glm::Vec3,
+    pub density: f32, // per voxel
+    pub max: glm::Vec3,
+    _pad: f32, // padding to ensure correct alignment
+    pub color: glm::Vec3,
+    _pad1: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FogVolumesUniform {
+    pub volumes: [FogVolumeUniform; MAX_FOG_VOLUMES],
+    pub count: u32,
+    _pad: [u32; 3], // padding to ensure correct alignment
+}
+
+pub struct FogVolumes {
+    pub uniform: FogVolumesUniform,
+}
+
+impl FogVolumes {
+    pub fn new() -> Self {
+        Self {
+            uniform: bytemuck::Zeroable::zeroed(),
+        }
+    }
+
+    /// upload the volumes of the scene, extra volumes are ignored.
+    pub fn update(&mut self, meta: &SceneMeta) {
+        let volumes = &meta.fog_volumes[..meta.fog_volumes.len().min(MAX_FOG_VOLUMES)];
+        for (dst, volume) in self.uniform.volumes.iter_mut().zip(volumes) {
+            dst.min = volume.min.into();
+            dst.max = volume.max.into();
+            dst.color = volume.color.into();
+            dst.density = meta.to_meters(volume.density);
+        }
+        self.uniform.count = volumes.len() as u32;
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.uniform)
+    }
+}
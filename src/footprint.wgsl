@@ -0,0 +1,42 @@
+#import "bindings.wgsl"::{ colors, linear_sampler }
+#import "ray.wgsl"::{ cam }
+#import "materials.wgsl"::{ voxel_albedo }
+
+// this shader is a "module" supposed to be included.
+// the primary rays are thin cones: the footprint of a pixel grows with the distance to the
+// camera. when it covers several voxels, the albedo comes from the color mip of that size
+// instead of the single voxel hit, which removes the shimmering of distant detail without
+// supersampling.
+//
+// this module "exports":
+// fn pixel_footprint(t: f32) -> f32
+// fn footprint_albedo(voxel: vec3u, t: f32) -> vec4f
+//
+// this module "requires":
+// const FOOTPRINT_LOD: u32; // 1: filter the albedo with the pixel footprint
+
+// width of a pixel at distance `t` from the camera, in voxels.
+fn pixel_footprint(t: f32) -> f32 {
+    return t * 2.0 * tan(cam.fov_y * 0.5) / cam.size.y;
+}
+
+// the albedo of the voxel hit at distance `t`, filtered over the pixel footprint.
+fn footprint_albedo(voxel: vec3u, t: f32) -> vec4f {
+    let exact = voxel_albedo(voxel);
+    if #FOOTPRINT_LOD == 0u {
+        return exact;
+    }
+    let lod = log2(pixel_footprint(t));
+    if lod <= 0.0 {
+        return exact;
+    }
+    let size = vec3f(textureDimensions(colors, 0u));
+    let filtered = textureSampleLevel(colors, linear_sampler, (vec3f(voxel) + 0.5) / size, lod);
+    // the mips average the empty voxels as transparent black.
+    if filtered.a <= 0.0 {
+        return exact;
+    }
+    let color = vec4f(filtered.rgb / filtered.a, exact.a);
+    // fade in over the first level, the voxels of the footprint are mostly the hit one.
+    return mix(exact, color, saturate(lod));
+}
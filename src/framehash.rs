@@ -0,0 +1,84 @@
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use xxhash_rust::xxh3::xxh3_64;
+
+// hashes of rendered frames, to detect rendering changes in ci without storing golden images.
+// the exact hash (xxh3 of the rgba pixels) changes with any pixel, so it only holds on the same
+// gpu and driver. the signature is a coarse grid of the mean luma of the frame, compared with a
+// tolerance to ignore small differences between machines.
+
+/// width and height of the signature grid.
+const SIGNATURE_SIZE: u32 = 16;
+
+#[derive(Serialize, Deserialize)]
+pub struct FrameHash {
+    pub frame: u32,
+    /// xxh3 of the pixels, in hexadecimal.
+    pub xxh3: String,
+    /// mean luma of each cell of the grid, row by row, in hexadecimal.
+    pub signature: String,
+}
+
+pub enum Comparison {
+    Identical,
+    /// the mean difference of the signatures, from 0 to 1.
+    Similar(f32),
+    Different(f32),
+}
+
+impl FrameHash {
+    pub fn new(frame: u32, image: &RgbaImage) -> Self {
+        Self {
+            frame,
+            xxh3: format!("{:016x}", xxh3_64(image.as_raw())),
+            signature: signature(image)
+                .iter()
+                .map(|luma| format!("{luma:02x}"))
+                .collect(),
+        }
+    }
+
+    pub fn compare(&self, reference: &Self, tolerance: f32) -> Comparison {
+        if self.xxh3 == reference.xxh3 {
+            return Comparison::Identical;
+        }
+        let distance = match (decode(&self.signature), decode(&reference.signature)) {
+            (Some(a), Some(b)) if a.len() == b.len() => {
+                let sum = a
+                    .iter()
+                    .zip(&b)
+                    .map(|(a, b)| a.abs_diff(*b) as f32)
+                    .sum::<f32>();
+                sum / (a.len() as f32 * 255.0)
+            }
+            _ => 1.0,
+        };
+        match distance <= tolerance {
+            true => Comparison::Similar(distance),
+            false => Comparison::Different(distance),
+        }
+    }
+}
+
+/// mean luma of each cell of a `SIGNATURE_SIZE`² grid over the image.
+fn signature(image: &RgbaImage) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let mut sums = vec![(0.0, 0u32); (SIGNATURE_SIZE * SIGNATURE_SIZE) as usize];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let cell = (y * SIGNATURE_SIZE / height) * SIGNATURE_SIZE + x * SIGNATURE_SIZE / width;
+        let [r, g, b, _] = pixel.0;
+        let (sum, count) = &mut sums[cell as usize];
+        *sum += 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+        *count += 1;
+    }
+    sums.iter()
+        .map(|(sum, count)| (sum / (*count).max(1) as f32).round() as u8)
+        .collect()
+}
+
+fn decode(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
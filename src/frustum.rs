@@ -0,0 +1,82 @@
+use nalgebra_glm as glm;
+
+use crate::camera::{Camera, CameraUniform};
+
+// debug camera split: the render camera can be frozen while the user keeps flying. the frozen
+// frustum is then drawn as a wireframe overlay, to inspect view-dependent behavior from outside.
+// view-dependent work (culling, lod by distance) must use `Frustum::render_camera`.
+
+// !! careful with the alignments! add padding fields if necessary.
+// see https://www.w3.org/TR/WGSL/#alignment-and-size
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FrustumUniform {
+    pub corners: [glm::Vec4; 8], // 4 near then 4 far corners. w is unused.
+    pub color: glm::Vec3,
+    pub visible: u32,
+    pub width: f32,
+    _pad: [f32; 3], // padding to ensure correct alignment
+}
+
+pub struct Frustum {
+    pub uniform: FrustumUniform,
+    frozen: Option<CameraUniform>,
+    /// distance of the far plane of the drawn frustum, in voxels.
+    pub far: f32,
+}
+
+impl Frustum {
+    pub fn new() -> Self {
+        Self {
+            uniform: FrustumUniform {
+                corners: Default::default(),
+                color: glm::vec3(0.2, 1.0, 0.4),
+                visible: 0,
+                width: 0.3,
+                _pad: Default::default(),
+            },
+            frozen: None,
+            far: 256.0,
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    /// freeze the render camera at the current camera, or release it.
+    pub fn set_frozen(&mut self, cam: &Camera, frozen: bool) {
+        self.frozen = frozen.then_some(cam.uniform);
+    }
+
+    /// the camera that view-dependent work should use: the frozen one, if any.
+    pub fn render_camera<'a>(&'a self, cam: &'a Camera) -> &'a CameraUniform {
+        self.frozen.as_ref().unwrap_or(&cam.uniform)
+    }
+
+    pub fn update(&mut self) {
+        let Some(cam) = &self.frozen else {
+            self.uniform.visible = 0;
+            return;
+        };
+
+        let tan = (cam.fov_y / 2.0).tan();
+        let near = 1.0;
+        let dists = [
+            near, near, near, near, self.far, self.far, self.far, self.far,
+        ];
+        let ndc = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+        for (i, dist) in dists.iter().enumerate() {
+            let (x, y) = ndc[i % 4];
+            let dir = cam.view_mat_inv * glm::vec4(x * tan * cam.aspect, y * tan, 1.0, 0.0);
+            let corner = cam.world_pos() + dir.xyz() * *dist;
+            self.uniform.corners[i] = glm::vec4(corner.x, corner.y, corner.z, 0.0);
+        }
+        self.uniform.visible = 1;
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.uniform)
+    }
+}
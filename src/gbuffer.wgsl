@@ -0,0 +1,119 @@
+#import "traversal.wgsl"::{ trace_primary_from }
+#import "ray.wgsl"::{ cam_pos, cam_ray_dir, view_depth }
+#import "environment.wgsl"::{ ground_t }
+#import "bindings.wgsl"::{ voxel_ids }
+
+// this shader is a "module" supposed to be included.
+// the g-buffer of the deferred renderer: the primary pass traces the camera rays once and
+// stores what they hit, the visibility and lighting passes read it back per pixel. the primary
+// pass also writes the depth of the hits, the rasterized overlays are tested against it.
+//
+// this module "exports":
+// var gbuffer_pos: texture_2d<f32>
+// var gbuffer_surface: texture_2d<u32>
+// var visibility: texture_2d<f32>
+// struct GSample
+// struct GBufferOut
+// const SURFACE_SKY: u32
+// const SURFACE_VOXEL: u32
+// const SURFACE_GROUND: u32
+// fn trace_gsample(screen_pos: vec2f, t_start: f32) -> GSample
+// fn pack_gsample(s: GSample) -> GBufferOut
+// fn load_gsample(pixel: vec2u) -> GSample
+// fn load_visibility(pixel: vec2u) -> vec2f
+
+// what the primary ray hit.
+const SURFACE_SKY: u32 = 0u;
+const SURFACE_VOXEL: u32 = 1u;
+const SURFACE_GROUND: u32 = 2u;
+
+// the iterations are stored in 14 bits.
+const MAX_STORED_ITER: u32 = 0x3fffu;
+
+// hit position and distance.
+@group(2) @binding(0)
+var gbuffer_pos: texture_2d<f32>;
+
+// voxel, iterations and kind of surface, material id, normal. see `pack_gsample`.
+@group(2) @binding(1)
+var gbuffer_surface: texture_2d<u32>;
+
+// sun shadow and ao, written by the visibility pass.
+@group(3) @binding(0)
+var visibility: texture_2d<f32>;
+
+struct GSample {
+    kind: u32,
+    pos: vec3f,
+    t: f32, // 1e9 for the sky
+    normal: vec3f,
+    voxel: vec3u,
+    material: u32, // palette index
+    iter: u32,
+}
+
+struct GBufferOut {
+    @location(0) pos: vec4f,
+    @location(1) surface: vec4u,
+    @builtin(frag_depth) depth: f32, // 0 for the sky, see `view_depth`
+}
+
+// trace the camera ray through `screen_pos` from the distance `t_start`, landing on the ground
+// plane when it leaves the volume downwards.
+fn trace_gsample(screen_pos: vec2f, t_start: f32) -> GSample {
+    let res = trace_primary_from(screen_pos, t_start);
+    var s = GSample(SURFACE_SKY, vec3f(0.0), 1e9, vec3f(0.0), vec3u(0u), 0u, res.iter);
+    if res.hit {
+        s.kind = SURFACE_VOXEL;
+        s.pos = res.pos;
+        s.t = res.t;
+        s.normal = res.normal;
+        s.voxel = res.voxel;
+        s.material = textureLoad(voxel_ids, res.voxel, 0).r;
+        return s;
+    }
+    let ray_dir = cam_ray_dir(screen_pos);
+    let ground_dist = ground_t(cam_pos(), ray_dir);
+    if ground_dist > 0.0 {
+        s.kind = SURFACE_GROUND;
+        s.pos = cam_pos() + ray_dir * ground_dist;
+        s.t = ground_dist;
+        s.normal = vec3f(0.0, 1.0, 0.0);
+    }
+    return s;
+}
+
+// the voxel coordinates fit in 16 bits, the normal in 8 bits per axis.
+fn pack_gsample(s: GSample) -> GBufferOut {
+    let iter = min(s.iter, MAX_STORED_ITER);
+    return GBufferOut(
+        vec4f(s.pos, s.t),
+        vec4u(
+            s.voxel.x | (s.voxel.y << 16u),
+            s.voxel.z | (iter << 16u) | (s.kind << 30u),
+            s.material,
+            pack4x8snorm(vec4f(s.normal, 0.0)),
+        ),
+        select(view_depth(s.pos), 0.0, s.kind == SURFACE_SKY),
+    );
+}
+
+fn load_gsample(pixel: vec2u) -> GSample {
+    let pos = textureLoad(gbuffer_pos, pixel, 0);
+    let surface = textureLoad(gbuffer_surface, pixel, 0);
+    var s: GSample;
+    s.kind = surface.y >> 30u;
+    s.pos = pos.xyz;
+    s.t = pos.w;
+    let normal = unpack4x8snorm(surface.w).xyz;
+    s.normal = select(vec3f(0.0), normalize(normal), s.kind != SURFACE_SKY);
+    s.voxel = vec3u(surface.x & 0xffffu, surface.x >> 16u, surface.y & 0xffffu);
+    s.material = surface.z;
+    s.iter = (surface.y >> 16u) & MAX_STORED_ITER;
+    return s;
+}
+
+// the sun shadow and the ao of the pixel, see `occlusion` in shading.wgsl.
+fn load_visibility(pixel: vec2u) -> vec2f {
+    return textureLoad(visibility, pixel, 0).rg;
+}
@@ -0,0 +1,225 @@
+use nalgebra_glm as glm;
+
+use crate::{
+    lights::{LightKind, LocalLight},
+    scene::SceneMeta,
+};
+
+// rasterized overlays drawn over the raymarched scene: debug gizmos, grid lines, and later the
+// meshes of entities. the primary pass writes the depth of its hits to a depth target, the
+// gizmos are drawn after the lighting pass and depth tested against it, so the voxels in front
+// of them hide them. see `gizmos.wgsl` for the projection. while the bounds are shown, the
+// handles of the local lights are dragged with the mouse, see `State::grab_light_handle`.
+
+/// vertices drawn at most, the extra lines are dropped.
+pub const MAX_GIZMO_VERTICES: usize = 1 << 16;
+
+/// half size of the cross marking a local light, in voxels.
+const LIGHT_CROSS: f32 = 2.0;
+
+/// half size of the boxes of the light handles, in voxels.
+pub const HANDLE_SIZE: f32 = 0.5;
+
+/// distance from a spot to the handle of its direction, in voxels.
+const DIR_HANDLE_DIST: f32 = 6.0;
+
+/// segments of the circles.
+const CIRCLE_SEGMENTS: usize = 32;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GizmoVertex {
+    /// in world coordinates, in voxels.
+    pub pos: glm::Vec3,
+    /// rgba.
+    pub color: [u8; 4],
+}
+
+/// a handle of a local light.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightHandle {
+    /// moves the light.
+    Pos,
+    /// turns a spot towards it.
+    Dir,
+}
+
+/// a light handle being dragged, in the plane facing the camera it was grabbed in.
+pub struct LightDrag {
+    /// index in the lights of the scene metadata.
+    pub light: usize,
+    pub handle: LightHandle,
+    /// distance of the plane along the view direction, in voxels.
+    pub depth: f32,
+}
+
+/// world positions of the handles of a light: the position of the point and spot lights, and
+/// the direction of the spots.
+pub fn light_handles(light: &LocalLight) -> Vec<(LightHandle, glm::Vec3)> {
+    let pos = glm::Vec3::from(light.pos);
+    let dir = glm::normalize(&glm::Vec3::from(light.dir));
+    match light.kind {
+        LightKind::Point => vec![(LightHandle::Pos, pos)],
+        LightKind::Spot => vec![
+            (LightHandle::Pos, pos),
+            (LightHandle::Dir, pos + dir * DIR_HANDLE_DIST),
+        ],
+        LightKind::Directional => vec![],
+    }
+}
+
+/// move a handle of a light to the world position `target`.
+pub fn drag_light_handle(light: &mut LocalLight, handle: LightHandle, target: &glm::Vec3) {
+    match handle {
+        LightHandle::Pos => light.pos = (*target).into(),
+        LightHandle::Dir => {
+            let dir = target - glm::Vec3::from(light.pos);
+            if dir.norm() > 1e-3 {
+                light.dir = glm::normalize(&dir).into();
+            }
+        }
+    }
+}
+
+/// the lines of the gizmos, rebuilt every frame.
+pub struct Gizmos {
+    /// outline the volume, the fog volumes and the local lights: the sphere reached by a point
+    /// light, the cone of a spot.
+    pub show_bounds: bool,
+    vertices: Vec<GizmoVertex>,
+}
+
+impl Gizmos {
+    pub fn new() -> Self {
+        Self {
+            show_bounds: false,
+            vertices: Vec::new(),
+        }
+    }
+
+    /// rebuild the debug gizmos of the scene, `bounds` being the size of the volume.
+    pub fn update(&mut self, bounds: &glm::Vec3, meta: &SceneMeta) {
+        self.vertices.clear();
+        if !self.show_bounds {
+            return;
+        }
+        self.aabb(&glm::Vec3::zeros(), bounds, [255, 255, 255, 255]);
+        for fog in &meta.fog_volumes {
+            let color = glm::Vec3::from(fog.color).map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+            self.aabb(
+                &fog.min.into(),
+                &fog.max.into(),
+                [color.x, color.y, color.z, 255],
+            );
+        }
+        for light in &meta.lights {
+            let pos = glm::Vec3::from(light.pos);
+            let dir = glm::normalize(&glm::Vec3::from(light.dir));
+            let radius = meta.to_voxels(light.radius);
+            let color = [255, 220, 64, 255];
+            for (handle, handle_pos) in light_handles(light) {
+                let half = glm::Vec3::repeat(HANDLE_SIZE);
+                self.aabb(&(handle_pos - half), &(handle_pos + half), color);
+                if handle == LightHandle::Dir {
+                    self.line(&pos, &handle_pos, color);
+                }
+            }
+            match light.kind {
+                LightKind::Point => {
+                    self.cross(&pos, LIGHT_CROSS, color);
+                    for axis in 0..3 {
+                        let mut normal = glm::Vec3::zeros();
+                        normal[axis] = 1.0;
+                        self.circle(&pos, &normal, radius, color);
+                    }
+                }
+                LightKind::Spot => {
+                    self.cross(&pos, LIGHT_CROSS, color);
+                    self.cone(&pos, &dir, radius, light.cone.to_radians(), color);
+                }
+                LightKind::Directional => {}
+            }
+        }
+    }
+
+    pub fn line(&mut self, a: &glm::Vec3, b: &glm::Vec3, color: [u8; 4]) {
+        if self.vertices.len() + 2 <= MAX_GIZMO_VERTICES {
+            self.vertices.push(GizmoVertex { pos: *a, color });
+            self.vertices.push(GizmoVertex { pos: *b, color });
+        }
+    }
+
+    /// the 12 edges of the box from `min` to `max`.
+    pub fn aabb(&mut self, min: &glm::Vec3, max: &glm::Vec3, color: [u8; 4]) {
+        let corner = |i: usize| {
+            glm::vec3(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        for i in 0..8 {
+            // each corner is linked to the corners with one more bit set.
+            for axis in [1, 2, 4] {
+                if i & axis == 0 {
+                    self.line(&corner(i), &corner(i | axis), color);
+                }
+            }
+        }
+    }
+
+    /// three lines of `2 * half_size` crossing at `center`.
+    pub fn cross(&mut self, center: &glm::Vec3, half_size: f32, color: [u8; 4]) {
+        for axis in 0..3 {
+            let mut offset = glm::Vec3::zeros();
+            offset[axis] = half_size;
+            self.line(&(center - offset), &(center + offset), color);
+        }
+    }
+
+    /// the circle of `radius` around `center`, in the plane of `normal`.
+    pub fn circle(&mut self, center: &glm::Vec3, normal: &glm::Vec3, radius: f32, color: [u8; 4]) {
+        let (u, v) = plane_axes(normal);
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(&point(i), &point(i + 1), color);
+        }
+    }
+
+    /// the cone from `apex` towards `dir` of half angle `angle` in radians, cut at `length`: its
+    /// base circle and 4 of its sides.
+    pub fn cone(
+        &mut self,
+        apex: &glm::Vec3,
+        dir: &glm::Vec3,
+        length: f32,
+        angle: f32,
+        color: [u8; 4],
+    ) {
+        let center = apex + dir * length * angle.cos();
+        let radius = length * angle.sin();
+        self.circle(&center, dir, radius, color);
+        let (u, v) = plane_axes(dir);
+        for side in [u, -u, v, -v] {
+            self.line(apex, &(center + side * radius), color);
+        }
+    }
+
+    pub fn vertices(&self) -> &[GizmoVertex] {
+        &self.vertices
+    }
+}
+
+/// two unit vectors orthogonal to `normal` and to each other.
+fn plane_axes(normal: &glm::Vec3) -> (glm::Vec3, glm::Vec3) {
+    let normal = glm::normalize(normal);
+    let other = match normal.x.abs() < 0.9 {
+        true => glm::vec3(1.0, 0.0, 0.0),
+        false => glm::vec3(0.0, 1.0, 0.0),
+    };
+    let u = glm::normalize(&glm::cross(&normal, &other));
+    (u, glm::cross(&normal, &u))
+}
@@ -0,0 +1,36 @@
+#import "ray.wgsl"::{ cam, view_pos, from_volume, DEPTH_NEAR }
+
+// the gizmos: lines in world coordinates rasterized over the lit scene, see `gizmos.rs`. they
+// are projected like the camera rays of `cam_ray_dir`, so the depth test against the depth
+// written by the primary pass hides them behind the voxels.
+//
+// this module "requires":
+// (the constants required by the imported modules)
+
+struct VertexInput {
+    @location(0) pos: vec3f,
+    @location(1) color: vec4f,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_pos: vec4f,
+    @location(0) color: vec4f,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    let v = view_pos(from_volume(in.pos));
+    let tan_half = tan(cam.fov_y / 2.0);
+    // after the division by w, the depth is DEPTH_NEAR / v.z as in `view_depth`.
+    out.clip_pos = vec4f(v.x / (tan_half * cam.aspect), v.y / tan_half, DEPTH_NEAR, v.z);
+    out.color = in.color;
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return in.color;
+}
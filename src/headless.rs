@@ -0,0 +1,327 @@
+use std::{
+    f32::consts::TAU,
+    fmt::Write,
+    fs, iter,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use nalgebra_glm as glm;
+use serde::Serialize;
+
+use crate::{
+    camera::Camera,
+    capture::{copy_texture, create_render_target},
+    cli::Backend,
+    error::Error,
+    framehash::{Comparison, FrameHash},
+    random::FrameSeed,
+    route::Route,
+    thumbnail::{offscreen_state, request_device, OFFSCREEN_FORMAT},
+    voxels::Voxels,
+};
+
+// `wender --headless`: renders frames offscreen along a scripted camera path, without a window,
+// to benchmark shader changes on machines without a display (e.g. in ci). writes into the output
+// directory:
+// - `frame-NNNN.png`: every `image_every`th frame, to compare the images between runs.
+// - `frames.csv`: the time of each frame, from the submission until the gpu is idle.
+// - `summary.json`: statistics of the frame times.
+// - `hashes.json`: with `hash`, the hashes of the frames instead of their images, see
+//   `framehash.rs`. compared with the hashes of a previous run with `compare_hashes`.
+// the camera follows a route file (see `route.rs`) at constant speed, or orbits the scene. the
+// scene time stays at 0 and the random numbers follow `seed`, so two runs render the same frames.
+
+/// frames rendered before the measures, while the driver warms its caches.
+const WARMUP_FRAMES: u32 = 5;
+
+/// distance along the camera path where the camera looks, in voxels.
+const LOOK_AHEAD: f32 = 16.0;
+
+pub struct HeadlessOptions {
+    pub out_dir: PathBuf,
+    pub frames: u32,
+    pub width: u32,
+    pub height: u32,
+    /// route file followed by the camera, an orbit around the scene if `None`.
+    pub camera_path: Option<PathBuf>,
+    /// save one image every this many frames, 0 for none.
+    pub image_every: u32,
+    /// write the hashes of the images instead of the images.
+    pub hash: bool,
+    /// `hashes.json` of a previous run, the run fails if a frame differs from it.
+    pub compare_hashes: Option<PathBuf>,
+    /// mean luma difference tolerated by `compare_hashes`, from 0 to 1.
+    pub tolerance: f32,
+    pub backend: Backend,
+    /// seed of the random numbers, see `random.rs`.
+    pub seed: u32,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    frames: usize,
+    width: u32,
+    height: u32,
+    mean_ms: f32,
+    median_ms: f32,
+    p95_ms: f32,
+    p99_ms: f32,
+    min_ms: f32,
+    max_ms: f32,
+    /// frames that differ from the reference hashes.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    changed_frames: Vec<u32>,
+}
+
+/// render `scene` offscreen with `options`. returns whether it succeeded.
+pub fn headless(scene: &Path, options: &HeadlessOptions) -> bool {
+    match pollster::block_on(run(scene, options)) {
+        Ok(summary) => {
+            println!(
+                "{} frames: mean {:.2} ms, median {:.2} ms, p99 {:.2} ms",
+                summary.frames, summary.mean_ms, summary.median_ms, summary.p99_ms
+            );
+            println!("wrote `{}`", options.out_dir.display());
+            if !summary.changed_frames.is_empty() {
+                eprintln!(
+                    "{} frames differ from the reference hashes",
+                    summary.changed_frames.len()
+                );
+            }
+            summary.changed_frames.is_empty()
+        }
+        Err(err) => {
+            eprintln!("error: {err}\nhint: {}", err.hint());
+            false
+        }
+    }
+}
+
+/// the scripted camera, at `t` from 0 to 1 along the path.
+enum CameraPath {
+    Route {
+        points: Vec<glm::Vec3>,
+        /// length of the path up to each point.
+        lengths: Vec<f32>,
+    },
+    Orbit {
+        center: glm::Vec3,
+        distance: f32,
+    },
+}
+
+impl CameraPath {
+    fn route(points: Vec<glm::Vec3>) -> Self {
+        let lengths = iter::once(0.0)
+            .chain(points.windows(2).scan(0.0, |len, w| {
+                *len += glm::distance(&w[0], &w[1]);
+                Some(*len)
+            }))
+            .collect();
+        Self::Route { points, lengths }
+    }
+
+    fn orbit(camera: &Camera, bounds: &glm::Vec3) -> Self {
+        let radius = glm::length(bounds) * 0.5;
+        let fov = f32::min(
+            camera.uniform.fov_y,
+            camera.uniform.fov_y * camera.uniform.aspect,
+        );
+        Self::Orbit {
+            center: bounds * 0.5,
+            distance: radius / (fov * 0.5).sin(),
+        }
+    }
+
+    /// the point at `len` voxels along the route, clamped to its ends.
+    fn point_at(points: &[glm::Vec3], lengths: &[f32], len: f32) -> glm::Vec3 {
+        let i = lengths.partition_point(|l| *l <= len);
+        if i == 0 {
+            return points[0];
+        }
+        if i == points.len() {
+            return points[points.len() - 1];
+        }
+        let t = (len - lengths[i - 1]) / (lengths[i] - lengths[i - 1]).max(f32::EPSILON);
+        glm::lerp(&points[i - 1], &points[i], t)
+    }
+
+    fn place(&self, camera: &mut Camera, t: f32) {
+        match self {
+            Self::Route { points, lengths } => {
+                let total = lengths[lengths.len() - 1];
+                let len = t * total;
+                // near the end, keep looking the way of the last stretch.
+                let from = len.min((total - LOOK_AHEAD).max(0.0));
+                let dir = Self::point_at(points, lengths, from + LOOK_AHEAD)
+                    - Self::point_at(points, lengths, from);
+                camera.set_pos(&Self::point_at(points, lengths, len));
+                if glm::length(&dir) > f32::EPSILON {
+                    camera.look_at(&(camera.pos() + dir));
+                }
+            }
+            Self::Orbit { center, distance } => {
+                let angle = t * TAU;
+                let elevation = 30f32.to_radians();
+                camera.set_pos(
+                    &(center
+                        + *distance
+                            * glm::vec3(
+                                elevation.cos() * angle.cos(),
+                                elevation.sin(),
+                                elevation.cos() * angle.sin(),
+                            )),
+                );
+                camera.look_at(center);
+            }
+        }
+    }
+}
+
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    let i = ((sorted.len() - 1) as f32 * p).round() as usize;
+    sorted[i]
+}
+
+fn summarize(times: &[f32], options: &HeadlessOptions) -> Summary {
+    let mut sorted = times.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    Summary {
+        frames: times.len(),
+        width: options.width,
+        height: options.height,
+        mean_ms: times.iter().sum::<f32>() / times.len() as f32,
+        median_ms: percentile(&sorted, 0.5),
+        p95_ms: percentile(&sorted, 0.95),
+        p99_ms: percentile(&sorted, 0.99),
+        min_ms: sorted[0],
+        max_ms: sorted[sorted.len() - 1],
+        changed_frames: Vec::new(),
+    }
+}
+
+fn write(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), Error> {
+    fs::write(path, contents).map_err(|e| Error::IOError(path.to_owned(), e))
+}
+
+fn read_hashes(path: &Path) -> Result<Vec<FrameHash>, Error> {
+    let source = fs::read_to_string(path).map_err(|e| Error::ReadError(path.to_owned(), e))?;
+    serde_json::from_str(&source).map_err(|e| Error::HashesError(path.to_owned(), e))
+}
+
+/// the frames of `hashes` that differ from `reference`, or are missing from it.
+fn compare_hashes(hashes: &[FrameHash], reference: &[FrameHash], tolerance: f32) -> Vec<u32> {
+    let mut changed = Vec::new();
+    for hash in hashes {
+        match reference.iter().find(|r| r.frame == hash.frame) {
+            Some(r) => match hash.compare(r, tolerance) {
+                Comparison::Identical => {}
+                Comparison::Similar(distance) => {
+                    println!("frame {}: similar ({distance:.4})", hash.frame);
+                }
+                Comparison::Different(distance) => {
+                    println!("frame {}: different ({distance:.4})", hash.frame);
+                    changed.push(hash.frame);
+                }
+            },
+            None => {
+                println!("frame {}: missing from the reference", hash.frame);
+                changed.push(hash.frame);
+            }
+        }
+    }
+    changed
+}
+
+async fn run(scene: &Path, options: &HeadlessOptions) -> Result<Summary, Error> {
+    let (width, height) = (options.width.max(1), options.height.max(1));
+    let frames = options.frames.max(1);
+
+    let reference = options
+        .compare_hashes
+        .as_deref()
+        .map(read_hashes)
+        .transpose()?;
+
+    let (device, queue, mip_path) = request_device("headless device", options.backend).await?;
+    let voxels = Voxels::from_path(scene)?;
+
+    let mut camera = Camera::new(glm::vec2(width as f32, height as f32));
+    camera.uniform.aspect = width as f32 / height as f32;
+    let path = match &options.camera_path {
+        Some(file) => {
+            let mut route = Route::new();
+            route.load(file)?;
+            if route.points.is_empty() {
+                return Err(Error::EmptyCameraPath(file.clone()));
+            }
+            CameraPath::route(route.points.iter().map(|p| p.xyz()).collect())
+        }
+        None => CameraPath::orbit(&camera, &voxels.bounds()),
+    };
+
+    let wgpu_state = offscreen_state(&device, &queue, &voxels, &camera, width, height, mip_path)?;
+    let target = create_render_target(&device, width, height, OFFSCREEN_FORMAT);
+    let view = target.create_view(&Default::default());
+
+    fs::create_dir_all(&options.out_dir).map_err(|e| Error::IOError(options.out_dir.clone(), e))?;
+
+    println!("rendering {frames} frames at {width}x{height}");
+    let mut frame_seed = FrameSeed::new(options.seed);
+    let mut times = Vec::with_capacity(frames as usize);
+    let mut hashes = Vec::new();
+    for frame in 0..WARMUP_FRAMES + frames {
+        let n = frame.saturating_sub(WARMUP_FRAMES);
+        path.place(&mut camera, n as f32 / (frames - 1).max(1) as f32);
+        camera.uniform.seed = frame_seed.next();
+        wgpu_state.write_cameras(&queue, &camera.uniform, &camera.uniform);
+
+        let start = Instant::now();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("headless encoder"),
+        });
+        wgpu_state.draw(&view, (width, height), &mut encoder);
+        queue.submit(iter::once(encoder.finish()));
+        device.poll(wgpu::Maintain::Wait);
+        if frame < WARMUP_FRAMES {
+            continue;
+        }
+        times.push(start.elapsed().as_secs_f32() * 1000.0);
+
+        // the readback is not part of the measure.
+        if options.image_every != 0 && n % options.image_every == 0 {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("headless readback encoder"),
+            });
+            let readback = copy_texture(&device, &mut encoder, &target);
+            queue.submit(iter::once(encoder.finish()));
+            let image = readback.read(&device);
+            match options.hash {
+                true => hashes.push(FrameHash::new(n, &image)),
+                false => image.save(options.out_dir.join(format!("frame-{n:04}.png")))?,
+            }
+        }
+    }
+
+    let csv = times
+        .iter()
+        .enumerate()
+        .fold("frame,ms\n".to_owned(), |mut csv, (n, ms)| {
+            let _ = writeln!(csv, "{n},{ms:.3}");
+            csv
+        });
+    write(&options.out_dir.join("frames.csv"), csv)?;
+
+    let mut summary = summarize(&times, options);
+    if options.hash {
+        let json = serde_json::to_string_pretty(&hashes).unwrap();
+        write(&options.out_dir.join("hashes.json"), json)?;
+    }
+    if let Some(reference) = &reference {
+        summary.changed_frames = compare_hashes(&hashes, reference, options.tolerance);
+    }
+    let json = serde_json::to_string_pretty(&summary).unwrap();
+    write(&options.out_dir.join("summary.json"), json)?;
+    Ok(summary)
+}
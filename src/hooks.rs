@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+// callbacks for the applications embedding the viewer, see `run_with_hooks`. the event loop calls
+// them when something happens in the renderer, so a host can react to it without forking the
+// event loop. they run on the thread of the event loop between two frames, a slow callback slows
+// the viewer down.
+
+/// the events of the viewer. every method does nothing by default.
+pub trait Hooks {
+    /// a scene was opened and is displayed, including the first one.
+    fn on_scene_loaded(&mut self, _event: &SceneLoaded) {}
+
+    /// voxels were placed or removed in the builder mode.
+    fn on_voxel_edited(&mut self, _event: &VoxelEdited) {}
+
+    /// the user double-clicked the scene, and the camera flies to the voxel hit if any.
+    fn on_pick(&mut self, _event: &Pick) {}
+
+    /// a frame was presented.
+    fn on_frame_stats(&mut self, _stats: &FrameStats) {}
+}
+
+/// the hooks of `run`, which ignore every event.
+impl Hooks for () {}
+
+#[derive(Clone, Debug)]
+pub struct SceneLoaded {
+    pub path: PathBuf,
+    /// size of the scene before padding, in voxels.
+    pub size: [f32; 3],
+}
+
+#[derive(Clone, Debug)]
+pub struct VoxelEdited {
+    /// the box of voxels filled, max excluded, in world coordinates.
+    pub min: [u32; 3],
+    pub max: [u32; 3],
+    /// the palette entry, 0 when the voxels were removed.
+    pub material: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Pick {
+    /// the cursor, in physical pixels from the top left of the window.
+    pub pixel: [f32; 2],
+    pub hit: Option<PickHit>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PickHit {
+    /// in world coordinates, in voxels.
+    pub pos: [f32; 3],
+    pub voxel: [u32; 3],
+    /// normal of the face hit.
+    pub normal: [f32; 3],
+    /// from the camera, in voxels.
+    pub distance: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct FrameStats<'a> {
+    /// real seconds since the previous frame.
+    pub frame_time: f32,
+    /// gpu time of the passes in milliseconds, empty unless the profiler is enabled and the
+    /// device supports timestamp queries.
+    pub gpu_timings: &'a [(&'static str, f32)],
+}
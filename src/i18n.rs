@@ -0,0 +1,451 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+// ui translations, as a plain key-map. the english text is the key: `tr("bake")` returns its
+// translation in the current language, or the key itself if there is none, so a missing entry
+// shows up in english instead of breaking the ui. log messages are not translated.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum Language {
+    #[default]
+    English,
+    French,
+}
+
+/// languages and their native names, in ui order.
+pub const LANGUAGES: [(Language, &str); 2] = [
+    (Language::English, "English"),
+    (Language::French, "Français"),
+];
+
+static CURRENT: AtomicU8 = AtomicU8::new(Language::English as u8);
+
+pub fn language() -> Language {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Language::French,
+        _ => Language::English,
+    }
+}
+
+pub fn set_language(language: Language) {
+    CURRENT.store(language as u8, Ordering::Relaxed);
+}
+
+/// translate a ui string to the current language.
+pub fn tr(text: &str) -> &str {
+    match language() {
+        Language::English => text,
+        Language::French => french(text).unwrap_or(text),
+    }
+}
+
+fn french(text: &str) -> Option<&'static str> {
+    Some(match text {
+        // windows
+        "Error" => "Erreur",
+        "Session" => "Session",
+        "Loading scene" => "Chargement de la scène",
+        "Debug" => "Débogage",
+        "Controls" => "Contrôles",
+        "Baked lighting" => "Éclairage précalculé",
+        "Reflection probes" => "Sondes de réflexion",
+        "Measure" => "Mesure",
+        "Route" => "Itinéraire",
+        "Environment" => "Environnement",
+        "Lights" => "Lumières",
+        "Compare" => "Comparaison",
+        "Fog volumes" => "Volumes de brouillard",
+        "Turntable" => "Vue tournante",
+        "Timelapse" => "Timelapse",
+
+        // error, session and loading
+        "the previous shaders are kept until the errors are fixed." => "les shaders précédents sont conservés jusqu'à la correction des erreurs.",
+        "high contrast" => "contraste élevé",
+        "debug colors" => "couleurs de débogage",
+        "channels" => "canaux",
+        "viridis and cividis stay readable with color blindness" => "viridis et cividis restent lisibles avec un daltonisme",
+        "dismiss" => "fermer",
+        "a previous session was found." => "une session précédente a été trouvée.",
+        "continue last session" => "reprendre la dernière session",
+        "save current settings" => "enregistrer les réglages actuels",
+        "start with the window size, scene, camera and shader settings next time" => "démarrer avec la taille de fenêtre, la scène, la caméra et les réglages des shaders la prochaine fois",
+        "start fresh" => "nouvelle session",
+        "chunks" => "blocs",
+        "decompressing" => "décompression",
+        "remapping the palette" => "remappage de la palette",
+        "padding the volume" => "remplissage du volume",
+        "deriving the colors" => "calcul des couleurs",
+        "cancel" => "annuler",
+
+        // debug
+        "fps" => "ips",
+        "frame watchdog" => "surveillance des images lentes",
+        "lower the quality when frames keep taking over 200 ms" => {
+            "baisser la qualité quand les images prennent plus de 200 ms à la suite"
+        }
+        "cam" => "caméra",
+        "speed" => "vitesse",
+        "copy view link" => "copier le lien de la vue",
+        "the camera, lighting and features, to share this view" => {
+            "la caméra, l'éclairage et les fonctionnalités, pour partager cette vue"
+        }
+        "go to view" => "aller à la vue",
+        "camera collisions" => "collisions de la caméra",
+        "orbit camera" => "caméra orbitale",
+        "turn around the center of the view: middle drag rotates, shift pans, the wheel zooms" => {
+            "tourner autour du centre de la vue : le clic du milieu tourne, maj déplace, la molette zoome"
+        }
+        "free flight (Q/E roll)" => "vol libre (roulis Q/E)",
+        "up axis" => "axe vertical",
+        "for the scenes that are not y-up" => "pour les scènes dont l'axe vertical n'est pas y",
+        "freeze render camera" => "figer la caméra de rendu",
+        "fly a separate debug camera, the frozen frustum is drawn" => {
+            "déplacer une caméra de débogage séparée, le frustum figé est dessiné"
+        }
+        "render cam distance" => "distance à la caméra de rendu",
+        "record frame history" => "enregistrer l'historique des images",
+        "history seconds" => "secondes d'historique",
+        "frames" => "images",
+        "export gif (F10)" => "exporter un gif (F10)",
+        "screenshot (F12)" => "capture d'écran (F12)",
+        "copy frame (F11)" => "copier l'image (F11)",
+        "copy the current view to the clipboard" => "copier la vue actuelle dans le presse-papiers",
+
+        // controls
+        "language" => "langue",
+        "ui scale" => "échelle de l'interface",
+        "render at logical resolution" => "rendu en résolution logique",
+        "render fewer pixels on high dpi monitors, and upscale the image" => {
+            "calculer moins de pixels sur les écrans haute densité, et agrandir l'image"
+        }
+        "dynamic resolution" => "résolution dynamique",
+        "lower the resolution when the frames are too slow for the target fps" => {
+            "baisser la résolution quand les images sont trop lentes pour les fps visés"
+        }
+        "target fps" => "fps visés",
+        "render scale" => "échelle du rendu",
+        "octree depth" => "profondeur de l'octree",
+        "octree max iter" => "itérations max de l'octree",
+        "render mode" => "mode de rendu",
+        "raymarched" => "lancer de rayons",
+        "path traced" => "tracé de chemins",
+        "path traced: a few bounces of light per pixel, averaged over the frames while the view is still" => {
+            "tracé de chemins : quelques rebonds de lumière par pixel, moyennés sur les images tant que la vue est immobile"
+        }
+        "accumulated frames" => "images accumulées",
+        "traversal" => "parcours",
+        "distance field" => "champ de distance",
+        "brick map" => "carte de briques",
+        "64-tree" => "arbre 64",
+        "render distance" => "distance d'affichage",
+        "resident bricks" => "briques résidentes",
+        "visibility culling" => "élimination des parties invisibles",
+        "skip the bricks not visible from the camera region" => {
+            "ignorer les briques invisibles depuis la région de la caméra"
+        }
+        "smooth contours" => "contours lissés",
+        "clip the surface voxels with a plane fitted to their neighbors" => {
+            "couper les voxels de surface par un plan ajusté à leurs voisins"
+        }
+        "iteration feedback" => "retour des itérations",
+        "count the iterations of the primary rays" => "compter les itérations des rayons primaires",
+        "auto max iter" => "itérations max automatiques",
+        "iterations p99" => "itérations (99e centile)",
+        "export octree" => "exporter l'octree",
+        "save the octree built on the gpu and compare it with the cpu reference" => {
+            "enregistrer l'octree construit sur le gpu et le comparer à la référence cpu"
+        }
+        "Octree inspector" => "Inspecteur d'octree",
+        "level" => "niveau",
+        "mismatching nodes" => "nœuds différents",
+        "slice viewer" => "visionneuse de coupes",
+        "Slice viewer" => "Visionneuse de coupes",
+        "Colors" => "Couleurs",
+        "1: ray complexity, 2: depth, 3: normals, 4: volume aabb entry and exit" => {
+            "1 : complexité des rayons, 2 : profondeur, 3 : normales, 4 : entrée et sortie de la boîte englobante"
+        }
+        "highlight rays missing the volume" => "surligner les rayons qui manquent le volume",
+        "rays that never enter the bounding box of the scene are tinted magenta" => {
+            "les rayons qui n'entrent jamais dans la boîte englobante de la scène sont teintés en magenta"
+        }
+        "show bounds" => "afficher les contours",
+        "outline the volume, the fog volumes and the local lights over the scene" => {
+            "dessiner le contour du volume, des volumes de brouillard et des lumières locales"
+        }
+        "brush" => "pinceau",
+        "[ and ] to resize" => "[ et ] pour redimensionner",
+        "snap" => "grille",
+        "g to cycle" => "g pour changer",
+        "target" => "cible",
+        "no target" => "aucune cible",
+        "remap" => "correspondance",
+        "ByIndex" => "par indice",
+        "Nearest" => "couleur la plus proche",
+        "how an imported palette recolors the scene: by entry index, or with the nearest \
+         imported color" => {
+            "comment une palette importée recolore la scène : par indice, ou avec la couleur \
+             importée la plus proche"
+        }
+        "import…" => "importer…",
+        "export…" => "exporter…",
+        "save scene" => "enregistrer la scène",
+        "left click removes the brush, right click places it against the face. enter fills the \
+         brush, delete clears it" => {
+            "clic gauche retire le pinceau, clic droit le place contre la face. entrée remplit le \
+             pinceau, suppr le vide"
+        }
+        "toggle builder mode (B)" => "activer le mode construction (B)",
+        "distance field max iter" => "itérations max du champ de distance",
+        "grid depth" => "profondeur de la grille",
+        "grid max iter" => "itérations max de la grille",
+        "shadow max iter" => "itérations max des ombres",
+        "shadow softness" => "douceur des ombres",
+        "shadow cone angle" => "angle du cône d'ombre",
+        "shadow strength" => "intensité des ombres",
+        "ao strength" => "intensité de l'occlusion ambiante",
+        "debug display" => "affichage de débogage",
+        "features" => "fonctionnalités",
+        "split-screen comparison" => "comparaison en écran partagé",
+        "the right of the divider is rendered with the settings below" => {
+            "la droite du séparateur est rendue avec les réglages ci-dessous"
+        }
+        "divider" => "séparateur",
+        "shadows" => "ombres",
+        "contact shadows" => "ombres de contact",
+        "ambient occlusion" => "occlusion ambiante",
+        "fog" => "brouillard",
+        "sky" => "ciel",
+        "ground" => "sol",
+        "brick culling" => "élimination des briques hors champ",
+        "beam optimization" => "optimisation par faisceaux",
+        "light culling" => "élimination des lumières par cluster",
+        "reflections" => "réflexions",
+        "shadow ray level" => "niveau des rayons d'ombre",
+        "stop the hard shadow rays at octants of 2^level voxels: faster, but blockier shadows" => {
+            "arrêter les rayons d'ombre aux octants de 2^niveau voxels : plus rapide, mais des ombres plus grossières"
+        }
+        "ao level" => "niveau de l'occlusion ambiante",
+        "sample the ao from a coarser mip level: darkens larger creases" => {
+            "échantillonner l'occlusion ambiante dans une mipmap plus grossière : assombrit les creux plus larges"
+        }
+        "MSAA level" => "niveau de MSAA",
+        "mip anti-aliasing" => "anticrénelage par mipmaps",
+        "mipmap filter" => "filtre des mipmaps",
+        "box" => "boîte",
+        "gaussian" => "gaussien",
+        "occupancy-weighted" => "pondéré par l'occupation",
+        "dominant color" => "couleur dominante",
+        "how the distant colors are downsampled. dominant color keeps the flat colors of stylized scenes" => {
+            "comment les couleurs lointaines sont sous-échantillonnées. la couleur dominante garde les aplats des scènes stylisées"
+        }
+        "average the colors of the distant voxels covered by each pixel" => "moyenne des couleurs des voxels lointains couverts par chaque pixel",
+        "angle" => "angle",
+        "azimuth" => "azimut",
+        "below 0, the sun has set and the sky turns to night" => {
+            "sous 0, le soleil est couché et le ciel passe à la nuit"
+        }
+        "turbidity" => "turbidité",
+        "haze of the sky, from clear to hazy" => "voile du ciel, de clair à brumeux",
+        "sun speed" => "vitesse du soleil",
+        "degrees per second of scene time" => "degrés par seconde de temps de la scène",
+        "time scale" => "échelle de temps",
+        "speed of the animations, - and = halve and double it" => "vitesse des animations, - et = la divisent et la doublent",
+        "place the sun" => "placer le soleil",
+        "click in the viewport to point the sun towards the cursor" => {
+            "cliquez dans la vue pour orienter le soleil vers le curseur"
+        }
+        "light shadows" => "ombres des lumières",
+        "sun shadows" => "ombres du soleil",
+        "sun shadow max iter" => "itérations max de l'ombre du soleil",
+        "sun shadow softness" => "douceur de l'ombre du soleil",
+        "shadow budget" => "budget des ombres",
+        "iterations shared by all the lights that cast shadows" => {
+            "itérations partagées par toutes les lumières qui projettent des ombres"
+        }
+
+        // baked lighting
+        "using baked lighting" => "éclairage précalculé utilisé",
+        "using realtime lighting" => "éclairage en temps réel utilisé",
+        "baking uses the current sun direction." => {
+            "le précalcul utilise la direction actuelle du soleil."
+        }
+        "bake" => "précalculer",
+        "bake and write into .wvox" => "précalculer et écrire dans le .wvox",
+        "clear" => "effacer",
+
+        // reflection probes
+        "probes" => "sondes",
+        "baked" => "précalculées",
+        "place at camera" => "placer à la caméra",
+        "reflection strength" => "intensité des réflexions",
+
+        // measure
+        "mark A" => "marquer A",
+        "mark B" => "marquer B",
+        "distance" => "distance",
+        "voxels" => "voxels",
+        "voxels per meter" => "voxels par mètre",
+        "noise seed: " => "graine du bruit : ",
+        "detail textures distance" => "distance des textures de détail",
+        "detail noise of the selected material" => "bruit de détail du matériau sélectionné",
+        "the selected material is water" => "le matériau sélectionné est de l'eau",
+        "roughness" => "rugosité",
+        "metalness" => "métallicité",
+        "emission" => "émission",
+        "index of refraction" => "indice de réfraction",
+        "foam along the shores and brighter shallow water" => "écume le long des rives et eau peu profonde plus claire",
+        "save scene metadata" => "enregistrer les métadonnées de la scène",
+
+        // route
+        "load" => "charger",
+        "points" => "points",
+        "visible" => "visible",
+        "color" => "couleur",
+        "width" => "largeur",
+
+        // environment
+        "lighting" => "éclairage",
+        "Noon" => "Midi",
+        "GoldenHour" => "Heure dorée",
+        "Night" => "Nuit",
+        "Overcast" => "Couvert",
+        "Studio" => "Studio",
+        "stored with the scene metadata, see the Measure window" => {
+            "enregistré avec les métadonnées de la scène, voir la fenêtre Mesure"
+        }
+        "ambient" => "lumière ambiante",
+        "exposure" => "exposition",
+        "gpu profiler" => "profileur gpu",
+        "gpu time of the passes, needs timestamp queries" => "temps gpu des passes, nécessite les requêtes d'horodatage",
+        "beam pre-pass" => "pré-passe de faisceaux",
+        "primary rays" => "rayons primaires",
+        "shadows and ao" => "ombres et occlusion",
+        "octree build" => "construction de l'octree",
+        "egui" => "interface",
+        "auto exposure" => "exposition automatique",
+        "adapt the exposure to the brightness of the view" => "adapter l'exposition à la luminosité de la vue",
+        "min EV" => "IL min",
+        "max EV" => "IL max",
+        "adaptation speed" => "vitesse d'adaptation",
+        "background" => "arrière-plan",
+        "Solid" => "Uni",
+        "Gradient" => "Dégradé",
+        "Sky" => "Ciel",
+        "StudioGrey" => "Gris studio",
+        "Black" => "Noir",
+        "White" => "Blanc",
+        "background colors" => "couleurs de l'arrière-plan",
+        "None" => "Aucun",
+        "Flat" => "Plat",
+        "Checker" => "Damier",
+        "ground colors" => "couleurs du sol",
+        "checker size (m)" => "taille du damier (m)",
+        "ground height: " => "hauteur du sol : ",
+        " voxels" => " voxels",
+        "horizon fog" => "brouillard à l'horizon",
+        "fog color" => "couleur du brouillard",
+        "fog distance (m)" => "distance du brouillard (m)",
+
+        // lights
+        "light" => "lumière",
+        "kind" => "type",
+        "point" => "ponctuelle",
+        "spot" => "projecteur",
+        "directional" => "directionnelle",
+        "move to camera" => "déplacer à la caméra",
+        "intensity" => "intensité",
+        "radius (m)" => "rayon (m)",
+        "cone" => "cône",
+        "add at camera" => "ajouter à la caméra",
+
+        // fog volumes
+        "volume" => "volume",
+        "min" => "min",
+        "max" => "max",
+        "density (per m)" => "densité (par m)",
+        "remove" => "supprimer",
+        "add around camera" => "ajouter autour de la caméra",
+
+        // turntable
+        "seconds" => "secondes",
+        "height" => "hauteur",
+        "elevation" => "élévation",
+        "export turntable" => "exporter la vue tournante",
+
+        // timelapse
+        "open" => "ouvrir",
+        "snapshot" => "instantané",
+        "pause" => "pause",
+        "play" => "lecture",
+        "seconds per snapshot" => "secondes par instantané",
+
+        // command palette
+        "search actions" => "rechercher une action",
+        "no matching action" => "aucune action correspondante",
+        "load scene…" => "charger une scène…",
+        "open scene in new tab…" => "ouvrir une scène dans un nouvel onglet…",
+        "open a scene in a new tab" => "ouvrir une scène dans un nouvel onglet",
+        "close tab" => "fermer l'onglet",
+        "save session" => "enregistrer la session",
+        "teleport to spawn" => "téléporter au point de départ",
+        "teleport to route start" => "téléporter au début de l'itinéraire",
+        "screenshot" => "capture d'écran",
+        "export gif" => "exporter un gif",
+        "reload shaders" => "recharger les shaders",
+        "bake lighting" => "précalculer l'éclairage",
+        "clear baked lighting" => "effacer l'éclairage précalculé",
+        "bake reflection probes" => "précalculer les sondes de réflexion",
+        "toggle" => "basculer",
+        "toggle camera collisions" => "basculer les collisions de la caméra",
+        "toggle frozen render camera" => "basculer la caméra de rendu figée",
+        "toggle route" => "basculer l'itinéraire",
+
+        // error hints
+        "check the scene path given as first argument." => {
+            "vérifiez le chemin de la scène donné en premier argument."
+        }
+        "rebuild with different cargo features, see `--diagnose`." => {
+            "recompilez avec d'autres features cargo, voir `--diagnose`."
+        }
+        "the file is not a valid MagicaVoxel .vox scene, save it again from MagicaVoxel." => {
+            "le fichier n'est pas une scène MagicaVoxel .vox valide, enregistrez-la à nouveau depuis MagicaVoxel."
+        }
+        "the file is not a valid .wvox scene, convert it again." => {
+            "le fichier n'est pas une scène .wvox valide, convertissez-la à nouveau."
+        }
+        "check the scene url and the network. the server must allow range requests." => {
+            "vérifiez l'url de la scène et le réseau. le serveur doit accepter les requêtes \
+             partielles (range)."
+        }
+        "the scene is not supported, see `--diagnose`." => {
+            "la scène n'est pas prise en charge, voir `--diagnose`."
+        }
+        "the file is not a valid .wchunks scene, export it again." => {
+            "le fichier n'est pas une scène .wchunks valide, exportez-la à nouveau."
+        }
+        "the scene of the session was moved or changed, open it directly." => {
+            "la scène de la session a été déplacée ou modifiée, ouvrez-la directement."
+        }
+        "delete `.wender-session` to start from scratch." => {
+            "supprimez `.wender-session` pour repartir de zéro."
+        }
+        "scene too large for this gpu: downscale it, or crop it when converting." => {
+            "scène trop grande pour ce gpu : réduisez-la, ou recadrez-la lors de la conversion."
+        }
+        "run `--check-shaders` to locate the error." => {
+            "lancez `--check-shaders` pour localiser l'erreur."
+        }
+        "check the output path, the format is picked from its extension." => {
+            "vérifiez le chemin de sortie, le format dépend de son extension."
+        }
+        "use a png strip, a JASC .pal or a lospec .hex file, the format is picked from its \
+         extension." => {
+            "utilisez une bande png, un fichier JASC .pal ou un fichier lospec .hex, le format \
+             dépend de son extension."
+        }
+
+        _ => return None,
+    })
+}
@@ -1,32 +1,140 @@
+mod accumulation;
+mod bake;
+mod brickmap;
+mod budget;
+mod cache;
 mod camera;
+mod capture;
+mod chunks;
+pub mod cli;
+mod clipboard;
+mod clock;
+mod collision;
+mod compare;
+mod config;
+mod detail;
+mod diagnose;
+mod dvo;
+mod editor;
+mod environment;
+mod error;
+mod exposure;
+mod features;
+mod feedback;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod fog;
+mod framehash;
+mod frustum;
+mod gizmos;
+mod headless;
+mod hooks;
+mod i18n;
 mod lights;
+mod loading;
+mod materials;
+mod noise;
+mod palette;
+mod palette_file;
 mod preproc;
+mod probes;
+mod pvs;
+mod random;
+mod remap;
+mod resolution;
+mod route;
+mod scene;
+mod session;
+mod settings;
+mod stats;
+mod stream;
+mod tabs;
+mod thumbnail;
+mod timelapse;
+mod turntable;
 mod ui;
+mod validate;
+mod viewlink;
 mod voxels;
+mod watchdog;
+mod watcher;
+mod web;
 mod wgpu_util;
 
-use std::{iter, sync::Arc, time::Duration};
+use std::{
+    iter, mem,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use ui::{run_egui, FpsCounter};
+use image::RgbaImage;
+use ui::{run_egui, DvoInspector, FpsCounter, Measure, SliceViewer};
+use web_time::{Duration, Instant};
 use wgpu::util::DeviceExt;
 use winit::{
     dpi::LogicalSize,
+    error::EventLoopError,
     event::*,
     event_loop::{ControlFlow, EventLoop, EventLoopBuilder},
     keyboard::{Key, KeyCode, NamedKey, PhysicalKey},
-    platform::x11::EventLoopBuilderExtX11,
     window::{Window, WindowBuilder},
 };
 
 use nalgebra_glm as glm;
+use ndarray::s;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use crate::camera::{Camera, Controller};
+use crate::accumulation::Accumulation;
+use crate::brickmap::BrickMap;
+use crate::budget::Fallback;
+use crate::cache::SceneCache;
+use crate::camera::{Camera, CameraMode, Controller};
+use crate::capture::{copy_texture, create_render_target, timestamped_path, FrameHistory};
+use crate::clipboard::ImageClipboard;
+use crate::clock::Clock;
+use crate::collision::Collider;
+use crate::compare::Compare;
+use crate::config::Config;
+use crate::dvo::Dvo;
+use crate::editor::Editor;
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::exposure::AutoExposure;
+use crate::feedback::IterFeedback;
+use crate::fog::FogVolumes;
+use crate::frustum::Frustum;
+use crate::gizmos::{drag_light_handle, light_handles, Gizmos, LightDrag, HANDLE_SIZE};
 use crate::lights::Lights;
+use crate::loading::{SceneLoad, Stage};
+use crate::materials::Materials;
+use crate::palette::CommandPalette;
+use crate::probes::{Probes, MAX_PROBES, PROBE_SIZE};
+use crate::random::FrameSeed;
+use crate::resolution::DynamicResolution;
+use crate::route::Route;
+use crate::scene::SceneMeta;
+use crate::session::Session;
+use crate::settings::Settings;
+use crate::stream::SceneStream;
+use crate::tabs::{SceneTab, Tabs};
+use crate::timelapse::Timelapse;
+use crate::turntable::Turntable;
+use crate::watchdog::Watchdog;
+use crate::watcher::ShaderWatcher;
 use crate::{voxels::Voxels, wgpu_util::*};
 
+pub use crate::config::scene_or_default;
+pub use crate::diagnose::diagnose;
+pub use crate::headless::{headless, HeadlessOptions};
+pub use crate::hooks::{FrameStats, Hooks, Pick, PickHit, SceneLoaded, VoxelEdited};
+pub use crate::pvs::bake_pvs;
+pub use crate::stats::export_stats;
+pub use crate::thumbnail::thumbnail;
+pub use crate::validate::validate;
+pub use crate::web::export_web;
+
 struct State {
     surface: wgpu::Surface<'static>,
     device: wgpu::Device,
@@ -37,29 +145,158 @@ struct State {
 
     window: Arc<Window>,
     cursor_grabbed: bool,
+    cursor_pos: glm::Vec2,
+    last_click: Option<(Instant, glm::Vec2)>,
+    /// the next click in the viewport points the sun towards the cursor.
+    placing_sun: bool,
+    /// the light handle dragged with the left button, see `grab_light_handle`.
+    light_drag: Option<LightDrag>,
+
+    scene_path: PathBuf,
+    meta: SceneMeta,
+    /// the displayed volume, kept on the cpu for editing.
+    voxels: Voxels,
+    /// spawn position and look-at target of the scene.
+    spawn: (glm::Vec3, glm::Vec3),
+    stream: Option<SceneStream>,
+    /// the scene picked in the file dialog, loading in the background.
+    scene_load: Option<SceneLoad>,
+    /// streamed bricks of the volume, when the brick map is the traversal.
+    bricks: Option<BrickMap>,
 
     camera: Camera,
     lights: Lights,
+    clock: Clock,
+    /// the seeds of the random numbers of the shaders, see `random.rs`.
+    frame_seed: FrameSeed,
+    route: Route,
+    environment: Environment,
+    frustum: Frustum,
+    settings: Settings,
+    probes: Probes,
+    fog_volumes: FogVolumes,
+    materials: Materials,
+    /// lights, environment and settings the sky ambient was last projected with.
+    sky_inputs: Vec<u8>,
+    accumulation: Accumulation,
     controller: Controller,
+    collider: Collider,
+    collisions: bool,
+    timelapse: Timelapse,
+    turntable: Turntable,
+    editor: Editor,
+    /// lines drawn over the scene, see `gizmos.rs`.
+    gizmos: Gizmos,
 
     egui_renderer: egui_wgpu::Renderer,
     egui_ctx: egui::Context,
     fps: FpsCounter,
+    history: FrameHistory,
+    clipboard: ImageClipboard,
+    measure: Measure,
+    palette: CommandPalette,
+    feedback: IterFeedback,
+    exposure: AutoExposure,
+    watchdog: Watchdog,
+    dvo_inspector: Option<DvoInspector>,
+    slice_viewer: SliceViewer,
 
     constants: ShaderConstants,
+    /// how the scene was degraded to fit in gpu memory, see `budget.rs`.
+    fallback: Option<Fallback>,
+    /// the octree depth was lowered in the ui, the scene is downsampled from its file.
+    reduced_depth: bool,
+
+    error: Option<Error>,
+    /// an informative message, shown until dismissed.
+    notice: Option<String>,
+    /// text of the view link box, see `viewlink.rs`.
+    view_link: String,
+    /// render the scene at the logical window size and upscale it, so the cost of a frame does
+    /// not depend on the dpi of the monitor.
+    logical_render: bool,
+    /// lowers the render size further to hold a frame rate, see `resolution.rs`.
+    resolution: DynamicResolution,
+    /// black and white ui with thick outlines, see `ui::visuals`.
+    high_contrast: bool,
+    /// offer to continue the last session, it is replaced by the current one on exit.
+    session_prompt: bool,
+    /// reloads the shaders when their sources are saved.
+    shader_watcher: Option<ShaderWatcher>,
+    /// the other open scenes.
+    tabs: Tabs,
+    /// split-screen comparison with a second settings profile.
+    compare: Compare,
+    /// the callbacks of the embedding application, see `run_with_hooks`.
+    hooks: Box<dyn Hooks>,
+}
+
+fn notice_of(fallback: &Fallback) -> String {
+    format!(
+        "the scene does not fit in gpu memory: {}.",
+        fallback.describe()
+    )
+}
+
+/// build the octree, the color mips, the distance field and the contours of the scene, reading
+/// the octree and the mips from the scene cache when `cache` is set and it is up to date.
+fn build_scene(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    wgpu_state: &mut WgpuState,
+    voxels: &Voxels,
+    constants: &ShaderConstants,
+    cache: bool,
+) {
+    let _span = tracing::info_span!("octree build").entered();
+    // compute svo on the gpu in the compute shader
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("compute encoder"),
+    });
+    let cache_key = cache.then(|| SceneCache::key(voxels, constants, wgpu_state.has_color_mips()));
+    let cache = cache_key.and_then(|key| SceneCache::load(&voxels.path, key));
+    match &cache {
+        Some(cache) => {
+            wgpu_state.write_octree(queue, &cache.dvo);
+            wgpu_state.write_color_mips(queue, &cache.color_mips);
+        }
+        None => {
+            wgpu_state.compute_octree(device, &mut encoder, voxels.dim());
+            wgpu_state.compute_mipmap(device, &mut encoder, voxels.dim());
+        }
+    }
+    wgpu_state.compute_sdf(device, &mut encoder);
+    wgpu_state.compute_contours(device, &mut encoder);
+    queue.submit(iter::once(encoder.finish()));
+
+    if let (Some(key), None) = (cache_key, cache) {
+        let cache = SceneCache::new(
+            key,
+            wgpu_state.read_octree(device, queue),
+            wgpu_state.read_color_mips(device, queue),
+        );
+        if let Err(err) = cache.save(&voxels.path) {
+            eprintln!("failed to save the scene cache: {}", err);
+        }
+    }
 }
 
 impl State {
-    async fn new(window: Window) -> Self {
+    async fn new(
+        window: Window,
+        scene: &Path,
+        startup: &Config,
+        backend: cli::Backend,
+    ) -> Result<Self, Error> {
         let window = Arc::new(window);
         let size = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::VULKAN,
+            backends: backend.backends(),
             ..Default::default()
         });
 
-        let surface = instance.create_surface(window.clone()).unwrap();
+        let surface = instance.create_surface(window.clone())?;
 
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
@@ -68,33 +305,39 @@ impl State {
                 force_fallback_adapter: false,
             })
             .await
-            .unwrap();
+            .ok_or(Error::NoAdapter)?;
 
         println!("{:#?}", adapter.get_info());
         println!("{:#?}", adapter.limits());
 
+        println!("{}", features::describe());
+        features::validate_adapter(&adapter)?;
+
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
-                    required_limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_defaults()
-                    } else {
-                        // wgpu::Limits {
-                        //     max_storage_buffer_binding_size: (1 << 30) * 2 - 1, // 5 GiB
-                        //     max_buffer_size: (1 << 30) * 2 - 1,                 // 5 GiB
-                        //     max_texture_dimension_3d: 2048,
-                        //     ..Default::default()
-                        // }
-                        adapter.limits()
-                    },
+                    // timestamp queries are optional, for the gpu profiler. the adapter specific
+                    // formats are native only, browsers expose the webgpu formats.
+                    required_features: adapter.features()
+                        & (wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                            | wgpu::Features::TIMESTAMP_QUERY),
+                    // browsers report the limits of the gpu as well, the downlevel defaults are
+                    // too small for the 3d storage textures.
+                    // wgpu::Limits {
+                    //     max_storage_buffer_binding_size: (1 << 30) * 2 - 1, // 5 GiB
+                    //     max_buffer_size: (1 << 30) * 2 - 1,                 // 5 GiB
+                    //     max_texture_dimension_3d: 2048,
+                    //     ..Default::default()
+                    // }
+                    required_limits: adapter.limits(),
                     // memory_hints: wgpu::MemoryHints::Performance,
                 },
                 None, // trace_path
             )
-            .await
-            .unwrap();
+            .await?;
+        let mip_path = features::mip_path(&adapter, device.features());
+        println!("color mipmaps: {mip_path:?}");
 
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
@@ -104,11 +347,13 @@ impl State {
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
         let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            // copies of the surface are used for frame captures, when supported.
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | (surface_caps.usages & wgpu::TextureUsages::COPY_SRC),
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::AutoVsync,
+            present_mode: startup.vsync.present_mode(),
             desired_maximum_frame_latency: 2,
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
@@ -116,60 +361,129 @@ impl State {
 
         surface.configure(&device, &surface_config);
 
-        let camera = Camera::new(glm::vec2(size.width as f32, size.height as f32));
-        let lights = Lights::new(
+        let mut camera = Camera::new(glm::vec2(size.width as f32, size.height as f32));
+        let mut lights = Lights::new(
             f32::to_degrees(glm::half_pi()),
             f32::to_degrees(glm::quarter_pi()),
         );
 
-        let voxels = Voxels::new();
+        let route = Route::new();
+
+        // chunked scenes start empty and fill in as the chunks arrive. on the web, the scene is
+        // always streamed, there is no file system to read it from.
+        let streamed =
+            cfg!(target_arch = "wasm32") || scene.extension().is_some_and(|ext| ext == "wchunks");
+        let (voxels, stream, fallback) = if streamed {
+            let (voxels, stream) = SceneStream::open(scene, glm::zero()).await?;
+            // the streamed chunks are written at full resolution, the scene cannot be downsampled.
+            let max_dim = device.limits().max_texture_dimension_3d;
+            if voxels.dim() > max_dim {
+                return Err(Error::SceneTooLarge {
+                    dim: voxels.dim(),
+                    max: max_dim,
+                });
+            }
+            (voxels, Some(stream), None)
+        } else {
+            let (voxels, fallback) = budget::fit(&device, Voxels::from_path(scene)?);
+            (voxels, None, fallback)
+        };
+
+        let mut environment = Environment::new();
+        if let Some(preset) = voxels.meta.lighting {
+            preset.apply(&mut lights, &mut environment);
+        }
+        lights.update_local(&voxels.meta);
+        environment.update(&voxels.meta);
 
-        let controller = Controller::new();
+        let mut frustum = Frustum::new();
+        frustum.far = voxels.dim() as f32;
+
+        let settings = Settings::new();
+        let probes = Probes::new();
+        let mut fog_volumes = FogVolumes::new();
+        fog_volumes.update(&voxels.meta);
+        let mut materials = Materials::new();
+        materials.update(&voxels.meta, voxels.palette());
+
+        let mut controller = Controller::new();
+        controller.speed = voxels.meta.to_voxels(Controller::DEFAULT_SPEED);
+        controller.sensitivity = startup.sensitivity;
+        controller.up = startup.up_axis;
+        let (spawn, target) = voxels.spawn(voxels.meta.to_voxels(Controller::EYE_HEIGHT));
+        camera.set_pos(&startup.spawn.map_or(spawn, glm::Vec3::from));
+        controller.look_at(&camera, &target);
+        if let Some(stream) = &stream {
+            stream.set_focus(&camera.pos());
+        }
+        let collider = Collider::new(&voxels);
+        let timelapse = Timelapse::new();
+        let turntable = Turntable::new();
 
         let egui_renderer = egui_wgpu::Renderer::new(&device, surface_config.format, None, 1);
         let egui_ctx = egui::Context::default();
         let fps = FpsCounter::new();
+        let history = FrameHistory::new();
+        let measure = Measure::new();
 
-        let grid_depth = 2;
+        let detail_atlas = detail::load_atlas(&voxels);
         let constants = ShaderConstants {
             octree_depth: voxels.dim().ilog2() - 1,
-            octree_max_iter: 200,
-            grid_depth,
-            grid_max_iter: 2u32.pow(grid_depth) * 4,
-            shadow_max_iter: 100,
-            shadow_cone_angle: 1,
-            shadow_strength: 10,
-            ao_strength: 10,
-            msaa_level: 1,
-            debug_display: 0,
+            baked_lighting: voxels.lightmap.is_some() as u32,
+            noise_seed: voxels.meta.noise_seed,
+            contours: voxels.meta.contours as u32,
+            detail_textures: detail_atlas.is_some() as u32,
+            detail_distance: voxels.meta.detail_distance,
+            ..startup.constants.clone()
         };
 
-        let wgpu_state = WgpuState::new(
+        let mut wgpu_state = WgpuState::new(
             &device,
             &queue,
             &surface_config,
             &Buffers {
                 camera: camera.as_bytes(),
                 lights: lights.as_bytes(),
+                local_lights: lights.local_bytes(),
+                route: route.as_bytes(),
+                environment: environment.as_bytes(),
+                frustum: frustum.as_bytes(),
+                settings: settings.as_bytes(),
+                probes: probes.as_bytes(),
+                fog_volumes: fog_volumes.as_bytes(),
+                materials: materials.as_bytes(),
                 voxels: voxels.voxels_bytes(),
                 colors: voxels.colors_bytes(),
+                lightmap: voxels.lightmap_bytes(),
             },
             &constants,
-        );
-
-        {
-            // compute svo on the gpu in the compute shader
-            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("compute encoder"),
-            });
-            wgpu_state.compute_octree(&device, &mut encoder, voxels.dim());
-            wgpu_state.compute_mipmap(&device, &mut encoder, voxels.dim());
-            queue.submit(iter::once(encoder.finish()));
+            Fallback::color_mips(fallback),
+            mip_path,
+        )?;
+        if detail_atlas.is_some() {
+            wgpu_state.set_detail(&device, &queue, detail_atlas.as_ref());
         }
+        let mut feedback = IterFeedback::new();
+        feedback.enabled = constants.iter_feedback != 0;
+        let mut exposure = AutoExposure::new();
+        exposure.enabled = constants.auto_exposure != 0;
+
+        // streamed scenes are incomplete, and there is no file system on the web.
+        let cache = !streamed && !cfg!(target_arch = "wasm32");
+        build_scene(&device, &queue, &mut wgpu_state, &voxels, &constants, cache);
 
-        Self {
+        Ok(Self {
             window,
             cursor_grabbed: false,
+            cursor_pos: glm::zero(),
+            last_click: None,
+            placing_sun: false,
+            light_drag: None,
+            scene_path: voxels.path.clone(),
+            meta: voxels.meta.clone(),
+            spawn: (spawn, target),
+            stream,
+            scene_load: None,
             wgpu_state,
             surface,
             device,
@@ -178,12 +492,53 @@ impl State {
             config: surface_config,
             camera,
             lights,
+            clock: Clock::new(),
+            frame_seed: FrameSeed::new(0),
+            route,
+            environment,
+            frustum,
+            settings,
+            probes,
+            fog_volumes,
+            materials,
+            sky_inputs: Vec::new(),
+            accumulation: Accumulation::new(),
             controller,
+            collider,
+            collisions: true,
+            timelapse,
+            turntable,
+            editor: Editor::new(voxels.palette()),
+            gizmos: Gizmos::new(),
+            bricks: (constants.traversal == TRAVERSAL_BRICKS).then(|| BrickMap::new(&voxels)),
+            voxels,
             egui_renderer,
             egui_ctx,
             fps,
+            history,
+            clipboard: ImageClipboard::new(),
+            measure,
+            palette: CommandPalette::new(),
+            feedback,
+            exposure,
+            watchdog: Watchdog::new(),
+            dvo_inspector: None,
+            slice_viewer: SliceViewer::new(),
             constants,
-        }
+            fallback,
+            reduced_depth: false,
+            error: None,
+            notice: fallback.map(|fallback| notice_of(&fallback)),
+            view_link: String::new(),
+            logical_render: false,
+            resolution: DynamicResolution::new(),
+            high_contrast: false,
+            session_prompt: false,
+            shader_watcher: ShaderWatcher::new(Path::new("src")),
+            tabs: Tabs::new(),
+            compare: Compare::new(),
+            hooks: Box::new(()),
+        })
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -193,15 +548,975 @@ impl State {
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
             self.camera.uniform.aspect = new_size.width as f32 / new_size.height as f32;
-            self.camera.uniform.size = glm::vec2(new_size.width as f32, new_size.height as f32);
+            self.update_render_size();
         }
     }
 
+    /// size the scene target after a resize, a dpi change, a change of `logical_render` or of
+    /// the dynamic resolution scale.
+    fn update_render_size(&mut self) {
+        let scale = self.window.scale_factor();
+        let logical = (self.logical_render && scale > 1.0).then(|| {
+            let logical = self.size.to_logical::<u32>(scale);
+            (logical.width.max(1), logical.height.max(1))
+        });
+        let size = (logical.is_some() || self.resolution.scale < 1.0).then(|| {
+            self.resolution
+                .scaled(logical.unwrap_or((self.size.width, self.size.height)))
+        });
+        self.wgpu_state
+            .set_render_size(&self.device, &self.config, size);
+
+        let (width, height) = size.unwrap_or((self.size.width, self.size.height));
+        self.camera.uniform.size = glm::vec2(width as f32, height as f32);
+    }
+
+    /// re-project the sky ambient when anything the sky depends on changed. the buffers must
+    /// already be written for this frame.
+    fn update_sky(&mut self) {
+        let sky_inputs = [
+            self.lights.as_bytes(),
+            self.environment.as_bytes(),
+            self.settings.as_bytes(),
+        ]
+        .concat();
+        if sky_inputs != self.sky_inputs {
+            self.wgpu_state.project_sky(&self.device, &self.queue);
+            self.sky_inputs = sky_inputs;
+        }
+    }
+
+    /// count a frame of the path traced average. the buffers drawn this frame were written at
+    /// the end of the last event, so this runs before `update` changes them.
+    fn update_accumulation(&mut self) {
+        let size = self.camera.uniform.size;
+        if self
+            .wgpu_state
+            .update_history(&self.device, (size.x as u32, size.y as u32))
+        {
+            self.accumulation.reset();
+        }
+        let constants = bincode::serialize(self.wgpu_state.constants()).unwrap_or_default();
+        let volume_version = self.wgpu_state.volume_version().to_le_bytes();
+        self.accumulation.update(
+            &self.camera.uniform,
+            &self.lights.uniform,
+            &[
+                self.lights.local_bytes(),
+                self.environment.as_bytes(),
+                self.settings.as_bytes(),
+                self.probes.as_bytes(),
+                self.fog_volumes.as_bytes(),
+                self.materials.as_bytes(),
+                &constants,
+                &volume_version,
+            ],
+        );
+        self.queue.write_buffer(
+            &self.wgpu_state.accumulation_buffer,
+            0,
+            self.accumulation.as_bytes(),
+        );
+    }
+
+    #[tracing::instrument(skip_all)]
     fn update(&mut self) {
-        self.controller.update_camera(&mut self.camera);
+        self.update_accumulation();
+        let dt = self.clock.tick();
+        let prev_pos = self.camera.pos();
+        self.controller
+            .update_camera(&mut self.camera, self.clock.frame_time);
+        if self.collisions && self.controller.mode == CameraMode::Fly {
+            let radius = self.meta.to_voxels(Controller::COLLISION_RADIUS);
+            let pos = self
+                .collider
+                .sweep_sphere(&prev_pos, &self.camera.pos(), radius);
+            self.camera.set_pos(&pos);
+        }
+        self.camera.uniform.time = self.clock.time;
+        self.camera.uniform.seed = self.frame_seed.next();
+        self.lights.animate(dt);
         self.lights.update();
+        self.lights.update_local(&self.meta);
+        if self.exposure.enabled {
+            self.exposure.adapt(&mut self.lights.uniform.exposure);
+        }
+        self.route.update();
+        self.environment.update(&self.meta);
+        self.fog_volumes.update(&self.meta);
+        self.materials.update(&self.meta, self.voxels.palette());
+        self.frustum.update();
+        self.update_stream();
+        if self.editor.enabled {
+            self.update_edit_target();
+        }
+        self.wgpu_state
+            .upload_edits(&self.device, &self.queue, &self.voxels);
+        self.update_traversal();
+        if self.watchdog.tick(self.clock.frame_time) {
+            self.step_down_quality();
+        }
+        // the gpu time of the previous frame, when it can be measured.
+        let profiler = &mut self.wgpu_state.profiler;
+        let frame_ms = match profiler.supported() {
+            true => profiler.frame_ms,
+            false => self.clock.frame_time * 1000.0,
+        };
+        profiler.frame_timing = self.resolution.enabled;
+        if self.resolution.tick(frame_ms) {
+            self.update_render_size();
+        }
+        // sliders are applied once released, the rebuild takes a while.
+        if self.constants != *self.wgpu_state.constants() && !self.egui_ctx.is_using_pointer() {
+            self.apply_constants();
+        }
+        self.update_compare();
+        if self.shader_watcher.as_ref().is_some_and(|w| w.changed()) {
+            self.wgpu_state
+                .reload_shaders(&self.device, &self.config, &self.constants);
+        }
+
+        match self.timelapse.update() {
+            Some(Ok(voxels)) => {
+                let voxels = self.fit_voxels(voxels);
+                self.set_voxels(voxels);
+            }
+            Some(Err(err)) => {
+                self.timelapse.playing = false;
+                self.error = Some(err.into());
+            }
+            None => {}
+        }
+
+        let loaded = self.scene_load.as_ref().and_then(SceneLoad::poll);
+        if let Some(res) = loaded {
+            self.scene_load = None;
+            match res {
+                Ok(voxels) => self.set_scene(voxels),
+                Err(voxels::Error::Cancelled(_)) => {}
+                Err(err) => self.error = Some(err.into()),
+            }
+        }
+    }
+
+    /// halve the iterations and the shadow budget, and lower the msaa, after repeated long
+    /// frames. see `watchdog.rs`.
+    fn step_down_quality(&mut self) {
+        const MIN_MAX_ITER: u32 = 32;
+        const MIN_SHADOW_ITER: u32 = 16;
+
+        let constants = &mut self.constants;
+        let max_iter = match constants.traversal {
+            TRAVERSAL_SDF => &mut constants.sdf_max_iter,
+            _ => &mut constants.octree_max_iter,
+        };
+        *max_iter = (*max_iter / 2).max(MIN_MAX_ITER);
+        let max_iter = *max_iter;
+        constants.shadow_max_iter = (constants.shadow_max_iter / 2).max(MIN_SHADOW_ITER);
+        constants.msaa_level = constants.msaa_level.saturating_sub(1).max(1);
+        let budget = &mut self.lights.uniform.shadow_budget;
+        *budget = (*budget / 2).max(MIN_SHADOW_ITER);
+
+        self.notice = Some(format!(
+            "frames took over {} ms, the quality was lowered: max iter {}, shadow iter {}, shadow \
+             budget {}, msaa {}. the watchdog can be disabled in the Debug window.",
+            (watchdog::THRESHOLD * 1000.0) as u32,
+            max_iter,
+            constants.shadow_max_iter,
+            budget,
+            constants.msaa_level,
+        ));
+    }
+
+    /// build the shaders of the comparison profile once the sliders are released, and place the
+    /// divider.
+    fn update_compare(&mut self) {
+        if !self.egui_ctx.is_using_pointer() {
+            let constants = self
+                .compare
+                .enabled
+                .then(|| self.compare.constants_for(&self.constants));
+            if !self
+                .wgpu_state
+                .set_compare(&self.device, &self.config, constants.as_ref())
+            {
+                self.compare.enabled = false;
+            }
+        }
+        let size = self.camera.uniform.size.map(|c| c as u32);
+        let split = (self.compare.split * size.x as f32) as u32;
+        self.wgpu_state.update_compare(
+            &self.queue,
+            self.compare.settings.as_bytes(),
+            split,
+            (size.x, size.y),
+        );
+    }
+
+    /// write the streamed chunks that arrived, nearest to the camera first. they are uploaded
+    /// with the edits, which rebuild the gpu structures over their bounding box only.
+    fn update_stream(&mut self) {
+        // bounds the time spent uploading each frame.
+        const MAX_CHUNKS_PER_FRAME: usize = 8;
+
+        let Some(stream) = &mut self.stream else {
+            return;
+        };
+        stream.set_focus(&self.camera.pos());
+
+        match stream.poll(MAX_CHUNKS_PER_FRAME) {
+            Ok(regions) => {
+                for region in &regions {
+                    self.collider.write_region(region.origin, &region.voxels);
+                    self.voxels
+                        .write_region(region.origin, &region.voxels, &region.colors);
+                    // the region axes are z, y, x.
+                    let [i, j, k] = region.origin;
+                    let (di, dj, dk) = region.voxels.dim();
+                    let min = glm::vec3(k, j, i).map(|c| c as u32);
+                    let max = min + glm::vec3(dk, dj, di).map(|c| c as u32);
+                    self.wgpu_state.mark_dirty(min, max);
+                    if let Some(bricks) = &mut self.bricks {
+                        bricks.invalidate(&self.voxels, min, max);
+                    }
+                }
+            }
+            Err(err) => self.error = Some(err.into()),
+        }
+
+        if stream.done() {
+            self.stream = None;
+        }
+    }
+
+    /// allocate the structures of the selected traversal, and stream the bricks near the camera
+    /// when it is the brick map.
+    fn update_traversal(&mut self) {
+        let contree = self.constants.traversal == TRAVERSAL_CONTREE;
+        if contree != self.wgpu_state.has_contree() {
+            self.wgpu_state
+                .set_contree(&self.device, &self.queue, contree);
+        }
+
+        let enabled = self.constants.traversal == TRAVERSAL_BRICKS;
+        if enabled != self.bricks.is_some() {
+            self.bricks = enabled.then(|| BrickMap::new(&self.voxels));
+            self.wgpu_state.set_brick_map(&self.device, enabled);
+        }
+        if let Some(bricks) = &mut self.bricks {
+            let update = bricks.update(&self.voxels, &self.camera.pos());
+            self.wgpu_state.write_bricks(&self.queue, &update);
+        }
+    }
+
+    /// (loaded, total) chunks of the streamed scene, while it is loading.
+    pub fn loading_progress(&self) -> Option<(usize, usize)> {
+        self.stream.as_ref().map(SceneStream::progress)
+    }
+
+    /// swap the displayed volume, keeping the camera and settings.
+    #[tracing::instrument(skip_all)]
+    fn set_voxels(&mut self, voxels: Voxels) {
+        let octree_depth = voxels.dim().ilog2() - 1;
+        let baked_lighting = voxels.lightmap.is_some() as u32;
+        let contours = voxels.meta.contours as u32;
+        let detail_atlas = detail::load_atlas(&voxels);
+        let detail_textures = detail_atlas.is_some() as u32;
+        if octree_depth != self.constants.octree_depth
+            || baked_lighting != self.constants.baked_lighting
+            || contours != self.constants.contours
+            || detail_textures != self.constants.detail_textures
+            || voxels.meta.detail_distance != self.constants.detail_distance
+        {
+            self.constants.octree_depth = octree_depth;
+            self.constants.baked_lighting = baked_lighting;
+            self.constants.contours = contours;
+            self.constants.detail_textures = detail_textures;
+            self.constants.detail_distance = voxels.meta.detail_distance;
+            self.wgpu_state
+                .reload_shaders(&self.device, &self.config, &self.constants);
+        }
+        self.wgpu_state.set_voxels(
+            &self.device,
+            &self.queue,
+            &voxels,
+            Fallback::color_mips(self.fallback),
+        );
+        self.wgpu_state
+            .set_detail(&self.device, &self.queue, detail_atlas.as_ref());
+        self.collider = Collider::new(&voxels);
+        self.editor.set_palette(voxels.palette());
+        if let Some(bricks) = &mut self.bricks {
+            let radius = bricks.radius;
+            *bricks = BrickMap::new(&voxels);
+            bricks.radius = radius;
+        }
+        // the streamed chunks belong to the previous scene.
+        self.stream = None;
+        self.voxels = voxels;
+    }
+
+    /// degrade the scene until it fits in gpu memory, and tell the user.
+    fn fit_voxels(&mut self, voxels: Voxels) -> Voxels {
+        let (voxels, fallback) = budget::fit(&self.device, voxels);
+        if fallback != self.fallback {
+            self.notice = fallback.map(|fallback| notice_of(&fallback));
+        }
+        self.fallback = fallback;
+        voxels
+    }
+
+    /// open another scene file, keeping the camera and settings.
+    fn load_scene(&mut self, path: &Path) -> Result<(), voxels::Error> {
+        self.scene_load = None;
+        self.set_scene(Voxels::from_path(path)?);
+        Ok(())
+    }
+
+    /// load another scene file in the background, it is opened like `load_scene` once loaded.
+    /// the scene still loading, if any, is abandoned.
+    fn start_loading(&mut self, path: &Path) {
+        self.scene_load = Some(SceneLoad::start(path));
+    }
+
+    /// the stage of the scene loading in the background and the fraction done.
+    pub fn scene_loading(&self) -> Option<(Stage, f32)> {
+        self.scene_load.as_ref().map(SceneLoad::progress)
+    }
+
+    fn cancel_loading(&mut self) {
+        self.scene_load = None;
+    }
+
+    /// display a loaded scene, keeping the camera and settings.
+    fn set_scene(&mut self, voxels: Voxels) {
+        let voxels = self.fit_voxels(voxels);
+        self.reduced_depth = false;
+        self.scene_path = voxels.path.clone();
+        self.meta = voxels.meta.clone();
+        self.constants.noise_seed = self.meta.noise_seed;
+        if let Some(preset) = self.meta.lighting {
+            preset.apply(&mut self.lights, &mut self.environment);
+        }
+        self.spawn = voxels.spawn(self.meta.to_voxels(Controller::EYE_HEIGHT));
+        self.set_voxels(voxels);
+        self.notify_scene_loaded();
+    }
+
+    fn notify_scene_loaded(&mut self) {
+        self.hooks.on_scene_loaded(&SceneLoaded {
+            path: self.scene_path.clone(),
+            size: self.voxels.bounds().into(),
+        });
+    }
+
+    /// open a scene in a new tab and switch to it. the camera starts at the spawn point.
+    fn open_tab(&mut self, path: &Path) -> Result<(), Error> {
+        let (voxels, fallback) = budget::fit(&self.device, Voxels::from_path(path)?);
+        let detail_atlas = detail::load_atlas(&voxels);
+        let constants = ShaderConstants {
+            octree_depth: voxels.dim().ilog2() - 1,
+            baked_lighting: voxels.lightmap.is_some() as u32,
+            noise_seed: voxels.meta.noise_seed,
+            contours: voxels.meta.contours as u32,
+            detail_textures: detail_atlas.is_some() as u32,
+            detail_distance: voxels.meta.detail_distance,
+            ..self.constants.clone()
+        };
+
+        let mut camera = Camera::new(self.camera.uniform.size);
+        camera.uniform.aspect = self.camera.uniform.aspect;
+        let mut controller = Controller::new();
+        controller.speed = voxels.meta.to_voxels(Controller::DEFAULT_SPEED);
+        controller.sensitivity = self.controller.sensitivity;
+        controller.up = self.controller.up;
+        let spawn = voxels.spawn(voxels.meta.to_voxels(Controller::EYE_HEIGHT));
+        camera.set_pos(&spawn.0);
+        controller.look_at(&camera, &spawn.1);
+
+        let mut wgpu_state = WgpuState::new(
+            &self.device,
+            &self.queue,
+            &self.config,
+            &Buffers {
+                camera: camera.as_bytes(),
+                lights: self.lights.as_bytes(),
+                local_lights: self.lights.local_bytes(),
+                route: self.route.as_bytes(),
+                environment: self.environment.as_bytes(),
+                frustum: self.frustum.as_bytes(),
+                settings: self.settings.as_bytes(),
+                probes: self.probes.as_bytes(),
+                fog_volumes: self.fog_volumes.as_bytes(),
+                materials: self.materials.as_bytes(),
+                voxels: voxels.voxels_bytes(),
+                colors: voxels.colors_bytes(),
+                lightmap: voxels.lightmap_bytes(),
+            },
+            &constants,
+            Fallback::color_mips(fallback),
+            self.wgpu_state.mip_path(),
+        )?;
+        if detail_atlas.is_some() {
+            wgpu_state.set_detail(&self.device, &self.queue, detail_atlas.as_ref());
+        }
+        let cache = !cfg!(target_arch = "wasm32");
+        build_scene(
+            &self.device,
+            &self.queue,
+            &mut wgpu_state,
+            &voxels,
+            &constants,
+            cache,
+        );
+
+        if let Some(fallback) = &fallback {
+            self.notice = Some(notice_of(fallback));
+        }
+        self.tabs.parked.push(Some(SceneTab {
+            wgpu_state,
+            scene_path: voxels.path.clone(),
+            meta: voxels.meta.clone(),
+            spawn,
+            stream: None,
+            bricks: (constants.traversal == TRAVERSAL_BRICKS).then(|| BrickMap::new(&voxels)),
+            camera,
+            controller,
+            collider: Collider::new(&voxels),
+            editor: Editor::new(voxels.palette()),
+            voxels,
+            constants,
+            fallback,
+            reduced_depth: false,
+            sky_inputs: Vec::new(),
+        }));
+        self.switch_tab(self.tabs.count() - 1);
+        Ok(())
+    }
+
+    /// make the tab `index` the active one. the render settings carry over to it, only the
+    /// constants that describe the scene are its own.
+    fn switch_tab(&mut self, index: usize) {
+        if index == self.tabs.active || index >= self.tabs.count() {
+            return;
+        }
+        let mut tab = self.tabs.parked[index]
+            .take()
+            .expect("only the active tab is not parked");
+        // the scene loading in the background was picked for the tab left.
+        self.scene_load = None;
+        let shared = self.constants.clone();
+        mem::swap(&mut self.wgpu_state, &mut tab.wgpu_state);
+        mem::swap(&mut self.scene_path, &mut tab.scene_path);
+        mem::swap(&mut self.meta, &mut tab.meta);
+        mem::swap(&mut self.voxels, &mut tab.voxels);
+        mem::swap(&mut self.spawn, &mut tab.spawn);
+        mem::swap(&mut self.stream, &mut tab.stream);
+        mem::swap(&mut self.bricks, &mut tab.bricks);
+        mem::swap(&mut self.camera, &mut tab.camera);
+        mem::swap(&mut self.controller, &mut tab.controller);
+        mem::swap(&mut self.collider, &mut tab.collider);
+        mem::swap(&mut self.editor, &mut tab.editor);
+        mem::swap(&mut self.constants, &mut tab.constants);
+        mem::swap(&mut self.fallback, &mut tab.fallback);
+        mem::swap(&mut self.reduced_depth, &mut tab.reduced_depth);
+        mem::swap(&mut self.sky_inputs, &mut tab.sky_inputs);
+        self.tabs.parked[self.tabs.active] = Some(tab);
+        self.tabs.active = index;
+
+        self.constants = shared.with_scene(&self.constants);
+        // the window may have been resized while the tab was parked.
+        self.camera.uniform.aspect = self.size.width as f32 / self.size.height as f32;
+        self.update_render_size();
+        // the inspectors show the resources of the previous scene.
+        self.slice_viewer.release(&mut self.egui_renderer);
+        self.dvo_inspector = None;
+    }
+
+    /// close the tab `index`, the last tab stays open.
+    fn close_tab(&mut self, index: usize) {
+        if self.tabs.count() == 1 || index >= self.tabs.count() {
+            return;
+        }
+        if index == self.tabs.active {
+            self.switch_tab(if index == 0 { 1 } else { index - 1 });
+        }
+        self.tabs.parked.remove(index);
+        if self.tabs.active > index {
+            self.tabs.active -= 1;
+        }
+    }
+
+    /// rebuild the pipelines and resources after the constants were changed in the ui.
+    fn apply_constants(&mut self) {
+        if self.constants.octree_depth != self.wgpu_state.constants().octree_depth {
+            self.set_octree_depth(self.constants.octree_depth);
+        }
+        let mips_changed = self.constants.mip_filter != self.wgpu_state.constants().mip_filter;
+        if self.constants != *self.wgpu_state.constants() {
+            self.wgpu_state
+                .reload_shaders(&self.device, &self.config, &self.constants);
+        }
+        if mips_changed {
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("mipmap encoder"),
+                });
+            self.wgpu_state
+                .compute_mipmap(&self.device, &mut encoder, self.voxels.dim());
+            self.queue.submit(iter::once(encoder.finish()));
+        }
+    }
+
+    /// rebuild the scene in a 2^(`depth` + 1) voxels wide volume. a shallower octree downsamples
+    /// the current scene, a deeper one reloads the scene file, up to its own depth.
+    fn set_octree_depth(&mut self, depth: u32) {
+        let current = self.voxels.dim().ilog2() - 1;
+        if self.stream.is_some() {
+            self.notice = Some("the depth of a streamed scene cannot be changed.".to_owned());
+            self.constants.octree_depth = current;
+            return;
+        }
+        let voxels = if depth < current {
+            self.reduced_depth = true;
+            self.voxels.downsample(current - depth)
+        } else {
+            match Voxels::from_path(&self.scene_path) {
+                Ok(voxels) => {
+                    let voxels = self.fit_voxels(voxels);
+                    let full = voxels.dim().ilog2() - 1;
+                    self.reduced_depth = depth < full;
+                    match depth < full {
+                        true => voxels.downsample(full - depth),
+                        false => voxels,
+                    }
+                }
+                Err(err) => {
+                    self.error = Some(err.into());
+                    self.constants.octree_depth = current;
+                    return;
+                }
+            }
+        };
+        self.set_voxels(voxels);
+    }
+
+    /// toggle the contours of the current scene. saved with the scene metadata.
+    fn set_contours(&mut self, enabled: bool) {
+        self.meta.contours = enabled;
+        self.constants.contours = enabled as u32;
+        self.wgpu_state
+            .set_contours(&self.device, &self.queue, enabled);
+        self.wgpu_state
+            .reload_shaders(&self.device, &self.config, &self.constants);
+    }
+
+    /// move the camera back to the spawn point of the scene.
+    fn teleport_to_spawn(&mut self) {
+        let (pos, target) = self.spawn;
+        self.camera.set_pos(&pos);
+        self.controller.look_at(&self.camera, &target);
+    }
+
+    /// bake the lighting of the scene file with the current sun, optionally writing it back
+    /// into the .wvox so later loads are lit without baking.
+    #[tracing::instrument(skip_all)]
+    fn bake_lighting(&mut self, save: bool) {
+        let mut voxels = match Voxels::from_path(&self.scene_path) {
+            Ok(voxels) => voxels,
+            Err(err) => {
+                self.error = Some(err.into());
+                return;
+            }
+        };
+        if let Some(Fallback::Downsample(levels)) = self.fallback {
+            voxels = voxels.downsample(levels);
+            if save {
+                eprintln!("not saving the baked lighting of a downsampled scene");
+            }
+        }
+        voxels.lightmap = Some(bake::bake(&voxels, &self.lights.uniform.sun.dir));
+
+        let save = save && !matches!(self.fallback, Some(Fallback::Downsample(_)));
+        if save {
+            // other formats are converted, a .vox is never overwritten.
+            let path = self.scene_path.with_extension("wvox");
+            if let Err(err) = voxels.save(&path) {
+                eprintln!("failed to save `{}`: {}", path.display(), err);
+            }
+        }
+
+        self.wgpu_state
+            .set_lightmap(&self.device, &self.queue, voxels.lightmap_bytes());
+        self.constants.baked_lighting = 1;
+        self.wgpu_state
+            .reload_shaders(&self.device, &self.config, &self.constants);
+    }
+
+    /// render the placed reflection probes. the probes are disabled while rendering, so they do
+    /// not reflect their own stale cubemaps.
+    #[tracing::instrument(skip_all)]
+    fn bake_probes(&mut self) {
+        self.probes.uniform.count = 0;
+        self.queue
+            .write_buffer(&self.wgpu_state.probes_buffer, 0, self.probes.as_bytes());
+
+        let target = create_render_target(&self.device, PROBE_SIZE, PROBE_SIZE, self.config.format);
+        self.wgpu_state
+            .reserve_gbuffer(&self.device, (PROBE_SIZE, PROBE_SIZE));
+        for (probe, pos) in self.probes.positions.iter().enumerate() {
+            for face in 0..6 {
+                let camera = Probes::face_camera(&self.camera.uniform, pos, face);
+                self.wgpu_state.write_cameras(&self.queue, &camera, &camera);
+                let layer = (probe * 6 + face) as u32;
+                self.wgpu_state
+                    .render_probe_face(&self.device, &self.queue, &target, layer);
+            }
+        }
+
+        // the camera and probes buffers are written again at the end of the frame. the faces were
+        // drawn over the path traced history, and the g-buffer was grown for them.
+        self.update_render_size();
+        self.probes.set_baked();
+        self.accumulation.reset();
+        println!(
+            "baked {}/{} reflection probes",
+            self.probes.positions.len(),
+            MAX_PROBES
+        );
+    }
+
+    /// read back the octree built on the gpu, save it next to the scene and compare it with the
+    /// cpu reference.
+    #[tracing::instrument(skip_all)]
+    fn export_dvo(&mut self) {
+        let dvo = self.wgpu_state.read_octree(&self.device, &self.queue);
+        let path = Dvo::path(&self.scene_path);
+        if let Err(err) = dvo.save(&path) {
+            eprintln!("failed to save `{}`: {}", path.display(), err);
+        }
+
+        let voxels = match Voxels::from_path(&self.scene_path) {
+            Ok(voxels) => voxels,
+            Err(err) => {
+                self.error = Some(err.into());
+                return;
+            }
+        };
+        let voxels = match self.fallback {
+            Some(Fallback::Downsample(levels)) => voxels.downsample(levels),
+            _ => voxels,
+        };
+        let reference = Dvo::reference(voxels.voxels());
+        let mismatches = dvo.mismatches(&reference);
+        println!("octree mismatches per level: {mismatches:?}");
+        self.dvo_inspector = Some(DvoInspector::new(dvo, reference, mismatches));
+    }
+
+    /// restore the last saved session.
+    fn continue_session(&mut self) {
+        self.session_prompt = false;
+        if let Err(err) = Session::load().and_then(|session| session.apply(self)) {
+            self.error = Some(err.into());
+        }
+    }
+
+    fn save_session(&self) {
+        // there is no working directory on the web.
+        if cfg!(target_arch = "wasm32") {
+            return;
+        }
+        match Session::capture(self).save() {
+            Ok(()) => println!("saved session `{}`", Session::path().display()),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    /// write `wender.toml`, read at the next startup.
+    fn save_config(&self) {
+        if let Err(err) = Config::capture(self).save() {
+            eprintln!("failed to save `{}`: {}", Config::path().display(), err);
+        }
+    }
+
+    fn clear_baked_lighting(&mut self) {
+        self.wgpu_state
+            .set_lightmap(&self.device, &self.queue, None);
+        self.constants.baked_lighting = 0;
+        self.wgpu_state
+            .reload_shaders(&self.device, &self.config, &self.constants);
+    }
+
+    /// point the sun towards the camera ray through `pixel`.
+    fn place_sun_at(&mut self, pixel: glm::Vec2) {
+        let size = glm::vec2(self.size.width as f32, self.size.height as f32);
+        let ndc = glm::vec2(2.0 * pixel.x / size.x - 1.0, 1.0 - 2.0 * pixel.y / size.y);
+        self.lights.set_dir(&self.camera.ray_dir(&ndc));
+        self.placing_sun = false;
+    }
+
+    /// start dragging the light handle drawn under a window position, in physical pixels: the
+    /// nearest one within a few pixels that is not hidden behind the voxels. returns whether a
+    /// handle was grabbed.
+    fn grab_light_handle(&mut self, pixel: glm::Vec2) -> bool {
+        const RADIUS: f32 = 12.0;
+        if !self.gizmos.show_bounds {
+            return false;
+        }
+
+        let size = glm::vec2(self.size.width as f32, self.size.height as f32);
+        let mut candidates = Vec::new();
+        for (i, light) in self.meta.lights.iter().enumerate() {
+            for (handle, pos) in light_handles(light) {
+                let Some(ndc) = self.camera.project(&pos) else {
+                    continue;
+                };
+                let p = glm::vec2(ndc.x + 1.0, 1.0 - ndc.y).component_mul(&size) / 2.0;
+                let dist = glm::distance(&p, &pixel);
+                if dist < RADIUS {
+                    candidates.push((dist, i, handle, pos));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        candidates.truncate(PICK_SAMPLES);
+        if candidates.is_empty() {
+            return false;
+        }
+
+        // one ray towards each candidate, the last one repeated to fill the rays.
+        let cam_pos = self.camera.pos();
+        let dirs = std::array::from_fn(|i| {
+            let (_, _, _, pos) = candidates[i.min(candidates.len() - 1)];
+            glm::normalize(&(pos - cam_pos))
+        });
+        let results = self
+            .wgpu_state
+            .pick(&self.device, &self.queue, &cam_pos, &dirs);
+        let visible = candidates
+            .iter()
+            .zip(&results)
+            .find(|((_, _, _, pos), res)| {
+                res.hit == 0 || res.t > glm::distance(pos, &cam_pos) - 2.0 * HANDLE_SIZE
+            });
+
+        let Some((&(_, light, handle, pos), _)) = visible else {
+            return false;
+        };
+        let forward = self.camera.ray_dir(&glm::zero());
+        self.light_drag = Some(LightDrag {
+            light,
+            handle,
+            depth: glm::dot(&(pos - cam_pos), &forward),
+        });
+        true
+    }
+
+    /// move the dragged light handle under a window position, in physical pixels.
+    fn drag_light_at(&mut self, pixel: glm::Vec2) {
+        let Some(drag) = &self.light_drag else {
+            return;
+        };
+        // the light was removed in the ui.
+        let Some(light) = self.meta.lights.get_mut(drag.light) else {
+            self.light_drag = None;
+            return;
+        };
+        let size = glm::vec2(self.size.width as f32, self.size.height as f32);
+        let ndc = glm::vec2(2.0 * pixel.x / size.x - 1.0, 1.0 - 2.0 * pixel.y / size.y);
+        let dir = self.camera.ray_dir(&ndc);
+        let forward = self.camera.ray_dir(&glm::zero());
+        let target = self.camera.pos() + dir * (drag.depth / glm::dot(&dir, &forward));
+        drag_light_handle(light, drag.handle, &target);
+    }
+
+    /// switch between the fly and orbit cameras. the orbit camera turns around the voxel at the
+    /// center of the view.
+    fn set_camera_mode(&mut self, mode: CameraMode) {
+        match mode {
+            CameraMode::Fly => self.controller.mode = CameraMode::Fly,
+            CameraMode::Orbit => {
+                let pos = self.camera.pos();
+                let dir = self.camera.ray_dir(&glm::vec2(0.0, 0.0));
+                let dist = self
+                    .voxels
+                    .raycast(&pos, &dir, f32::INFINITY)
+                    .map_or(self.controller.distance, |(_, t)| t);
+                self.controller
+                    .orbit_around(&self.camera, &(pos + dir * dist));
+                self.window
+                    .set_cursor_grab(winit::window::CursorGrabMode::None)
+                    .ok();
+                self.window.set_cursor_visible(true);
+                self.cursor_grabbed = false;
+            }
+        }
     }
 
+    /// fly to the voxel under a window position, in physical pixels. a few rays around the
+    /// position are cast and the nearest hit is used, so thin gaps do not miss the surface.
+    fn focus_at(&mut self, pixel: glm::Vec2) {
+        const OFFSETS: [(f32, f32); PICK_SAMPLES] =
+            [(0.0, 0.0), (2.0, 0.0), (-2.0, 0.0), (0.0, 2.0), (0.0, -2.0)];
+
+        let size = glm::vec2(self.size.width as f32, self.size.height as f32);
+        let dirs = OFFSETS.map(|(x, y)| {
+            let p = pixel + glm::vec2(x, y);
+            let ndc = glm::vec2(2.0 * p.x / size.x - 1.0, 1.0 - 2.0 * p.y / size.y);
+            self.camera.ray_dir(&ndc)
+        });
+
+        let results = self
+            .wgpu_state
+            .pick(&self.device, &self.queue, &self.camera.pos(), &dirs);
+        let nearest = results
+            .iter()
+            .filter(|res| res.hit != 0)
+            .min_by(|a, b| a.t.total_cmp(&b.t));
+
+        if let Some(hit) = nearest {
+            self.controller.fly_to(&self.camera, &hit.pos);
+        }
+        self.hooks.on_pick(&Pick {
+            pixel: pixel.into(),
+            hit: nearest.map(|hit| PickHit {
+                pos: hit.pos.into(),
+                voxel: hit.voxel.into(),
+                normal: hit.normal.into(),
+                distance: hit.t,
+            }),
+        });
+    }
+
+    /// fill the box `min..max` of world coordinates with a palette entry, 0 to clear it. the gpu
+    /// textures are updated at the next frame.
+    fn edit_voxels(&mut self, min: glm::UVec3, max: glm::UVec3, material: u32) {
+        if !self.voxels.fill(min, max, material) {
+            return;
+        }
+        self.wgpu_state.mark_dirty(min, max);
+        if let Some(bricks) = &mut self.bricks {
+            bricks.invalidate(&self.voxels, min, max);
+        }
+
+        let max = max.map(|c| c.min(self.voxels.dim()));
+        // world x and z are swapped relative to the array axes.
+        let region = s![
+            min.z as usize..max.z as usize,
+            min.y as usize..max.y as usize,
+            min.x as usize..max.x as usize
+        ];
+        self.collider.write_region(
+            [min.z as usize, min.y as usize, min.x as usize],
+            &self.voxels.voxels().slice(region).to_owned(),
+        );
+        self.hooks.on_voxel_edited(&VoxelEdited {
+            min: min.into(),
+            max: max.into(),
+            material,
+        });
+    }
+
+    /// remove the brush at the targeted voxel, or place the selected palette entry against the
+    /// targeted face.
+    fn edit_at_target(&mut self, remove: bool) {
+        let (region, material) = if remove {
+            (self.editor.brush_box(), 0)
+        } else {
+            (self.editor.place_box(), self.editor.material)
+        };
+        let Some((min, max)) = region else {
+            return;
+        };
+        if material != 0 {
+            self.editor.select(material);
+        }
+        self.edit_voxels(min, max, material);
+    }
+
+    /// recolor the scene with the colors of a palette file, see `palette_file::Remap`.
+    fn import_palette(&mut self, path: &Path) -> Result<(), palette_file::Error> {
+        let imported = palette_file::load(path)?;
+        let palette = palette_file::remap(self.voxels.palette(), &imported, self.editor.remap);
+        self.voxels.set_palette(palette);
+        self.wgpu_state
+            .set_colors(&self.device, &self.queue, &self.voxels);
+        self.editor.set_palette(self.voxels.palette());
+        println!(
+            "imported {} colors from `{}`",
+            imported.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    /// write the edited scene as a .wvox next to the scene file. a .vox is never overwritten.
+    fn save_scene(&mut self) {
+        if self.reduced_depth || matches!(self.fallback, Some(Fallback::Downsample(_))) {
+            self.notice = Some("a downsampled scene cannot be saved.".to_owned());
+            return;
+        }
+        let path = self.scene_path.with_extension("wvox");
+        if let Err(err) = self.voxels.save(&path) {
+            eprintln!("failed to save `{}`: {}", path.display(), err);
+        }
+    }
+
+    /// find the voxel under the crosshair, at the center of the window.
+    fn update_edit_target(&mut self) {
+        let dir = self.camera.ray_dir(&glm::zero());
+        let results = self.wgpu_state.pick(
+            &self.device,
+            &self.queue,
+            &self.camera.pos(),
+            &[dir; PICK_SAMPLES],
+        );
+        let hit = &results[0];
+        self.editor.target = (hit.hit != 0).then(|| self.editor.snapped(hit.voxel));
+        self.editor.normal = hit.normal.map(|c| c.round() as i32);
+    }
+
+    /// builder mode shortcuts: 1-9 pick from the hotbar, [ and ] resize the brush, g cycles the
+    /// grid snapping, enter fills the brush at the target and delete clears it. returns whether
+    /// the key was used.
+    fn editor_key(&mut self, key: KeyCode) -> bool {
+        if !self.editor.enabled {
+            return false;
+        }
+        const DIGITS: [KeyCode; 9] = [
+            KeyCode::Digit1,
+            KeyCode::Digit2,
+            KeyCode::Digit3,
+            KeyCode::Digit4,
+            KeyCode::Digit5,
+            KeyCode::Digit6,
+            KeyCode::Digit7,
+            KeyCode::Digit8,
+            KeyCode::Digit9,
+        ];
+        match key {
+            KeyCode::BracketLeft => self.editor.grow_brush(-1),
+            KeyCode::BracketRight => self.editor.grow_brush(1),
+            KeyCode::KeyG => self.editor.cycle_snap(),
+            KeyCode::Enter | KeyCode::Delete => {
+                if let Some((min, max)) = self.editor.brush_box() {
+                    let material = match key {
+                        KeyCode::Enter => self.editor.material,
+                        _ => 0,
+                    };
+                    self.edit_voxels(min, max, material);
+                }
+            }
+            key => match DIGITS.iter().position(|k| *k == key) {
+                Some(n) => self.editor.select_recent(n),
+                None => return false,
+            },
+        }
+        true
+    }
+
+    #[tracing::instrument(skip_all, fields(traversal = self.constants.traversal))]
     fn render(&mut self, egui_state: &mut egui_winit::State) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
         let view = output
@@ -215,18 +1530,119 @@ impl State {
             });
 
         self.draw_scene(&view, &mut encoder);
+
+        let readback = (self.history.should_capture()
+            && self.config.usage.contains(wgpu::TextureUsages::COPY_SRC))
+        .then(|| copy_texture(&self.device, &mut encoder, &output.texture));
+
         self.draw_egui(egui_state, &view, &mut encoder);
+        self.wgpu_state.profiler.resolve(&mut encoder);
 
         self.queue.submit(iter::once(encoder.finish()));
+        self.wgpu_state.profiler.read(&self.device);
+
+        if let Some(readback) = readback {
+            self.history.push(readback.read(&self.device));
+        }
+
+        if self.feedback.tick() {
+            self.update_feedback();
+        }
+        if self.exposure.tick() {
+            let histogram = self
+                .wgpu_state
+                .read_luma_histogram(&self.device, &self.queue);
+            self.exposure.measure(&histogram);
+        }
+
         output.present();
+        self.hooks.on_frame_stats(&FrameStats {
+            frame_time: self.clock.frame_time,
+            gpu_timings: &self.wgpu_state.profiler.timings,
+        });
 
         Ok(())
     }
 
+    /// read back the iterations histogram, and tune the max iter of the current traversal in
+    /// auto mode.
+    fn update_feedback(&mut self) {
+        let histogram = self
+            .wgpu_state
+            .read_iter_histogram(&self.device, &self.queue);
+        self.feedback.p99 = IterFeedback::percentile(&histogram, 0.99);
+        let Some(p99) = self.feedback.p99 else {
+            return;
+        };
+
+        let max_iter = match self.constants.traversal {
+            TRAVERSAL_SDF => &mut self.constants.sdf_max_iter,
+            _ => &mut self.constants.octree_max_iter,
+        };
+        println!("iterations p99: {p99} / {max_iter}");
+
+        if self.feedback.auto {
+            if let Some(tuned) = self.feedback.tune(*max_iter) {
+                println!("max iter: {max_iter} -> {tuned}");
+                *max_iter = tuned;
+                self.wgpu_state
+                    .reload_shaders(&self.device, &self.config, &self.constants);
+            }
+        }
+    }
+
+    /// render the current view at the window size.
+    fn render_frame(&self) -> RgbaImage {
+        let target = create_render_target(
+            &self.device,
+            self.config.width,
+            self.config.height,
+            self.config.format,
+        );
+        let view = target.create_view(&Default::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("screenshot encoder"),
+            });
+        self.draw_scene(&view, &mut encoder);
+        let readback = copy_texture(&self.device, &mut encoder, &target);
+        self.queue.submit(iter::once(encoder.finish()));
+        readback.read(&self.device)
+    }
+
+    /// save the current view as a png.
+    fn screenshot(&self) {
+        let path = timestamped_path("screenshot", "png");
+        match self.render_frame().save(&path) {
+            Ok(()) => println!("wrote `{}`", path.display()),
+            Err(err) => eprintln!("failed to save `{}`: {}", path.display(), err),
+        }
+    }
+
+    /// put the current view on the system clipboard.
+    fn copy_frame(&mut self) {
+        let image = self.render_frame();
+        match self.clipboard.copy(&image) {
+            Ok(()) => println!("copied the frame to the clipboard"),
+            Err(err) => eprintln!("{err}"),
+        }
+    }
+
+    fn export_history(&self) {
+        let path = timestamped_path("capture", "gif");
+        if let Err(err) = self.history.export_gif(&path) {
+            eprintln!("failed to export `{}`: {}", path.display(), err);
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
     fn draw_scene(&self, view: &wgpu::TextureView, encoder: &mut wgpu::CommandEncoder) {
-        self.wgpu_state.draw(view, encoder);
+        let size = (self.config.width, self.config.height);
+        self.wgpu_state.draw_scaled(view, size, encoder);
     }
 
+    #[tracing::instrument(skip_all)]
     fn draw_egui(
         &mut self,
         egui_state: &mut egui_winit::State,
@@ -237,7 +1653,8 @@ impl State {
 
         let egui_screen = egui_wgpu::ScreenDescriptor {
             size_in_pixels: [self.config.width, self.config.height],
-            pixels_per_point: self.window.scale_factor() as f32,
+            // includes the ui scale set by the user on top of the dpi of the monitor.
+            pixels_per_point: egui_output.pixels_per_point,
         };
 
         let egui_primitives = self
@@ -268,6 +1685,7 @@ impl State {
                         store: wgpu::StoreOp::Store,
                     },
                 })],
+                timestamp_writes: self.wgpu_state.profiler.render_writes("egui"),
                 ..Default::default()
             });
 
@@ -281,8 +1699,59 @@ impl State {
     }
 }
 
+/// compile every shader module standalone with the default constants, reporting failures.
+/// returns whether all modules compiled.
+pub fn check_shaders() -> bool {
+    let constants = ShaderConstants::default().to_hashmap();
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    let results = match preproc::check_modules(&dir, &constants) {
+        Ok(results) => results,
+        Err(err) => {
+            eprintln!("{}", err);
+            return false;
+        }
+    };
+
+    let mut ok = true;
+    for (path, res) in results {
+        match res {
+            Ok(()) => println!("ok     {}", path.display()),
+            Err(err) => {
+                println!("FAILED {}", path.display());
+                eprintln!("{}", err);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// the event loop of the platform. on linux, x11 (or xwayland) is preferred when available, and
+/// wayland is used otherwise.
+fn create_event_loop() -> Result<EventLoop<()>, EventLoopError> {
+    #[allow(unused_mut)]
+    let mut builder = EventLoopBuilder::new();
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    if std::env::var_os("DISPLAY").is_some() {
+        use winit::platform::x11::EventLoopBuilderExtX11;
+        builder.with_x11();
+    }
+    builder.build()
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub async fn run() {
+    run_with_hooks(()).await
+}
+
+/// run the viewer like `run`, calling `hooks` on the events of the renderer.
+pub async fn run_with_hooks(hooks: impl Hooks + 'static) {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             std::panic::set_hook(Box::new(console_error_panic_hook::hook));
@@ -292,36 +1761,93 @@ pub async fn run() {
         }
     }
 
-    let event_loop = EventLoopBuilder::new()
-        .with_x11()
-        .build()
-        .expect("failed to create event loop");
-    let window = WindowBuilder::new()
+    #[allow(unused_mut)]
+    let mut args = <cli::Args as clap::Parser>::parse();
+
+    // `--trace` records the spans to a chrome://tracing compatible file, written on exit.
+    #[cfg(not(target_arch = "wasm32"))]
+    let _trace_guard = args.trace.then(|| {
+        use tracing_subscriber::prelude::*;
+        let path = timestamped_path("trace", "json");
+        println!("tracing to `{}`", path.display());
+        let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new().file(path).build();
+        tracing_subscriber::registry().with(layer).init();
+        guard
+    });
+
+    let startup = Config::load();
+
+    let event_loop = match create_event_loop() {
+        Ok(event_loop) => event_loop,
+        Err(err) => return error::show_fatal(&err.into()),
+    };
+    let window = match WindowBuilder::new()
         .with_title("Wender")
-        .with_inner_size(LogicalSize::new(800.0, 800.0))
+        .with_inner_size(LogicalSize::new(
+            startup.window_size.0 as f64,
+            startup.window_size.1 as f64,
+        ))
         .build(&event_loop)
-        .unwrap();
+    {
+        Ok(window) => window,
+        Err(err) => return error::show_fatal(&err.into()),
+    };
 
     #[cfg(target_arch = "wasm32")]
     {
-        // Winit prevents sizing with CSS, so we have to set
-        // the size manually when on web.
-        use winit::dpi::PhysicalSize;
-        window.set_inner_size(PhysicalSize::new(450, 400));
-
+        // the canvas is sized by the css of the page, winit reports its size changes as resize
+        // events.
         use winit::platform::web::WindowExtWebSys;
         web_sys::window()
             .and_then(|win| win.document())
             .and_then(|doc| {
                 let dst = doc.get_element_by_id("wasm-example")?;
-                let canvas = web_sys::Element::from(window.canvas());
+                let canvas = web_sys::Element::from(window.canvas()?);
                 dst.append_child(&canvas).ok()?;
                 Some(())
             })
             .expect("Couldn't append canvas to document body.");
+
+        // the page picks the scene, see `web/index.html`.
+        args.scene = Some(
+            web_sys::window()
+                .and_then(|win| win.document())
+                .and_then(|doc| doc.get_element_by_id("wasm-example"))
+                .and_then(|dst| dst.get_attribute("data-scene"))
+                .unwrap_or_else(|| "scene.wchunks".to_owned())
+                .into(),
+        );
     }
 
-    let mut state = State::new(window).await;
+    let session = match args.continue_session.then(Session::load).transpose() {
+        Ok(session) => session,
+        Err(err) => return error::show_fatal(&err.into()),
+    };
+    let scene = match session {
+        Some(ref session) => session.scene.clone(),
+        None => args.scene.unwrap_or(startup.scene.clone()),
+    };
+
+    let mut state = match State::new(window, &scene, &startup, args.backend).await {
+        Ok(state) => state,
+        Err(err) => return error::show_fatal(&err),
+    };
+    match session {
+        Some(session) => {
+            if let Err(err) = session.apply(&mut state) {
+                state.error = Some(err.into());
+            }
+        }
+        None => {
+            if let Ok(previous) = Session::load() {
+                previous.apply_preferences(&mut state);
+                state.session_prompt = true;
+            }
+        }
+    }
+    state.frame_seed = FrameSeed::new(args.seed);
+    state.hooks = Box::new(hooks);
+    state.notify_scene_loaded();
 
     let mut egui_state = egui_winit::State::new(
         state.egui_ctx.clone(),
@@ -338,7 +1864,7 @@ pub async fn run() {
             match event {
                 Event::DeviceEvent { ref event, .. } => match event {
                     DeviceEvent::MouseMotion { delta } => {
-                        if state.cursor_grabbed {
+                        if state.cursor_grabbed || state.controller.is_dragging() {
                             state.controller.process_mouse(*delta);
                         }
                     }
@@ -353,9 +1879,23 @@ pub async fn run() {
                         repaint: _,
                     } = egui_state.on_window_event(&state.window, event);
 
+                    // the ui takes precedence over the camera, e.g. when typing in a text field.
+                    let ui_keyboard = state.egui_ctx.wants_keyboard_input();
+                    let ui_pointer = state.egui_ctx.wants_pointer_input() && !state.cursor_grabbed;
+                    state
+                        .controller
+                        .set_input_enabled(!ui_keyboard, !ui_pointer);
+
                     if !consumed {
                         match event {
-                            WindowEvent::CloseRequested => elwt.exit(),
+                            WindowEvent::CloseRequested => {
+                                state.save_session();
+                                elwt.exit();
+                            }
+                            WindowEvent::KeyboardInput { event, .. } if ui_keyboard => {
+                                // still forward releases, keys pressed before focus must not stick.
+                                state.controller.process_keyboard(event);
+                            }
                             WindowEvent::KeyboardInput { event, .. } => {
                                 if event.state == ElementState::Pressed
                                     && event.logical_key == Key::Named(NamedKey::Escape)
@@ -377,16 +1917,52 @@ pub async fn run() {
                                         &state.config,
                                         &state.constants,
                                     );
+                                } else if event.state == ElementState::Pressed
+                                    && event.logical_key == Key::Named(NamedKey::F10)
+                                {
+                                    state.export_history();
+                                } else if event.state == ElementState::Pressed
+                                    && event.logical_key == Key::Named(NamedKey::F12)
+                                {
+                                    state.screenshot();
+                                } else if event.state == ElementState::Pressed
+                                    && event.logical_key == Key::Named(NamedKey::F11)
+                                {
+                                    state.copy_frame();
+                                } else if event.state == ElementState::Pressed
+                                    && matches!(
+                                        event.physical_key,
+                                        PhysicalKey::Code(KeyCode::KeyB)
+                                    )
+                                {
+                                    state.editor.enabled = !state.editor.enabled;
+                                } else if let (ElementState::Pressed, PhysicalKey::Code(key)) =
+                                    (event.state, event.physical_key)
+                                {
+                                    if !state.editor_key(key) && !state.clock.process_key(key) {
+                                        state.controller.process_keyboard(event);
+                                    }
                                 } else {
                                     state.controller.process_keyboard(event);
                                 }
                             }
+                            WindowEvent::CursorMoved { position, .. } => {
+                                state.cursor_pos = glm::vec2(position.x as f32, position.y as f32);
+                                if state.light_drag.is_some() {
+                                    state.drag_light_at(state.cursor_pos);
+                                }
+                            }
                             WindowEvent::Resized(physical_size) => {
                                 state.resize(*physical_size);
                             }
+                            WindowEvent::ScaleFactorChanged { .. } => {
+                                // egui picks up the new scale factor from the event, the
+                                // window size in pixels changes with it.
+                                state.resize(state.window.inner_size());
+                            }
                             WindowEvent::MouseWheel { delta, .. } => match delta {
                                 MouseScrollDelta::LineDelta(_, y) => {
-                                    state.controller.speed *= 2f32.powf(-y);
+                                    state.controller.scroll(*y);
                                 }
                                 MouseScrollDelta::PixelDelta(_) => {}
                             },
@@ -395,15 +1971,54 @@ pub async fn run() {
                                 button,
                                 ..
                             } => {
-                                if *button_state == ElementState::Pressed
+                                if *button == MouseButton::Middle {
+                                    state
+                                        .controller
+                                        .set_dragging(*button_state == ElementState::Pressed);
+                                } else if *button_state == ElementState::Released
                                     && *button == MouseButton::Left
                                 {
-                                    state
-                                        .window
-                                        .set_cursor_grab(winit::window::CursorGrabMode::Locked)
-                                        .ok();
-                                    state.window.set_cursor_visible(false);
-                                    state.cursor_grabbed = true;
+                                    state.light_drag = None;
+                                } else if *button_state == ElementState::Pressed
+                                    && *button == MouseButton::Left
+                                    && state.placing_sun
+                                {
+                                    state.place_sun_at(state.cursor_pos);
+                                } else if *button_state == ElementState::Pressed
+                                    && *button == MouseButton::Left
+                                    && !state.cursor_grabbed
+                                    && state.grab_light_handle(state.cursor_pos)
+                                {
+                                    // the light follows the cursor until the button is released.
+                                } else if *button_state == ElementState::Pressed
+                                    && state.cursor_grabbed
+                                    && state.editor.enabled
+                                    && matches!(button, MouseButton::Left | MouseButton::Right)
+                                {
+                                    // left removes, right places, like in minecraft.
+                                    state.edit_at_target(*button == MouseButton::Left);
+                                } else if *button_state == ElementState::Pressed
+                                    && *button == MouseButton::Left
+                                {
+                                    const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+                                    let now = Instant::now();
+                                    match state.last_click {
+                                        Some((time, pos)) if now - time < DOUBLE_CLICK => {
+                                            state.focus_at(pos);
+                                            state.last_click = None;
+                                        }
+                                        _ => state.last_click = Some((now, state.cursor_pos)),
+                                    }
+
+                                    // the orbit camera is dragged with a visible cursor.
+                                    if state.controller.mode == CameraMode::Fly {
+                                        state
+                                            .window
+                                            .set_cursor_grab(winit::window::CursorGrabMode::Locked)
+                                            .ok();
+                                        state.window.set_cursor_visible(false);
+                                        state.cursor_grabbed = true;
+                                    }
                                 }
                             }
                             WindowEvent::RedrawRequested => {
@@ -433,12 +2048,53 @@ pub async fn run() {
                 _ => {}
             }
 
+            state.wgpu_state.write_cameras(
+                &state.queue,
+                &state.camera.uniform,
+                state.frustum.render_camera(&state.camera),
+            );
             state
                 .queue
-                .write_buffer(&state.wgpu_state.camera_buffer, 0, state.camera.as_bytes());
+                .write_buffer(&state.wgpu_state.lights_buffer, 0, state.lights.as_bytes());
+            state.queue.write_buffer(
+                &state.wgpu_state.local_lights_buffer,
+                0,
+                state.lights.local_bytes(),
+            );
             state
                 .queue
-                .write_buffer(&state.wgpu_state.lights_buffer, 0, state.lights.as_bytes());
+                .write_buffer(&state.wgpu_state.route_buffer, 0, state.route.as_bytes());
+            state.queue.write_buffer(
+                &state.wgpu_state.environment_buffer,
+                0,
+                state.environment.as_bytes(),
+            );
+            state.queue.write_buffer(
+                &state.wgpu_state.frustum_buffer,
+                0,
+                state.frustum.as_bytes(),
+            );
+            state.queue.write_buffer(
+                &state.wgpu_state.settings_buffer,
+                0,
+                state.settings.as_bytes(),
+            );
+            state
+                .queue
+                .write_buffer(&state.wgpu_state.probes_buffer, 0, state.probes.as_bytes());
+            state.queue.write_buffer(
+                &state.wgpu_state.fog_volumes_buffer,
+                0,
+                state.fog_volumes.as_bytes(),
+            );
+            state.queue.write_buffer(
+                &state.wgpu_state.materials_buffer,
+                0,
+                state.materials.as_bytes(),
+            );
+            state.gizmos.update(&state.voxels.bounds(), &state.meta);
+            state.wgpu_state.write_gizmos(&state.queue, &state.gizmos);
+            state.update_sky();
         })
         .expect("event loop run failed");
 }
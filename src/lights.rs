@@ -1,18 +1,208 @@
 use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    environment::{BackgroundMode, BackgroundPreset, Environment},
+    scene::SceneMeta,
+};
+
+// the sun, and the lights placed in the scene. the sun drives the sky and the cone traced soft
+// shadows. the local lights (point, spot or directional) are stored in the scene metadata and
+// uploaded as a storage buffer, they cast shadows like the sun, see `shading.wgsl`. the pixels only
+// shade the lights listed for their cluster of the view frustum, see `clusters.wgsl`.
+
+/// capacity of the local lights buffer.
+pub const MAX_LOCAL_LIGHTS: usize = 256;
 
 // !! careful with the alignments! add padding fields if necessary.
 // see https://www.w3.org/TR/WGSL/#alignment-and-size
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightUniform {
+    pub dir: glm::Vec3,
+    pub shadow: u32, // bool
+    pub shadow_max_iter: u32,
+    pub shadow_softness: f32,
+    _pad: [f32; 2], // padding to ensure correct alignment
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightsUniform {
-    pub sun_dir: glm::Vec3,
-    _pad: [f32; 1], // padding to ensure correct alignment
+    pub sun: LightUniform,
+    // cone tracing iterations per pixel, shared by the lights that cast shadows.
+    pub shadow_budget: u32,
+    pub ambient: f32, // multiplier of the ambient light
+    pub exposure: f32,
+    pub turbidity: f32, // haze of the sky, see `sky.wgsl`
+    pub local_count: u32,
+    pub local_shadowed: u32, // local lights that cast shadows, they share `shadow_budget`
+    _pad: [u32; 2],          // padding to ensure correct alignment
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightKind {
+    Point,
+    Spot,
+    Directional,
+}
+
+impl LightKind {
+    pub const ALL: [Self; 3] = [Self::Point, Self::Spot, Self::Directional];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Point => "point",
+            Self::Spot => "spot",
+            Self::Directional => "directional",
+        }
+    }
+}
+
+/// a light placed in the scene, besides the sun.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalLight {
+    pub kind: LightKind,
+    /// position, in voxels. unused by the directional lights.
+    pub pos: [f32; 3],
+    /// direction the light shines towards. unused by the point lights.
+    pub dir: [f32; 3],
+    pub color: [f32; 3],
+    /// brightness at one meter, or of a directional light.
+    pub intensity: f32,
+    /// distance at which the light fades out, in meters.
+    pub radius: f32,
+    /// half angle of a spot cone, in degrees.
+    pub cone: f32,
+    pub shadow: bool,
+    /// cone tracing iterations of the soft shadow, further capped by the shadow budget.
+    pub shadow_max_iter: u32,
+    /// distance before the shadow turns hard, in voxels.
+    pub shadow_softness: f32,
+}
+
+impl Default for LocalLight {
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point,
+            pos: [0.0; 3],
+            dir: [0.0, -1.0, 0.0],
+            color: [1.0, 0.9, 0.7],
+            intensity: 1.0,
+            radius: 10.0,
+            cone: 30.0,
+            shadow: true,
+            shadow_max_iter: 50,
+            shadow_softness: 2.0,
+        }
+    }
+}
+
+impl LocalLight {
+    /// a white point light at `pos`.
+    pub fn at(pos: &glm::Vec3) -> Self {
+        Self {
+            pos: (*pos).into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LocalLightUniform {
+    pub pos: glm::Vec3,
+    pub kind: u32, // 0: point, 1: spot, 2: directional
+    pub dir: glm::Vec3,
+    pub radius: f32,      // voxels
+    pub color: glm::Vec3, // premultiplied by the intensity
+    pub cos_cone: f32,
+    pub shadow: u32, // bool
+    pub shadow_max_iter: u32,
+    pub shadow_softness: f32, // voxels
+    _pad: u32,                // padding to ensure correct alignment
+}
+
+/// named looks setting the sun, ambient, exposure, background and fog together. a scene can
+/// pick one in its metadata.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum LightingPreset {
+    Noon,
+    GoldenHour,
+    Night,
+    Overcast,
+    Studio,
+}
+
+impl LightingPreset {
+    pub const ALL: [Self; 5] = [
+        Self::Noon,
+        Self::GoldenHour,
+        Self::Night,
+        Self::Overcast,
+        Self::Studio,
+    ];
+
+    pub fn apply(self, lights: &mut Lights, environment: &mut Environment) {
+        // angle, azimuth, ambient, exposure, shadow softness, turbidity
+        let (angle, azimuth, ambient, exposure, softness, turbidity) = match self {
+            Self::Noon => (45.0, 75.0, 1.0, 1.0, 5.0, 2.5),
+            Self::GoldenHour => (250.0, 8.0, 0.8, 1.1, 5.0, 4.0),
+            Self::Night => (120.0, 20.0, 0.3, 0.35, 5.0, 2.5),
+            Self::Overcast => (90.0, 60.0, 1.6, 0.8, 30.0, 10.0),
+            Self::Studio => (90.0, 45.0, 1.0, 1.0, 5.0, 2.5),
+        };
+        lights.angle = angle;
+        lights.azimuth = azimuth;
+        lights.uniform.ambient = ambient;
+        lights.uniform.exposure = exposure;
+        lights.uniform.sun.shadow_softness = softness;
+        lights.uniform.turbidity = turbidity;
+        lights.update();
+
+        // background, then fog color and distance (meters)
+        let fog = match self {
+            Self::Noon => {
+                environment.background_mode = BackgroundMode::Sky;
+                None
+            }
+            Self::GoldenHour => {
+                environment.background_mode = BackgroundMode::Sky;
+                Some((glm::vec3(0.9, 0.65, 0.45), 800.0))
+            }
+            Self::Night => {
+                environment.background_mode = BackgroundMode::Solid;
+                environment.uniform.background_color = glm::vec3(0.01, 0.015, 0.04);
+                Some((glm::vec3(0.02, 0.03, 0.06), 400.0))
+            }
+            Self::Overcast => {
+                environment.background_mode = BackgroundMode::Gradient;
+                environment.uniform.background_color = glm::vec3(0.55, 0.58, 0.62);
+                environment.uniform.background_horizon = glm::vec3(0.7, 0.72, 0.75);
+                Some((glm::vec3(0.7, 0.72, 0.75), 600.0))
+            }
+            Self::Studio => {
+                environment.apply_preset(BackgroundPreset::StudioGrey);
+                None
+            }
+        };
+        environment.fog_enabled = fog.is_some();
+        if let Some((color, distance)) = fog {
+            environment.uniform.fog_color = color;
+            environment.fog_distance = distance;
+        }
+    }
 }
 
 pub struct Lights {
     pub uniform: LightsUniform,
     pub angle: f32,   // degrees
     pub azimuth: f32, // degrees
+    /// rotation of the sun around the vertical axis, in degrees per second of scene time.
+    pub sun_speed: f32,
+    pub local: [LocalLightUniform; MAX_LOCAL_LIGHTS],
 }
 
 fn from_angle_azimuth(angle: f32, azimuth: f32) -> glm::Vec3 {
@@ -30,19 +220,73 @@ impl Lights {
     pub fn new(angle: f32, azimuth: f32) -> Self {
         Self {
             uniform: LightsUniform {
-                sun_dir: from_angle_azimuth(angle, azimuth),
+                sun: LightUniform {
+                    dir: from_angle_azimuth(angle, azimuth),
+                    shadow: true as u32,
+                    shadow_max_iter: 100,
+                    shadow_softness: 5.0,
+                    _pad: Default::default(),
+                },
+                shadow_budget: 200,
+                ambient: 1.0,
+                exposure: 1.0,
+                turbidity: 2.5,
+                local_count: 0,
+                local_shadowed: 0,
                 _pad: Default::default(),
             },
             angle,
             azimuth,
+            sun_speed: 0.0,
+            local: bytemuck::Zeroable::zeroed(),
         }
     }
 
+    /// point the sun towards `dir`. the sun stays above the horizon.
+    pub fn set_dir(&mut self, dir: &glm::Vec3) {
+        let dir = glm::normalize(dir);
+        self.angle = f32::to_degrees(f32::atan2(dir.z, dir.x)).rem_euclid(360.0);
+        self.azimuth = f32::to_degrees(f32::asin(dir.y.clamp(-1.0, 1.0))).clamp(0.0, 90.0);
+        self.update();
+    }
+
+    /// move the sun by `dt` seconds of scene time.
+    pub fn animate(&mut self, dt: f32) {
+        self.angle = (self.angle + self.sun_speed * dt).rem_euclid(360.0);
+    }
+
     pub fn update(&mut self) {
-        self.uniform.sun_dir = from_angle_azimuth(self.angle, self.azimuth)
+        self.uniform.sun.dir = from_angle_azimuth(self.angle, self.azimuth)
+    }
+
+    /// upload the local lights of the scene, extra lights are ignored.
+    pub fn update_local(&mut self, meta: &SceneMeta) {
+        let lights = &meta.lights[..meta.lights.len().min(MAX_LOCAL_LIGHTS)];
+        for (dst, light) in self.local.iter_mut().zip(lights) {
+            // the falloff is in voxels: a light of intensity 1 is 1 at one meter.
+            let scale = match light.kind {
+                LightKind::Directional => 1.0,
+                _ => meta.voxels_per_meter.powi(2),
+            };
+            dst.pos = light.pos.into();
+            dst.kind = light.kind as u32;
+            dst.dir = glm::normalize(&light.dir.into());
+            dst.radius = meta.to_voxels(light.radius);
+            dst.color = glm::Vec3::from(light.color) * light.intensity * scale;
+            dst.cos_cone = light.cone.to_radians().cos();
+            dst.shadow = light.shadow as u32;
+            dst.shadow_max_iter = light.shadow_max_iter;
+            dst.shadow_softness = light.shadow_softness;
+        }
+        self.uniform.local_count = lights.len() as u32;
+        self.uniform.local_shadowed = lights.iter().filter(|light| light.shadow).count() as u32;
     }
 
     pub fn as_bytes(&self) -> &[u8] {
         bytemuck::bytes_of(&self.uniform)
     }
+
+    pub fn local_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.local)
+    }
 }
@@ -0,0 +1,57 @@
+// this shader is a "module" supposed to be included.
+//
+// this module "exports":
+// var<uniform> lights: Lights
+// var<storage> local_lights: array<LocalLight>
+// const LIGHT_POINT: u32
+// const LIGHT_SPOT: u32
+// const LIGHT_DIRECTIONAL: u32
+// fn shadow_iter_budget(max_iter: u32) -> u32
+
+// see `LightUniform` in lights.rs.
+struct Light {
+    dir: vec3f,
+    shadow: u32, // 0 to disable the shadows of this light
+    shadow_max_iter: u32,
+    shadow_softness: f32, // distance before the shadow turns hard, in voxels
+}
+
+struct Lights {
+    sun: Light,
+    shadow_budget: u32, // cone tracing iterations shared by the shadowed lights
+    ambient: f32, // multiplier of the ambient light
+    exposure: f32,
+    turbidity: f32, // haze of the sky, from 2 (clear) to 10, see sky.wgsl
+    local_count: u32, // lights in `local_lights`
+    local_shadowed: u32, // local lights that cast shadows
+}
+
+const LIGHT_POINT: u32 = 0u;
+const LIGHT_SPOT: u32 = 1u;
+const LIGHT_DIRECTIONAL: u32 = 2u;
+
+// see `LocalLightUniform` in lights.rs.
+struct LocalLight {
+    pos: vec3f,
+    kind: u32,
+    dir: vec3f, // direction the light shines towards
+    radius: f32, // distance where the light fades out, in voxels
+    color: vec3f, // premultiplied by the intensity
+    cos_cone: f32, // of the half angle of a spot
+    shadow: u32,
+    shadow_max_iter: u32,
+    shadow_softness: f32, // distance before the shadow turns hard, in voxels
+}
+
+@group(0) @binding(1)
+var<uniform> lights: Lights;
+
+@group(0) @binding(12)
+var<storage, read> local_lights: array<LocalLight>;
+
+// iterations of the soft shadow of a light: its own limit `max_iter`, or its share of the budget
+// when more lights cast shadows, the sun and the local lights alike.
+fn shadow_iter_budget(max_iter: u32) -> u32 {
+    let shadowed = u32(lights.sun.shadow != 0u) + lights.local_shadowed;
+    return min(max_iter, lights.shadow_budget / max(shadowed, 1u));
+}
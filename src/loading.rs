@@ -0,0 +1,137 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use crate::voxels::{self, Voxels};
+
+// loading of a scene on a background thread, so the viewer stays responsive and the load can be
+// abandoned when another file is picked. `Voxels::load` runs the stages below in order, each one
+// in parallel over slabs of the volume with rayon. the stages report their progress and skip the
+// remaining slabs once cancelled. the slabs are independent, so the scene does not depend on the
+// number of threads.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stage {
+    /// read the file, and inflate the chunks of a .wchunks.
+    Decompress,
+    /// apply the palette remap, see `remap.rs`.
+    Remap,
+    /// copy the volume into its power of 2 cube.
+    Pad,
+    /// derive the colors from the palette.
+    Colors,
+}
+
+impl Stage {
+    const ALL: [Self; 4] = [Self::Decompress, Self::Remap, Self::Pad, Self::Colors];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Decompress => "decompressing",
+            Self::Remap => "remapping the palette",
+            Self::Pad => "padding the volume",
+            Self::Colors => "deriving the colors",
+        }
+    }
+}
+
+/// the progress of a load, shared between the loading thread and the viewer.
+#[derive(Default)]
+pub struct LoadProgress {
+    stage: AtomicUsize,
+    /// steps done and total of the current stage.
+    done: AtomicUsize,
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl LoadProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// start `stage`, made of `total` steps.
+    pub fn begin(&self, stage: Stage, total: usize) {
+        self.stage.store(stage as usize, Ordering::Relaxed);
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    pub fn step(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// the current stage, and the fraction of the whole load done.
+    pub fn get(&self) -> (Stage, f32) {
+        let stage = self.stage.load(Ordering::Relaxed);
+        let done = self.done.load(Ordering::Relaxed);
+        let total = self.total.load(Ordering::Relaxed).max(1);
+        let fraction =
+            (stage as f32 + done.min(total) as f32 / total as f32) / Stage::ALL.len() as f32;
+        (Stage::ALL[stage], fraction)
+    }
+}
+
+/// a scene loading on a background thread. dropping it cancels the load.
+pub struct SceneLoad {
+    pub path: PathBuf,
+    progress: Arc<LoadProgress>,
+    rx: Receiver<Result<Voxels, voxels::Error>>,
+}
+
+impl SceneLoad {
+    pub fn start(path: &Path) -> Self {
+        let progress = Arc::new(LoadProgress::new());
+        let (tx, rx) = mpsc::channel();
+        let thread_path = path.to_owned();
+        let thread_progress = progress.clone();
+        thread::spawn(move || {
+            tx.send(Voxels::load(&thread_path, &thread_progress)).ok();
+        });
+        Self {
+            path: path.to_owned(),
+            progress,
+            rx,
+        }
+    }
+
+    pub fn progress(&self) -> (Stage, f32) {
+        self.progress.get()
+    }
+
+    /// the scene, once it is loaded.
+    pub fn poll(&self) -> Option<Result<Voxels, voxels::Error>> {
+        match self.rx.try_recv() {
+            Ok(res) => Some(res),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) if self.progress.is_cancelled() => {
+                Some(Err(voxels::Error::Cancelled(self.path.clone())))
+            }
+            // the loading thread panicked.
+            Err(TryRecvError::Disconnected) => Some(Err(voxels::Error::IOError(
+                self.path.clone(),
+                std::io::Error::other("the loading thread panicked"),
+            ))),
+        }
+    }
+}
+
+impl Drop for SceneLoad {
+    fn drop(&mut self) {
+        self.progress.cancel();
+    }
+}
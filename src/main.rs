@@ -1,5 +1,55 @@
-use wender::run;
+use clap::Parser;
+use wender::{
+    cli::{Args, Command},
+    run, HeadlessOptions,
+};
 
 fn main() {
-    pollster::block_on(run());
+    let args = Args::parse();
+
+    let ok = match args.command {
+        Some(Command::Thumbnail {
+            scene,
+            output,
+            size,
+            view,
+            backend,
+        }) => wender::thumbnail(&scene, &output, size, view, backend),
+        Some(Command::ExportWeb {
+            scene,
+            out_dir,
+            pkg,
+            max_dim,
+        }) => wender::export_web(&scene, &out_dir, &pkg, max_dim),
+        Some(Command::Stats { scene, out_dir }) => wender::export_stats(&scene, &out_dir),
+        Some(Command::Pvs { scene, distance }) => wender::bake_pvs(&scene, distance),
+        Some(Command::Validate {
+            scene,
+            repair,
+            output,
+        }) => wender::validate(&scene, repair, output.as_deref()),
+        None if args.check_shaders => wender::check_shaders(),
+        None if args.diagnose => wender::diagnose(args.backend),
+        None if args.headless => wender::headless(
+            &wender::scene_or_default(args.scene),
+            &HeadlessOptions {
+                out_dir: args.out_dir,
+                frames: args.frames,
+                width: args.width,
+                height: args.height,
+                camera_path: args.camera_path,
+                image_every: args.image_every,
+                hash: args.hash,
+                compare_hashes: args.compare_hashes,
+                tolerance: args.tolerance,
+                backend: args.backend,
+                seed: args.seed,
+            },
+        ),
+        None => {
+            pollster::block_on(run());
+            return;
+        }
+    };
+    std::process::exit(if ok { 0 } else { 1 });
 }
@@ -0,0 +1,115 @@
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+use crate::scene::SceneMeta;
+
+// the material palette: color and surface properties of the palette entries, from the palette and
+// the scene metadata. uploaded as a storage buffer indexed by voxel value, see `materials.wgsl`.
+// the shading reads the albedo of the voxels hit from here, the colors texture and its mips remain
+// for the cone traced shadows and ao and for the distant filtered albedo.
+
+/// capacity of the materials buffer. must match `MAX_MATERIALS` in `materials.wgsl`.
+pub const MAX_MATERIALS: usize = 256;
+
+/// the voxels of the entry are water, see `water.wgsl`.
+pub const MATERIAL_WATER: u32 = 1 << 0;
+
+/// surface properties of a palette entry, evaluated by `brdf` in `shading.wgsl`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Surface {
+    /// the palette entry, 1-based like the voxels.
+    pub entry: u32,
+    /// from 0 (mirror) to 1 (matte).
+    pub roughness: f32,
+    /// from 0 (dielectric) to 1 (metal, tinted reflections without diffuse).
+    pub metalness: f32,
+    /// light emitted, as a multiple of the albedo.
+    pub emissive: f32,
+    /// index of refraction, sets the reflectance of the dielectrics.
+    pub ior: f32,
+}
+
+impl Default for Surface {
+    fn default() -> Self {
+        Self {
+            entry: 0,
+            roughness: 0.7,
+            metalness: 0.0,
+            emissive: 0.0,
+            ior: 1.5,
+        }
+    }
+}
+
+// !! careful with the alignments! add padding fields if necessary.
+// see https://www.w3.org/TR/WGSL/#alignment-and-size
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialUniform {
+    pub albedo: glm::Vec4,
+    /// amplitude of the detail noise, see `detail.rs`.
+    pub detail_noise: f32,
+    pub flags: u32,
+    pub roughness: f32,
+    pub metalness: f32,
+    pub emissive: f32,
+    pub ior: f32,
+    pub _pad: [f32; 2],
+}
+
+impl MaterialUniform {
+    fn set_surface(&mut self, surface: &Surface) {
+        self.roughness = surface.roughness.clamp(0.0, 1.0);
+        self.metalness = surface.metalness.clamp(0.0, 1.0);
+        self.emissive = surface.emissive.max(0.0);
+        self.ior = surface.ior.max(1.0);
+    }
+}
+
+pub struct Materials {
+    /// indexed by voxel value, entry 0 is the empty voxel.
+    pub uniform: [MaterialUniform; MAX_MATERIALS],
+}
+
+/// the material of the 1-based palette entry `entry`, if it fits in the buffer.
+fn entry_mut(entries: &mut [MaterialUniform], entry: u32) -> Option<&mut MaterialUniform> {
+    (entry as usize)
+        .checked_sub(1)
+        .and_then(|i| entries.get_mut(i))
+}
+
+impl Materials {
+    pub fn new() -> Self {
+        Self {
+            uniform: bytemuck::Zeroable::zeroed(),
+        }
+    }
+
+    /// upload the materials of the scene, extra palette entries are ignored.
+    pub fn update(&mut self, meta: &SceneMeta, palette: &[[u8; 4]]) {
+        self.uniform = bytemuck::Zeroable::zeroed();
+        let entries = &mut self.uniform[1..];
+        for (dst, color) in entries.iter_mut().zip(palette) {
+            dst.albedo = glm::Vec4::from(color.map(|c| c as f32 / 255.0));
+            dst.set_surface(&Surface::default());
+        }
+        for (dst, amplitude) in entries.iter_mut().zip(&meta.detail_noise) {
+            dst.detail_noise = amplitude.clamp(0.0, 1.0);
+        }
+        for material in &meta.water {
+            if let Some(dst) = entry_mut(entries, *material) {
+                dst.flags |= MATERIAL_WATER;
+            }
+        }
+        for surface in &meta.surfaces {
+            if let Some(dst) = entry_mut(entries, surface.entry) {
+                dst.set_surface(surface);
+            }
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.uniform)
+    }
+}
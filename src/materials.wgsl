@@ -0,0 +1,56 @@
+#import "bindings.wgsl"::{ voxel_ids, colors }
+
+// this shader is a "module" supposed to be included.
+//
+// this module "exports":
+// var<storage> materials: array<Material, MAX_MATERIALS>
+// fn material_of(voxel: vec3u) -> Material
+// fn voxel_albedo(voxel: vec3u) -> vec4f
+// fn default_material() -> Material
+// fn is_water(material: Material) -> bool
+
+const MAX_MATERIALS: u32 = 256u;
+const MATERIAL_WATER: u32 = 1u;
+
+// see `MaterialUniform` in materials.rs.
+struct Material {
+    albedo: vec4f,
+    detail_noise: f32,
+    flags: u32,
+    roughness: f32,
+    metalness: f32,
+    emissive: f32,
+    ior: f32,
+}
+
+@group(1) @binding(13)
+var<storage, read> materials: array<Material, MAX_MATERIALS>;
+
+// the material of the palette entry of a voxel. the entries past the end of the buffer take the
+// default material.
+fn material_of(voxel: vec3u) -> Material {
+    let id = textureLoad(voxel_ids, voxel, 0).r;
+    if id < MAX_MATERIALS {
+        return materials[id];
+    }
+    return default_material();
+}
+
+// the palette color of a voxel. the entries past the end of the buffer take the color of the
+// colors texture.
+fn voxel_albedo(voxel: vec3u) -> vec4f {
+    let id = textureLoad(voxel_ids, voxel, 0).r;
+    if id < MAX_MATERIALS {
+        return materials[id].albedo;
+    }
+    return textureLoad(colors, voxel, 0);
+}
+
+// the surface of the palette entries without one in the scene metadata, see `Surface::default`.
+fn default_material() -> Material {
+    return Material(vec4f(1.0), 0.0, 0u, 0.7, 0.0, 0.0, 1.5);
+}
+
+fn is_water(material: Material) -> bool {
+    return (material.flags & MATERIAL_WATER) != 0u;
+}
@@ -1,26 +1,182 @@
+// the colors texture format depends on the `f16_colors` cargo feature. without read-only storage
+// textures of that format, the level is computed into a buffer which is copied into the texture,
+// see `MipPath`.
+#if MIPMAP_COPY == 1
+@group(0) @binding(0)
+var in_tex: texture_3d<f32>;
+
+@group(0) @binding(1)
+var<storage, read_write> out_buf: array<u32>;
+
+struct Region {
+    origin: vec4u,
+    // texels per row and rows per slice of `out_buf`, the rows are padded for the copy.
+    row: u32,
+    rows: u32,
+}
+
+// the dispatched box, `out_buf` holds this box only.
+@group(0) @binding(2)
+var<uniform> region: Region;
+
+fn first_texel() -> vec3u {
+    return region.origin.xyz;
+}
+
+fn load(coord: vec3u) -> vec4f {
+    return textureLoad(in_tex, coord, 0);
+}
+
+fn store(index: vec3u, color: vec4f) {
+    let local = index - region.origin.xyz;
+    let i = (local.z * region.rows + local.y) * region.row + local.x;
+#if COLORS_F16 == 1
+    out_buf[i * 2u] = pack2x16float(color.xy);
+    out_buf[i * 2u + 1u] = pack2x16float(color.zw);
+#else
+    out_buf[i] = pack4x8unorm(color);
+#endif
+}
+#else
+#if COLORS_F16 == 1
+@group(0) @binding(0)
+var in_tex: texture_storage_3d<rgba16float, read>;
+
+@group(0) @binding(1)
+var out_tex: texture_storage_3d<rgba16float, write>;
+#else
 @group(0) @binding(0)
 var in_tex: texture_storage_3d<rgba8unorm, read>;
 
 @group(0) @binding(1)
 var out_tex: texture_storage_3d<rgba8unorm, write>;
+#endif
+
+// first texel of the dispatched box, the level is recomputed only there after an edit.
+@group(0) @binding(2)
+var<uniform> origin: vec4u;
+
+fn first_texel() -> vec3u {
+    return origin.xyz;
+}
+
+fn load(coord: vec3u) -> vec4f {
+    return textureLoad(in_tex, coord);
+}
+
+fn store(index: vec3u, color: vec4f) {
+    textureStore(out_tex, index, color);
+}
+#endif
+
+// the downsampling kernels, see `ShaderConstants::mip_filter`. the empty voxels are transparent
+// black, the alpha of a level is the occupancy of its texels.
+//
+// unfortunately I cannot use a sampler in a compute shader (wgsl limitation),
+// and I cannot use a 3D texture as a render target (to use a fragment shader),
+// at least until https://github.com/gfx-rs/wgpu/issues/6040 lands.
+// so we are doing manual filtering here.
+
+// colors closer than this are the same color for the dominant color kernel.
+const DOMINANT_TOLERANCE: f32 = 0.02;
+
+// the `i`-th of the 8 texels of the previous level covered by `index`.
+fn child(index: vec3u, i: u32) -> vec3u {
+    return index * 2u + vec3u(i & 1u, (i >> 1u) & 1u, i >> 2u);
+}
+
+// a texel of the previous level, clamped to its bounds.
+fn load_clamped(coord: vec3i) -> vec4f {
+    let size = vec3i(textureDimensions(in_tex));
+    return load(vec3u(clamp(coord, vec3i(0), size - 1)));
+}
+
+fn box_filter(index: vec3u) -> vec4f {
+    var sum = vec4f(0.0);
+    for (var i = 0u; i < 8u; i++) {
+        sum += load(child(index, i));
+    }
+    return sum / 8.0;
+}
+
+// binomial weights 1 3 3 1 over the 4 texels around the 2 children on each axis, close to a
+// gaussian of the width of a texel of the level.
+fn gaussian_filter(index: vec3u) -> vec4f {
+    var weights = array<f32, 4>(1.0, 3.0, 3.0, 1.0);
+    let base = vec3i(index * 2u) - 1;
+    var sum = vec4f(0.0);
+    for (var z = 0; z < 4; z++) {
+        for (var y = 0; y < 4; y++) {
+            for (var x = 0; x < 4; x++) {
+                let weight = weights[x] * weights[y] * weights[z];
+                sum += load_clamped(base + vec3i(x, y, z)) * weight;
+            }
+        }
+    }
+    return sum / 512.0;
+}
+
+// each child weighs by its occupancy: the mostly empty children weigh less, so the thin walls
+// stay opaque in the distant levels instead of fading with the empty space around them.
+fn occupancy_filter(index: vec3u) -> vec4f {
+    var sum = vec4f(0.0);
+    var weight = 0.0;
+    for (var i = 0u; i < 8u; i++) {
+        let texel = load(child(index, i));
+        sum += texel * texel.a;
+        weight += texel.a;
+    }
+    if weight <= 0.0 {
+        return vec4f(0.0);
+    }
+    return sum / weight;
+}
+
+// the color covering most of the children, with the occupancy of the box filter. the distant
+// levels keep the flat colors of stylized scenes instead of blending them.
+fn dominant_filter(index: vec3u) -> vec4f {
+    var texels: array<vec4f, 8>;
+    var occupancy = 0.0;
+    for (var i = 0u; i < 8u; i++) {
+        texels[i] = load(child(index, i));
+        occupancy += texels[i].a;
+    }
+
+    var best = vec3f(0.0);
+    var best_score = 0.0;
+    for (var i = 0u; i < 8u; i++) {
+        if texels[i].a <= 0.0 {
+            continue;
+        }
+        let color = texels[i].rgb / texels[i].a;
+        var score = 0.0;
+        for (var j = 0u; j < 8u; j++) {
+            let other = texels[j];
+            if other.a > 0.0 && distance(other.rgb / other.a, color) < DOMINANT_TOLERANCE {
+                score += other.a;
+            }
+        }
+        if score > best_score {
+            best_score = score;
+            best = color;
+        }
+    }
+    let alpha = occupancy / 8.0;
+    return vec4f(best * alpha, alpha);
+}
 
 @compute @workgroup_size(1)
-fn cs_main(@builtin(global_invocation_id) index: vec3u, @builtin(num_workgroups) size: vec3u) {
-    // let filter_pos = vec3f(index) / vec3f(size);
-    // let filtered = textureSample(in_tex, tex_sampler, filter_pos);
-    // unfortunately I cannot use a sampler in a compute shader (wgsl limitation),
-    // and I cannot use a 3D texture as a render target (to use a fragment shader),
-    // at least until https://github.com/gfx-rs/wgpu/issues/6040 lands.
-    // so we are doing manual linear filtering here.
-    // let filtered = textureLoad(coords)
-    let t = 1.0 / 8.0;
-    let filtered = textureLoad(in_tex, index * 2u + vec3u(0u, 0u, 0u)) * t
-                 + textureLoad(in_tex, index * 2u + vec3u(0u, 0u, 1u)) * t
-                 + textureLoad(in_tex, index * 2u + vec3u(0u, 1u, 0u)) * t
-                 + textureLoad(in_tex, index * 2u + vec3u(0u, 1u, 1u)) * t
-                 + textureLoad(in_tex, index * 2u + vec3u(1u, 0u, 0u)) * t
-                 + textureLoad(in_tex, index * 2u + vec3u(1u, 0u, 1u)) * t
-                 + textureLoad(in_tex, index * 2u + vec3u(1u, 1u, 0u)) * t
-                 + textureLoad(in_tex, index * 2u + vec3u(1u, 1u, 1u)) * t;
-    textureStore(out_tex, index, filtered);
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+    let index = id + first_texel();
+    var filtered: vec4f;
+    if #MIP_FILTER == 1u {
+        filtered = gaussian_filter(index);
+    } else if #MIP_FILTER == 2u {
+        filtered = occupancy_filter(index);
+    } else if #MIP_FILTER == 3u {
+        filtered = dominant_filter(index);
+    } else {
+        filtered = box_filter(index);
+    }
+    store(index, filtered);
 }
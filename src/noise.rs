@@ -0,0 +1,9 @@
+// the hash of the procedural noise, implemented identically in `noise.wgsl`. the cpu random
+// numbers (`random.rs`) use it too, so the seeds mean the same on both sides.
+
+/// pcg hash, see https://www.jcgt.org/published/0009/03/02/
+pub fn pcg(v: u32) -> u32 {
+    let state = v.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
@@ -0,0 +1,116 @@
+// this shader is a "module" supposed to be included.
+// `pcg` must stay identical to `noise.rs`, so the seeds match between cpu and gpu.
+//
+// this module "exports":
+// fn pcg(v: u32) -> u32
+// fn hash3(cell: vec3i, seed: u32) -> u32
+// fn unit_float(h: u32) -> f32
+// fn value_noise(p: vec3f, seed: u32) -> f32
+// fn gradient_noise(p: vec3f, seed: u32) -> f32
+// fn fbm(p: vec3f, octaves: u32, seed: u32) -> f32
+//
+// procedural features should pass the scene seed `#NOISE_SEED` as `seed`.
+
+// pcg hash, see https://www.jcgt.org/published/0009/03/02/
+fn pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn hash3(cell: vec3i, seed: u32) -> u32 {
+    let c = bitcast<vec3u>(cell);
+    return pcg(seed ^ pcg(c.x ^ pcg(c.y ^ pcg(c.z))));
+}
+
+// a float in [0, 1) from the 24 high bits of a hash.
+fn unit_float(h: u32) -> f32 {
+    return f32(h >> 8u) / 16777216.0;
+}
+
+fn smooth(t: vec3f) -> vec3f {
+    return t * t * (3.0 - 2.0 * t);
+}
+
+fn quintic(t: vec3f) -> vec3f {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    return a + (b - a) * t;
+}
+
+// one of the 12 cube edge gradients, as in improved perlin noise.
+fn grad(h_: u32, p: vec3f) -> f32 {
+    let h = h_ & 15u;
+    let u = select(p.y, p.x, h < 8u);
+    let v = select(select(p.z, p.x, h == 12u || h == 14u), p.y, h < 4u);
+    return select(-u, u, (h & 1u) == 0u) + select(-v, v, (h & 2u) == 0u);
+}
+
+fn trilinear(c0: f32, c1: f32, c2: f32, c3: f32, c4: f32, c5: f32, c6: f32, c7: f32, t: vec3f) -> f32 {
+    let x00 = lerp(c0, c1, t.x);
+    let x10 = lerp(c2, c3, t.x);
+    let x01 = lerp(c4, c5, t.x);
+    let x11 = lerp(c6, c7, t.x);
+    return lerp(lerp(x00, x10, t.y), lerp(x01, x11, t.y), t.z);
+}
+
+fn value_corner(cell: vec3i, offset: vec3i, seed: u32) -> f32 {
+    return unit_float(hash3(cell + offset, seed));
+}
+
+// value noise in [0, 1).
+fn value_noise(p: vec3f, seed: u32) -> f32 {
+    let base = floor(p);
+    let cell = vec3i(base);
+    let f = p - base;
+
+    return trilinear(
+        value_corner(cell, vec3i(0, 0, 0), seed),
+        value_corner(cell, vec3i(1, 0, 0), seed),
+        value_corner(cell, vec3i(0, 1, 0), seed),
+        value_corner(cell, vec3i(1, 1, 0), seed),
+        value_corner(cell, vec3i(0, 0, 1), seed),
+        value_corner(cell, vec3i(1, 0, 1), seed),
+        value_corner(cell, vec3i(0, 1, 1), seed),
+        value_corner(cell, vec3i(1, 1, 1), seed),
+        smooth(f),
+    );
+}
+
+fn gradient_corner(cell: vec3i, f: vec3f, offset: vec3i, seed: u32) -> f32 {
+    return grad(hash3(cell + offset, seed), f - vec3f(offset));
+}
+
+// gradient (perlin) noise, roughly in [-1, 1].
+fn gradient_noise(p: vec3f, seed: u32) -> f32 {
+    let base = floor(p);
+    let cell = vec3i(base);
+    let f = p - base;
+
+    return trilinear(
+        gradient_corner(cell, f, vec3i(0, 0, 0), seed),
+        gradient_corner(cell, f, vec3i(1, 0, 0), seed),
+        gradient_corner(cell, f, vec3i(0, 1, 0), seed),
+        gradient_corner(cell, f, vec3i(1, 1, 0), seed),
+        gradient_corner(cell, f, vec3i(0, 0, 1), seed),
+        gradient_corner(cell, f, vec3i(1, 0, 1), seed),
+        gradient_corner(cell, f, vec3i(0, 1, 1), seed),
+        gradient_corner(cell, f, vec3i(1, 1, 1), seed),
+        quintic(f),
+    );
+}
+
+// fractal sum of gradient noise octaves, each octave with its own seed.
+fn fbm(p: vec3f, octaves: u32, seed: u32) -> f32 {
+    var sum = 0.0;
+    var amp = 0.5;
+    var freq = 1.0;
+    for (var i = 0u; i < octaves; i++) {
+        sum += amp * gradient_noise(p * freq, seed + i);
+        amp *= 0.5;
+        freq *= 2.0;
+    }
+    return sum;
+}
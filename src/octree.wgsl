@@ -1,10 +1,15 @@
 #import "util.wgsl"::{ vmin, vmax, cmpmin, cmpmax }
-#import "bindings.wgsl"::{ colors, dvo }
+#import "bindings.wgsl"::{ colors, dvo, visible_bricks }
+#import "culling.wgsl"::{ CULL_DEPTH, brick_index }
 
 // this shader is a "module" supposed to be included.
 // 
 // this module "exports":
 // fn raycast(ray_pos: vec3f, ray_dir: vec3f) -> CastResult
+// fn raycast_coarse(ray_pos: vec3f, ray_dir: vec3f, level: u32) -> CastResult
+// fn raycast_culled(ray_pos: vec3f, ray_dir: vec3f) -> CastResult
+// fn raycast_beam(ray_pos: vec3f, ray_dir: vec3f, beam: f32) -> CastResult
+// fn intersection(ray_pos: vec3f, ray_dir: vec3f) -> Intersect
 // 
 // this module "requires":
 // const #OCTREE_DEPTH: u32; // depth = 0 for a 2^3 volume: depth = log2(n) - 1.
@@ -133,7 +138,12 @@ fn next_solid_octants(node_coord: vec3u, depth: u32, ray_pos: vec3f, inv_dir: ve
     return next_octants;
 }
 
-fn raycast_octree_impl(ray_pos_: vec3f, ray_dir_: vec3f) -> CastResult {
+// `level` is the log2 of the size of the octants where the descent stops, and the first solid
+// octant is a hit. levels up to #GRID_DEPTH descend down to the voxels. with `cull`, the bricks
+// outside the camera frustum are skipped, see `culling.wgsl`. with a `beam` width per unit of
+// distance, the descent also stops at the octants too small for the beam, see `raycast_beam`.
+fn raycast_octree_impl(ray_pos_: vec3f, ray_dir_: vec3f, level: u32, cull: bool, beam: f32) -> CastResult {
+    let coarse_depth = #OCTREE_DEPTH - min(level, #OCTREE_DEPTH);
     var depth = 0u;
     var octants_stack = array<u32, (#OCTREE_DEPTH - #GRID_DEPTH)>();
 
@@ -163,6 +173,13 @@ fn raycast_octree_impl(ray_pos_: vec3f, ray_dir_: vec3f) -> CastResult {
             let octant_index = next_octants & 7u;
             next_octants >>= 4u;
 
+            if cull && depth == CULL_DEPTH {
+                let brick = mirror_coord(node_coord * 2u + unpack_octant(octant_index), depth + 1u, mirror);
+                if visible_bricks[brick_index(brick)] == 0u {
+                    continue;
+                }
+            }
+
             if depth == #OCTREE_DEPTH - #GRID_DEPTH { // found a leaf
                 let octant_coord = node_coord * 2u + unpack_octant(octant_index);
                 let voxels_per_octant = vec3u(1u << #GRID_DEPTH);
@@ -186,11 +203,30 @@ fn raycast_octree_impl(ray_pos_: vec3f, ray_dir_: vec3f) -> CastResult {
                     return CastResult(pos, normal, res.voxel, i + res.iter, t, true);
                 }
             }
-            else { // recurse, push current node to stack
-                octants_stack[depth] = next_octants;
-                depth += 1u;
-                node_coord = node_coord * 2u + unpack_octant(octant_index);
-                next_octants = next_solid_octants(node_coord, depth, ray_pos, inv_dir, mirror);
+            else {
+                let octant_coord = node_coord * 2u + unpack_octant(octant_index);
+                let voxels_per_octant = 1u << (#OCTREE_DEPTH - depth);
+                let octant_start_t = (vec3f(octant_coord * voxels_per_octant) - ray_pos) * inv_dir;
+                var coarse_leaf = level > #GRID_DEPTH && depth == coarse_depth;
+                if beam > 0.0 {
+                    // the children would be smaller than the beam where it leaves the octant.
+                    let octant_end_t = (vec3f((octant_coord + 1u) * voxels_per_octant) - ray_pos) * inv_dir;
+                    coarse_leaf = coarse_leaf || f32(voxels_per_octant) < 4.0 * beam * vmin(octant_end_t);
+                }
+
+                if coarse_leaf { // found a coarse leaf
+                    let t = max(vmax(octant_start_t), 0.0);
+                    let pos = ray_pos_ + ray_dir_ * t;
+                    let normal = vec3f(cmpmax(octant_start_t)) * -sign(ray_dir_);
+                    let voxel = mirror_coord(octant_coord, depth + 1u, mirror) * voxels_per_octant;
+                    return CastResult(pos, normal, voxel, i, t, true);
+                }
+                else { // recurse, push current node to stack
+                    octants_stack[depth] = next_octants;
+                    depth += 1u;
+                    node_coord = octant_coord;
+                    next_octants = next_solid_octants(node_coord, depth, ray_pos, inv_dir, mirror);
+                }
             }
         }
     }
@@ -227,6 +263,33 @@ fn raycast_grid_impl(ray_pos: vec3f, ray_dir: vec3f, t: f32, max_t: vec3f, mirro
 
 // exported function
 fn raycast(ray_pos: vec3f, ray_dir: vec3f) -> CastResult {
+    return raycast_coarse(ray_pos, ray_dir, 0u);
+}
+
+// exported function
+// `raycast` for the rays inside the camera frustum, which skips the bricks marked outside it by
+// the culling pass.
+fn raycast_culled(ray_pos: vec3f, ray_dir: vec3f) -> CastResult {
+    return raycast_checked(ray_pos, ray_dir, 0u, true, 0.0);
+}
+
+// exported function
+// the raycast of the beam pre-pass, for a beam widening by `beam` per unit of distance. the hit
+// is on the boundary of the first solid octant smaller than 4 times the beam where the ray
+// leaves it, see `beam.wgsl`.
+fn raycast_beam(ray_pos: vec3f, ray_dir: vec3f, beam: f32) -> CastResult {
+    return raycast_checked(ray_pos, ray_dir, 0u, false, beam);
+}
+
+// exported function
+// a faster and conservative raycast, which stops at the octants of 2^level voxels. the hit is on
+// the boundary of the first solid octant, in front of the voxels.
+fn raycast_coarse(ray_pos: vec3f, ray_dir: vec3f, level: u32) -> CastResult {
+    return raycast_checked(ray_pos, ray_dir, level, false, 0.0);
+}
+
+// skip the descent for the rays that miss the volume.
+fn raycast_checked(ray_pos: vec3f, ray_dir: vec3f, level: u32, cull: bool, beam: f32) -> CastResult {
     let scene_width = f32(2u << #OCTREE_DEPTH);
     let tr_pos = ray_pos / scene_width;
     var t = intersection(tr_pos, ray_dir);
@@ -237,7 +300,7 @@ fn raycast(ray_pos: vec3f, ray_dir: vec3f) -> CastResult {
     }
 
     else {
-        let res = raycast_octree_impl(ray_pos, ray_dir);
+        let res = raycast_octree_impl(ray_pos, ray_dir, level, cull, beam);
         return res;
     }
 }
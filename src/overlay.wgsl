@@ -0,0 +1,98 @@
+#import "ray.wgsl"::{ from_volume }
+
+// this shader is a "module" supposed to be included.
+//
+// this module "exports":
+// fn route_glow(ray_pos: vec3f, ray_dir: vec3f, max_t: f32) -> vec3f
+// fn frustum_glow(ray_pos: vec3f, ray_dir: vec3f, max_t: f32) -> vec3f
+//
+// overlays are not part of the voxel volume: they are composited on top of the shaded color
+// by measuring how close the primary ray passes to line segments. the segments are uploaded in
+// world coordinates, the rays are in render space.
+
+struct Route {
+    color: vec3f,
+    width: f32,
+    len: u32,
+}
+
+@group(0) @binding(2)
+var<uniform> route: Route;
+
+@group(0) @binding(3)
+var<storage, read> route_points: array<vec4f>;
+
+struct Frustum {
+    corners: array<vec4f, 8>, // 4 near then 4 far corners
+    color: vec3f,
+    visible: u32,
+    width: f32,
+}
+
+@group(0) @binding(5)
+var<uniform> frustum: Frustum;
+
+// closest distance between the ray segment [ray_pos, ray_pos + ray_dir * max_t] and the segment [a, b].
+// ray_dir must be normalized.
+fn ray_segment_dist(ray_pos: vec3f, ray_dir: vec3f, max_t: f32, a: vec3f, b: vec3f) -> f32 {
+    let v = b - a;
+    let w = ray_pos - a;
+    let uv = dot(ray_dir, v);
+    let vv = max(dot(v, v), 1e-6);
+    let uw = dot(ray_dir, w);
+    let vw = dot(v, w);
+    let denom = vv - uv * uv;
+
+    // parameter along the segment of the closest point to the (infinite) ray.
+    var s = select((vw - uv * uw) / denom, 0.0, denom < 1e-6);
+    s = clamp(s, 0.0, 1.0);
+    // then the closest point along the ray, and back again on the segment.
+    let t = clamp(s * uv - uw, 0.0, max_t);
+    s = clamp((t * uv + vw) / vv, 0.0, 1.0);
+
+    return distance(ray_pos + ray_dir * t, a + v * s);
+}
+
+fn glow(dist: f32, width: f32) -> f32 {
+    let x = dist / width;
+    return exp(-x * x);
+}
+
+fn route_glow(ray_pos: vec3f, ray_dir: vec3f, max_t: f32) -> vec3f {
+    var intensity = 0.0;
+
+    for (var i = 1u; i < route.len; i++) {
+        let a = from_volume(route_points[i - 1u].xyz);
+        let b = from_volume(route_points[i].xyz);
+        let dist = ray_segment_dist(ray_pos, ray_dir, max_t, a, b);
+        intensity = max(intensity, glow(dist, route.width));
+    }
+
+    return route.color * intensity;
+}
+
+// wireframe of the frozen render camera frustum: the 4 edges of the near and far planes, and
+// the 4 edges joining them.
+fn frustum_glow(ray_pos: vec3f, ray_dir: vec3f, max_t: f32) -> vec3f {
+    if frustum.visible == 0u {
+        return vec3f(0.0);
+    }
+
+    var corners: array<vec3f, 8>;
+    for (var i = 0u; i < 8u; i++) {
+        corners[i] = from_volume(frustum.corners[i].xyz);
+    }
+
+    var intensity = 0.0;
+
+    for (var i = 0u; i < 4u; i++) {
+        let j = (i + 1u) % 4u;
+        let near = ray_segment_dist(ray_pos, ray_dir, max_t, corners[i], corners[j]);
+        let far = ray_segment_dist(ray_pos, ray_dir, max_t, corners[i + 4u], corners[j + 4u]);
+        let side = ray_segment_dist(ray_pos, ray_dir, max_t, corners[i], corners[i + 4u]);
+        let dist = min(min(near, far), side);
+        intensity = max(intensity, glow(dist, frustum.width));
+    }
+
+    return frustum.color * intensity;
+}
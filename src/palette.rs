@@ -0,0 +1,265 @@
+use std::path::PathBuf;
+
+use crate::{i18n::tr, settings::FEATURES, turntable::export_turntable, State};
+
+// the command palette (ctrl+p): every action of the viewer in one searchable list.
+// actions are matched with a fuzzy subsequence search, and run after the ui pass since most of
+// them need the whole state.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    LoadScene,
+    OpenTab,
+    ContinueSession,
+    SaveSession,
+    ToggleFeature(u32),
+    ToggleCollisions,
+    ToggleFrozenCamera,
+    ToggleRoute,
+    ToggleBuilderMode,
+    TeleportToSpawn,
+    TeleportToRouteStart,
+    Screenshot,
+    ExportGif,
+    ExportTurntable,
+    ReloadShaders,
+    BakeLighting,
+    ClearBakedLighting,
+    BakeProbes,
+}
+
+/// name and action of every entry of the palette, in display order.
+fn entries() -> Vec<(String, Action)> {
+    let mut entries = vec![
+        (tr("load scene…").to_owned(), Action::LoadScene),
+        (tr("open scene in new tab…").to_owned(), Action::OpenTab),
+        (
+            tr("continue last session").to_owned(),
+            Action::ContinueSession,
+        ),
+        (tr("save session").to_owned(), Action::SaveSession),
+        (tr("teleport to spawn").to_owned(), Action::TeleportToSpawn),
+        (
+            tr("teleport to route start").to_owned(),
+            Action::TeleportToRouteStart,
+        ),
+        (tr("screenshot").to_owned(), Action::Screenshot),
+        (tr("export gif").to_owned(), Action::ExportGif),
+        (tr("export turntable").to_owned(), Action::ExportTurntable),
+        (tr("reload shaders").to_owned(), Action::ReloadShaders),
+        (tr("bake lighting").to_owned(), Action::BakeLighting),
+        (
+            tr("clear baked lighting").to_owned(),
+            Action::ClearBakedLighting,
+        ),
+        (tr("bake reflection probes").to_owned(), Action::BakeProbes),
+        (
+            tr("toggle camera collisions").to_owned(),
+            Action::ToggleCollisions,
+        ),
+        (
+            tr("toggle frozen render camera").to_owned(),
+            Action::ToggleFrozenCamera,
+        ),
+        (tr("toggle route").to_owned(), Action::ToggleRoute),
+        (
+            tr("toggle builder mode (B)").to_owned(),
+            Action::ToggleBuilderMode,
+        ),
+    ];
+    entries.extend(FEATURES.iter().map(|(name, bit)| {
+        (
+            format!("{} {}", tr("toggle"), tr(name)),
+            Action::ToggleFeature(*bit),
+        )
+    }));
+    entries
+}
+
+/// score of `text` for `query`, or None if the query is not a subsequence of the text.
+/// consecutive matches and matches at the start of words score higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut prev_match = None;
+
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = pos + text[pos..].iter().position(|c| *c == q)?;
+        score += 1;
+        if prev_match == Some(found.wrapping_sub(1)) {
+            score += 4;
+        }
+        if found == 0 || text[found - 1] == ' ' {
+            score += 3;
+        }
+        prev_match = Some(found);
+        pos = found + 1;
+    }
+
+    // shorter names first among equal matches.
+    Some(score * 100 - text.len() as i32)
+}
+
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    fn toggle(&mut self) {
+        self.open = !self.open;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// draw the palette if it is open. returns the action picked by the user, if any.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Action> {
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::P)) {
+            self.toggle();
+        }
+        if !self.open {
+            return None;
+        }
+
+        let mut matches = entries()
+            .into_iter()
+            .filter_map(|(name, action)| Some((fuzzy_score(&self.query, &name)?, name, action)))
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(score, ..)| -score);
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        if down {
+            self.selected += 1;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut picked = None;
+        egui::Window::new("Command palette")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 40.0))
+            .fixed_size(egui::vec2(320.0, 0.0))
+            .show(ctx, |ui| {
+                let search = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text(tr("search actions"))
+                        .desired_width(f32::INFINITY),
+                );
+                search.request_focus();
+                if search.changed() {
+                    self.selected = 0;
+                }
+
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (i, (_, name, action)) in matches.iter().enumerate() {
+                            let label = ui.selectable_label(i == self.selected, name);
+                            if i == self.selected && (up || down) {
+                                label.scroll_to_me(None);
+                            }
+                            if label.clicked() {
+                                picked = Some(*action);
+                            }
+                        }
+                        if matches.is_empty() {
+                            ui.weak(tr("no matching action"));
+                        }
+                    });
+            });
+
+        if enter {
+            picked = matches.get(self.selected).map(|(_, _, action)| *action);
+        }
+        if picked.is_some() || escape {
+            self.toggle();
+        }
+        picked
+    }
+}
+
+/// pick a scene with the native file dialog.
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_scene() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title("Load scene")
+        .add_filter("scene", &["wvox", "wchunks", "vox"])
+        .pick_file()
+}
+
+/// the web viewer shows the scene of its page only.
+#[cfg(target_arch = "wasm32")]
+fn pick_scene() -> Option<PathBuf> {
+    None
+}
+
+pub fn run_action(state: &mut State, action: Action) {
+    match action {
+        Action::LoadScene => {
+            if let Some(path) = pick_scene() {
+                state.start_loading(&path);
+            }
+        }
+        Action::OpenTab => {
+            if let Some(path) = pick_scene() {
+                if let Err(err) = state.open_tab(&path) {
+                    state.error = Some(err);
+                }
+            }
+        }
+        Action::ContinueSession => state.continue_session(),
+        Action::SaveSession => state.save_session(),
+        Action::ToggleFeature(feature) => {
+            let enabled = state.settings.enabled(feature);
+            state.settings.set_enabled(feature, !enabled);
+        }
+        Action::ToggleCollisions => state.collisions = !state.collisions,
+        Action::ToggleFrozenCamera => {
+            let frozen = state.frustum.is_frozen();
+            state.frustum.set_frozen(&state.camera, !frozen);
+        }
+        Action::ToggleRoute => state.route.visible = !state.route.visible,
+        Action::ToggleBuilderMode => state.editor.enabled = !state.editor.enabled,
+        Action::TeleportToSpawn => state.teleport_to_spawn(),
+        Action::TeleportToRouteStart => match state.route.points.first() {
+            Some(start) => state.camera.set_pos(&start.xyz()),
+            None => eprintln!("no route loaded"),
+        },
+        Action::Screenshot => state.screenshot(),
+        Action::ExportGif => state.export_history(),
+        Action::ExportTurntable => {
+            if let Err(err) = export_turntable(state) {
+                eprintln!("turntable export failed: {}", err);
+            }
+        }
+        Action::ReloadShaders => {
+            state
+                .wgpu_state
+                .reload_shaders(&state.device, &state.config, &state.constants)
+        }
+        Action::BakeLighting => state.bake_lighting(false),
+        Action::ClearBakedLighting => state.clear_baked_lighting(),
+        Action::BakeProbes => state.bake_probes(),
+    }
+}
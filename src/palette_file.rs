@@ -0,0 +1,170 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+// palette import and export in the usual formats of voxel and pixel art tools:
+// - `.png`: a strip of colors like the MagicaVoxel palettes, read row by row.
+// - `.pal`: JASC-PAL text (`JASC-PAL`, `0100`, the count, then `r g b` per line).
+// - `.hex`: the Lospec format, `rrggbb` per line.
+// an imported palette is remapped onto the scene palette, see `Remap`.
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to read `{0}`: {1}")]
+    IOError(PathBuf, std::io::Error),
+    #[error("failed to read `{0}`: {1}")]
+    ImageError(PathBuf, image::ImageError),
+    #[error("invalid palette in `{0}`, line {1}")]
+    ParseError(PathBuf, usize),
+    #[error("`{0}` is not a palette, expected .png, .pal or .hex")]
+    UnknownFormat(PathBuf),
+    #[error("`{0}` contains no colors")]
+    Empty(PathBuf),
+}
+
+/// how the entries of the scene palette take the colors of an imported palette.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Remap {
+    /// entry n takes the nth imported color, the remaining entries are kept.
+    ByIndex,
+    /// each entry takes the imported color nearest to its own.
+    Nearest,
+}
+
+impl Remap {
+    pub const ALL: [Self; 2] = [Self::ByIndex, Self::Nearest];
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn parse_pal(path: &Path, source: &str) -> Result<Vec<[u8; 4]>, Error> {
+    if !source.starts_with("JASC-PAL") {
+        return Err(Error::ParseError(path.to_owned(), 1));
+    }
+    // after the magic, the version and the number of colors.
+    source
+        .lines()
+        .enumerate()
+        .skip(3)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            let rgb = line
+                .split_whitespace()
+                .map(|c| c.parse::<u8>())
+                .collect::<Result<Vec<_>, _>>();
+            match rgb.as_deref() {
+                Ok([r, g, b, ..]) => Ok([*r, *g, *b, 255]),
+                _ => Err(Error::ParseError(path.to_owned(), n + 1)),
+            }
+        })
+        .collect()
+}
+
+fn parse_hex(path: &Path, source: &str) -> Result<Vec<[u8; 4]>, Error> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n, line.trim().trim_start_matches('#')))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(n, line)| {
+            u32::from_str_radix(line, 16)
+                .ok()
+                .filter(|_| line.len() == 6)
+                .map(|rgb| {
+                    let [_, r, g, b] = rgb.to_be_bytes();
+                    [r, g, b, 255]
+                })
+                .ok_or(Error::ParseError(path.to_owned(), n + 1))
+        })
+        .collect()
+}
+
+/// read a palette file, the format is picked from its extension.
+pub fn load(path: &Path) -> Result<Vec<[u8; 4]>, Error> {
+    let ext = extension(path);
+    let palette = if ext == "png" {
+        let image = image::open(path).map_err(|e| Error::ImageError(path.to_owned(), e))?;
+        image.to_rgba8().pixels().map(|p| p.0).collect()
+    } else {
+        let parse = match ext.as_str() {
+            "pal" => parse_pal,
+            "hex" => parse_hex,
+            _ => return Err(Error::UnknownFormat(path.to_owned())),
+        };
+        let source = fs::read_to_string(path).map_err(|e| Error::IOError(path.to_owned(), e))?;
+        parse(path, &source)?
+    };
+    if palette.is_empty() {
+        return Err(Error::Empty(path.to_owned()));
+    }
+    Ok(palette)
+}
+
+/// write a palette file, the format is picked from its extension. a png is a 1 pixel high strip.
+pub fn save(path: &Path, palette: &[[u8; 4]]) -> Result<(), Error> {
+    let source = match extension(path).as_str() {
+        "png" => {
+            let pixels = palette.iter().flatten().copied().collect();
+            let image = image::RgbaImage::from_raw(palette.len() as u32, 1, pixels).unwrap();
+            image
+                .save(path)
+                .map_err(|e| Error::ImageError(path.to_owned(), e))?;
+            println!("wrote `{}`", path.display());
+            return Ok(());
+        }
+        "pal" => palette.iter().fold(
+            format!("JASC-PAL\n0100\n{}\n", palette.len()),
+            |mut source, [r, g, b, _]| {
+                let _ = writeln!(source, "{r} {g} {b}");
+                source
+            },
+        ),
+        "hex" => palette
+            .iter()
+            .fold(String::new(), |mut source, [r, g, b, _]| {
+                let _ = writeln!(source, "{r:02x}{g:02x}{b:02x}");
+                source
+            }),
+        _ => return Err(Error::UnknownFormat(path.to_owned())),
+    };
+    fs::write(path, source).map_err(|e| Error::IOError(path.to_owned(), e))?;
+    println!("wrote `{}`", path.display());
+    Ok(())
+}
+
+fn distance(a: &[u8; 4], b: &[u8; 4]) -> u32 {
+    a.iter()
+        .zip(b)
+        .take(3)
+        .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+        .sum()
+}
+
+/// the scene `palette` recolored with the `imported` colors. the number of entries does not
+/// change, so the voxels keep their indices.
+pub fn remap(palette: &[[u8; 4]], imported: &[[u8; 4]], mode: Remap) -> Vec<[u8; 4]> {
+    match mode {
+        Remap::ByIndex => palette
+            .iter()
+            .enumerate()
+            .map(|(i, color)| *imported.get(i).unwrap_or(color))
+            .collect(),
+        Remap::Nearest => palette
+            .iter()
+            .map(|color| {
+                *imported
+                    .iter()
+                    .min_by_key(|c| distance(c, color))
+                    .unwrap_or(color)
+            })
+            .collect(),
+    }
+}
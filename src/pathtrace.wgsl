@@ -0,0 +1,171 @@
+#import "octree.wgsl"::{ raycast }
+#import "ray.wgsl"::{ cam, cam_pos, cam_ray_dir, to_volume }
+#import "traversal.wgsl"::{ trace_primary }
+#import "lights.wgsl"::{ lights }
+#import "sky.wgsl"::{ sky_color }
+#import "environment.wgsl"::{ ground_t, ground_albedo }
+#import "settings.wgsl"::{ feature_enabled, FEATURE_SHADOWS }
+#import "materials.wgsl"::{ Material, material_of, voxel_albedo, default_material }
+#import "shading.wgsl"::{ ambient_light, brdf, local_lighting, reflectance, schlick, PI }
+#import "random.wgsl"::{ rng_state, rand }
+
+// this shader is a "module" supposed to be included.
+// the path traced mode (RENDER_MODE 1): each frame traces one path per pixel through the dvo,
+// with the sun and the local lights sampled at every bounce and the ambient light where the
+// path escapes. the frames are averaged in the history buffer, see `accumulation.rs`. the
+// detail textures, the water and the cone traced shadows of the raymarched mode are ignored.
+//
+// this module "exports":
+// var<uniform> accum: Accumulation
+// var<storage> history: array<vec2u>
+// struct PathSample
+// fn path_trace(screen_pos: vec2f, pixel: vec2u) -> PathSample
+// fn accumulate(pixel: vec2u, color: vec3f) -> vec3f
+
+// bounces after the primary hit.
+const PATH_BOUNCES: u32 = 3u;
+// radiance of a path at most, it trades a little energy for fewer fireflies.
+const MAX_RADIANCE: f32 = 4.0;
+// the random sequence of the paths, see `random.wgsl`.
+const RNG_STREAM: u32 = 0u;
+
+// see `AccumulationUniform` in accumulation.rs.
+struct Accumulation {
+    frame: u32, // frames already averaged in the history, 0 to restart
+    width: u32, // of the history, in pixels
+}
+
+@group(0) @binding(13)
+var<uniform> accum: Accumulation;
+
+// the average color of each pixel, rgb packed as f16.
+@group(0) @binding(14)
+var<storage, read_write> history: array<vec2u>;
+
+struct PathSample {
+    radiance: vec3f,
+    t: f32, // distance to the primary hit, 1e9 for the sky
+}
+
+// a direction around `normal`, with a probability proportional to the cosine.
+fn cosine_dir(normal: vec3f, state: ptr<function, u32>) -> vec3f {
+    let r = sqrt(rand(state));
+    let phi = 2.0 * PI * rand(state);
+    // orthonormal basis, see Duff et al. 2017, "Building an Orthonormal Basis, Revisited".
+    let s = select(-1.0, 1.0, normal.z >= 0.0);
+    let a = -1.0 / (s + normal.z);
+    let b = normal.x * normal.y * a;
+    let tangent = vec3f(1.0 + s * normal.x * normal.x * a, s * b, -s * normal.x);
+    let bitangent = vec3f(b, s + normal.y * normal.y * a, -normal.y);
+    let z = sqrt(max(1.0 - r * r, 0.0));
+    return normalize(tangent * r * cos(phi) + bitangent * r * sin(phi) + normal * z);
+}
+
+// the direct light at a path vertex: the sun with a hard shadow ray, and the local lights.
+fn direct_light(material: Material, base_color: vec3f, view_dir: vec3f, pos: vec3f, normal: vec3f) -> vec3f {
+    let sun = lights.sun;
+    let sun_up = smoothstep(-0.05, 0.05, sun.dir.y);
+    var light = vec3f(0.0);
+    if sun_up > 0.0 && dot(normal, sun.dir) > 0.0 {
+        var visible = true;
+        if feature_enabled(FEATURE_SHADOWS) && sun.shadow != 0u {
+            visible = !raycast(to_volume(pos + normal * 1e-3), sun.dir).hit;
+        }
+        if visible {
+            light = brdf(material, base_color, normal, view_dir, sun.dir) * sun_up;
+        }
+    }
+    return light + local_lighting(material, base_color, view_dir, pos, normal);
+}
+
+fn path_trace(screen_pos: vec2f, pixel: vec2u) -> PathSample {
+    var state = rng_state(pixel, RNG_STREAM);
+    // the jitter over the pixel anti-aliases the average.
+    let jitter = (vec2f(rand(&state), rand(&state)) - 0.5) * 2.0 / cam.size;
+    let primary = trace_primary(screen_pos + jitter);
+
+    var ray_pos = cam_pos();
+    var ray_dir = cam_ray_dir(screen_pos + jitter);
+    var hit = primary.hit;
+    var t = primary.t;
+    var pos = primary.pos;
+    var normal = primary.normal;
+    var voxel = primary.voxel;
+
+    var radiance = vec3f(0.0);
+    var throughput = vec3f(1.0);
+    var first_t = 1e9;
+
+    for (var bounce = 0u; bounce <= PATH_BOUNCES; bounce++) {
+        var material = default_material();
+        var albedo = vec3f(0.0);
+        if hit {
+            material = material_of(voxel);
+            albedo = voxel_albedo(voxel).rgb;
+        } else {
+            // rays leaving the volume downwards land on the infinite ground plane.
+            let ground_dist = ground_t(ray_pos, ray_dir);
+            if ground_dist <= 0.0 {
+                if bounce == 0u {
+                    radiance = sky_color(ray_dir, lights.sun.dir);
+                } else {
+                    radiance += throughput * ambient_light(ray_dir);
+                }
+                break;
+            }
+            t = ground_dist;
+            pos = ray_pos + ray_dir * ground_dist;
+            normal = vec3f(0.0, 1.0, 0.0);
+            albedo = ground_albedo(pos);
+        }
+        if bounce == 0u {
+            first_t = t;
+        }
+
+        let base_color = pow(albedo, vec3f(2.2));
+        let view_dir = -ray_dir;
+        radiance += throughput * (direct_light(material, base_color, view_dir, pos, normal) + albedo * material.emissive);
+        if bounce == PATH_BOUNCES {
+            break;
+        }
+
+        // reflect or scatter, with the probability of the fresnel reflectance.
+        let fresnel = schlick(reflectance(material, base_color), saturate(dot(normal, view_dir)));
+        let p_specular = mix(max(fresnel.r, max(fresnel.g, fresnel.b)), 1.0, material.metalness);
+        if rand(&state) < p_specular {
+            let roughness = material.roughness * material.roughness;
+            ray_dir = normalize(reflect(ray_dir, normal) + roughness * cosine_dir(normal, &state));
+            throughput *= fresnel / p_specular;
+        } else {
+            ray_dir = cosine_dir(normal, &state);
+            throughput *= (1.0 - fresnel) * (1.0 - material.metalness) * base_color / (1.0 - p_specular);
+        }
+        if dot(ray_dir, normal) <= 0.0 || all(throughput < vec3f(1e-3)) {
+            break;
+        }
+
+        ray_pos = pos + normal * 1e-3;
+        let res = raycast(to_volume(ray_pos), ray_dir);
+        hit = res.hit;
+        pos = ray_pos + ray_dir * res.t;
+        normal = res.normal;
+        voxel = res.voxel;
+    }
+
+    return PathSample(min(radiance, vec3f(MAX_RADIANCE)), first_t);
+}
+
+// the average of `color` with the previous frames of the pixel, written back to the history.
+fn accumulate(pixel: vec2u, color: vec3f) -> vec3f {
+    let i = pixel.y * accum.width + pixel.x;
+    if pixel.x >= accum.width || i >= arrayLength(&history) {
+        return color;
+    }
+    var average = color;
+    if accum.frame > 0u {
+        let prev = vec3f(unpack2x16float(history[i].x), unpack2x16float(history[i].y).x);
+        average = mix(prev, color, 1.0 / f32(accum.frame + 1u));
+    }
+    history[i] = vec2u(pack2x16float(average.rg), pack2x16float(vec2f(average.b, 0.0)));
+    return average;
+}
@@ -0,0 +1,29 @@
+#import "octree.wgsl"::{ raycast }
+
+// cast a few rays from the cpu through the octree, e.g. to find the voxel under the mouse.
+// one invocation per ray, results are read back by the cpu.
+
+struct Rays {
+    pos: vec4f,
+    dirs: array<vec4f, #PICK_SAMPLES>,
+}
+
+struct PickResult {
+    pos: vec3f,
+    t: f32,
+    voxel: vec3u,
+    hit: u32,
+    normal: vec3f,
+}
+
+@group(0) @binding(0)
+var<uniform> rays: Rays;
+
+@group(0) @binding(1)
+var<storage, read_write> results: array<PickResult, #PICK_SAMPLES>;
+
+@compute @workgroup_size(1)
+fn cs_main(@builtin(global_invocation_id) index: vec3u) {
+    let res = raycast(rays.pos.xyz, rays.dirs[index.x].xyz);
+    results[index.x] = PickResult(res.pos, res.t, res.voxel, u32(res.hit), res.normal);
+}
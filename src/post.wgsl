@@ -0,0 +1,75 @@
+#import "environment.wgsl"::{ env }
+#import "settings.wgsl"::{ feature_enabled, FEATURE_FOG }
+#import "lights.wgsl"::{ lights }
+#import "ray.wgsl"::{ from_volume }
+
+// this shader is a "module" supposed to be included.
+// post-processing of the shaded color, applied once per pixel.
+//
+// this module "exports":
+// fn apply_fog(color: vec3f, dist: f32) -> vec3f
+// fn apply_horizon_fog(color: vec3f, ray_dir: vec3f) -> vec3f
+// fn apply_fog_volumes(color: vec3f, ray_pos: vec3f, ray_dir: vec3f, max_t: f32) -> vec3f
+// fn composite(color: vec3f, overlay: vec3f) -> vec3f
+
+const MAX_FOG_VOLUMES: u32 = 8u; // see fog.rs
+
+struct FogVolume {
+    min: vec3f,
+    density: f32, // extinction per voxel
+    max: vec3f,
+    color: vec3f,
+}
+
+struct FogVolumes {
+    volumes: array<FogVolume, MAX_FOG_VOLUMES>,
+    count: u32,
+}
+
+@group(0) @binding(9)
+var<uniform> fog_volumes: FogVolumes;
+
+fn apply_fog(color: vec3f, dist: f32) -> vec3f {
+    if env.fog_enabled == 0u || !feature_enabled(FEATURE_FOG) {
+        return color;
+    }
+    let f = saturate(dist / env.fog_distance);
+    return mix(color, env.fog_color, f * f);
+}
+
+// rays grazing the horizon fade into the fog color, hiding the end of the ground plane.
+fn apply_horizon_fog(color: vec3f, ray_dir: vec3f) -> vec3f {
+    if env.fog_enabled == 0u || !feature_enabled(FEATURE_FOG) {
+        return color;
+    }
+    let f = pow(1.0 - abs(ray_dir.y), 8.0);
+    return mix(color, env.fog_color, f);
+}
+
+// local fog boxes crossed by the ray before `max_t`, the boxes being in world coordinates. the fog
+// is homogeneous inside a box, so the transmittance through it is exp(-density * length).
+fn apply_fog_volumes(color: vec3f, ray_pos: vec3f, ray_dir: vec3f, max_t: f32) -> vec3f {
+    if !feature_enabled(FEATURE_FOG) {
+        return color;
+    }
+    var res = color;
+    for (var i = 0u; i < fog_volumes.count; i++) {
+        let volume = fog_volumes.volumes[i];
+        let t_a = (from_volume(volume.min) - ray_pos) / ray_dir;
+        let t_b = (from_volume(volume.max) - ray_pos) / ray_dir;
+        let t_min = min(t_a, t_b);
+        let t_max = max(t_a, t_b);
+        let t_in = max(max(t_min.x, t_min.y), max(t_min.z, 0.0));
+        let t_out = min(min(t_max.x, t_max.y), min(t_max.z, max_t));
+        if t_out > t_in {
+            let transmittance = exp(-volume.density * (t_out - t_in));
+            res = mix(volume.color, res, transmittance);
+        }
+    }
+    return res;
+}
+
+// the exposure scales the scene, overlays are added on top of the final color.
+fn composite(color: vec3f, overlay: vec3f) -> vec3f {
+    return saturate(color * lights.exposure + overlay);
+}
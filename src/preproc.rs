@@ -16,7 +16,7 @@ use thiserror::Error;
 use wgpu::naga::{
     self,
     front::wgsl,
-    valid::{Capabilities, ShaderStages},
+    valid::{Capabilities, ShaderStages, ValidationFlags, Validator},
 };
 
 /// a straightforward wgsl preprocessor.
@@ -27,6 +27,8 @@ pub enum Error {
     IOError(PathBuf),
     #[error("while composing `{0}`: {1}")]
     ComposerError(PathBuf, String, ComposerError),
+    #[error("while validating `{0}`: {1}")]
+    ValidationError(PathBuf, String),
 }
 
 pub struct Context<'a> {
@@ -34,6 +36,82 @@ pub struct Context<'a> {
     pub constants: &'a HashMap<String, f64>,
 }
 
+/// the shader sources, embedded in the web viewer which has no file system. looked up by file
+/// name, the modules are all in `src/`.
+#[cfg(target_arch = "wasm32")]
+const EMBEDDED_SHADERS: &[(&str, &str)] = &[
+    ("beam.wgsl", include_str!("beam.wgsl")),
+    ("benchmark.wgsl", include_str!("benchmark.wgsl")),
+    ("bindings.wgsl", include_str!("bindings.wgsl")),
+    ("blit.wgsl", include_str!("blit.wgsl")),
+    ("brickmap.wgsl", include_str!("brickmap.wgsl")),
+    ("clusters.wgsl", include_str!("clusters.wgsl")),
+    ("colormap.wgsl", include_str!("colormap.wgsl")),
+    (
+        "compute_contours.wgsl",
+        include_str!("compute_contours.wgsl"),
+    ),
+    ("compute_contree.wgsl", include_str!("compute_contree.wgsl")),
+    ("compute_culling.wgsl", include_str!("compute_culling.wgsl")),
+    (
+        "compute_light_culling.wgsl",
+        include_str!("compute_light_culling.wgsl"),
+    ),
+    ("compute_octree.wgsl", include_str!("compute_octree.wgsl")),
+    ("compute_sdf.wgsl", include_str!("compute_sdf.wgsl")),
+    ("conetrace.wgsl", include_str!("conetrace.wgsl")),
+    ("contours.wgsl", include_str!("contours.wgsl")),
+    ("contree.wgsl", include_str!("contree.wgsl")),
+    ("culling.wgsl", include_str!("culling.wgsl")),
+    ("detail.wgsl", include_str!("detail.wgsl")),
+    ("environment.wgsl", include_str!("environment.wgsl")),
+    ("exposure.wgsl", include_str!("exposure.wgsl")),
+    ("feedback.wgsl", include_str!("feedback.wgsl")),
+    ("footprint.wgsl", include_str!("footprint.wgsl")),
+    ("gbuffer.wgsl", include_str!("gbuffer.wgsl")),
+    ("gizmos.wgsl", include_str!("gizmos.wgsl")),
+    ("lights.wgsl", include_str!("lights.wgsl")),
+    ("materials.wgsl", include_str!("materials.wgsl")),
+    ("mipmap.wgsl", include_str!("mipmap.wgsl")),
+    ("noise.wgsl", include_str!("noise.wgsl")),
+    ("octree.wgsl", include_str!("octree.wgsl")),
+    ("overlay.wgsl", include_str!("overlay.wgsl")),
+    ("pathtrace.wgsl", include_str!("pathtrace.wgsl")),
+    ("pick.wgsl", include_str!("pick.wgsl")),
+    ("post.wgsl", include_str!("post.wgsl")),
+    ("primary.wgsl", include_str!("primary.wgsl")),
+    ("probes.wgsl", include_str!("probes.wgsl")),
+    ("random.wgsl", include_str!("random.wgsl")),
+    ("ray.wgsl", include_str!("ray.wgsl")),
+    ("sdf.wgsl", include_str!("sdf.wgsl")),
+    ("settings.wgsl", include_str!("settings.wgsl")),
+    ("sh.wgsl", include_str!("sh.wgsl")),
+    ("shader.wgsl", include_str!("shader.wgsl")),
+    ("shading.wgsl", include_str!("shading.wgsl")),
+    ("sky.wgsl", include_str!("sky.wgsl")),
+    ("sky_sh.wgsl", include_str!("sky_sh.wgsl")),
+    ("slice.wgsl", include_str!("slice.wgsl")),
+    ("traversal.wgsl", include_str!("traversal.wgsl")),
+    ("util.wgsl", include_str!("util.wgsl")),
+    ("visibility.wgsl", include_str!("visibility.wgsl")),
+    ("water.wgsl", include_str!("water.wgsl")),
+];
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_source(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_source(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    EMBEDDED_SHADERS
+        .iter()
+        .find(|(file, _)| *file == name)
+        .map(|(_, source)| (*source).to_owned())
+}
+
+#[tracing::instrument(skip_all, fields(main = %context.main.display()))]
 pub fn preprocess_shader(context: &Context) -> Result<naga::Module, Error> {
     enum TmpError {
         Processed(Error),
@@ -44,19 +122,22 @@ pub fn preprocess_shader(context: &Context) -> Result<naga::Module, Error> {
         path: &Path,
         defs: &HashMap<String, ShaderDefValue>,
     ) -> Result<(), TmpError> {
-        let mod_name = format!("\"{}\"", path.file_name().unwrap().to_string_lossy());
+        let mod_name = format!(
+            "\"{}\"",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
 
         if composer.contains_module(&mod_name) {
             return Ok(());
         }
 
-        let source = fs::read_to_string(path)
-            .map_err(|_| TmpError::Processed(Error::IOError(path.to_owned())))?;
+        let source = read_source(path)
+            .ok_or_else(|| TmpError::Processed(Error::IOError(path.to_owned())))?;
         let (name, imports, defines) = naga_oil::compose::get_preprocessor_data(&source);
 
         for import in imports.iter() {
             if import.import.starts_with('"') && import.import.ends_with('"') {
-                let mut path = path.parent().unwrap().to_path_buf();
+                let mut path = path.parent().unwrap_or(Path::new("")).to_path_buf();
                 path.push(&import.import[1..import.import.len() - 1]);
                 rec_preproc(composer, &path, defs)?;
             }
@@ -65,7 +146,7 @@ pub fn preprocess_shader(context: &Context) -> Result<naga::Module, Error> {
         let module = composer
             .add_composable_module(ComposableModuleDescriptor {
                 source: &source,
-                file_path: path.to_str().unwrap(),
+                file_path: &path.to_string_lossy(),
                 language: compose::ShaderLanguage::Wgsl,
                 as_name: Some(mod_name),
                 additional_imports: &[],
@@ -87,7 +168,7 @@ pub fn preprocess_shader(context: &Context) -> Result<naga::Module, Error> {
     );
 
     let source =
-        fs::read_to_string(&context.main).map_err(|_| Error::IOError(context.main.to_owned()))?;
+        read_source(context.main).ok_or_else(|| Error::IOError(context.main.to_owned()))?;
 
     let (name, imports, defines) = naga_oil::compose::get_preprocessor_data(&source);
     let imports = imports
@@ -99,7 +180,7 @@ pub fn preprocess_shader(context: &Context) -> Result<naga::Module, Error> {
     // this is a for loop with early return on error.
     let err = imports.iter().find_map(|import| {
         if import.starts_with('"') && import.ends_with('"') {
-            let mut path = context.main.parent().unwrap().to_path_buf();
+            let mut path = context.main.parent().unwrap_or(Path::new("")).to_path_buf();
             path.push(&import[1..import.len() - 1]);
             let res = rec_preproc(&mut composer, &path, &defs);
             res.err()
@@ -121,7 +202,7 @@ pub fn preprocess_shader(context: &Context) -> Result<naga::Module, Error> {
     let module = composer
         .make_naga_module(NagaModuleDescriptor {
             source: &source,
-            file_path: context.main.to_str().unwrap(),
+            file_path: &context.main.to_string_lossy(),
             shader_type: ShaderType::Wgsl,
             shader_defs: defs,
             additional_imports: &[],
@@ -133,6 +214,40 @@ pub fn preprocess_shader(context: &Context) -> Result<naga::Module, Error> {
     Ok(module)
 }
 
+/// compile and validate each shader module of `dir` on its own, with the given constants.
+/// bindings come from the modules' own imports (`bindings.wgsl`), so this catches broken
+/// imports and type errors in modules that are not (yet) used by a pipeline.
+pub fn check_modules(
+    dir: &Path,
+    constants: &HashMap<String, f64>,
+) -> Result<Vec<(PathBuf, Result<(), Error>)>, Error> {
+    let mut paths = fs::read_dir(dir)
+        .map_err(|_| Error::IOError(dir.to_owned()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wgsl"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let results = paths
+        .into_iter()
+        .map(|path| {
+            let context = Context {
+                main: &path,
+                constants,
+            };
+            let res = preprocess_shader(&context).and_then(|module| {
+                Validator::new(ValidationFlags::all(), Capabilities::all())
+                    .validate(&module)
+                    .map(|_| ())
+                    .map_err(|e| Error::ValidationError(path.to_owned(), e.emit_to_string("")))
+            });
+            (path, res)
+        })
+        .collect();
+
+    Ok(results)
+}
+
 pub fn build_shader(context: &Context) -> Result<String, Error> {
     fn rec_preprocess(path: &Path, included_files: &mut Vec<PathBuf>) -> Result<String, Error> {
         // avoid multiple inclusions
@@ -151,7 +266,7 @@ pub fn build_shader(context: &Context) -> Result<String, Error> {
 
         for captures in re.captures_iter(&source) {
             let filename = captures.get(1).unwrap().as_str();
-            let mut path = path.parent().unwrap().to_owned();
+            let mut path = path.parent().unwrap_or(Path::new("")).to_owned();
             path.push(filename);
             let include_source = rec_preprocess(&path, included_files)?;
             let include_source = format!(
@@ -202,3 +317,23 @@ pub fn build_shader(context: &Context) -> Result<String, Error> {
 
     // Ok(module)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::wgpu_util::ShaderConstants;
+
+    #[test]
+    fn modules_compile() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let constants = ShaderConstants::default().to_hashmap();
+        let results = super::check_modules(&dir, &constants).unwrap();
+        assert!(!results.is_empty());
+        let failed = results
+            .into_iter()
+            .filter_map(|(path, res)| res.err().map(|err| format!("{}: {err}", path.display())))
+            .collect::<Vec<_>>();
+        assert!(failed.is_empty(), "{}", failed.join("\n"));
+    }
+}
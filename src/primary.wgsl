@@ -0,0 +1,60 @@
+#import "gbuffer.wgsl"::{ GBufferOut, trace_gsample, pack_gsample }
+#import "feedback.wgsl"::{ record_iter }
+#import "settings.wgsl"::{ feature_enabled, FEATURE_BEAM }
+#import "ray.wgsl"::{ is_render_cam }
+
+// the primary pass of the deferred renderer: traces the camera rays through the volume and
+// writes what they hit to the g-buffer, see `gbuffer.wgsl`. the other passes of the render
+// pipeline read it instead of tracing the rays again. the rays start at the distance found by the
+// beam pre-pass for their tile, see `beam.wgsl`, unless the render camera is frozen elsewhere.
+//
+// this module "requires":
+// (the constants required by the imported modules)
+
+// the distances of the beam pre-pass, at the corners of the tiles.
+@group(2) @binding(0)
+var beam_depth: texture_2d<f32>;
+
+// pixels per side of the tiles of the beam pre-pass, see `BEAM_TILE` in wgpu_util.rs.
+const BEAM_TILE: u32 = 8u;
+
+struct VertexInput {
+    @location(0) pos: vec2f,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_pos: vec4f,
+    @location(0) pos: vec2f,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.pos = in.pos;
+    out.clip_pos = vec4f(out.pos, 0.0, 1.0);
+
+    return out;
+}
+
+// the distance in front of every hit of the tile of `pixel`: the nearest of its 4 corners.
+fn beam_start(pixel: vec2u) -> f32 {
+    let tile = pixel / BEAM_TILE;
+    let last = textureDimensions(beam_depth) - 1u;
+    let d00 = textureLoad(beam_depth, min(tile, last), 0).r;
+    let d10 = textureLoad(beam_depth, min(tile + vec2u(1u, 0u), last), 0).r;
+    let d01 = textureLoad(beam_depth, min(tile + vec2u(0u, 1u), last), 0).r;
+    let d11 = textureLoad(beam_depth, min(tile + vec2u(1u, 1u), last), 0).r;
+    return min(min(d00, d10), min(d01, d11));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> GBufferOut {
+    var t_start = 0.0;
+    if feature_enabled(FEATURE_BEAM) && is_render_cam() {
+        t_start = beam_start(vec2u(in.clip_pos.xy));
+    }
+    let s = trace_gsample(in.pos, t_start);
+    record_iter(s.iter);
+    return pack_gsample(s);
+}
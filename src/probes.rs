@@ -0,0 +1,95 @@
+use nalgebra_glm as glm;
+
+use crate::camera::CameraUniform;
+
+// reflection probes: a few points where the scene is rendered into a cubemap, e.g. inside rooms.
+// the shader reflects on shiny surfaces with the cubemap of the nearest probe, which is much
+// cheaper than cone tracing the reflections per pixel. the probes are baked on demand and go
+// stale when the scene or the sun change.
+
+/// capacity of the probes cubemap array. must match `MAX_PROBES` in `probes.wgsl`.
+pub const MAX_PROBES: usize = 4;
+
+/// width and height of a cubemap face, in pixels.
+pub const PROBE_SIZE: u32 = 128;
+
+/// right, up and forward axes of the 6 cubemap faces, in the layer order of the gpu (+x, -x,
+/// +y, -y, +z, -z). the camera builds rays with the same axes, see `Camera::ray_dir`.
+const FACES: [[[f32; 3]; 3]; 6] = [
+    [[0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+    [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]],
+    [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]],
+    [[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]],
+    [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    [[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]],
+];
+
+// !! careful with the alignments! add padding fields if necessary.
+// see https://www.w3.org/TR/WGSL/#alignment-and-size
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ProbesUniform {
+    pub positions: [glm::Vec4; MAX_PROBES], // w is unused.
+    pub count: u32,                         // number of baked probes
+    pub strength: f32,
+    _pad: [f32; 2], // padding to ensure correct alignment
+}
+
+pub struct Probes {
+    pub uniform: ProbesUniform,
+    /// placed probes, baked or not.
+    pub positions: Vec<glm::Vec3>,
+}
+
+impl Probes {
+    pub fn new() -> Self {
+        Self {
+            uniform: ProbesUniform {
+                positions: Default::default(),
+                count: 0,
+                strength: 0.5,
+                _pad: Default::default(),
+            },
+            positions: Vec::new(),
+        }
+    }
+
+    /// place a probe, it is used after the next bake. returns false when all the probes are
+    /// already placed.
+    pub fn add(&mut self, pos: glm::Vec3) -> bool {
+        if self.positions.len() >= MAX_PROBES {
+            return false;
+        }
+        self.positions.push(pos);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.uniform.count = 0;
+    }
+
+    /// the placed probes were rendered, use them in the shader.
+    pub fn set_baked(&mut self) {
+        for (dst, pos) in self.uniform.positions.iter_mut().zip(&self.positions) {
+            *dst = glm::vec4(pos.x, pos.y, pos.z, 0.0);
+        }
+        self.uniform.count = self.positions.len() as u32;
+    }
+
+    /// camera rendering the face `face` of the probe at `pos`.
+    pub fn face_camera(camera: &CameraUniform, pos: &glm::Vec3, face: usize) -> CameraUniform {
+        let [right, up, forward] = FACES[face].map(glm::Vec3::from);
+        let mut camera = *camera;
+        camera.pos = pos + camera.volume_pos;
+        camera.fov_y = glm::half_pi();
+        camera.aspect = 1.0;
+        camera.size = glm::vec2(PROBE_SIZE as f32, PROBE_SIZE as f32);
+        camera.view_mat_inv = glm::mat3_to_mat4(&glm::Mat3::from_columns(&[right, up, forward]));
+        camera
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.uniform)
+    }
+}
@@ -0,0 +1,40 @@
+#import "bindings.wgsl"::{ probes_tex, linear_sampler }
+#import "settings.wgsl"::{ feature_enabled, FEATURE_REFLECTIONS }
+
+// this shader is a "module" supposed to be included.
+//
+// this module "exports":
+// var<uniform> probes: Probes
+// fn probes_enabled() -> bool
+// fn probe_reflection(pos: vec3f, dir: vec3f) -> vec3f
+
+const MAX_PROBES: u32 = 4u; // see probes.rs
+
+struct Probes {
+    positions: array<vec4f, MAX_PROBES>,
+    count: u32, // number of baked probes, 0 to disable the reflections
+    strength: f32,
+}
+
+@group(0) @binding(8)
+var<uniform> probes: Probes;
+
+// whether the surfaces reflect the probes: some are baked, and the reflections are enabled.
+fn probes_enabled() -> bool {
+    return probes.count > 0u && feature_enabled(FEATURE_REFLECTIONS);
+}
+
+// color seen in direction `dir` from `pos`, looked up in the cubemap of the nearest probe.
+// only call when `probes_enabled()`.
+fn probe_reflection(pos: vec3f, dir: vec3f) -> vec3f {
+    var nearest = 0u;
+    var nearest_dist = 1e20;
+    for (var i = 0u; i < probes.count; i++) {
+        let d = distance(pos, probes.positions[i].xyz);
+        if d < nearest_dist {
+            nearest = i;
+            nearest_dist = d;
+        }
+    }
+    return textureSampleLevel(probes_tex, linear_sampler, dir, nearest, 0.0).rgb;
+}
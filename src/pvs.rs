@@ -0,0 +1,205 @@
+use std::{
+    f32::consts::PI,
+    fs::File,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use itertools::iproduct;
+use nalgebra_glm as glm;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+use crate::{brickmap::BRICK, voxels::Voxels};
+
+// potentially visible sets, for scenes made of enclosed interiors. the volume is cut in regions
+// of a few bricks, and an offline pass (`wender pvs`) casts rays from the empty space of each
+// region to find the regions they can hit. the result is saved next to the scene,
+// `scene.wvox` -> `scene.wpvs`, and the brick map only streams the bricks of the regions visible
+// from the region of the camera, see `BrickMap::update`.
+//
+// the rays sample the visibility, they can miss a small opening: the hit regions are grown by one
+// region to cover the gaps. regions without empty space at the sample points (the camera is not
+// expected there) and bricks farther than the rays see everything.
+
+/// regions per axis at most, the sets are bitsets of all the regions.
+const MAX_REGIONS: u32 = 16;
+/// side of the smallest region, in bricks.
+const MIN_REGION: u32 = 4;
+/// ray origins per axis of a region, on a regular lattice.
+const ORIGINS: u32 = 3;
+/// ray directions from each origin, spread over the sphere.
+const DIRECTIONS: usize = 256;
+
+#[derive(Serialize, Deserialize)]
+pub struct Pvs {
+    key: u64,
+    /// side of a region, in voxels.
+    region: u32,
+    /// regions per axis.
+    regions: u32,
+    /// length of the rays, in voxels.
+    distance: f32,
+    /// bitset of the regions visible from each region, empty if the region was not sampled.
+    visible: Vec<Vec<u64>>,
+}
+
+/// directions spread evenly over the unit sphere, on a fibonacci spiral.
+fn sphere_dirs(n: usize) -> Vec<glm::Vec3> {
+    let golden = PI * (3.0 - 5f32.sqrt());
+    (0..n)
+        .map(|i| {
+            let y = 1.0 - 2.0 * (i as f32 + 0.5) / n as f32;
+            let r = (1.0 - y * y).sqrt();
+            let a = golden * i as f32;
+            glm::vec3(r * a.cos(), y, r * a.sin())
+        })
+        .collect()
+}
+
+impl Pvs {
+    pub fn path(scene: &Path) -> PathBuf {
+        scene.with_extension("wpvs")
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn key(voxels: &Voxels) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        voxels.voxels_bytes().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// index of the region containing the world cell `cell`, if it is in the volume.
+    fn region_index(&self, cell: &glm::IVec3) -> Option<usize> {
+        let n = self.regions as i32;
+        let r = cell / self.region as i32;
+        r.iter()
+            .all(|c| (0..n).contains(c))
+            .then(|| ((r.z * n + r.y) * n + r.x) as usize)
+    }
+
+    /// cast rays of `distance` voxels from the empty space of every region.
+    #[tracing::instrument(skip_all)]
+    pub fn compute(voxels: &Voxels, distance: f32) -> Self {
+        let bricks = voxels.dim() / BRICK;
+        let region = bricks.div_ceil(MAX_REGIONS).max(MIN_REGION) * BRICK;
+        let regions = voxels.dim().div_ceil(region);
+        let mut pvs = Self {
+            key: Self::key(voxels),
+            region,
+            regions,
+            distance,
+            visible: Vec::new(),
+        };
+
+        let count = regions.pow(3) as usize;
+        println!("computing the visibility of {count} regions...");
+        let start = Instant::now();
+        let dirs = sphere_dirs(DIRECTIONS);
+        pvs.visible = (0..count)
+            .into_par_iter()
+            .map(|i| {
+                let n = regions as usize;
+                let corner =
+                    glm::vec3(i % n, i / n % n, i / (n * n)).map(|c| (c as u32 * region) as f32);
+                let origins = iproduct!(0..ORIGINS, 0..ORIGINS, 0..ORIGINS)
+                    .map(|(x, y, z)| {
+                        let f = (glm::vec3(x, y, z).map(|c| c as f32) + glm::vec3(0.5, 0.5, 0.5))
+                            / ORIGINS as f32;
+                        corner + f * region as f32
+                    })
+                    .filter(|p| !voxels.is_solid(p.map(|c| c.floor() as i32)))
+                    .collect::<Vec<_>>();
+                if origins.is_empty() {
+                    return Vec::new();
+                }
+
+                let mut hit = vec![0u64; count.div_ceil(64)];
+                hit[i / 64] |= 1 << (i % 64);
+                for (origin, dir) in iproduct!(&origins, &dirs) {
+                    if let Some((cell, _)) = voxels.raycast(origin, dir, distance) {
+                        if let Some(j) = pvs.region_index(&cell) {
+                            hit[j / 64] |= 1 << (j % 64);
+                        }
+                    }
+                }
+                pvs.grow(&hit)
+            })
+            .collect();
+        println!("computed the visibility in {:.1?}", start.elapsed());
+        pvs
+    }
+
+    /// `set` with the neighbors of each region added.
+    fn grow(&self, set: &[u64]) -> Vec<u64> {
+        let n = self.regions as i32;
+        let mut grown = set.to_vec();
+        for i in (0..n.pow(3)).filter(|i| set[*i as usize / 64] & (1 << (i % 64)) != 0) {
+            let r = glm::vec3(i % n, i / n % n, i / (n * n));
+            for (x, y, z) in iproduct!(-1..=1, -1..=1, -1..=1) {
+                let neighbor = r + glm::vec3(x, y, z);
+                if neighbor.iter().all(|c| (0..n).contains(c)) {
+                    let j = ((neighbor.z * n + neighbor.y) * n + neighbor.x) as usize;
+                    grown[j / 64] |= 1 << (j % 64);
+                }
+            }
+        }
+        grown
+    }
+
+    /// whether `brick`, at `dist` voxels from the camera at `pos`, may be visible from it.
+    pub fn is_visible(&self, pos: &glm::Vec3, brick: &glm::UVec3, dist: f32) -> bool {
+        // the rays start anywhere in the region of the camera.
+        let diagonal = self.region as f32 * 3f32.sqrt();
+        if dist + diagonal > self.distance {
+            return true;
+        }
+        let from = self.region_index(&pos.map(|c| c.floor() as i32));
+        let to = self.region_index(&(brick * BRICK).cast::<i32>());
+        match (from.map(|i| &self.visible[i]), to) {
+            (Some(set), Some(j)) if !set.is_empty() => set[j / 64] & (1 << (j % 64)) != 0,
+            _ => true,
+        }
+    }
+
+    /// the visibility of `voxels`, if it was computed for these voxels.
+    #[tracing::instrument(skip_all)]
+    pub fn load(voxels: &Voxels) -> Option<Self> {
+        let path = Self::path(&voxels.path);
+        let file = BufReader::new(File::open(&path).ok()?);
+        let pvs: Self = bincode::deserialize_from(file).ok()?;
+        (pvs.key == Self::key(voxels)).then(|| {
+            println!("loaded visibility `{}`", path.display());
+            pvs
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(scene = %scene.display()))]
+    pub fn save(&self, scene: &Path) -> bincode::Result<()> {
+        let path = Self::path(scene);
+        let mut file = BufWriter::new(File::create(&path)?);
+        bincode::serialize_into(&mut file, self)?;
+        println!("wrote visibility `{}`", path.display());
+        Ok(())
+    }
+}
+
+/// compute the visibility of `scene` and save it next to it. returns whether it succeeded.
+pub fn bake_pvs(scene: &Path, distance: f32) -> bool {
+    let voxels = match Voxels::from_path(scene) {
+        Ok(voxels) => voxels,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return false;
+        }
+    };
+    match Pvs::compute(&voxels, distance).save(scene) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("failed to save the visibility: {err}");
+            false
+        }
+    }
+}
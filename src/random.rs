@@ -0,0 +1,28 @@
+use crate::noise::pcg;
+
+// the randomness of the renderer comes from one seed, so two runs with the same `--seed` render
+// the same images bit for bit (given the same gpu and driver), which the benchmarks and the
+// golden images of `--headless --hash` rely on. every frame draws a new frame seed from it,
+// uploaded in `CameraUniform::seed`, and the stochastic effects of the shaders derive their
+// random numbers from it and the pixel, see `random.wgsl`. nothing reads the wall clock or an
+// os rng for randomness.
+
+pub struct FrameSeed {
+    /// the seed of the run, see `--seed`.
+    pub seed: u32,
+    /// frames drawn since the start.
+    frame: u32,
+}
+
+impl FrameSeed {
+    pub fn new(seed: u32) -> Self {
+        Self { seed, frame: 0 }
+    }
+
+    /// the seed of the next frame. it depends only on the seed and the frame count.
+    pub fn next(&mut self) -> u32 {
+        let frame_seed = pcg(self.seed ^ pcg(self.frame));
+        self.frame = self.frame.wrapping_add(1);
+        frame_seed
+    }
+}
@@ -0,0 +1,23 @@
+#import "ray.wgsl"::{ cam }
+#import "noise.wgsl"::{ pcg, unit_float }
+
+// this shader is a "module" supposed to be included.
+// the random numbers of the stochastic effects (path tracing, jittered samples). they derive from
+// the frame seed of the camera uniform and the pixel only, so a run is reproducible with the same
+// `--seed`, see `random.rs`. each effect passes its own `stream`, so two effects of the same pixel
+// do not draw correlated numbers.
+//
+// this module "exports":
+// fn rng_state(pixel: vec2u, stream: u32) -> u32
+// fn rand(state: ptr<function, u32>) -> f32
+
+// the state of a random sequence for `pixel` this frame.
+fn rng_state(pixel: vec2u, stream: u32) -> u32 {
+    return pcg(pixel.x ^ pcg(pixel.y ^ pcg(cam.seed ^ pcg(stream))));
+}
+
+// the next float in [0, 1) of the sequence.
+fn rand(state: ptr<function, u32>) -> f32 {
+    *state = pcg(*state);
+    return unit_float(*state);
+}
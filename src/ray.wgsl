@@ -0,0 +1,110 @@
+// this shader is a "module" supposed to be included.
+//
+// this module "exports":
+// var<uniform> cam: Camera
+// var<uniform> render_cam: Camera
+// fn cam_pos() -> vec3f
+// fn to_volume(pos: vec3f) -> vec3f
+// fn from_volume(pos: vec3f) -> vec3f
+// fn cam_ray_dir(pos: vec2f) -> vec3f
+// fn camera_ray_dir(camera: Camera, pos: vec2f) -> vec3f
+// fn view_pos(pos: vec3f) -> vec3f
+// fn render_view_pos(pos: vec3f) -> vec3f
+// fn is_render_cam() -> bool
+// fn view_depth(pos: vec3f) -> f32
+// const DEPTH_NEAR: f32
+// fn msaa_offset(i: u32, j: u32) -> vec2f
+//
+// this module "requires":
+// const MSAA_LEVEL: u32; // msaa with 2^n probes, 0 to disable
+//
+// the positions are in render space, relative to the origin of `Camera::rebase` near the camera,
+// so the distances to the camera keep their precision far from the world origin. the volume
+// textures, and the lights, probes, fog volumes and overlays uploaded by the cpu, are in world
+// coordinates: `to_volume` and `from_volume` move between the two.
+
+struct Camera {
+    pos: vec3f,
+    fov_y: f32,
+    size: vec2f,
+    aspect: f32,
+    time: f32, // seconds of scene time
+    volume_pos: vec3f, // of the world origin in render space, see `Camera::rebase`
+    seed: u32, // of the random numbers of this frame, see `random.wgsl`
+    view_mat_inv: mat4x4f,
+}
+
+@group(0) @binding(0)
+var<uniform> cam: Camera;
+
+// the camera of the view-dependent work: the culling, the beam pre-pass and the light clusters.
+// it stays behind `cam` while it is frozen, see `Frustum::render_camera`.
+@group(0) @binding(15)
+var<uniform> render_cam: Camera;
+
+// position of the camera, in render space.
+fn cam_pos() -> vec3f {
+    return cam.pos;
+}
+
+// render space position `pos` in world coordinates, to look up the volume.
+fn to_volume(pos: vec3f) -> vec3f {
+    return pos - cam.volume_pos;
+}
+
+// world position `pos` in render space. `volume_pos` is a multiple of a power of 2 larger than
+// the precision of `pos`, so the difference is exact.
+fn from_volume(pos: vec3f) -> vec3f {
+    return pos + cam.volume_pos;
+}
+
+// direction of the primary ray through `pos`, in normalized screen coordinates.
+fn cam_ray_dir(pos: vec2f) -> vec3f {
+    return camera_ray_dir(cam, pos);
+}
+
+// `cam_ray_dir` of another camera.
+fn camera_ray_dir(camera: Camera, pos: vec2f) -> vec3f {
+    return (camera.view_mat_inv * normalize(vec4f(
+        pos.x * tan(camera.fov_y / 2.0) * camera.aspect,
+        pos.y * tan(camera.fov_y / 2.0),
+        1.0,
+        0.0,
+    ))).xyz;
+}
+
+// near plane of the depth target, in voxels.
+const DEPTH_NEAR: f32 = 0.01;
+
+// render space position `pos` relative to the camera, looking towards +z.
+fn view_pos(pos: vec3f) -> vec3f {
+    return (transpose(cam.view_mat_inv) * vec4f(pos - cam_pos(), 0.0)).xyz;
+}
+
+// render space position `pos` relative to the render camera, looking towards +z. the origins of
+// the two cameras differ by a multiple of the rebase step, their difference is exact.
+fn render_view_pos(pos: vec3f) -> vec3f {
+    let rel = pos + (render_cam.volume_pos - cam.volume_pos) - render_cam.pos;
+    return (transpose(render_cam.view_mat_inv) * vec4f(rel, 0.0)).xyz;
+}
+
+// whether the render camera is `cam`, i.e. it is not frozen.
+fn is_render_cam() -> bool {
+    let m = cam.view_mat_inv;
+    let r = render_cam.view_mat_inv;
+    return all(cam.pos == render_cam.pos) && all(cam.volume_pos == render_cam.volume_pos)
+        && cam.fov_y == render_cam.fov_y && cam.aspect == render_cam.aspect
+        && all(m[0] == r[0]) && all(m[1] == r[1]) && all(m[2] == r[2]);
+}
+
+// reversed depth of `pos` with an infinite far plane: 1 on the near plane, towards 0 far away.
+// the rasterized overlays project their vertices to the same depth, see `gizmos.wgsl`.
+fn view_depth(pos: vec3f) -> f32 {
+    return DEPTH_NEAR / max(view_pos(pos).z, DEPTH_NEAR);
+}
+
+// screen offset of the msaa probe (i, j), with i, j in [0, 2 * MSAA_LEVEL).
+fn msaa_offset(i: u32, j: u32) -> vec2f {
+    let pos = (2.0 * (vec2f(f32(i), f32(j)) - f32(#MSAA_LEVEL)) - 1.0) / (4.0 * f32(#MSAA_LEVEL * #MSAA_LEVEL) - 1.0);
+    return pos / cam.size;
+}
@@ -0,0 +1,103 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ndarray::Array3;
+use serde::Deserialize;
+
+// palette fixes applied when a scene is loaded, to correct the palette of existing exports (e.g. a
+// wrong block color in mca2vox) without converting them again. they are read from a sidecar file,
+// `scene.wvox` -> `scene.remap.toml`:
+//
+//     [[index]]   # voxels of entry 12 use entry 5 instead, 0 removes them
+//     from = 12
+//     to = 5
+//
+//     [[color]]   # entry 7 gets a new color
+//     index = 7
+//     rgba = [255, 136, 0, 255]
+//
+// indices are 1-based like the voxels. once the scene is saved, the fixes are part of it and the
+// sidecar is renamed to `scene.remap.toml.applied`, so they are not applied twice.
+
+#[derive(Deserialize)]
+struct IndexRemap {
+    from: u32,
+    to: u32,
+}
+
+#[derive(Deserialize)]
+struct ColorRemap {
+    index: u32,
+    rgba: [u8; 4],
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct PaletteRemap {
+    index: Vec<IndexRemap>,
+    color: Vec<ColorRemap>,
+}
+
+impl PaletteRemap {
+    pub fn path(scene: &Path) -> PathBuf {
+        scene.with_extension("remap.toml")
+    }
+
+    /// the remap of a scene, if it has a valid one.
+    pub fn load(scene: &Path) -> Option<Self> {
+        let path = Self::path(scene);
+        let source = fs::read_to_string(&path).ok()?;
+        toml::from_str(&source)
+            .map_err(|err| {
+                eprintln!(
+                    "ignoring invalid palette remap `{}`: {}",
+                    path.display(),
+                    err
+                );
+            })
+            .ok()
+    }
+
+    /// remap the voxels and recolor the palette. entries outside of the palette are skipped.
+    pub fn apply(&self, voxels: &mut Array3<u32>, palette: &mut [[u8; 4]]) {
+        let len = palette.len() as u32;
+        let valid = |i: u32| (1..=len).contains(&i);
+
+        let mut table = (0..=len).collect::<Vec<_>>();
+        for remap in &self.index {
+            if valid(remap.from) && (remap.to == 0 || valid(remap.to)) {
+                table[remap.from as usize] = remap.to;
+            } else {
+                eprintln!("skipping palette remap {} -> {}", remap.from, remap.to);
+            }
+        }
+        voxels.par_mapv_inplace(|v| table.get(v as usize).copied().unwrap_or(v));
+
+        for remap in &self.color {
+            match valid(remap.index) {
+                true => palette[remap.index as usize - 1] = remap.rgba,
+                false => eprintln!("skipping palette color {}", remap.index),
+            }
+        }
+        println!(
+            "remapped {} palette indices and {} colors",
+            self.index.len(),
+            self.color.len()
+        );
+    }
+
+    /// the remap of `scene` was saved into it.
+    pub fn retire(scene: &Path) {
+        let path = Self::path(scene);
+        let applied = path.with_extension("toml.applied");
+        match fs::rename(&path, &applied) {
+            Ok(()) => println!(
+                "the palette remap is saved, renamed it `{}`",
+                applied.display()
+            ),
+            Err(err) => eprintln!("failed to rename `{}`: {}", path.display(), err),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+// dynamic resolution: the scene target shrinks when the frames take longer than the budget of the
+// target fps, and grows back when they are well under it. the scene is upscaled to the window, as
+// with the logical resolution (see `State::update_render_size`). the frame time is the gpu time
+// of the passes when the device supports timestamp queries, otherwise the real frame time, which
+// includes the wait for vsync.
+// the frame times are averaged over `WINDOW` frames, and the scale only moves when the average
+// leaves the band from `LOWER` to `UPPER` times the budget. it then waits `COOLDOWN` frames for
+// the new target to settle, so it does not oscillate around the budget.
+
+/// fraction of the window size rendered at least, per axis.
+pub const MIN_SCALE: f32 = 0.25;
+/// the scale is a multiple of it, so close frame times give the same target size.
+const STEP: f32 = 1.0 / 16.0;
+/// frames averaged.
+const WINDOW: usize = 16;
+/// the scale is lowered above this fraction of the budget...
+const UPPER: f32 = 1.0;
+/// ...and raised below this one.
+const LOWER: f32 = 0.75;
+/// frames ignored after a change, while the target is reallocated.
+const COOLDOWN: u32 = 30;
+
+pub struct DynamicResolution {
+    pub enabled: bool,
+    pub target_fps: f32,
+    /// fraction of the window size rendered, per axis.
+    pub scale: f32,
+    /// the last frame times, in milliseconds.
+    frame_times: VecDeque<f32>,
+    cooldown: u32,
+}
+
+impl DynamicResolution {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            target_fps: 60.0,
+            scale: 1.0,
+            frame_times: VecDeque::with_capacity(WINDOW),
+            cooldown: 0,
+        }
+    }
+
+    /// count a frame of `frame_ms` milliseconds. returns whether the scale changed, the scene
+    /// target must then be resized.
+    pub fn tick(&mut self, frame_ms: f32) -> bool {
+        if !self.enabled {
+            self.frame_times.clear();
+            return self.set_scale(1.0);
+        }
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return false;
+        }
+        if self.frame_times.len() == WINDOW {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_ms);
+        if self.frame_times.len() < WINDOW {
+            return false;
+        }
+
+        let average = self.frame_times.iter().sum::<f32>() / WINDOW as f32;
+        let budget = 1000.0 / self.target_fps;
+        let scale = if average > budget * UPPER {
+            // the cost follows the number of pixels, the square of the scale. it drops at once
+            // to the estimated scale, and grows back one step at a time.
+            let estimate = self.scale * (budget / average).sqrt();
+            ((estimate / STEP).floor() * STEP).min(self.scale - STEP)
+        } else if average < budget * LOWER {
+            self.scale + STEP
+        } else {
+            return false;
+        };
+        self.set_scale(scale.clamp(MIN_SCALE, 1.0))
+    }
+
+    fn set_scale(&mut self, scale: f32) -> bool {
+        if scale == self.scale {
+            return false;
+        }
+        self.scale = scale;
+        self.frame_times.clear();
+        self.cooldown = COOLDOWN;
+        true
+    }
+
+    /// the size of the scene target for a window of `size` pixels.
+    pub fn scaled(&self, size: (u32, u32)) -> (u32, u32) {
+        let scale = |c: u32| ((c as f32 * self.scale).round() as u32).max(1);
+        (scale(size.0), scale(size.1))
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use nalgebra_glm as glm;
+use thiserror::Error;
+
+// a polyline of world coordinates, drawn as a glowing ribbon over the scene.
+// the points can be imported from a JSON array (`[[x, y, z], ...]`) or a CSV file (`x,y,z` per line).
+
+/// capacity of the route points storage buffer.
+pub const MAX_ROUTE_POINTS: usize = 1024;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to read `{0}`")]
+    IOError(PathBuf),
+    #[error("invalid json in `{0}`: {1}")]
+    JsonError(PathBuf, serde_json::Error),
+    #[error("invalid csv in `{0}`, line {1}")]
+    CsvError(PathBuf, usize),
+}
+
+// !! careful with the alignments! add padding fields if necessary.
+// see https://www.w3.org/TR/WGSL/#alignment-and-size
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct RouteUniform {
+    pub color: glm::Vec3,
+    pub width: f32,
+    pub len: u32,
+    _pad: [u32; 3], // padding to ensure correct alignment
+}
+
+pub struct Route {
+    pub uniform: RouteUniform,
+    pub points: Vec<glm::Vec4>, // w is unused, vec4 keeps the storage array stride at 16 bytes.
+    pub visible: bool,
+    pub file: String,
+}
+
+fn parse_json(path: &Path, source: &str) -> Result<Vec<glm::Vec3>, Error> {
+    let points: Vec<[f32; 3]> =
+        serde_json::from_str(source).map_err(|e| Error::JsonError(path.to_owned(), e))?;
+    Ok(points.into_iter().map(glm::Vec3::from).collect())
+}
+
+fn parse_csv(path: &Path, source: &str) -> Result<Vec<glm::Vec3>, Error> {
+    let mut points = Vec::new();
+
+    for (n, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields = line
+            .split(|c| c == ',' || c == ';' || c == '\t')
+            .map(|f| f.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>();
+
+        match fields.as_deref() {
+            Ok([x, y, z, ..]) => points.push(glm::vec3(*x, *y, *z)),
+            // tolerate a header line.
+            Err(_) if n == 0 => continue,
+            _ => return Err(Error::CsvError(path.to_owned(), n + 1)),
+        }
+    }
+
+    Ok(points)
+}
+
+impl Route {
+    pub fn new() -> Self {
+        Self {
+            uniform: RouteUniform {
+                color: glm::vec3(1.0, 0.6, 0.1),
+                width: 0.5,
+                len: 0,
+                _pad: Default::default(),
+            },
+            points: Vec::new(),
+            visible: true,
+            file: String::new(),
+        }
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<(), Error> {
+        let source = fs::read_to_string(path).map_err(|_| Error::IOError(path.to_owned()))?;
+
+        let is_json = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("json"))
+            .unwrap_or(false);
+
+        let mut points = if is_json {
+            parse_json(path, &source)?
+        } else {
+            parse_csv(path, &source)?
+        };
+
+        if points.len() > MAX_ROUTE_POINTS {
+            eprintln!(
+                "route has {} points, truncating to {MAX_ROUTE_POINTS}",
+                points.len()
+            );
+            points.truncate(MAX_ROUTE_POINTS);
+        }
+
+        println!("loaded route `{}`: {} points", path.display(), points.len());
+        self.points = points
+            .iter()
+            .map(|p| glm::vec4(p.x, p.y, p.z, 0.0))
+            .collect();
+        Ok(())
+    }
+
+    pub fn update(&mut self) {
+        self.uniform.len = if self.visible {
+            self.points.len() as u32
+        } else {
+            0
+        };
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.uniform)
+    }
+
+    pub fn points_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.points)
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fog::FogVolume,
+    lights::{LightingPreset, LocalLight},
+    materials::Surface,
+};
+
+// scene metadata is stored in a sidecar file next to the scene: `scene.wvox` -> `scene.meta.toml`.
+// the .wvox container itself only holds voxels and palette, and is shared with other tools.
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneMeta {
+    /// number of voxels spanning one meter. minecraft exports use 1 voxel per block, i.e. 1 per meter.
+    pub voxels_per_meter: f32,
+    /// seed of the procedural noise, see `noise.wgsl`.
+    pub noise_seed: u32,
+    /// lighting applied when the scene is opened.
+    pub lighting: Option<LightingPreset>,
+    /// local fog boxes, see `fog.rs`.
+    pub fog_volumes: Vec<FogVolume>,
+    /// point, spot and directional lights besides the sun, see `lights.rs`.
+    pub lights: Vec<LocalLight>,
+    /// smooth the silhouettes with per-voxel contours, see `compute_contours.wgsl`.
+    pub contours: bool,
+    /// atlas of the block textures of the palette entries, relative to the scene, see `detail.rs`.
+    pub detail_textures: Option<PathBuf>,
+    /// distance where the detail textures have faded out, in voxels.
+    pub detail_distance: u32,
+    /// detail noise amplitude of each palette entry, in order, from 0 to 1. see `detail.rs`.
+    pub detail_noise: Vec<f32>,
+    /// palette entries rendered as water, 1-based like the voxels. see `water.wgsl`.
+    pub water: Vec<u32>,
+    /// roughness, metalness, emission and index of refraction of palette entries, the others
+    /// keep the defaults of `Surface`. see `materials.rs`.
+    pub surfaces: Vec<Surface>,
+}
+
+impl Default for SceneMeta {
+    fn default() -> Self {
+        Self {
+            voxels_per_meter: 1.0,
+            noise_seed: 0,
+            lighting: None,
+            fog_volumes: Vec::new(),
+            lights: Vec::new(),
+            contours: false,
+            detail_textures: None,
+            detail_distance: 32,
+            detail_noise: Vec::new(),
+            water: Vec::new(),
+            surfaces: Vec::new(),
+        }
+    }
+}
+
+impl SceneMeta {
+    pub fn path(scene: &Path) -> PathBuf {
+        scene.with_extension("meta.toml")
+    }
+
+    /// load the sidecar of a scene, or the defaults if there is none.
+    pub fn load(scene: &Path) -> Self {
+        let path = Self::path(scene);
+        let Ok(source) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        toml::from_str(&source).unwrap_or_else(|err| {
+            eprintln!(
+                "ignoring invalid scene metadata `{}`: {}",
+                path.display(),
+                err
+            );
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, scene: &Path) -> std::io::Result<()> {
+        let path = Self::path(scene);
+        let source = toml::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        fs::write(&path, source)?;
+        println!("wrote `{}`", path.display());
+        Ok(())
+    }
+
+    pub fn to_meters(&self, voxels: f32) -> f32 {
+        voxels / self.voxels_per_meter
+    }
+
+    pub fn to_voxels(&self, meters: f32) -> f32 {
+        meters * self.voxels_per_meter
+    }
+
+    /// the metadata of the scene downsampled by `factor`: lengths in voxels shrink with it.
+    pub fn downsampled(&self, factor: f32) -> Self {
+        let mut meta = self.clone();
+        meta.voxels_per_meter /= factor;
+        // the textures map onto whole blocks.
+        meta.detail_textures = None;
+        for volume in &mut meta.fog_volumes {
+            volume.min = volume.min.map(|x| x / factor);
+            volume.max = volume.max.map(|x| x / factor);
+        }
+        for light in &mut meta.lights {
+            light.pos = light.pos.map(|x| x / factor);
+        }
+        meta
+    }
+}
@@ -0,0 +1,67 @@
+#import "util.wgsl"::{ vmin, vmax, cmpmin, cmpmax }
+#import "octree.wgsl"::{ CastResult }
+#import "bindings.wgsl"::{ colors, sdf }
+
+// this shader is a "module" supposed to be included.
+// an alternative to the dvo descent of octree.wgsl: sphere tracing through the brick distance
+// field computed by compute_sdf.wgsl. empty space is skipped in steps of the distance to the
+// nearest occupied brick, then the voxels around occupied bricks are walked one by one.
+//
+// this module "exports":
+// fn raycast_sdf(ray_pos: vec3f, ray_dir: vec3f) -> CastResult
+//
+// this module "requires":
+// const #OCTREE_DEPTH: u32;
+// const #SDF_MAX_ITER: u32 // max number of steps per ray.
+
+const SDF_BRICK_LOG2: u32 = 2u; // 4^3 voxels per brick
+
+fn sdf_no_hit(iter: u32) -> CastResult {
+    return CastResult(vec3f(0.1), vec3f(0.0), vec3u(0u), iter, 0.0, false);
+}
+
+fn raycast_sdf(ray_pos: vec3f, ray_dir: vec3f) -> CastResult {
+    let size = f32(2u << #OCTREE_DEPTH);
+    let inv_dir = 1.0 / ray_dir;
+
+    // clip the ray to the volume
+    let t_a = (vec3f(0.0) - ray_pos) * inv_dir;
+    let t_b = (vec3f(size) - ray_pos) * inv_dir;
+    let t_in = max(vmax(min(t_a, t_b)), 0.0);
+    let t_out = vmin(max(t_a, t_b));
+    if t_in > t_out {
+        return sdf_no_hit(0u);
+    }
+
+    var t = t_in;
+    var normal = vec3f(cmpmax(min(t_a, t_b))) * -sign(ray_dir);
+
+    for (var i = 0u; i < #SDF_MAX_ITER; i++) {
+        if t > t_out {
+            return sdf_no_hit(i);
+        }
+
+        // nudge the sample inside the voxel the ray is entering.
+        let sample_pos = clamp(ray_pos + ray_dir * (t + 1e-3), vec3f(0.0), vec3f(size - 1.0));
+        let voxel = vec3u(sample_pos);
+
+        let dist = textureLoad(sdf, voxel >> vec3u(SDF_BRICK_LOG2), 0).r;
+        if dist > 0u {
+            t += f32(dist << SDF_BRICK_LOG2);
+            continue;
+        }
+
+        let albedo = textureLoad(colors, voxel, 0);
+        if any(albedo != vec4f(0.0)) {
+            return CastResult(ray_pos + ray_dir * t, normal, voxel, i, t, true);
+        }
+
+        // step to the next voxel boundary
+        let next_t = (vec3f(voxel) + step(vec3f(0.0), ray_dir) - ray_pos) * inv_dir;
+        let mask = cmpmin(next_t);
+        t = vmin(next_t);
+        normal = vec3f(mask) * -sign(ray_dir);
+    }
+
+    return sdf_no_hit(#SDF_MAX_ITER);
+}
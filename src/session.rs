@@ -0,0 +1,203 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    camera::Controller,
+    i18n::{self, Language},
+    settings::Settings,
+    ui, voxels,
+    wgpu_util::ShaderConstants,
+    State,
+};
+
+// the application session, saved to `.wender-session` in the working directory on exit and
+// restored with "continue last session" (or `--continue`), for reviews spanning several days.
+// only the path of the scene is saved: baked lighting is written to the scene file itself.
+// the preferences (language, ui scale, contrast) are restored at startup even without continuing.
+
+pub const SESSION_FILE: &str = ".wender-session";
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to access `{0}`: {1}")]
+    IOError(PathBuf, io::Error),
+    #[error("invalid session `{0}`: {1}")]
+    FormatError(PathBuf, serde_json::Error),
+    #[error(transparent)]
+    SceneError(#[from] voxels::Error),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub scene: PathBuf,
+    pub camera_pos: [f32; 3],
+    /// yaw and pitch of the camera controller.
+    pub camera_view: (f64, f64),
+    pub speed: f32,
+    pub collisions: bool,
+    /// sun angle and azimuth, in degrees.
+    pub sun: (f32, f32),
+    pub features: u32,
+    /// dvo levels of the shadow and ao rays, see `settings.rs`.
+    pub shadow_level: u32,
+    pub ao_level: u32,
+    pub constants: ShaderConstants,
+    pub route_file: String,
+    pub route_visible: bool,
+    /// zoom of the ui on top of the dpi of the monitor.
+    pub ui_scale: f32,
+    pub logical_render: bool,
+    pub dynamic_resolution: bool,
+    pub target_fps: f32,
+    pub high_contrast: bool,
+    pub language: Language,
+    /// window positions, sizes and collapsed sections.
+    pub ui: Option<egui::Memory>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            scene: PathBuf::new(),
+            camera_pos: [0.0; 3],
+            camera_view: (0.0, 0.0),
+            speed: Controller::DEFAULT_SPEED,
+            collisions: true,
+            sun: (90.0, 45.0),
+            features: Settings::new().uniform.features,
+            shadow_level: 0,
+            ao_level: 0,
+            constants: Default::default(),
+            route_file: String::new(),
+            route_visible: true,
+            ui_scale: 1.0,
+            logical_render: false,
+            dynamic_resolution: false,
+            target_fps: 60.0,
+            high_contrast: false,
+            language: Language::English,
+            ui: None,
+        }
+    }
+}
+
+impl Session {
+    pub fn path() -> &'static Path {
+        Path::new(SESSION_FILE)
+    }
+
+    pub fn exists() -> bool {
+        Self::path().is_file()
+    }
+
+    pub fn load() -> Result<Self, Error> {
+        let path = Self::path();
+        let source = fs::read_to_string(path).map_err(|e| Error::IOError(path.to_owned(), e))?;
+        serde_json::from_str(&source).map_err(|e| Error::FormatError(path.to_owned(), e))
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let path = Self::path();
+        let source = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::FormatError(path.to_owned(), e))?;
+        fs::write(path, source).map_err(|e| Error::IOError(path.to_owned(), e))
+    }
+
+    /// snapshot of the current state.
+    pub fn capture(state: &State) -> Self {
+        Self {
+            scene: state.scene_path.clone(),
+            camera_pos: state.camera.pos().into(),
+            camera_view: state.controller.view(),
+            speed: state.controller.speed,
+            collisions: state.collisions,
+            sun: (state.lights.angle, state.lights.azimuth),
+            features: state.settings.uniform.features,
+            shadow_level: state.settings.uniform.shadow_level,
+            ao_level: state.settings.uniform.ao_level,
+            constants: state.constants.clone(),
+            route_file: state.route.file.clone(),
+            route_visible: state.route.visible,
+            ui_scale: state.egui_ctx.zoom_factor(),
+            logical_render: state.logical_render,
+            dynamic_resolution: state.resolution.enabled,
+            target_fps: state.resolution.target_fps,
+            high_contrast: state.high_contrast,
+            language: i18n::language(),
+            ui: Some(state.egui_ctx.memory(|mem| mem.clone())),
+        }
+    }
+
+    /// restore the session, loading its scene if another one is open.
+    pub fn apply(mut self, state: &mut State) -> Result<(), Error> {
+        if self.scene != state.scene_path {
+            state.load_scene(&self.scene)?;
+        }
+
+        state.camera.set_pos(&glm::Vec3::from(self.camera_pos));
+        state.controller.set_view(self.camera_view);
+        state.controller.speed = self.speed;
+        state.collisions = self.collisions;
+        (state.lights.angle, state.lights.azimuth) = self.sun;
+        state.settings.uniform.features = self.features;
+        state.settings.uniform.shadow_level = self.shadow_level;
+        state.settings.uniform.ao_level = self.ao_level;
+
+        // the volume, baked lighting, noise seed and detail textures come from the scene, not
+        // the session.
+        state.constants = ShaderConstants {
+            octree_depth: state.constants.octree_depth,
+            baked_lighting: state.constants.baked_lighting,
+            noise_seed: state.constants.noise_seed,
+            contours: state.constants.contours,
+            detail_textures: state.constants.detail_textures,
+            detail_distance: state.constants.detail_distance,
+            ..self.constants
+        };
+        state.exposure.enabled = state.constants.auto_exposure != 0;
+        state
+            .wgpu_state
+            .reload_shaders(&state.device, &state.config, &state.constants);
+
+        state.route.file = self.route_file;
+        state.route.visible = self.route_visible;
+        if !state.route.file.is_empty() {
+            match state.route.load(Path::new(&state.route.file)) {
+                Ok(()) => state.queue.write_buffer(
+                    &state.wgpu_state.route_points_buffer,
+                    0,
+                    state.route.points_bytes(),
+                ),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+
+        // the egui memory resets the egui options, the preferences are applied after it.
+        if let Some(memory) = self.ui.take() {
+            state.egui_ctx.memory_mut(|mem| *mem = memory);
+        }
+        self.apply_preferences(state);
+
+        println!("restored session `{}`", Self::path().display());
+        Ok(())
+    }
+
+    /// restore the settings that are kept even when not continuing the session.
+    pub fn apply_preferences(&self, state: &mut State) {
+        i18n::set_language(self.language);
+        state.egui_ctx.set_zoom_factor(self.ui_scale);
+        state.logical_render = self.logical_render;
+        state.resolution.enabled = self.dynamic_resolution;
+        state.resolution.target_fps = self.target_fps;
+        state.high_contrast = self.high_contrast;
+        state.egui_ctx.set_visuals(ui::visuals(self.high_contrast));
+        state.update_render_size();
+    }
+}
@@ -0,0 +1,76 @@
+// runtime kill switches for the major shading features, sent to the shader as a bitmask.
+// disabling features one by one helps isolating performance and correctness issues.
+// the bits must match the `FEATURE_*` constants in `settings.wgsl`.
+// the shadow and ao rays also get their own level of the dvo, tuned apart from the primary rays:
+// a coarser level is faster, but the occluders grow to whole octants.
+
+pub const FEATURE_SHADOWS: u32 = 1 << 0;
+pub const FEATURE_AO: u32 = 1 << 1;
+pub const FEATURE_FOG: u32 = 1 << 2;
+pub const FEATURE_SKY: u32 = 1 << 3;
+pub const FEATURE_GROUND: u32 = 1 << 4;
+pub const FEATURE_CONTACT_SHADOWS: u32 = 1 << 5;
+pub const FEATURE_BRICK_CULLING: u32 = 1 << 6;
+pub const FEATURE_BEAM: u32 = 1 << 7;
+pub const FEATURE_LIGHT_CULLING: u32 = 1 << 8;
+pub const FEATURE_REFLECTIONS: u32 = 1 << 9;
+
+/// name and bit of each feature, in ui order.
+pub const FEATURES: [(&str, u32); 10] = [
+    ("shadows", FEATURE_SHADOWS),
+    ("contact shadows", FEATURE_CONTACT_SHADOWS),
+    ("ambient occlusion", FEATURE_AO),
+    ("reflections", FEATURE_REFLECTIONS),
+    ("fog", FEATURE_FOG),
+    ("sky", FEATURE_SKY),
+    ("ground", FEATURE_GROUND),
+    ("brick culling", FEATURE_BRICK_CULLING),
+    ("beam optimization", FEATURE_BEAM),
+    ("light culling", FEATURE_LIGHT_CULLING),
+];
+
+// !! careful with the alignments! add padding fields if necessary.
+// see https://www.w3.org/TR/WGSL/#alignment-and-size
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SettingsUniform {
+    pub features: u32,
+    /// log2 of the octants where the hard shadow rays stop, 0 for the voxels.
+    pub shadow_level: u32,
+    /// mip level of the volume sampled by the ao, 0 for the voxels.
+    pub ao_level: u32,
+    _pad: u32, // padding to ensure correct alignment
+}
+
+pub struct Settings {
+    pub uniform: SettingsUniform,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            uniform: SettingsUniform {
+                features: FEATURES.iter().fold(0, |acc, (_, bit)| acc | bit),
+                shadow_level: 0,
+                ao_level: 0,
+                _pad: Default::default(),
+            },
+        }
+    }
+
+    pub fn enabled(&self, feature: u32) -> bool {
+        self.uniform.features & feature != 0
+    }
+
+    pub fn set_enabled(&mut self, feature: u32, enabled: bool) {
+        if enabled {
+            self.uniform.features |= feature;
+        } else {
+            self.uniform.features &= !feature;
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.uniform)
+    }
+}
@@ -0,0 +1,35 @@
+// this shader is a "module" supposed to be included.
+//
+// this module "exports":
+// var<uniform> settings: Settings
+// the levels of `settings.shadow_level` and `settings.ao_level` are the log2 of the voxels per
+// octant, 0 for the voxels.
+// const FEATURE_SHADOWS, FEATURE_AO, FEATURE_FOG, FEATURE_SKY, FEATURE_GROUND, FEATURE_CONTACT_SHADOWS: u32
+// const FEATURE_BRICK_CULLING, FEATURE_BEAM, FEATURE_LIGHT_CULLING, FEATURE_REFLECTIONS: u32
+// fn feature_enabled(feature: u32) -> bool
+//
+// the feature bits must match `settings.rs`.
+
+const FEATURE_SHADOWS: u32 = 1u;
+const FEATURE_AO: u32 = 2u;
+const FEATURE_FOG: u32 = 4u;
+const FEATURE_SKY: u32 = 8u;
+const FEATURE_GROUND: u32 = 16u;
+const FEATURE_CONTACT_SHADOWS: u32 = 32u;
+const FEATURE_BRICK_CULLING: u32 = 64u;
+const FEATURE_BEAM: u32 = 128u;
+const FEATURE_LIGHT_CULLING: u32 = 256u;
+const FEATURE_REFLECTIONS: u32 = 512u;
+
+struct Settings {
+    features: u32,
+    shadow_level: u32, // octree level of the hard shadow rays, see `raycast_coarse`
+    ao_level: u32, // mip level sampled by the ao, see `trace_ao`
+}
+
+@group(0) @binding(6)
+var<uniform> settings: Settings;
+
+fn feature_enabled(feature: u32) -> bool {
+    return (settings.features & feature) != 0u;
+}
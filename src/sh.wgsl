@@ -0,0 +1,45 @@
+// order 2 real spherical harmonics (9 coefficients), see
+// "An Efficient Representation for Irradiance Environment Maps", Ramamoorthi and Hanrahan.
+//
+// this shader is a "module" supposed to be included.
+//
+// this module "exports":
+// const SH_COEFFS: u32
+// fn sh_basis(dir: vec3f) -> array<f32, 9>
+// fn sh_irradiance(coeffs: array<vec3f, 9>, normal: vec3f) -> vec3f
+
+const SH_COEFFS: u32 = 9u;
+const PI: f32 = 3.14159265;
+
+fn sh_basis(dir: vec3f) -> array<f32, 9> {
+    let x = dir.x;
+    let y = dir.y;
+    let z = dir.z;
+    return array<f32, 9>(
+        0.282095,
+        0.488603 * y,
+        0.488603 * z,
+        0.488603 * x,
+        1.092548 * x * y,
+        1.092548 * y * z,
+        0.315392 * (3.0 * z * z - 1.0),
+        1.092548 * x * z,
+        0.546274 * (x * x - y * y),
+    );
+}
+
+// cosine-weighted average of the radiance around `normal`, i.e. irradiance / pi.
+fn sh_irradiance(coeffs: array<vec3f, 9>, normal: vec3f) -> vec3f {
+    // convolution with the clamped cosine lobe, per band.
+    let band = array<f32, 9>(
+        PI,
+        2.0 * PI / 3.0, 2.0 * PI / 3.0, 2.0 * PI / 3.0,
+        PI / 4.0, PI / 4.0, PI / 4.0, PI / 4.0, PI / 4.0,
+    );
+    let basis = sh_basis(normal);
+    var res = vec3f(0.0);
+    for (var i = 0u; i < SH_COEFFS; i++) {
+        res += coeffs[i] * band[i] * basis[i];
+    }
+    return max(res / PI, vec3f(0.0));
+}
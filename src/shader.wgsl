@@ -1,27 +1,34 @@
-#import "octree.wgsl"::{ raycast }
-
-#import "conetrace.wgsl"::{ trace_ao, trace_shadow }
-#import "bindings.wgsl"::{ colors, dvo }
-
+#import "ray.wgsl"::{ cam_pos, cam_ray_dir, msaa_offset, to_volume }
+#import "traversal.wgsl"::{ trace_primary }
+#import "lights.wgsl"::{ lights }
+#import "shading.wgsl"::{ shade_lit, shade_voxel, shade_voxel_lit }
+#import "gbuffer.wgsl"::{ load_gsample, load_visibility, SURFACE_VOXEL, SURFACE_GROUND }
+#import "sky.wgsl"::{ sky_color }
+#import "environment.wgsl"::{ ground_albedo }
+#import "materials.wgsl"::{ default_material }
+#import "post.wgsl"::{ apply_fog, apply_horizon_fog, apply_fog_volumes, composite }
+#import "overlay.wgsl"::{ route_glow, frustum_glow }
+#import "bindings.wgsl"::{ dvo }
+#import "exposure.wgsl"::{ record_luminance }
+#import "octree.wgsl"::{ intersection, Intersect }
+#import "colormap.wgsl"::{ colormap }
+#import "pathtrace.wgsl"::{ path_trace, accumulate }
+
+// entry points of the lighting pass, the last pass of the render pipeline. the scene is drawn in
+// three passes: primary (camera rays into the g-buffer, see `gbuffer.wgsl`), visibility (sun
+// shadows and ao) and lighting, which shades the g-buffer. the raymarcher itself is split in
+// modules: ray (camera rays), traversal (octree), shading (lights, shadows, ao), sky
+// (background), post (fog, compositing) and overlay. pathtrace replaces the shading in the path
+// traced mode.
+//
 // this module "requires":
-// const OCTREE_DEPTH: u32; // depth = 0 for a 2^3 volume: depth = log2(n) - 1.
 // const OCTREE_MAX_ITER: u32; // max number of hit tests in the octree per ray.
-// const GRID_DEPTH: u32;
-// const GRID_MAX_ITER: u32;
 // const MSAA_LEVEL: u32; // msaa with 2^n probes, 0 to disable
-// const DEBUG_DISPLAY: u32; // display ray complexity instead of color
-
-struct Camera {
-    pos: vec3f,
-    fov_y: f32,
-    size: vec2f,
-    aspect: f32,
-    view_mat_inv: mat4x4f,
-}
-
-struct Lights {
-    sun_dir: vec3f,
-}
+// const DEBUG_DISPLAY: u32; // display ray complexity, depth, normals or aabb distances instead of color
+// const DEBUG_PALETTE: u32; // 0: one channel per quantity, 1: viridis, 2: cividis
+// const SHOW_AABB_MISSES: u32; // tint the rays that never enter the volume aabb
+// const RENDER_MODE: u32; // 0: raymarched, 1: path traced, see `pathtrace.wgsl`
+// (and the constants required by the imported modules)
 
 struct VertexInput {
     @location(0) pos: vec2f,
@@ -32,20 +39,6 @@ struct VertexOutput {
     @location(0) pos: vec2f,
 }
 
-@group(0) @binding(0)
-var<uniform> cam: Camera;
-
-@group(0) @binding(1)
-var<uniform> lights: Lights;
-
-// @group(1) @binding(0)
-// var dvo: texture_3d<u32>;
-
-// @group(1) @binding(1)
-// var colors: texture_storage_3d<rgba8unorm, read>;
-
-// preproc_include(octree.wgsl)
-
 @vertex
 fn vs_main(in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
@@ -56,76 +49,40 @@ fn vs_main(in: VertexInput) -> VertexOutput {
     return out;
 }
 
-fn shade(albedo: vec4f, view_pos: vec3f, hit_pos: vec3f, hit_normal: vec3f) -> vec4f {
-    let ambient_color = albedo.rgb * 0.1;
-    let diffuse_color = pow(albedo.rgb, vec3f(2.2));
-    let specular_color = vec3f(1.0, 1.0, 1.0) * 0.1;
-    let shininess = 16.0;
-
-    let view_dir = normalize(view_pos - hit_pos);
-    let light_dir = lights.sun_dir;
-    let half_vector = normalize(light_dir + view_dir);
-
-    var ambient_term = ambient_color;
-    var diffuse_term = max(dot(hit_normal, light_dir), 0.0) * diffuse_color;
-    var specular_term = pow(max(dot(hit_normal, half_vector), 0.0), shininess) * specular_color;
-
-    if (#AO_STRENGTH != 0u) {
-        let ao = trace_ao(hit_pos, hit_normal);
-        let strength = f32(#AO_STRENGTH) / 10.0;
-        ambient_term *= (1.0 - ao * strength);
-    }
-
-    if (#SHADOW_STRENGTH != 0u) {
-        let soft_dist = 5.0;
-        let soft_falloff = 0.2;
-        let res = raycast(hit_pos + light_dir * 0.001, light_dir);
-        let hard_shadow = f32(res.hit);
-        let soft_shadow = trace_shadow(hit_pos, light_dir, soft_dist);
-        let hard_decay = 1.0 - clamp((res.t - soft_dist) * soft_falloff, 0.0, 1.0);
-        let t = hard_shadow * hard_decay;
-        let shadow = mix(soft_shadow, hard_shadow, t);
-        let strength = f32(#SHADOW_STRENGTH) / 10.0;
-
-        diffuse_term *= (1.0 - shadow * strength);
-        specular_term *= (1.0 - shadow * strength);
-    }
-
-    var shading_color = ambient_term + diffuse_term + specular_term;
-
-    return vec4f(saturate(shading_color), 1.0);
+// entry and exit distances of a ray through the aabb of the volume, in voxels.
+// `ray_pos` is in render space.
+fn volume_span(ray_pos: vec3f, ray_dir: vec3f) -> Intersect {
+    let size = f32(2u << #OCTREE_DEPTH);
+    let t = intersection(to_volume(ray_pos) / size, ray_dir);
+    return Intersect(t.t_min * size, t.t_max * size);
 }
 
-// is pos is on a cube surface, returns the normal of the corresponding cube face.
-fn cube_face_normal(ipos: vec3i, pos: vec3f) -> vec3f {
-    let off = pos - vec3f(ipos) - 0.5; // value between [-0.5, 0.5]
-    let dist = abs(off);
-    let max_dist = max(max(dist.x, dist.y), dist.z);
-    return sign(off) * vec3f(dist == vec3f(max_dist));
-}
-
-fn cam_ray_dir(pos: vec2f) -> vec3f {
-    return (cam.view_mat_inv * normalize(vec4f(
-        pos.x * tan(cam.fov_y / 2.0) * cam.aspect,
-        pos.y * tan(cam.fov_y / 2.0),
-        1.0,
-        0.0,
-    ))).xyz;
+fn misses_volume(span: Intersect) -> bool {
+    return span.t_min > span.t_max || span.t_max < 0.0;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4f {
     let ray_dir = cam_ray_dir(in.pos);
+    let pixel = vec2u(in.clip_pos.xy);
 
-    let res = raycast(cam.pos, ray_dir);
+    let res = load_gsample(pixel);
+    let hit = res.kind == SURFACE_VOXEL;
 
     // display ray complexity
     if #DEBUG_DISPLAY == 1u {
         let complexity = f32(res.iter) / f32(#OCTREE_MAX_ITER);
+        // the misses are darker instead of another hue.
+        if #DEBUG_PALETTE != 0u {
+            if res.iter == #OCTREE_MAX_ITER {
+                return vec4f(1.0, 1.0, 1.0, 1.0);
+            }
+            return vec4f(colormap(complexity, #DEBUG_PALETTE) * select(0.4, 1.0, hit), 1.0);
+        }
         if res.iter == #OCTREE_MAX_ITER {
             return vec4f(0.0, 0.0, 1.0, 1.0);
         }
-        else if res.hit {
+        else if hit {
             return vec4f(complexity, 0.0, 0.0, 1.0);
         }
         else {
@@ -136,8 +93,11 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4f {
     // display depth buffer
     else if #DEBUG_DISPLAY == 2u {
         let max_t = f32(1u << textureNumLevels(dvo));
-        var depth = 1.0 - saturate(res.t / max_t);
+        var depth = select(0.0, 1.0 - saturate(res.t / max_t), hit);
         depth = pow(depth, 2.0); // just to give more contrast to higher values
+        if #DEBUG_PALETTE != 0u {
+            return vec4f(colormap(depth, #DEBUG_PALETTE), 1.0);
+        }
         return vec4f(vec3f(depth), 1.0);
     }
 
@@ -146,28 +106,76 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4f {
         return vec4f(abs(res.normal) - 0.8 * -sign(res.normal), 1.0);
     }
 
-    if res.hit {
-        let albedo = textureLoad(colors, res.voxel, 0);
-        var col = shade(albedo, cam.pos, res.pos, res.normal);
+    // display the aabb entry (red) and exit (green) distances, rays missing the volume in blue
+    else if #DEBUG_DISPLAY == 4u {
+        let span = volume_span(cam_pos(), ray_dir);
+        if misses_volume(span) {
+            return vec4f(0.0, 0.0, 1.0, 1.0);
+        }
+        let max_t = f32(4u << #OCTREE_DEPTH);
+        // the entry distance only, the exit is further by the depth of the volume.
+        if #DEBUG_PALETTE != 0u {
+            return vec4f(colormap(saturate(max(span.t_min, 0.0) / max_t), #DEBUG_PALETTE), 1.0);
+        }
+        return vec4f(saturate(max(span.t_min, 0.0) / max_t), saturate(span.t_max / max_t), 0.0, 1.0);
+    }
+
+    let max_t = select(1e9, res.t, hit);
+    let overlay = route_glow(cam_pos(), ray_dir, max_t) + frustum_glow(cam_pos(), ray_dir, max_t);
+
+    // path traced, averaged with the previous frames.
+    if #RENDER_MODE == 1u {
+        let sample = path_trace(in.pos, pixel);
+        var col = sample.radiance;
+        if sample.t < 1e9 {
+            col = apply_fog(col, sample.t);
+        } else {
+            col = apply_horizon_fog(col, ray_dir);
+        }
+        col = apply_fog_volumes(col, cam_pos(), ray_dir, sample.t);
+        col = accumulate(pixel, col);
+        record_luminance(col);
+        return vec4f(composite(col, overlay), 1.0);
+    }
+
+    if hit {
+        let occluded = load_visibility(pixel);
+        var col = shade_voxel_lit(res.voxel, cam_pos(), res.pos, res.normal, occluded);
         // return col;
 
-        // MSAA
+        // MSAA, the extra probes are traced and shaded here.
         for (var i = 0u; i < #MSAA_LEVEL * 2u; i++) {
             for (var j = 0u; j < #MSAA_LEVEL * 2u; j++) {
-                let pos = (2.0 * (vec2f(f32(i), f32(j)) - f32(#MSAA_LEVEL)) - 1.0) / (4.0 * f32(#MSAA_LEVEL * #MSAA_LEVEL) - 1.0);
-                let jitter = pos / cam.size;
-                let ray_dir = cam_ray_dir(in.pos + jitter);
-                let res = raycast(cam.pos, ray_dir);
-                let albedo = textureLoad(colors, res.voxel, 0);
-                col += shade(albedo, cam.pos, res.pos, res.normal);
+                let res = trace_primary(in.pos + msaa_offset(i, j));
+                col += shade_voxel(res.voxel, cam_pos(), res.pos, res.normal);
             }
         }
 
-        return col / (1.0 + f32(#MSAA_LEVEL * #MSAA_LEVEL * 4u));
+        col /= (1.0 + f32(#MSAA_LEVEL * #MSAA_LEVEL * 4u));
+        var fogged = apply_fog(col.rgb, res.t);
+        fogged = apply_fog_volumes(fogged, cam_pos(), ray_dir, res.t);
+        record_luminance(fogged);
+        return vec4f(composite(fogged, overlay), col.a);
     }
 
     else {
-        var col = res.pos;
-        return vec4f(col, 1.0);
+        var col = sky_color(ray_dir, lights.sun.dir);
+
+        // rays leaving the volume downwards land on the infinite ground plane.
+        if res.kind == SURFACE_GROUND {
+            let albedo = vec4f(ground_albedo(res.pos), 1.0);
+            let occluded = load_visibility(pixel);
+            col = shade_lit(albedo, default_material(), cam_pos(), res.pos, res.normal, occluded.x, occluded.y).rgb;
+            col = apply_fog(col, res.t);
+        }
+
+        col = apply_horizon_fog(col, ray_dir);
+        col = apply_fog_volumes(col, cam_pos(), ray_dir, res.t);
+
+        if #SHOW_AABB_MISSES == 1u && misses_volume(volume_span(cam_pos(), ray_dir)) {
+            col = mix(col, vec3f(1.0, 0.0, 1.0), 0.5);
+        }
+        record_luminance(col);
+        return vec4f(composite(col, overlay), 1.0);
     }
 }
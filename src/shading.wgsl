@@ -0,0 +1,254 @@
+#import "octree.wgsl"::{ raycast_coarse }
+#import "conetrace.wgsl"::{ trace_ao, trace_shadow }
+#import "bindings.wgsl"::{ colors, lightmap, cluster_lights }
+#import "settings.wgsl"::{ settings, feature_enabled, FEATURE_SHADOWS, FEATURE_AO, FEATURE_SKY, FEATURE_LIGHT_CULLING }
+#import "clusters.wgsl"::{ cluster_of, CLUSTER_STRIDE, NO_CLUSTER }
+#import "lights.wgsl"::{ lights, local_lights, shadow_iter_budget, LIGHT_SPOT, LIGHT_DIRECTIONAL }
+#import "environment.wgsl"::{ env }
+#import "sh.wgsl"::{ sh_irradiance, SH_COEFFS }
+#import "probes.wgsl"::{ probes, probes_enabled, probe_reflection }
+#import "detail.wgsl"::{ apply_detail, noisy_albedo, noisy_normal }
+#import "materials.wgsl"::{ Material, material_of, is_water }
+#import "water.wgsl"::{ shade_water }
+#import "footprint.wgsl"::{ footprint_albedo }
+#import "ray.wgsl"::{ to_volume, from_volume }
+
+// this shader is a "module" supposed to be included.
+//
+// this module "exports":
+// var<storage> sky_sh: array<vec4f, 9>
+// const PI: f32
+// fn ambient_light(normal: vec3f) -> vec3f
+// fn reflectance(material: Material, base_color: vec3f) -> vec3f
+// fn schlick(f0: vec3f, cos_theta: f32) -> vec3f
+// fn brdf(material: Material, base_color: vec3f, normal: vec3f, view_dir: vec3f, light_dir: vec3f) -> vec3f
+// fn local_lighting(material: Material, base_color: vec3f, view_dir: vec3f, hit_pos: vec3f, hit_normal: vec3f) -> vec3f
+// fn shade_lit(albedo: vec4f, material: Material, view_pos: vec3f, hit_pos: vec3f, hit_normal: vec3f, shadow: f32, ao: f32) -> vec4f
+// fn occlusion(hit_pos: vec3f, hit_normal: vec3f) -> vec2f
+// fn voxel_occlusion(voxel: vec3u, hit_pos: vec3f, hit_normal: vec3f) -> vec2f
+// fn shade(albedo: vec4f, material: Material, view_pos: vec3f, hit_pos: vec3f, hit_normal: vec3f) -> vec4f
+// fn shade_voxel_lit(voxel: vec3u, view_pos: vec3f, hit_pos: vec3f, hit_normal: vec3f, occluded: vec2f) -> vec4f
+// fn shade_voxel(voxel: vec3u, view_pos: vec3f, hit_pos: vec3f, hit_normal: vec3f) -> vec4f
+//
+// this module "requires":
+// const SHADOW_STRENGTH: u32;
+// const AO_STRENGTH: u32;
+// const BAKED_LIGHTING: u32; // use the baked shadows and ao from the lightmap
+
+@group(0) @binding(7)
+var<storage, read> sky_sh: array<vec4f, 9>;
+
+const PI: f32 = 3.14159265;
+
+// sky_sh holds the radiance of the sky projected by sky_sh.wgsl. the solid background is a
+// studio backdrop rather than a light source, so it keeps the constant ambient.
+fn ambient_light(normal: vec3f) -> vec3f {
+    if env.background_mode == 0u || !feature_enabled(FEATURE_SKY) {
+        return vec3f(0.1) * lights.ambient;
+    }
+    var coeffs: array<vec3f, 9>;
+    for (var i = 0u; i < SH_COEFFS; i++) {
+        coeffs[i] = sky_sh[i].xyz;
+    }
+    let sky_ambient_scale = 0.2;
+    return sh_irradiance(coeffs, normal) * sky_ambient_scale * lights.ambient;
+}
+
+// reflectance at normal incidence: from the index of refraction for the dielectrics, the base
+// color for the metals.
+fn reflectance(material: Material, base_color: vec3f) -> vec3f {
+    let r = (material.ior - 1.0) / (material.ior + 1.0);
+    return mix(vec3f(r * r), base_color, material.metalness);
+}
+
+fn schlick(f0: vec3f, cos_theta: f32) -> vec3f {
+    return f0 + (1.0 - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// the light reflected towards `view_dir` from a light of unit intensity in `light_dir`:
+// cook-torrance specular (ggx distribution, schlick-smith visibility, schlick fresnel) over a
+// lambert diffuse. the diffuse is not divided by pi, the lights are given as the light reflected
+// by a white surface facing them.
+fn brdf(material: Material, base_color: vec3f, normal: vec3f, view_dir: vec3f, light_dir: vec3f) -> vec3f {
+    let n_dot_l = dot(normal, light_dir);
+    if n_dot_l <= 0.0 {
+        return vec3f(0.0);
+    }
+    let half_vector = normalize(light_dir + view_dir);
+    let n_dot_v = max(dot(normal, view_dir), 1e-4);
+    let n_dot_h = saturate(dot(normal, half_vector));
+    let v_dot_h = saturate(dot(view_dir, half_vector));
+
+    let alpha = max(material.roughness * material.roughness, 2e-3);
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    let distribution = a2 / (PI * d * d);
+    let k = alpha * 0.5;
+    let visibility = 0.25 / ((n_dot_l * (1.0 - k) + k) * (n_dot_v * (1.0 - k) + k));
+    let fresnel = schlick(reflectance(material, base_color), v_dot_h);
+
+    let diffuse = (1.0 - fresnel) * (1.0 - material.metalness) * base_color;
+    let specular = distribution * visibility * fresnel * PI;
+    return (diffuse + specular) * n_dot_l;
+}
+
+// the light of the local lights, through the brdf like the sun. the lights that cast shadows cast
+// them like the sun, with their share of the shadow budget. with the light culling, only the lights
+// of the cluster of the hit are walked, see `clusters.wgsl`.
+fn local_lighting(material: Material, base_color: vec3f, view_dir: vec3f, hit_pos: vec3f, hit_normal: vec3f) -> vec3f {
+    let shadow_strength = f32(#SHADOW_STRENGTH) / 10.0;
+    let shadows = #SHADOW_STRENGTH != 0u && feature_enabled(FEATURE_SHADOWS);
+    var total = vec3f(0.0);
+
+    var cluster = NO_CLUSTER;
+    if feature_enabled(FEATURE_LIGHT_CULLING) {
+        cluster = cluster_of(hit_pos);
+    }
+    let base = cluster * CLUSTER_STRIDE;
+    var count = lights.local_count;
+    if cluster != NO_CLUSTER {
+        count = cluster_lights[base];
+    }
+
+    for (var n = 0u; n < count; n++) {
+        var i = n;
+        if cluster != NO_CLUSTER {
+            i = cluster_lights[base + 1u + n];
+        }
+        let light = local_lights[i];
+        var light_dir = -light.dir;
+        var dist = 1e9;
+        var attenuation = 1.0;
+        if light.kind != LIGHT_DIRECTIONAL {
+            let to_light = from_volume(light.pos) - hit_pos;
+            dist = length(to_light);
+            light_dir = to_light / dist;
+            // inverse square, windowed to reach 0 at the radius.
+            let window = saturate(1.0 - pow(dist / light.radius, 4.0));
+            attenuation = window * window / (dist * dist + 1.0);
+        }
+        if light.kind == LIGHT_SPOT {
+            let edge = mix(light.cos_cone, 1.0, 0.2);
+            attenuation *= smoothstep(light.cos_cone, edge, dot(-light_dir, light.dir));
+        }
+        let n_dot_l = dot(hit_normal, light_dir);
+        if attenuation <= 0.0 || n_dot_l <= 0.0 {
+            continue;
+        }
+
+        var visibility = 1.0;
+        if shadows && light.shadow != 0u {
+            let budget = shadow_iter_budget(light.shadow_max_iter);
+            let shadow = light_shadow(to_volume(hit_pos + hit_normal * 1e-3), light_dir, dist, light.shadow_softness, budget);
+            visibility -= shadow * shadow_strength;
+        }
+        let reflected = brdf(material, base_color, hit_normal, view_dir, light_dir);
+        total += reflected * light.color * attenuation * visibility;
+    }
+    return total;
+}
+
+// shadow and ao are between 0 (none) and 1 (fully shadowed / occluded).
+fn shade_lit(albedo: vec4f, material: Material, view_pos: vec3f, hit_pos: vec3f, hit_normal: vec3f, shadow: f32, ao: f32) -> vec4f {
+    let base_color = pow(albedo.rgb, vec3f(2.2));
+    let view_dir = normalize(view_pos - hit_pos);
+    let reflect_dir = reflect(-view_dir, hit_normal);
+    let light_dir = lights.sun.dir;
+    let f0 = reflectance(material, base_color);
+
+    // the metals have no diffuse, they reflect the ambient light instead.
+    var ambient_term = albedo.rgb * ambient_light(hit_normal) * (1.0 - material.metalness);
+    ambient_term += ambient_light(reflect_dir) * f0 * material.metalness;
+    // the sun fades out as it sets.
+    let sun_up = smoothstep(-0.05, 0.05, light_dir.y);
+    var direct_term = brdf(material, base_color, hit_normal, view_dir, light_dir) * sun_up;
+
+    let ao_strength = f32(#AO_STRENGTH) / 10.0;
+    ambient_term *= (1.0 - ao * ao_strength);
+
+    let shadow_strength = f32(#SHADOW_STRENGTH) / 10.0;
+    direct_term *= (1.0 - shadow * shadow_strength);
+
+    var shading_color = ambient_term + direct_term;
+    shading_color += local_lighting(material, base_color, view_dir, hit_pos, hit_normal);
+
+    // reflections from the baked probes, stronger at grazing angles (schlick fresnel).
+    if probes_enabled() {
+        let fresnel = schlick(f0, saturate(dot(hit_normal, view_dir)));
+        let reflection = probe_reflection(to_volume(hit_pos), reflect_dir);
+        shading_color = mix(shading_color, reflection, saturate(fresnel * probes.strength));
+    }
+
+    shading_color += albedo.rgb * material.emissive;
+
+    return vec4f(saturate(shading_color), 1.0);
+}
+
+// the shadow towards a light up to `max_dist`, between 0 (lit) and 1 (shadowed): hard next to
+// the occluders, softening over `softness` voxels. the cone tracing stops after `max_iter`
+// iterations. `pos` is in world coordinates.
+fn light_shadow(pos: vec3f, light_dir: vec3f, max_dist: f32, softness: f32, max_iter: u32) -> f32 {
+    let soft_falloff = 0.2;
+    let res = raycast_coarse(pos + light_dir * 0.001, light_dir, settings.shadow_level);
+    let hard_shadow = f32(res.hit && res.t < max_dist);
+    let soft_shadow = trace_shadow(pos, light_dir, softness, max_dist, max_iter);
+    let hard_decay = 1.0 - clamp((res.t - softness) * soft_falloff, 0.0, 1.0);
+    return mix(soft_shadow, hard_shadow, hard_shadow * hard_decay);
+}
+
+// the sun shadow and the ao of a surface, see `shade_lit`. computed by the visibility pass of
+// the deferred renderer, see `visibility.wgsl`.
+fn occlusion(hit_pos: vec3f, hit_normal: vec3f) -> vec2f {
+    let pos = to_volume(hit_pos);
+    let light = lights.sun;
+    let light_dir = light.dir;
+    var ao = 0.0;
+    var shadow = 0.0;
+
+    if (#AO_STRENGTH != 0u && feature_enabled(FEATURE_AO)) {
+        ao = trace_ao(pos, hit_normal, settings.ao_level);
+    }
+
+    if (#SHADOW_STRENGTH != 0u && feature_enabled(FEATURE_SHADOWS) && light.shadow != 0u) {
+        let budget = shadow_iter_budget(light.shadow_max_iter);
+        shadow = light_shadow(pos, light_dir, 1e9, light.shadow_softness, budget);
+    }
+    return vec2f(shadow, ao);
+}
+
+// `occlusion` of a voxel surface, from the lightmap when the lighting is baked.
+fn voxel_occlusion(voxel: vec3u, hit_pos: vec3f, hit_normal: vec3f) -> vec2f {
+    if #BAKED_LIGHTING == 1u {
+        let baked = textureLoad(lightmap, voxel, 0).rg;
+        let shadow = select(0.0, 1.0 - baked.r, feature_enabled(FEATURE_SHADOWS));
+        let ao = select(0.0, baked.g, feature_enabled(FEATURE_AO));
+        return vec2f(shadow, ao);
+    }
+    let material = material_of(voxel);
+    return occlusion(hit_pos, noisy_normal(hit_normal, to_volume(hit_pos), material.detail_noise));
+}
+
+fn shade(albedo: vec4f, material: Material, view_pos: vec3f, hit_pos: vec3f, hit_normal: vec3f) -> vec4f {
+    let occluded = occlusion(hit_pos, hit_normal);
+    return shade_lit(albedo, material, view_pos, hit_pos, hit_normal, occluded.x, occluded.y);
+}
+
+// `occluded` is the shadow and the ao of `voxel_occlusion`.
+fn shade_voxel_lit(voxel: vec3u, view_pos: vec3f, hit_pos: vec3f, hit_normal: vec3f, occluded: vec2f) -> vec4f {
+    let material = material_of(voxel);
+    let dist = distance(view_pos, hit_pos);
+    // the textures and the noise are anchored to the world.
+    let pos = to_volume(hit_pos);
+    var albedo = apply_detail(footprint_albedo(voxel, dist), voxel, pos, hit_normal, dist);
+    albedo = noisy_albedo(albedo, pos, material.detail_noise);
+    if is_water(material) {
+        albedo = shade_water(albedo, voxel, pos);
+    }
+    let normal = noisy_normal(hit_normal, pos, material.detail_noise);
+    return shade_lit(albedo, material, view_pos, hit_pos, normal, occluded.x, occluded.y);
+}
+
+fn shade_voxel(voxel: vec3u, view_pos: vec3f, hit_pos: vec3f, hit_normal: vec3f) -> vec4f {
+    let occluded = voxel_occlusion(voxel, hit_pos, hit_normal);
+    return shade_voxel_lit(voxel, view_pos, hit_pos, hit_normal, occluded);
+}
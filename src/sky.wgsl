@@ -0,0 +1,131 @@
+#import "environment.wgsl"::{ env }
+#import "lights.wgsl"::{ lights }
+#import "noise.wgsl"::{ hash3, unit_float }
+#import "settings.wgsl"::{ feature_enabled, FEATURE_SKY }
+
+// this shader is a "module" supposed to be included.
+// the sky background mode is the analytic daylight model of Preetham et al. 1999, "A practical
+// analytic model for daylight", driven by the sun direction and `lights.turbidity` (2: clear,
+// 10: hazy). the sun disk is reddened by the air it crosses near the horizon, and the sky fades
+// to a night sky with stars when the sun sets.
+//
+// this module "exports":
+// fn sky_color(ray_dir: vec3f, sun_dir: vec3f) -> vec3f
+
+const PI: f32 = 3.14159265;
+// the luminance of the model is in kcd/m², about 20 at the zenith of a clear noon sky.
+const SKY_SCALE: f32 = 0.04;
+// cosine of the angular radius of the sun disk, a bit larger than the real 0.27°.
+const SUN_COS: f32 = 0.99995;
+const SUN_INTENSITY: f32 = 10.0;
+const NIGHT_COLOR: vec3f = vec3f(0.004, 0.006, 0.015);
+// directions per radian in the star grid, and the fraction of cells holding a star.
+const STAR_DENSITY: f32 = 300.0;
+const STAR_FRACTION: f32 = 0.002;
+
+// Perez distribution of the luminance at view angle `theta` from the zenith and `gamma` from the
+// sun, coefficients `a` to `e`.
+fn perez(theta: f32, gamma: f32, a: f32, b: f32, c: f32, d: f32, e: f32) -> f32 {
+    let cos_gamma = cos(gamma);
+    let gradation = 1.0 + a * exp(b / max(cos(theta), 0.01));
+    let indicatrix = 1.0 + c * exp(d * gamma) + e * cos_gamma * cos_gamma;
+    return gradation * indicatrix;
+}
+
+// the Y, x and y channels of the Perez distribution, relative to the zenith.
+fn perez_ratio(theta: f32, gamma: f32, theta_sun: f32, coeffs: array<f32, 5>) -> f32 {
+    let c = coeffs;
+    let view = perez(theta, gamma, c[0], c[1], c[2], c[3], c[4]);
+    let zenith = perez(0.0, theta_sun, c[0], c[1], c[2], c[3], c[4]);
+    return view / zenith;
+}
+
+fn xyY_to_rgb(xyY: vec3f) -> vec3f {
+    let Y = xyY.z;
+    let X = xyY.x / xyY.y * Y;
+    let Z = (1.0 - xyY.x - xyY.y) / xyY.y * Y;
+    // XYZ to linear sRGB.
+    return max(vec3f(
+        3.2406 * X - 1.5372 * Y - 0.4986 * Z,
+        -0.9689 * X + 1.8758 * Y + 0.0415 * Z,
+        0.0557 * X - 0.2040 * Y + 1.0570 * Z,
+    ), vec3f(0.0));
+}
+
+fn preetham(ray_dir: vec3f, sun_dir: vec3f) -> vec3f {
+    let t = lights.turbidity;
+    // the model holds for a sun above the horizon, and views above it.
+    let theta = acos(clamp(ray_dir.y, 0.0, 1.0));
+    let theta_sun = acos(clamp(sun_dir.y, 0.01, 1.0));
+    let gamma = acos(clamp(dot(ray_dir, sun_dir), -1.0, 1.0));
+
+    let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+    let zenith_Y = (4.0453 * t - 4.9710) * tan(chi) - 0.2155 * t + 2.4192;
+    let ts = vec3f(theta_sun * theta_sun * theta_sun, theta_sun * theta_sun, theta_sun);
+    let zenith_x = t * t * dot(vec3f(0.00166, -0.00375, 0.00209), ts)
+        + t * (dot(vec3f(-0.02903, 0.06377, -0.03202), ts) + 0.00394)
+        + dot(vec3f(0.11693, -0.21196, 0.06052), ts) + 0.25886;
+    let zenith_y = t * t * dot(vec3f(0.00275, -0.00610, 0.00317), ts)
+        + t * (dot(vec3f(-0.04214, 0.08970, -0.04153), ts) + 0.00516)
+        + dot(vec3f(0.15346, -0.26756, 0.06670), ts) + 0.26688;
+
+    let coeffs_Y = array(
+        0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251,
+        0.1206 * t - 2.5771, -0.0670 * t + 0.3703,
+    );
+    let coeffs_x = array(
+        -0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125,
+        -0.0641 * t - 0.8989, -0.0033 * t + 0.0452,
+    );
+    let coeffs_y = array(
+        -0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102,
+        -0.0441 * t - 1.6537, -0.0109 * t + 0.0529,
+    );
+
+    let xyY = vec3f(
+        zenith_x * perez_ratio(theta, gamma, theta_sun, coeffs_x),
+        zenith_y * perez_ratio(theta, gamma, theta_sun, coeffs_y),
+        zenith_Y * perez_ratio(theta, gamma, theta_sun, coeffs_Y) * SKY_SCALE,
+    );
+    return xyY_to_rgb(xyY);
+}
+
+// the sun disk, dimmed and reddened by the air mass along the view near the horizon.
+fn sun_disk(ray_dir: vec3f, sun_dir: vec3f) -> vec3f {
+    let disk = smoothstep(SUN_COS - 0.00002, SUN_COS, dot(ray_dir, sun_dir));
+    let air_mass = 1.0 / max(sun_dir.y + 0.05, 0.05);
+    let extinction = vec3f(0.02, 0.05, 0.12) * lights.turbidity;
+    return exp(-extinction * air_mass) * disk * SUN_INTENSITY;
+}
+
+fn stars(ray_dir: vec3f) -> vec3f {
+    let cell = vec3i(floor(ray_dir * STAR_DENSITY));
+    let h = unit_float(hash3(cell, 0u));
+    let star = saturate((h - (1.0 - STAR_FRACTION)) / STAR_FRACTION);
+    return vec3f(star * star);
+}
+
+fn sky_color(ray_dir: vec3f, sun_dir: vec3f) -> vec3f {
+    // with the sky disabled, every background mode falls back to the solid color.
+    if !feature_enabled(FEATURE_SKY) {
+        return env.background_color;
+    }
+
+    // vertical gradient from the horizon to the zenith, mirrored below the horizon.
+    if env.background_mode == 1u {
+        return mix(env.background_horizon, env.background_color, abs(ray_dir.y));
+    }
+
+    // physically based sky above a dim ground, fading to the night when the sun sets.
+    else if env.background_mode == 2u {
+        let day = smoothstep(-0.1, 0.05, sun_dir.y);
+        var col = preetham(ray_dir, sun_dir) * day + NIGHT_COLOR * (1.0 - day);
+        if ray_dir.y > 0.0 {
+            col += sun_disk(ray_dir, sun_dir) + stars(ray_dir) * (1.0 - day);
+        }
+        let below = vec3f(0.3, 0.3, 0.32) * max(day, 0.05);
+        return mix(col, below, saturate(-ray_dir.y * 4.0));
+    }
+
+    return env.background_color;
+}
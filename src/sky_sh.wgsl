@@ -0,0 +1,48 @@
+#import "lights.wgsl"::{ lights }
+#import "sky.wgsl"::{ sky_color }
+#import "sh.wgsl"::{ sh_basis, SH_COEFFS }
+
+// projects the procedural sky into spherical harmonics, used for the ambient light.
+// a single workgroup: each invocation integrates one column of a latitude-longitude grid,
+// then the first one sums them. dispatched when the sun or the environment change.
+
+const THETA_STEPS: u32 = 32u;
+const PHI_STEPS: u32 = 64u; // = workgroup size
+const PI: f32 = 3.14159265;
+
+@group(0) @binding(7)
+var<storage, read_write> sky_sh: array<vec4f, 9>;
+
+var<workgroup> partial: array<array<vec3f, 9>, PHI_STEPS>;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(local_invocation_index) index: u32) {
+    let d_theta = PI / f32(THETA_STEPS);
+    let d_phi = 2.0 * PI / f32(PHI_STEPS);
+    let phi = (f32(index) + 0.5) * d_phi;
+
+    var coeffs = array<vec3f, 9>();
+    for (var i = 0u; i < THETA_STEPS; i++) {
+        let theta = (f32(i) + 0.5) * d_theta;
+        let dir = vec3f(sin(theta) * cos(phi), cos(theta), sin(theta) * sin(phi));
+        let solid_angle = sin(theta) * d_theta * d_phi;
+        let radiance = sky_color(dir, lights.sun.dir) * solid_angle;
+        let basis = sh_basis(dir);
+        for (var c = 0u; c < SH_COEFFS; c++) {
+            coeffs[c] += radiance * basis[c];
+        }
+    }
+    partial[index] = coeffs;
+
+    workgroupBarrier();
+
+    if index == 0u {
+        for (var c = 0u; c < SH_COEFFS; c++) {
+            var sum = vec3f(0.0);
+            for (var i = 0u; i < PHI_STEPS; i++) {
+                sum += partial[i][c];
+            }
+            sky_sh[c] = vec4f(sum, 0.0);
+        }
+    }
+}
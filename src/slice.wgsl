@@ -0,0 +1,60 @@
+// a z slice of one of the scene 3d textures, for the slice viewer debug window.
+// uses the fullscreen quad of the render pipeline.
+
+struct SliceParams {
+    source: u32, // 0: voxels, 1: dvo, 2: colors. see `SliceSource`.
+    level: u32,
+    z: u32,
+}
+
+@group(0) @binding(0) var voxels: texture_3d<u32>;
+@group(0) @binding(1) var dvo: texture_3d<u32>;
+@group(0) @binding(2) var colors: texture_3d<f32>;
+@group(0) @binding(3) var<uniform> params: SliceParams;
+
+struct VertexInput {
+    @location(0) pos: vec2f,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_pos: vec4f,
+    @location(0) uv: vec2f,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.uv = vec2f(in.pos.x + 1.0, 1.0 - in.pos.y) * 0.5;
+    out.clip_pos = vec4f(in.pos, 0.0, 1.0);
+
+    return out;
+}
+
+fn texel(dim: vec3u, uv: vec2f) -> vec3u {
+    let xy = min(vec2u(uv * vec2f(dim.xy)), dim.xy - 1u);
+    return vec3u(xy, min(params.z, dim.z - 1u));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    switch params.source {
+        case 0u: {
+            // palette indices, with a distinct color each.
+            let index = textureLoad(voxels, texel(textureDimensions(voxels), in.uv), 0).r;
+            let color = fract(vec3f(f32(index)) * vec3f(0.137, 0.311, 0.713));
+            return vec4f(select(vec3f(0.0), 0.3 + 0.7 * color, index != 0u), 1.0);
+        }
+        case 1u: {
+            // number of solid octants of each node.
+            let dim = textureDimensions(dvo, params.level);
+            let node = textureLoad(dvo, texel(dim, in.uv), i32(params.level)).r;
+            return vec4f(vec3f(f32(countOneBits(node)) / 8.0), 1.0);
+        }
+        default: {
+            let dim = textureDimensions(colors, params.level);
+            let color = textureLoad(colors, texel(dim, in.uv), i32(params.level));
+            return vec4f(color.rgb, 1.0);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::voxels::{self, Voxels};
+
+// `wender stats`: statistics of a scene for offline analysis, e.g. of a converted minecraft
+// server. writes into the output directory:
+// - `materials.csv`: the number of voxels of each palette entry.
+// - `heights.csv`: per height, the number of voxels and of columns whose top voxel is there.
+// - `heightmap.png`: the top-down height map, a 16-bit grayscale image of the height of the top
+//   voxel + 1 of each column, 0 being an empty column. x is right, z is down.
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to write `{0}`: {1}")]
+    IOError(PathBuf, io::Error),
+    #[error("failed to write `{0}`: {1}")]
+    ImageError(PathBuf, image::ImageError),
+    #[error(transparent)]
+    SceneError(#[from] voxels::Error),
+}
+
+/// write the statistics of `scene` into `out_dir`. returns whether it succeeded.
+pub fn export_stats(scene: &Path, out_dir: &Path) -> bool {
+    match export(scene, out_dir) {
+        Ok(()) => {
+            println!("wrote `{}`", out_dir.display());
+            true
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            false
+        }
+    }
+}
+
+struct Stats {
+    /// voxels of each palette entry, 0-based.
+    materials: Vec<u64>,
+    /// voxels at each height.
+    layers: Vec<u64>,
+    /// columns whose top voxel is at each height.
+    surface: Vec<u64>,
+    /// height of the top voxel + 1 of each column, 0 if empty, indexed `[z, x]`.
+    heightmap: Vec<u16>,
+}
+
+fn compute(voxels: &Voxels) -> Stats {
+    let (sz, sy, sx) = voxels.shape();
+    let array = voxels.voxels();
+    let mut stats = Stats {
+        materials: vec![0; voxels.palette().len()],
+        layers: vec![0; sy],
+        surface: vec![0; sy],
+        heightmap: vec![0; sx * sz],
+    };
+
+    for ((z, y, x), v) in array.indexed_iter() {
+        if *v == 0 || z >= sz || y >= sy || x >= sx {
+            continue;
+        }
+        if let Some(count) = stats.materials.get_mut(*v as usize - 1) {
+            *count += 1;
+        }
+        stats.layers[y] += 1;
+        let top = &mut stats.heightmap[z * sx + x];
+        *top = (*top).max(y as u16 + 1);
+    }
+    for top in stats.heightmap.iter().filter(|top| **top != 0) {
+        stats.surface[*top as usize - 1] += 1;
+    }
+    stats
+}
+
+fn write_csv(path: &Path, header: &str, rows: impl Iterator<Item = String>) -> Result<(), Error> {
+    let source = rows.fold(format!("{header}\n"), |mut source, row| {
+        let _ = writeln!(source, "{row}");
+        source
+    });
+    fs::write(path, source).map_err(|e| Error::IOError(path.to_owned(), e))
+}
+
+fn export(scene: &Path, out_dir: &Path) -> Result<(), Error> {
+    let voxels = Voxels::from_path(scene)?;
+    let stats = compute(&voxels);
+    let (sz, _, sx) = voxels.shape();
+
+    fs::create_dir_all(out_dir).map_err(|e| Error::IOError(out_dir.to_owned(), e))?;
+
+    write_csv(
+        &out_dir.join("materials.csv"),
+        "index,color,voxels",
+        voxels
+            .palette()
+            .iter()
+            .zip(&stats.materials)
+            .enumerate()
+            .map(|(i, ([r, g, b, _], count))| format!("{},#{r:02x}{g:02x}{b:02x},{count}", i + 1)),
+    )?;
+
+    write_csv(
+        &out_dir.join("heights.csv"),
+        "height,voxels,surface columns",
+        stats
+            .layers
+            .iter()
+            .zip(&stats.surface)
+            .enumerate()
+            .map(|(y, (voxels, surface))| format!("{y},{voxels},{surface}")),
+    )?;
+
+    let path = out_dir.join("heightmap.png");
+    let image =
+        image::ImageBuffer::<image::Luma<u16>, _>::from_raw(sx as u32, sz as u32, stats.heightmap)
+            .unwrap();
+    image
+        .save(&path)
+        .map_err(|e| Error::ImageError(path.clone(), e))?;
+
+    let total = stats.materials.iter().sum::<u64>();
+    let columns = stats.surface.iter().sum::<u64>();
+    println!(
+        "{total} voxels in {} materials, {columns} of {} columns occupied",
+        stats.materials.iter().filter(|c| **c != 0).count(),
+        sx * sz,
+    );
+    Ok(())
+}
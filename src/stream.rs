@@ -0,0 +1,403 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+};
+
+use nalgebra_glm as glm;
+use ndarray::{s, Array3};
+use thiserror::Error;
+
+use crate::{
+    chunks::{self, ChunkEntry, Header},
+    voxels::{self, colorize, ColorsFormat, Voxels, VoxelsFormat},
+};
+
+// progressive loading of chunked scenes (.wchunks). the header is read first, then the chunks
+// are loaded in the background, the nearest to the camera first, and swapped in as they arrive.
+// on the web, chunks are fetched with http range requests. natively, they are read from the file.
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to read `{0}`: {1}")]
+    IOError(PathBuf, io::Error),
+    #[error("failed to download `{0}`: {1}")]
+    FetchError(PathBuf, String),
+    #[error("invalid scene `{0}`: {1}")]
+    ChunksError(PathBuf, chunks::Error),
+    #[error(transparent)]
+    SceneError(#[from] voxels::Error),
+}
+
+enum Message {
+    Chunk(ChunkEntry, Array3<u32>),
+    Error(String),
+}
+
+pub struct SceneStream {
+    path: PathBuf,
+    palette: Vec<[u8; 4]>,
+    chunk_size: usize,
+    dim: usize,
+    total: usize,
+    loaded: usize,
+    focus: Arc<Mutex<glm::Vec3>>,
+    rx: Receiver<Message>,
+}
+
+/// a chunk ready to be written into the volume, clipped to it. the origin is in array axes.
+pub struct Region {
+    pub origin: [usize; 3],
+    pub voxels: Array3<VoxelsFormat>,
+    pub colors: Array3<ColorsFormat>,
+}
+
+/// remove and return the chunk nearest to `focus`, in world coordinates.
+fn take_nearest(
+    remaining: &mut Vec<ChunkEntry>,
+    focus: &Mutex<glm::Vec3>,
+    chunk_size: usize,
+) -> Option<ChunkEntry> {
+    let focus = *focus.lock().unwrap();
+    let half = chunk_size as f32 * 0.5;
+    let dist = |entry: &ChunkEntry| {
+        // world x and z are swapped relative to the array axes.
+        let [i, j, k] = entry.origin(chunk_size).map(|x| x as f32 + half);
+        glm::distance2(&glm::vec3(k, j, i), &focus)
+    };
+    let nearest = (0..remaining.len())
+        .min_by(|a, b| dist(&remaining[*a]).total_cmp(&dist(&remaining[*b])))?;
+    Some(remaining.swap_remove(nearest))
+}
+
+/// decompress a chunk and check its palette indices, the header was validated when opening.
+fn decompress(
+    entry: &ChunkEntry,
+    data: &[u8],
+    chunk_size: usize,
+    palette_len: usize,
+) -> Result<Array3<u32>, String> {
+    chunks::decompress(entry, data, chunk_size)
+        .and_then(|chunk| chunks::check_indices(entry, &chunk, palette_len).map(|_| chunk))
+        .map_err(|e| e.to_string())
+}
+
+/// byte range of a chunk in the file, `None` on overflow.
+fn data_range(entry: &ChunkEntry, data_start: u64) -> Option<(u64, u64)> {
+    let start = data_start.checked_add(entry.offset)?;
+    Some((start, start.checked_add(entry.len)?))
+}
+
+impl SceneStream {
+    /// read the header of a chunked scene and start loading its chunks. returns the empty
+    /// scene, to be filled with the chunks returned by `poll`.
+    pub async fn open(path: &Path, focus: glm::Vec3) -> Result<(Voxels, Self), Error> {
+        let (header, source) = source::open(path).await?;
+
+        let (tx, rx) = mpsc::channel();
+        let focus = Arc::new(Mutex::new(focus));
+        source::spawn_loader(
+            source,
+            header.chunks.clone(),
+            header.chunk_size,
+            header.palette.len(),
+            focus.clone(),
+            tx,
+        );
+
+        let voxels = Voxels::from_parts(
+            Array3::zeros(header.shape),
+            header.palette.clone(),
+            None,
+            path,
+        )?;
+
+        let stream = Self {
+            path: path.to_owned(),
+            palette: header.palette,
+            chunk_size: header.chunk_size,
+            dim: voxels.dim() as usize,
+            total: header.chunks.len(),
+            loaded: 0,
+            focus,
+            rx,
+        };
+        Ok((voxels, stream))
+    }
+
+    /// chunks are loaded nearest to this world position first.
+    pub fn set_focus(&self, pos: &glm::Vec3) {
+        *self.focus.lock().unwrap() = *pos;
+    }
+
+    /// (loaded, total) number of chunks.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.loaded, self.total)
+    }
+
+    pub fn done(&self) -> bool {
+        self.loaded == self.total
+    }
+
+    /// up to `max` chunks that arrived since the last call.
+    pub fn poll(&mut self, max: usize) -> Result<Vec<Region>, Error> {
+        let mut regions = Vec::new();
+
+        while regions.len() < max {
+            match self.rx.try_recv() {
+                Ok(Message::Chunk(entry, chunk)) => match self.clip(&entry, &chunk) {
+                    Some(region) => {
+                        self.loaded += 1;
+                        regions.push(region);
+                    }
+                    None => {
+                        self.total = self.loaded;
+                        let err = chunks::Error::ChunkError(entry.pos);
+                        return Err(Error::ChunksError(self.path.clone(), err));
+                    }
+                },
+                Ok(Message::Error(err)) => {
+                    // stop reporting progress, the remaining chunks will not arrive.
+                    self.total = self.loaded;
+                    return Err(Error::FetchError(self.path.clone(), err));
+                }
+                Err(_) => break,
+            }
+        }
+
+        Ok(regions)
+    }
+
+    /// `None` if the chunk lies outside of the volume.
+    fn clip(&self, entry: &ChunkEntry, chunk: &Array3<u32>) -> Option<Region> {
+        let origin = entry.origin(self.chunk_size);
+        let [di, dj, dk] = origin.map(|o| match self.dim.checked_sub(o) {
+            Some(d) if d > 0 => Some(d.min(self.chunk_size)),
+            _ => None,
+        });
+        let (di, dj, dk) = (di?, dj?, dk?);
+        let voxels = chunk
+            .slice(s![..di, ..dj, ..dk])
+            .mapv(|v| v as VoxelsFormat);
+        let colors = colorize(&self.palette, &voxels);
+        Some(Region {
+            origin,
+            voxels,
+            colors,
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod source {
+    use std::{
+        fs::File,
+        io::{Read, Seek, SeekFrom},
+        thread,
+    };
+
+    use super::*;
+
+    pub(super) struct Source {
+        path: PathBuf,
+        file: File,
+        data_start: u64,
+    }
+
+    pub(super) async fn open(path: &Path) -> Result<(Header, Source), Error> {
+        let io_err = |e| Error::IOError(path.to_owned(), e);
+        let chunks_err = |e| Error::ChunksError(path.to_owned(), e);
+
+        let mut file = File::open(path).map_err(io_err)?;
+        let mut prefix = [0u8; chunks::PREFIX_LEN];
+        file.read_exact(&mut prefix).map_err(io_err)?;
+        let header_len = chunks::read_header_len(&prefix).map_err(chunks_err)?;
+        let file_len = file.metadata().map_err(io_err)?.len();
+        if (chunks::PREFIX_LEN + header_len) as u64 > file_len {
+            return Err(chunks_err(chunks::Error::HeaderLen(header_len as u64)));
+        }
+        let mut bytes = vec![0u8; header_len];
+        file.read_exact(&mut bytes).map_err(io_err)?;
+        let header = chunks::read_header(&bytes).map_err(chunks_err)?;
+
+        // the chunks are read with their length from the header, which must fit in the file.
+        let data_start = (chunks::PREFIX_LEN + header_len) as u64;
+        for entry in &header.chunks {
+            if !matches!(data_range(entry, data_start), Some((_, end)) if end <= file_len) {
+                return Err(chunks_err(chunks::Error::ChunkError(entry.pos)));
+            }
+        }
+
+        let source = Source {
+            path: path.to_owned(),
+            file,
+            data_start,
+        };
+        Ok((header, source))
+    }
+
+    impl Source {
+        fn read(
+            &mut self,
+            entry: &ChunkEntry,
+            chunk_size: usize,
+            palette_len: usize,
+        ) -> Result<Array3<u32>, String> {
+            // checked against the file length in `open`.
+            let mut data = vec![0u8; entry.len as usize];
+            self.file
+                .seek(SeekFrom::Start(self.data_start + entry.offset))
+                .and_then(|_| self.file.read_exact(&mut data))
+                .map_err(|e| format!("`{}`: {e}", self.path.display()))?;
+            decompress(entry, &data, chunk_size, palette_len)
+        }
+    }
+
+    pub(super) fn spawn_loader(
+        mut source: Source,
+        mut remaining: Vec<ChunkEntry>,
+        chunk_size: usize,
+        palette_len: usize,
+        focus: Arc<Mutex<glm::Vec3>>,
+        tx: Sender<Message>,
+    ) {
+        thread::spawn(move || {
+            while let Some(entry) = take_nearest(&mut remaining, &focus, chunk_size) {
+                let msg = match source.read(&entry, chunk_size, palette_len) {
+                    Ok(chunk) => Message::Chunk(entry, chunk),
+                    Err(err) => Message::Error(err),
+                };
+                let failed = matches!(msg, Message::Error(_));
+                // the receiver is dropped when the scene is replaced.
+                if tx.send(msg).is_err() || failed {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod source {
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+
+    use super::*;
+
+    pub(super) struct Source {
+        url: String,
+        data_start: u64,
+        /// the whole file, when the server does not support range requests.
+        whole: Option<Vec<u8>>,
+    }
+
+    fn js_err(err: JsValue) -> String {
+        err.as_string().unwrap_or_else(|| format!("{err:?}"))
+    }
+
+    /// fetch the bytes `[start, end)` of `url`. servers without range support answer with the
+    /// whole file, with status 200 instead of 206.
+    async fn fetch_range(url: &str, start: u64, end: u64) -> Result<(u16, Vec<u8>), String> {
+        let request = web_sys::Request::new_with_str(url).map_err(js_err)?;
+        request
+            .headers()
+            .set("Range", &format!("bytes={start}-{}", end - 1))
+            .map_err(js_err)?;
+
+        let window = web_sys::window().ok_or("no window")?;
+        let response: web_sys::Response = JsFuture::from(window.fetch_with_request(&request))
+            .await
+            .map_err(js_err)?
+            .dyn_into()
+            .map_err(js_err)?;
+        if !response.ok() {
+            return Err(format!("http status {}", response.status()));
+        }
+
+        let buffer = JsFuture::from(response.array_buffer().map_err(js_err)?)
+            .await
+            .map_err(js_err)?;
+        Ok((response.status(), js_sys::Uint8Array::new(&buffer).to_vec()))
+    }
+
+    pub(super) async fn open(path: &Path) -> Result<(Header, Source), Error> {
+        let url = path.to_string_lossy().into_owned();
+        let fetch_err = |e| Error::FetchError(path.to_owned(), e);
+        let chunks_err = |e| Error::ChunksError(path.to_owned(), e);
+
+        let (status, prefix) = fetch_range(&url, 0, chunks::PREFIX_LEN as u64)
+            .await
+            .map_err(fetch_err)?;
+        let header_len = chunks::read_header_len(&prefix).map_err(chunks_err)?;
+        let data_start = chunks::PREFIX_LEN + header_len;
+
+        let (whole, header_bytes) = if status == 200 {
+            log::warn!("the server does not support range requests, downloading `{url}` at once");
+            let header_bytes = prefix
+                .get(chunks::PREFIX_LEN..data_start)
+                .map(<[u8]>::to_vec);
+            (Some(prefix), header_bytes.unwrap_or_default())
+        } else {
+            let (_, bytes) = fetch_range(&url, chunks::PREFIX_LEN as u64, data_start as u64)
+                .await
+                .map_err(fetch_err)?;
+            (None, bytes)
+        };
+        let header = chunks::read_header(&header_bytes).map_err(chunks_err)?;
+
+        let source = Source {
+            url,
+            data_start: data_start as u64,
+            whole,
+        };
+        Ok((header, source))
+    }
+
+    impl Source {
+        async fn read(
+            &self,
+            entry: &ChunkEntry,
+            chunk_size: usize,
+            palette_len: usize,
+        ) -> Result<Array3<u32>, String> {
+            let invalid = || chunks::Error::ChunkError(entry.pos).to_string();
+            let (start, end) = data_range(entry, self.data_start).ok_or_else(invalid)?;
+            let data = match &self.whole {
+                Some(whole) => usize::try_from(start)
+                    .ok()
+                    .zip(usize::try_from(end).ok())
+                    .and_then(|(start, end)| whole.get(start..end))
+                    .ok_or("truncated file")?
+                    .to_vec(),
+                None => fetch_range(&self.url, start, end).await?.1,
+            };
+            decompress(entry, &data, chunk_size, palette_len)
+        }
+    }
+
+    pub(super) fn spawn_loader(
+        source: Source,
+        mut remaining: Vec<ChunkEntry>,
+        chunk_size: usize,
+        palette_len: usize,
+        focus: Arc<Mutex<glm::Vec3>>,
+        tx: Sender<Message>,
+    ) {
+        wasm_bindgen_futures::spawn_local(async move {
+            while let Some(entry) = take_nearest(&mut remaining, &focus, chunk_size) {
+                let msg = match source.read(&entry, chunk_size, palette_len).await {
+                    Ok(chunk) => Message::Chunk(entry, chunk),
+                    Err(err) => Message::Error(err),
+                };
+                let failed = matches!(msg, Message::Error(_));
+                // the receiver is dropped when the scene is replaced.
+                if tx.send(msg).is_err() || failed {
+                    return;
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::{Path, PathBuf};
+
+use nalgebra_glm as glm;
+
+use crate::{
+    brickmap::BrickMap,
+    budget::Fallback,
+    camera::{Camera, Controller},
+    collision::Collider,
+    editor::Editor,
+    scene::SceneMeta,
+    stream::SceneStream,
+    voxels::Voxels,
+    wgpu_util::{ShaderConstants, WgpuState},
+};
+
+// several scenes open in one window, to compare conversion settings or versions of a map by
+// switching tabs. each tab owns the gpu resources, the volume and the camera of its scene. the
+// device, the lights, the environment and the render settings are shared. the active tab lives in
+// `State` itself and the others are parked here, so the rest of the viewer only knows one scene,
+// see `State::switch_tab`.
+
+/// the part of `State` that belongs to a scene.
+pub struct SceneTab {
+    pub wgpu_state: WgpuState,
+    pub scene_path: PathBuf,
+    pub meta: SceneMeta,
+    pub voxels: Voxels,
+    pub spawn: (glm::Vec3, glm::Vec3),
+    pub stream: Option<SceneStream>,
+    pub bricks: Option<BrickMap>,
+    pub camera: Camera,
+    pub controller: Controller,
+    pub collider: Collider,
+    pub editor: Editor,
+    pub constants: ShaderConstants,
+    pub fallback: Option<Fallback>,
+    pub reduced_depth: bool,
+    pub sky_inputs: Vec<u8>,
+}
+
+pub struct Tabs {
+    /// the parked tabs, `None` at the index of the active one.
+    pub parked: Vec<Option<SceneTab>>,
+    pub active: usize,
+}
+
+impl Tabs {
+    pub fn new() -> Self {
+        Self {
+            parked: vec![None],
+            active: 0,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.parked.len()
+    }
+
+    /// the file name of the scene of each tab, `active` being the scene of the active one.
+    pub fn titles(&self, active: &Path) -> Vec<String> {
+        self.parked
+            .iter()
+            .map(|tab| {
+                let path = tab.as_ref().map_or(active, |tab| &tab.scene_path);
+                path.file_stem().map_or_else(
+                    || path.display().to_string(),
+                    |s| s.to_string_lossy().into(),
+                )
+            })
+            .collect()
+    }
+}
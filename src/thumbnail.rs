@@ -0,0 +1,232 @@
+use std::{iter, path::Path};
+
+use nalgebra_glm as glm;
+
+use crate::{
+    camera::Camera,
+    capture::{copy_texture, create_render_target},
+    cli::{Backend, View},
+    detail,
+    environment::Environment,
+    error::Error,
+    features,
+    fog::FogVolumes,
+    frustum::Frustum,
+    lights::Lights,
+    materials::Materials,
+    probes::Probes,
+    route::Route,
+    settings::Settings,
+    voxels::Voxels,
+    wgpu_util::{Buffers, MipPath, ShaderConstants, WgpuState},
+};
+
+// `wender thumbnail`: renders a preview image of a scene without opening a window.
+// the camera frames the bounding box of the scene from a three-quarter view, in perspective or
+// almost orthographic. the raymarcher only has perspective cameras, the orthographic view is a
+// narrow field of view from far away.
+// the offscreen setup is shared with `wender --headless`, see `headless.rs`.
+
+/// color format of the offscreen targets.
+pub(crate) const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// vertical field of view of `View::Orthographic`, in degrees.
+const ORTHOGRAPHIC_FOV: f32 = 1.0;
+
+/// render `scene` to the image `output` of `size`x`size` pixels. returns whether it succeeded.
+pub fn thumbnail(scene: &Path, output: &Path, size: u32, view: View, backend: Backend) -> bool {
+    match pollster::block_on(render(scene, output, size, view, backend)) {
+        Ok(()) => {
+            println!("wrote `{}`", output.display());
+            true
+        }
+        Err(err) => {
+            eprintln!("error: {err}\nhint: {}", err.hint());
+            false
+        }
+    }
+}
+
+/// a camera facing the center of the bounding box, far enough to see all of it.
+fn frame_camera(bounds: &glm::Vec3, size: u32, view: View) -> Camera {
+    let mut camera = Camera::new(glm::vec2(size as f32, size as f32));
+    camera.uniform.aspect = 1.0;
+    if let View::Orthographic = view {
+        camera.uniform.fov_y = ORTHOGRAPHIC_FOV.to_radians();
+    }
+
+    let center = bounds * 0.5;
+    let radius = glm::length(bounds) * 0.5;
+    let distance = radius / (camera.uniform.fov_y * 0.5).sin();
+    // three-quarter view, from above.
+    let view_dir = glm::normalize(&glm::vec3(1.0, 0.8, 1.0));
+    camera.set_pos(&(center + view_dir * distance));
+    camera.look_at(&center);
+    camera
+}
+
+/// a gpu device without a window surface, for the offscreen commands, and how it computes the
+/// color mipmaps.
+pub(crate) async fn request_device(
+    label: &str,
+    backend: Backend,
+) -> Result<(wgpu::Device, wgpu::Queue, MipPath), Error> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: backend.backends(),
+        ..Default::default()
+    });
+
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .await
+        .ok_or(Error::NoAdapter)?;
+    features::validate_adapter(&adapter)?;
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some(label),
+                required_features: adapter.features()
+                    & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                required_limits: adapter.limits(),
+            },
+            None,
+        )
+        .await?;
+    let mip_path = features::mip_path(&adapter, device.features());
+    Ok((device, queue, mip_path))
+}
+
+/// the render state of `voxels` for offscreen targets of `OFFSCREEN_FORMAT`, with the octree and
+/// mipmaps built.
+pub(crate) fn offscreen_state(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    voxels: &Voxels,
+    camera: &Camera,
+    width: u32,
+    height: u32,
+    mip_path: MipPath,
+) -> Result<WgpuState, Error> {
+    // the render pipeline only needs the color format of the surface.
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: OFFSCREEN_FORMAT,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+        desired_maximum_frame_latency: 2,
+        alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+        view_formats: vec![],
+    };
+    scene_state(device, queue, voxels, camera, &config, mip_path)
+}
+
+/// the render state of `voxels` with the default lighting, for targets of the format and size of
+/// `config`, with the octree and mipmaps built.
+pub(crate) fn scene_state(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    voxels: &Voxels,
+    camera: &Camera,
+    config: &wgpu::SurfaceConfiguration,
+    mip_path: MipPath,
+) -> Result<WgpuState, Error> {
+    let max_dim = device.limits().max_texture_dimension_3d;
+    if voxels.dim() > max_dim {
+        return Err(Error::SceneTooLarge {
+            dim: voxels.dim(),
+            max: max_dim,
+        });
+    }
+
+    let mut lights = Lights::new(
+        f32::to_degrees(glm::half_pi()),
+        f32::to_degrees(glm::quarter_pi()),
+    );
+    lights.update_local(&voxels.meta);
+    let route = Route::new();
+    let mut environment = Environment::new();
+    environment.update(&voxels.meta);
+    let frustum = Frustum::new();
+    let settings = Settings::new();
+    let probes = Probes::new();
+    let mut fog_volumes = FogVolumes::new();
+    fog_volumes.update(&voxels.meta);
+    let mut materials = Materials::new();
+    materials.update(&voxels.meta, voxels.palette());
+
+    let detail_atlas = detail::load_atlas(voxels);
+    let constants = ShaderConstants {
+        octree_depth: voxels.dim().ilog2() - 1,
+        baked_lighting: voxels.lightmap.is_some() as u32,
+        noise_seed: voxels.meta.noise_seed,
+        contours: voxels.meta.contours as u32,
+        detail_textures: detail_atlas.is_some() as u32,
+        detail_distance: voxels.meta.detail_distance,
+        ..Default::default()
+    };
+
+    let mut wgpu_state = WgpuState::new(
+        device,
+        queue,
+        config,
+        &Buffers {
+            camera: camera.as_bytes(),
+            lights: lights.as_bytes(),
+            local_lights: lights.local_bytes(),
+            route: route.as_bytes(),
+            environment: environment.as_bytes(),
+            frustum: frustum.as_bytes(),
+            settings: settings.as_bytes(),
+            probes: probes.as_bytes(),
+            fog_volumes: fog_volumes.as_bytes(),
+            materials: materials.as_bytes(),
+            voxels: voxels.voxels_bytes(),
+            colors: voxels.colors_bytes(),
+            lightmap: voxels.lightmap_bytes(),
+        },
+        &constants,
+        true,
+        mip_path,
+    )?;
+    if detail_atlas.is_some() {
+        wgpu_state.set_detail(device, queue, detail_atlas.as_ref());
+    }
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("offscreen build encoder"),
+    });
+    wgpu_state.compute_octree(device, &mut encoder, voxels.dim());
+    wgpu_state.compute_sdf(device, &mut encoder);
+    wgpu_state.compute_contours(device, &mut encoder);
+    wgpu_state.compute_mipmap(device, &mut encoder, voxels.dim());
+    queue.submit(iter::once(encoder.finish()));
+    Ok(wgpu_state)
+}
+
+async fn render(
+    scene: &Path,
+    output: &Path,
+    size: u32,
+    view: View,
+    backend: Backend,
+) -> Result<(), Error> {
+    let (device, queue, mip_path) = request_device("thumbnail device", backend).await?;
+    let voxels = Voxels::from_path(scene)?;
+    let camera = frame_camera(&voxels.bounds(), size, view);
+    let wgpu_state = offscreen_state(&device, &queue, &voxels, &camera, size, size, mip_path)?;
+
+    let target = create_render_target(&device, size, size, OFFSCREEN_FORMAT);
+    let view = target.create_view(&Default::default());
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("thumbnail encoder"),
+    });
+    wgpu_state.draw(&view, (size, size), &mut encoder);
+    let readback = copy_texture(&device, &mut encoder, &target);
+    queue.submit(iter::once(encoder.finish()));
+
+    readback.read(&device).save(output)?;
+    Ok(())
+}
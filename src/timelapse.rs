@@ -0,0 +1,123 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use web_time::Instant;
+
+use crate::voxels::{self, Voxels};
+
+// plays back an ordered sequence of .wvox snapshots (e.g. daily exports of a server map).
+// the next snapshot is loaded on a background thread while the current one is displayed,
+// so playback only swaps textures on the render thread. a seek loads its snapshot the same
+// way, the previous one stays displayed meanwhile.
+
+pub struct Timelapse {
+    pub frames: Vec<PathBuf>,
+    pub current: usize,
+    pub playing: bool,
+    pub interval: f32, // seconds per snapshot
+    pub dir: String,
+    requested: Option<usize>,
+    last_swap: Instant,
+    prefetch: Option<(usize, Receiver<Result<Voxels, voxels::Error>>)>,
+}
+
+impl Timelapse {
+    pub fn new() -> Self {
+        Self {
+            frames: Vec::new(),
+            current: 0,
+            playing: false,
+            interval: 1.0,
+            dir: String::new(),
+            requested: None,
+            last_swap: Instant::now(),
+            prefetch: None,
+        }
+    }
+
+    /// list the .wvox files of a directory, in lexicographic order.
+    pub fn open(&mut self, dir: &Path) -> std::io::Result<()> {
+        let mut frames = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map(|ext| ext == "wvox").unwrap_or(false))
+            .collect::<Vec<_>>();
+        frames.sort();
+
+        println!(
+            "timelapse: {} snapshots in `{}`",
+            frames.len(),
+            dir.display()
+        );
+        self.frames = frames;
+        self.current = 0;
+        self.playing = false;
+        self.prefetch = None;
+        self.requested = (!self.frames.is_empty()).then_some(0);
+        Ok(())
+    }
+
+    /// jump to a snapshot. it is swapped in by `update` once it is loaded.
+    pub fn seek(&mut self, index: usize) {
+        if index < self.frames.len() {
+            self.requested = Some(index);
+        }
+    }
+
+    fn start_prefetch(&mut self, index: usize) {
+        let path = self.frames[index].clone();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            tx.send(Voxels::from_path(&path)).ok();
+        });
+        self.prefetch = Some((index, rx));
+    }
+
+    /// returns the snapshot to display, if it changed.
+    pub fn update(&mut self) -> Option<Result<Voxels, voxels::Error>> {
+        let len = self.frames.len();
+
+        if self.playing
+            && len > 1
+            && self.requested.is_none()
+            && self.last_swap.elapsed().as_secs_f32() >= self.interval
+        {
+            self.requested = Some((self.current + 1) % len);
+        }
+
+        let index = self.requested?;
+
+        let voxels = match self.prefetch.take() {
+            Some((i, rx)) if i == index => match rx.try_recv() {
+                Ok(voxels) => voxels,
+                // still loading: keep displaying the current snapshot.
+                Err(TryRecvError::Empty) => {
+                    self.prefetch = Some((i, rx));
+                    return None;
+                }
+                Err(TryRecvError::Disconnected) => Err(voxels::Error::IOError(
+                    self.frames[index].clone(),
+                    std::io::Error::other("the loading thread panicked"),
+                )),
+            },
+            // a seek away from the prefetched snapshot.
+            _ => {
+                self.start_prefetch(index);
+                return None;
+            }
+        };
+
+        self.requested = None;
+        self.current = index;
+        self.last_swap = Instant::now();
+
+        if len > 1 {
+            self.start_prefetch((index + 1) % len);
+        }
+
+        Some(voxels)
+    }
+}
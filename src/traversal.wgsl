@@ -0,0 +1,100 @@
+#import "octree.wgsl"::{ raycast, raycast_culled, CastResult }
+#import "settings.wgsl"::{ feature_enabled, FEATURE_BRICK_CULLING }
+#import "ray.wgsl"::{ cam_pos, cam_ray_dir, to_volume }
+#import "sdf.wgsl"::{ raycast_sdf }
+#import "brickmap.wgsl"::{ raycast_bricks }
+#import "contree.wgsl"::{ raycast_contree }
+#import "contours.wgsl"::{ clip_contour }
+
+// this shader is a "module" supposed to be included.
+//
+// this module "exports":
+// fn trace_primary(screen_pos: vec2f) -> CastResult
+// fn trace_primary_from(screen_pos: vec2f, t_start: f32) -> CastResult
+// fn cube_face_normal(ipos: vec3i, pos: vec3f) -> vec3f
+//
+// this module "requires":
+// const TRAVERSAL: u32; // one of the `TRAVERSAL_*` constants below
+// const CONTOURS: u32; // 1: clip the hit voxels with their contour
+
+// max number of voxels skipped because the ray missed their contour.
+const CONTOUR_MAX_SKIPS: u32 = 4u;
+
+// `ray_pos` is in render space, like the position of the hit.
+fn cast_primary(ray_pos: vec3f, ray_dir: vec3f) -> CastResult {
+    var res = cast_volume(to_volume(ray_pos), ray_dir);
+    if res.hit {
+        res.pos = ray_pos + ray_dir * res.t;
+    }
+    return res;
+}
+
+// the values of `#TRAVERSAL`, see `TRAVERSAL_DVO` and the next constants in wgpu_util.rs.
+const TRAVERSAL_DVO: u32 = 0u;
+const TRAVERSAL_SDF: u32 = 1u;
+const TRAVERSAL_BRICKS: u32 = 2u;
+const TRAVERSAL_CONTREE: u32 = 3u;
+
+// the traversals work in world coordinates, the coordinates of the volume textures.
+fn cast_volume(ray_pos: vec3f, ray_dir: vec3f) -> CastResult {
+    if #TRAVERSAL == TRAVERSAL_SDF {
+        return raycast_sdf(ray_pos, ray_dir);
+    }
+    if #TRAVERSAL == TRAVERSAL_BRICKS {
+        return raycast_bricks(ray_pos, ray_dir);
+    }
+    if #TRAVERSAL == TRAVERSAL_CONTREE {
+        return raycast_contree(ray_pos, ray_dir);
+    }
+    if feature_enabled(FEATURE_BRICK_CULLING) {
+        return raycast_culled(ray_pos, ray_dir);
+    }
+    return raycast(ray_pos, ray_dir);
+}
+
+// cast the camera ray through `screen_pos` into the volume.
+fn trace_primary(screen_pos: vec2f) -> CastResult {
+    return trace_primary_from(screen_pos, 0.0);
+}
+
+// `trace_primary`, starting the ray at the distance `t_start` known to be in front of the first
+// hit, see `beam.wgsl`.
+fn trace_primary_from(screen_pos: vec2f, t_start: f32) -> CastResult {
+    let ray_dir = cam_ray_dir(screen_pos);
+    if #CONTOURS == 0u {
+        var res = cast_primary(cam_pos() + ray_dir * t_start, ray_dir);
+        res.t += t_start;
+        return res;
+    }
+
+    // when the ray passes beside the contour of the hit voxel, resume the traversal behind it.
+    var t = t_start;
+    var iter = 0u;
+    var res: CastResult;
+    for (var i = 0u; i <= CONTOUR_MAX_SKIPS; i++) {
+        res = cast_primary(cam_pos() + ray_dir * t, ray_dir);
+        res.iter += iter;
+        res.t += t;
+        if !res.hit {
+            return res;
+        }
+        let clip = clip_contour(res.voxel, to_volume(cam_pos()), ray_dir);
+        if clip.t_in <= clip.t_out {
+            res.t = max(clip.t_in, 0.0);
+            res.pos = cam_pos() + ray_dir * res.t;
+            res.normal = clip.normal;
+            return res;
+        }
+        t = clip.t_exit + 1e-3;
+        iter = res.iter;
+    }
+    return res;
+}
+
+// is pos is on a cube surface, returns the normal of the corresponding cube face.
+fn cube_face_normal(ipos: vec3i, pos: vec3f) -> vec3f {
+    let off = pos - vec3f(ipos) - 0.5; // value between [-0.5, 0.5]
+    let dist = abs(off);
+    let max_dist = max(max(dist.x, dist.y), dist.z);
+    return sign(off) * vec3f(dist == vec3f(max_dist));
+}
@@ -0,0 +1,155 @@
+use std::{
+    f32::consts::TAU,
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use nalgebra_glm as glm;
+
+use crate::{
+    camera::Camera,
+    capture::{copy_texture, create_render_target},
+    State,
+};
+
+// renders a camera orbit around the volume's bounding sphere and encodes it with ffmpeg.
+// the output container and codec are picked by ffmpeg from the file extension (.mp4, .webm, ...).
+
+pub struct Turntable {
+    pub seconds: f32,
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+    pub elevation: f32, // degrees
+    pub output: String,
+}
+
+impl Turntable {
+    pub fn new() -> Self {
+        Self {
+            seconds: 8.0,
+            fps: 30,
+            width: 1280,
+            height: 720,
+            elevation: 30.0,
+            output: "turntable.mp4".to_owned(),
+        }
+    }
+}
+
+fn spawn_ffmpeg(
+    width: u32,
+    height: u32,
+    fps: u32,
+    output: &str,
+) -> io::Result<std::process::Child> {
+    Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error"])
+        .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+        .args(["-s", &format!("{width}x{height}")])
+        .args(["-r", &fps.to_string()])
+        .args(["-i", "-"])
+        .args(["-pix_fmt", "yuv420p"])
+        .arg(output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                io::Error::new(io::ErrorKind::NotFound, "ffmpeg was not found in PATH")
+            }
+            _ => e,
+        })
+}
+
+pub fn export_turntable(state: &mut State) -> io::Result<()> {
+    let settings = &state.turntable;
+    // yuv420p requires even dimensions.
+    let (width, height) = (settings.width & !1, settings.height & !1);
+    let frames = (settings.seconds * settings.fps as f32).round().max(1.0) as u32;
+
+    let dim = 2u32.pow(state.constants.octree_depth + 1) as f32;
+    let center = glm::vec3(dim, dim, dim) * 0.5;
+    let radius = dim * 3f32.sqrt() * 0.5;
+
+    let mut camera = Camera::new(glm::vec2(width as f32, height as f32));
+    camera.uniform.aspect = width as f32 / height as f32;
+    camera.uniform.fov_y = state.camera.uniform.fov_y;
+    let fov = f32::min(
+        camera.uniform.fov_y,
+        camera.uniform.fov_y * camera.uniform.aspect,
+    );
+    let distance = radius / (fov * 0.5).sin();
+    let elevation = settings.elevation.to_radians();
+
+    let target = create_render_target(&state.device, width, height, state.config.format);
+    state
+        .wgpu_state
+        .reserve_gbuffer(&state.device, (width, height));
+    let view = target.create_view(&Default::default());
+
+    let mut ffmpeg = spawn_ffmpeg(width, height, settings.fps, &settings.output)?;
+    let mut stdin = ffmpeg.stdin.take().unwrap();
+
+    println!("exporting turntable: {frames} frames at {width}x{height}");
+
+    // ffmpeg is waited for even when writing a frame fails, e.g. when it exited early.
+    let written = (|| -> io::Result<()> {
+        for frame in 0..frames {
+            let angle = frame as f32 / frames as f32 * TAU;
+            camera.set_pos(
+                &(center
+                    + distance
+                        * glm::vec3(
+                            elevation.cos() * angle.cos(),
+                            elevation.sin(),
+                            elevation.cos() * angle.sin(),
+                        )),
+            );
+            camera.look_at(&center);
+            state
+                .wgpu_state
+                .write_cameras(&state.queue, &camera.uniform, &camera.uniform);
+
+            let mut encoder =
+                state
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("turntable encoder"),
+                    });
+            state.wgpu_state.draw(&view, (width, height), &mut encoder);
+            let readback = copy_texture(&state.device, &mut encoder, &target);
+            state.queue.submit(std::iter::once(encoder.finish()));
+
+            let image = readback.read(&state.device);
+            stdin.write_all(image.as_raw())?;
+        }
+        Ok(())
+    })();
+
+    drop(stdin);
+    let status = ffmpeg.wait();
+
+    // restore the interactive camera, and the g-buffer of the window.
+    state.wgpu_state.write_cameras(
+        &state.queue,
+        &state.camera.uniform,
+        state.frustum.render_camera(&state.camera),
+    );
+    state.update_render_size();
+
+    let status = status?;
+    match written {
+        Ok(()) if status.success() => {
+            println!("wrote `{}`", state.turntable.output);
+            Ok(())
+        }
+        Ok(()) => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("ffmpeg exited with {status}"),
+        )),
+        Err(err) => Err(io::Error::new(
+            err.kind(),
+            format!("failed to write the frames to ffmpeg ({err}), it exited with {status}"),
+        )),
+    }
+}
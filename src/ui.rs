@@ -1,8 +1,30 @@
-use std::time::{Duration, Instant};
+use std::path::PathBuf;
 
 use itertools::Itertools;
+use nalgebra_glm as glm;
+use web_time::{Duration, Instant};
 
-use crate::State;
+use crate::{
+    camera::{CameraMode, UpAxis},
+    clock,
+    dvo::Dvo,
+    editor::{Editor, SNAPS},
+    environment::{BackgroundMode, BackgroundPreset, GroundMode},
+    fog::{FogVolume, MAX_FOG_VOLUMES},
+    i18n::{self, tr, LANGUAGES},
+    lights::{LightKind, LightingPreset, LocalLight, MAX_LOCAL_LIGHTS},
+    materials::Surface,
+    palette::{run_action, Action},
+    palette_file::{self, Remap},
+    probes::MAX_PROBES,
+    settings::{Settings, FEATURES},
+    turntable::export_turntable,
+    viewlink::ViewLink,
+    wgpu_util::{
+        SliceSource, SLICE_SIZE, TRAVERSAL_BRICKS, TRAVERSAL_CONTREE, TRAVERSAL_DVO, TRAVERSAL_SDF,
+    },
+    State,
+};
 
 pub struct FpsCounter {
     history: [Instant; Self::HISTORY_SIZE],
@@ -41,16 +63,510 @@ impl FpsCounter {
     }
 }
 
+/// measures the distance between two marked camera positions, in meters.
+pub struct Measure {
+    a: Option<glm::Vec3>,
+    b: Option<glm::Vec3>,
+}
+
+impl Measure {
+    pub fn new() -> Self {
+        Self { a: None, b: None }
+    }
+}
+
+/// slices of the octree read back from the gpu, next to the cpu reference.
+pub struct DvoInspector {
+    dvo: Dvo,
+    reference: Dvo,
+    mismatches: Vec<usize>,
+    level: usize,
+    z: usize,
+    texture: Option<egui::TextureHandle>,
+}
+
+impl DvoInspector {
+    pub fn new(dvo: Dvo, reference: Dvo, mismatches: Vec<usize>) -> Self {
+        Self {
+            dvo,
+            reference,
+            mismatches,
+            level: 0,
+            z: 0,
+            texture: None,
+        }
+    }
+
+    /// the slice as an image: grey by number of solid octants, red where the nodes differ from
+    /// the reference.
+    fn slice_image(&self) -> egui::ColorImage {
+        let slice = self.dvo.slice(self.level, self.z);
+        let reference = self.reference.slice(self.level, self.z);
+        let (h, w) = slice.dim();
+        let pixels = slice
+            .iter()
+            .zip(reference.iter())
+            .map(|(node, reference)| {
+                let v = (node.count_ones() * 255 / 8) as u8;
+                if node == reference {
+                    egui::Color32::from_gray(v)
+                } else {
+                    egui::Color32::from_rgb(255, v / 2, v / 2)
+                }
+            })
+            .collect();
+        egui::ColorImage {
+            size: [w, h],
+            pixels,
+        }
+    }
+}
+
+/// a z slice of the voxels, dvo or colors texture, drawn on the gpu by `slice.wgsl`.
+pub struct SliceViewer {
+    pub open: bool,
+    source: SliceSource,
+    level: u32,
+    z: u32,
+    texture: Option<egui::TextureId>,
+}
+
+impl SliceViewer {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            source: SliceSource::Voxels,
+            level: 0,
+            z: 0,
+            texture: None,
+        }
+    }
+
+    /// free the texture of the slice, it is registered again for the current scene.
+    pub fn release(&mut self, renderer: &mut egui_wgpu::Renderer) {
+        if let Some(texture) = self.texture.take() {
+            renderer.free_texture(&texture);
+        }
+    }
+}
+
+/// a square of a palette color, outlined when selected.
+fn swatch(ui: &mut egui::Ui, rgba: [u8; 4], selected: bool) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(20.0, 20.0), egui::Sense::click());
+    let [r, g, b, _] = rgba;
+    ui.painter()
+        .rect_filled(rect, 2.0, egui::Color32::from_rgb(r, g, b));
+    if selected {
+        ui.painter()
+            .rect_stroke(rect, 2.0, ui.visuals().selection.stroke);
+    }
+    response
+}
+
+/// actions of the builder mode hud that need the whole state.
+enum HudAction {
+    ImportPalette,
+    ExportPalette,
+    SaveScene,
+}
+
+/// pick a palette file with the native file dialog, to open or to save.
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_palette(save: bool) -> Option<PathBuf> {
+    let dialog = rfd::FileDialog::new().add_filter("palette", &["hex", "pal", "png"]);
+    if save {
+        dialog.set_file_name("palette.hex").save_file()
+    } else {
+        dialog.pick_file()
+    }
+}
+
+/// there is no file system on the web.
+#[cfg(target_arch = "wasm32")]
+fn pick_palette(_save: bool) -> Option<PathBuf> {
+    None
+}
+
+/// the divider of the split-screen comparison, dragged to move the split.
+fn compare_divider(ctx: &egui::Context, split: &mut f32) {
+    let screen = ctx.screen_rect();
+    let x = screen.left() + screen.width() * *split;
+    let rect = egui::Rect::from_x_y_ranges(x - 4.0..=x + 4.0, screen.y_range());
+    let response = egui::Area::new(egui::Id::new("compare divider"))
+        .fixed_pos(rect.min)
+        .order(egui::Order::Background)
+        .show(ctx, |ui| {
+            ui.allocate_exact_size(rect.size(), egui::Sense::drag()).1
+        })
+        .inner;
+    if response.dragged() {
+        *split = (*split + response.drag_delta().x / screen.width()).clamp(0.0, 1.0);
+    }
+    if response.hovered() || response.dragged() {
+        ctx.set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+    }
+
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("compare labels"),
+    ));
+    painter.line_segment(
+        [rect.center_top(), rect.center_bottom()],
+        egui::Stroke::new(2.0, egui::Color32::WHITE),
+    );
+    for (label, offset, align) in [
+        ("A", -8.0, egui::Align2::RIGHT_TOP),
+        ("B", 8.0, egui::Align2::LEFT_TOP),
+    ] {
+        painter.text(
+            egui::pos2(x + offset, screen.top() + 8.0),
+            align,
+            label,
+            egui::FontId::proportional(16.0),
+            egui::Color32::WHITE,
+        );
+    }
+}
+
+/// the builder mode hud at the bottom of the screen, and a crosshair on the targeted voxel.
+fn editor_hud(ctx: &egui::Context, editor: &mut Editor) -> Option<HudAction> {
+    let mut action = None;
+    let center = ctx.screen_rect().center();
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("crosshair"),
+    ));
+    let stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+    painter.line_segment(
+        [center - egui::vec2(8.0, 0.0), center + egui::vec2(8.0, 0.0)],
+        stroke,
+    );
+    painter.line_segment(
+        [center - egui::vec2(0.0, 8.0), center + egui::vec2(0.0, 8.0)],
+        stroke,
+    );
+
+    egui::Area::new(egui::Id::new("builder hud"))
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -10.0))
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    if let Some(color) = editor.color(editor.material) {
+                        swatch(ui, color, true);
+                    }
+                    ui.label(format!("#{}", editor.material));
+                    ui.separator();
+                    ui.label(format!("{}: {}", tr("brush"), editor.brush))
+                        .on_hover_text(tr("[ and ] to resize"));
+                    ui.separator();
+                    ui.label(tr("snap")).on_hover_text(tr("g to cycle"));
+                    for snap in SNAPS {
+                        ui.selectable_value(&mut editor.snap, snap, snap.to_string());
+                    }
+                    ui.separator();
+                    match editor.target {
+                        Some(t) => ui
+                            .label(format!("{}: ({}, {}, {})", tr("target"), t.x, t.y, t.z))
+                            .on_hover_text(tr(
+                                "left click removes the brush, right click places it against the \
+                                 face. enter fills the brush, delete clears it",
+                            )),
+                        None => ui.label(tr("no target")),
+                    };
+                });
+
+                ui.horizontal(|ui| {
+                    for (n, material) in editor.recent.clone().into_iter().enumerate() {
+                        let color = editor.color(material).unwrap_or_default();
+                        let response = swatch(ui, color, material == editor.material)
+                            .on_hover_text(format!("{} (#{material})", n + 1));
+                        if response.clicked() {
+                            editor.select(material);
+                        }
+                    }
+                });
+
+                egui::CollapsingHeader::new(tr("palette")).show(ui, |ui| {
+                    egui::Grid::new("palette grid")
+                        .spacing(egui::vec2(2.0, 2.0))
+                        .show(ui, |ui| {
+                            for (i, color) in editor.palette.clone().into_iter().enumerate() {
+                                let material = i as u32 + 1;
+                                let response = swatch(ui, color, material == editor.material)
+                                    .on_hover_text(format!("#{material}"));
+                                if response.clicked() {
+                                    editor.select(material);
+                                }
+                                if i % 16 == 15 {
+                                    ui.end_row();
+                                }
+                            }
+                        });
+
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_label(tr("remap"))
+                            .selected_text(tr(&format!("{:?}", editor.remap)).to_owned())
+                            .show_ui(ui, |ui| {
+                                for remap in Remap::ALL {
+                                    ui.selectable_value(
+                                        &mut editor.remap,
+                                        remap,
+                                        tr(&format!("{remap:?}")),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(tr(
+                                "how an imported palette recolors the scene: by entry index, or \
+                                 with the nearest imported color",
+                            ));
+                        if ui.button(tr("import…")).clicked() {
+                            action = Some(HudAction::ImportPalette);
+                        }
+                        if ui.button(tr("export…")).clicked() {
+                            action = Some(HudAction::ExportPalette);
+                        }
+                    });
+                    if ui.button(tr("save scene")).clicked() {
+                        action = Some(HudAction::SaveScene);
+                    }
+                });
+            });
+        });
+    action
+}
+
+/// the egui theme, black and white with thick outlines in high contrast mode.
+pub fn visuals(high_contrast: bool) -> egui::Visuals {
+    let mut visuals = egui::Visuals::dark();
+    if !high_contrast {
+        return visuals;
+    }
+    visuals.override_text_color = Some(egui::Color32::WHITE);
+    visuals.window_fill = egui::Color32::BLACK;
+    visuals.panel_fill = egui::Color32::BLACK;
+    visuals.extreme_bg_color = egui::Color32::BLACK;
+    visuals.window_stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+    visuals.selection.bg_fill = egui::Color32::from_rgb(0, 90, 200);
+    visuals.selection.stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+    visuals.hyperlink_color = egui::Color32::from_rgb(120, 200, 255);
+    visuals.error_fg_color = egui::Color32::from_rgb(255, 120, 120);
+    visuals.warn_fg_color = egui::Color32::from_rgb(255, 220, 0);
+    let widgets = &mut visuals.widgets;
+    for widget in [
+        &mut widgets.noninteractive,
+        &mut widgets.inactive,
+        &mut widgets.hovered,
+        &mut widgets.active,
+        &mut widgets.open,
+    ] {
+        widget.bg_stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+        widget.fg_stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+    }
+    widgets.noninteractive.bg_fill = egui::Color32::BLACK;
+    widgets.inactive.bg_fill = egui::Color32::from_gray(20);
+    widgets.inactive.weak_bg_fill = egui::Color32::from_gray(20);
+    widgets.hovered.bg_fill = egui::Color32::from_gray(60);
+    widgets.hovered.weak_bg_fill = egui::Color32::from_gray(60);
+    widgets.active.bg_fill = egui::Color32::from_rgb(0, 90, 200);
+    widgets.active.weak_bg_fill = egui::Color32::from_rgb(0, 90, 200);
+    visuals
+}
+
+/// the sliders of the dvo levels of the shadow and ao rays, up to `max_level`.
+fn ray_levels(ui: &mut egui::Ui, settings: &mut Settings, max_level: u32) {
+    ui.add(
+        egui::Slider::new(&mut settings.uniform.shadow_level, 0..=max_level)
+            .text(tr("shadow ray level")),
+    )
+    .on_hover_text(tr(
+        "stop the hard shadow rays at octants of 2^level voxels: faster, but blockier shadows",
+    ));
+    ui.add(egui::Slider::new(&mut settings.uniform.ao_level, 0..=max_level).text(tr("ao level")))
+        .on_hover_text(tr(
+            "sample the ao from a coarser mip level: darkens larger creases",
+        ));
+}
+
+/// a window with a translated title. the id stays the same across languages, egui keys the
+/// persisted window layout by it.
+fn window(title: &'static str) -> egui::Window<'static> {
+    egui::Window::new(tr(title)).id(egui::Id::new(title))
+}
+
 pub fn run_egui(state: &mut State, egui_state: &mut egui_winit::State) -> egui::FullOutput {
     let raw_input = egui_state.take_egui_input(&state.window);
 
     state.fps.tick();
 
+    // actions that need the whole state run after the ui pass.
+    let mut export_requested = false;
+    let mut gif_requested = false;
+    let mut screenshot_requested = false;
+    let mut copy_frame_requested = false;
+    let mut bake_requested = None;
+    let mut clear_bake_requested = false;
+    let mut bake_probes_requested = false;
+    let mut contours_requested = None;
+    let mut export_dvo_requested = false;
+    let mut continue_requested = false;
+    let mut cancel_loading_requested = false;
+    let mut save_config_requested = false;
+    let mut palette_action = None;
+    let mut hud_action = None;
+    let mut tab_requested = None;
+    let mut close_tab_requested = None;
+    let mut open_tab_requested = false;
+
+    if state.slice_viewer.open && state.slice_viewer.texture.is_none() {
+        let view = state.wgpu_state.slice_view();
+        state.slice_viewer.texture = Some(state.egui_renderer.register_native_texture(
+            &state.device,
+            &view,
+            wgpu::FilterMode::Nearest,
+        ));
+    }
+
     let full_output = state.egui_ctx.run(raw_input, |ctx| {
         let fps = state.fps.durations();
         let avg_fps = 10000 / fps.iter().rev().take(10).sum::<Duration>().as_millis();
 
-        egui::Window::new("Debug").show(&ctx, |ui| {
+        if let Some(err) = &state.error {
+            let mut dismissed = false;
+            window("Error")
+                .collapsible(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(&ctx, |ui| {
+                    ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                    ui.label(tr(err.hint()));
+                    dismissed = ui.button(tr("dismiss")).clicked();
+                });
+            if dismissed {
+                state.error = None;
+            }
+        }
+
+        if let Some(notice) = &state.notice {
+            let mut dismissed = false;
+            window("Notice")
+                .collapsible(false)
+                .anchor(egui::Align2::CENTER_TOP, egui::Vec2::ZERO)
+                .show(&ctx, |ui| {
+                    ui.label(notice);
+                    dismissed = ui.button(tr("dismiss")).clicked();
+                });
+            if dismissed {
+                state.notice = None;
+            }
+        }
+
+        if !state.wgpu_state.shader_errors.is_empty() {
+            let mut dismissed = false;
+            window("Shader errors")
+                .default_width(600.0)
+                .show(&ctx, |ui| {
+                    ui.label(tr(
+                        "the previous shaders are kept until the errors are fixed.",
+                    ));
+                    egui::ScrollArea::vertical()
+                        .max_height(400.0)
+                        .show(ui, |ui| {
+                            for err in &state.wgpu_state.shader_errors {
+                                ui.separator();
+                                ui.colored_label(
+                                    ui.visuals().error_fg_color,
+                                    egui::RichText::new(err).monospace(),
+                                );
+                            }
+                        });
+                    dismissed = ui.button(tr("dismiss")).clicked();
+                });
+            if dismissed {
+                state.wgpu_state.shader_errors.clear();
+            }
+        }
+
+        palette_action = state.palette.show(&ctx);
+
+        if state.tabs.count() > 1 {
+            egui::TopBottomPanel::top("tabs").show(&ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let titles = state.tabs.titles(&state.scene_path);
+                    for (i, title) in titles.into_iter().enumerate() {
+                        if ui
+                            .selectable_label(i == state.tabs.active, title)
+                            .clicked()
+                        {
+                            tab_requested = Some(i);
+                        }
+                        if ui
+                            .small_button("×")
+                            .on_hover_text(tr("close tab"))
+                            .clicked()
+                        {
+                            close_tab_requested = Some(i);
+                        }
+                        ui.separator();
+                    }
+                    open_tab_requested = ui
+                        .button("+")
+                        .on_hover_text(tr("open a scene in a new tab"))
+                        .clicked();
+                });
+            });
+        }
+
+        if state.editor.enabled {
+            hud_action = editor_hud(&ctx, &mut state.editor);
+        }
+
+        if state.session_prompt {
+            window("Session")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(&ctx, |ui| {
+                    ui.label(tr("a previous session was found."));
+                    ui.horizontal(|ui| {
+                        continue_requested = ui.button(tr("continue last session")).clicked();
+                        if ui.button(tr("start fresh")).clicked() {
+                            state.session_prompt = false;
+                        }
+                    });
+                });
+        }
+
+        if let Some((loaded, total)) = state.loading_progress() {
+            window("Loading scene")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -20.0))
+                .show(&ctx, |ui| {
+                    let progress = loaded as f32 / total.max(1) as f32;
+                    ui.add(
+                        egui::ProgressBar::new(progress)
+                            .desired_width(240.0)
+                            .text(format!("{loaded} / {total} {}", tr("chunks"))),
+                    );
+                });
+        } else if let Some((stage, progress)) = state.scene_loading() {
+            window("Loading scene")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -20.0))
+                .show(&ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::ProgressBar::new(progress)
+                                .desired_width(240.0)
+                                .text(tr(stage.name())),
+                        );
+                        cancel_loading_requested = ui.button(tr("cancel")).clicked();
+                    });
+                });
+        }
+
+        window("Debug").show(&ctx, |ui| {
             egui_plot::Plot::new("FPS")
                 .height(100.0)
                 .include_y(0)
@@ -66,45 +582,1126 @@ pub fn run_egui(state: &mut State, egui_state: &mut egui_winit::State) -> egui::
                         .collect::<egui_plot::PlotPoints>();
                     ui.line(egui_plot::Line::new(points));
                 });
-            ui.label(format!("fps: {}", avg_fps));
-            ui.label(format!("cam: {:?}", state.camera.uniform.pos));
-            ui.label(format!("speed: {}", state.controller.speed));
+            ui.label(format!("{}: {}", tr("fps"), avg_fps));
+            let profiler = &mut state.wgpu_state.profiler;
+            ui.add_enabled(
+                profiler.supported(),
+                egui::Checkbox::new(&mut profiler.enabled, tr("gpu profiler")),
+            )
+            .on_hover_text(tr("gpu time of the passes, needs timestamp queries"));
+            ui.checkbox(&mut state.watchdog.enabled, tr("frame watchdog"))
+                .on_hover_text(tr("lower the quality when frames keep taking over 200 ms"));
+            if profiler.enabled {
+                egui::Grid::new("gpu timings").show(ui, |ui| {
+                    for (label, ms) in &profiler.timings {
+                        ui.label(tr(label));
+                        ui.label(format!("{ms:.2} ms"));
+                        ui.end_row();
+                    }
+                });
+            }
+            let pos = state.camera.pos();
+            ui.label(format!("{}: {:?}", tr("cam"), pos));
+            ui.label(format!(
+                "{}: ({:.1}, {:.1}, {:.1}) m",
+                tr("cam"),
+                state.meta.to_meters(pos.x),
+                state.meta.to_meters(pos.y),
+                state.meta.to_meters(pos.z),
+            ));
+            ui.label(format!(
+                "{}: {:.1} ({:.2} m/s)",
+                tr("speed"),
+                state.controller.speed,
+                state.meta.to_meters(state.controller.speed),
+            ));
+            ui.checkbox(&mut state.collisions, tr("camera collisions"));
+            let mut orbit = state.controller.mode == CameraMode::Orbit;
+            if ui
+                .checkbox(&mut orbit, tr("orbit camera"))
+                .on_hover_text(tr(
+                    "turn around the center of the view: middle drag rotates, shift pans, the wheel zooms",
+                ))
+                .changed()
+            {
+                state.set_camera_mode(match orbit {
+                    true => CameraMode::Orbit,
+                    false => CameraMode::Fly,
+                });
+            }
+            ui.checkbox(
+                &mut state.controller.free_flight,
+                tr("free flight (Q/E roll)"),
+            );
+            egui::ComboBox::from_label(tr("up axis"))
+                .selected_text(format!("{:?}", state.controller.up))
+                .show_ui(ui, |ui| {
+                    for axis in UpAxis::ALL {
+                        ui.selectable_value(&mut state.controller.up, axis, format!("{axis:?}"));
+                    }
+                })
+                .response
+                .on_hover_text(tr("for the scenes that are not y-up"));
+            ui.horizontal(|ui| {
+                if ui
+                    .button(tr("copy view link"))
+                    .on_hover_text(tr("the camera, lighting and features, to share this view"))
+                    .clicked()
+                {
+                    state.view_link = ViewLink::capture(state).encode();
+                    ui.output_mut(|o| o.copied_text = state.view_link.clone());
+                }
+                ui.text_edit_singleline(&mut state.view_link);
+                if ui.button(tr("go to view")).clicked() {
+                    match ViewLink::decode(&state.view_link) {
+                        Ok(link) => link.apply(state),
+                        Err(err) => state.notice = Some(err.to_string()),
+                    }
+                }
+            });
+            let mut frozen = state.frustum.is_frozen();
+            if ui
+                .checkbox(&mut frozen, tr("freeze render camera"))
+                .on_hover_text(tr(
+                    "fly a separate debug camera, the frozen frustum is drawn",
+                ))
+                .changed()
+            {
+                state.frustum.set_frozen(&state.camera, frozen);
+            }
+            if state.frustum.is_frozen() {
+                let render_pos = state.frustum.render_camera(&state.camera).world_pos();
+                let dist = glm::distance(&render_pos, &state.camera.pos());
+                ui.label(format!(
+                    "{}: {:.1} m",
+                    tr("render cam distance"),
+                    state.meta.to_meters(dist)
+                ));
+            }
+
+            ui.separator();
+            ui.checkbox(&mut state.history.enabled, tr("record frame history"));
+            ui.add(
+                egui::Slider::new(&mut state.history.seconds, 1.0..=30.0)
+                    .text(tr("history seconds")),
+            );
+            ui.horizontal(|ui| {
+                ui.label(format!("{} {}", state.history.len(), tr("frames")));
+                gif_requested = ui.button(tr("export gif (F10)")).clicked();
+            });
+            ui.horizontal(|ui| {
+                screenshot_requested = ui.button(tr("screenshot (F12)")).clicked();
+                copy_frame_requested = ui
+                    .button(tr("copy frame (F11)"))
+                    .on_hover_text(tr("copy the current view to the clipboard"))
+                    .clicked();
+            });
         });
 
-        egui::Window::new("Controls").show(&ctx, |ui| {
+        window("Controls").show(&ctx, |ui| {
+            let mut language = i18n::language();
+            egui::ComboBox::from_label(tr("language"))
+                .selected_text(LANGUAGES.iter().find(|(l, _)| *l == language).unwrap().1)
+                .show_ui(ui, |ui| {
+                    for (l, name) in LANGUAGES {
+                        ui.selectable_value(&mut language, l, name);
+                    }
+                });
+            i18n::set_language(language);
+            let mut ui_scale = ctx.zoom_factor();
+            let scale = ui.add(
+                egui::Slider::new(&mut ui_scale, 0.5..=3.0)
+                    .step_by(0.25)
+                    .text(tr("ui scale")),
+            );
+            if scale.drag_stopped() || (scale.changed() && !scale.dragged()) {
+                ctx.set_zoom_factor(ui_scale);
+            }
+            if ui
+                .checkbox(&mut state.high_contrast, tr("high contrast"))
+                .changed()
+            {
+                ctx.set_visuals(visuals(state.high_contrast));
+            }
+            if ui
+                .checkbox(
+                    &mut state.logical_render,
+                    tr("render at logical resolution"),
+                )
+                .on_hover_text(tr(
+                    "render fewer pixels on high dpi monitors, and upscale the image",
+                ))
+                .changed()
+            {
+                state.update_render_size();
+            }
+            ui.checkbox(&mut state.resolution.enabled, tr("dynamic resolution"))
+                .on_hover_text(tr(
+                    "lower the resolution when the frames are too slow for the target fps",
+                ));
+            if state.resolution.enabled {
+                ui.add(
+                    egui::Slider::new(&mut state.resolution.target_fps, 15.0..=144.0)
+                        .step_by(1.0)
+                        .text(tr("target fps")),
+                );
+                ui.label(format!(
+                    "{}: {}%",
+                    tr("render scale"),
+                    (state.resolution.scale * 100.0).round()
+                ));
+            }
+            save_config_requested = ui
+                .button(tr("save current settings"))
+                .on_hover_text(tr(
+                    "start with the window size, scene, camera and shader settings next time",
+                ))
+                .clicked();
             ui.add(
-                egui::Slider::new(&mut state.constants.octree_depth, 0..=10).text("octree depth"),
+                egui::Slider::new(&mut state.constants.octree_depth, 0..=10)
+                    .text(tr("octree depth")),
             );
             ui.add(
                 egui::Slider::new(&mut state.constants.octree_max_iter, 0..=1000)
-                    .text("octree max iter"),
+                    .text(tr("octree max iter")),
+            );
+            let modes = ["raymarched", "path traced"];
+            egui::ComboBox::from_label(tr("render mode"))
+                .selected_text(tr(modes[state.constants.render_mode as usize % modes.len()]))
+                .show_ui(ui, |ui| {
+                    for (i, name) in modes.iter().enumerate() {
+                        ui.selectable_value(&mut state.constants.render_mode, i as u32, tr(name));
+                    }
+                })
+                .response
+                .on_hover_text(tr(
+                    "path traced: a few bounces of light per pixel, averaged over the frames while the view is still",
+                ));
+            if state.constants.render_mode == 1 {
+                ui.label(format!(
+                    "{}: {}",
+                    tr("accumulated frames"),
+                    state.accumulation.frames()
+                ));
+            }
+            let traversals = [
+                (TRAVERSAL_DVO, "octree (dvo)"),
+                (TRAVERSAL_SDF, "distance field"),
+                (TRAVERSAL_BRICKS, "brick map"),
+                (TRAVERSAL_CONTREE, "64-tree"),
+            ];
+            let selected = traversals
+                .iter()
+                .find(|(traversal, _)| *traversal == state.constants.traversal)
+                .map_or("", |(_, name)| *name);
+            egui::ComboBox::from_label(tr("traversal"))
+                .selected_text(tr(selected))
+                .show_ui(ui, |ui| {
+                    for (traversal, name) in traversals {
+                        ui.selectable_value(&mut state.constants.traversal, traversal, tr(name));
+                    }
+                });
+            ui.add(
+                egui::Slider::new(&mut state.constants.sdf_max_iter, 0..=2000)
+                    .text(tr("distance field max iter")),
+            );
+            if let Some(bricks) = &mut state.bricks {
+                ui.add(
+                    egui::Slider::new(&mut bricks.radius, 64.0..=4096.0)
+                        .logarithmic(true)
+                        .text(tr("render distance")),
+                );
+                ui.label(format!(
+                    "{}: {}",
+                    tr("resident bricks"),
+                    bricks.resident_count()
+                ));
+                if bricks.has_pvs() {
+                    ui.checkbox(&mut bricks.culling, tr("visibility culling"))
+                        .on_hover_text(tr("skip the bricks not visible from the camera region"));
+                }
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .checkbox(&mut state.feedback.enabled, tr("iteration feedback"))
+                    .on_hover_text(tr("count the iterations of the primary rays"))
+                    .changed()
+                {
+                    state.constants.iter_feedback = state.feedback.enabled as u32;
+                    state
+                        .wgpu_state
+                        .reload_shaders(&state.device, &state.config, &state.constants);
+                }
+                ui.add_enabled(
+                    state.feedback.enabled,
+                    egui::Checkbox::new(&mut state.feedback.auto, tr("auto max iter")),
+                );
+            });
+            if let (true, Some(p99)) = (state.feedback.enabled, state.feedback.p99) {
+                ui.label(format!("{}: {p99}", tr("iterations p99")));
+            }
+            ui.add(
+                egui::Slider::new(&mut state.constants.grid_depth, 0..=10).text(tr("grid depth")),
             );
-            ui.add(egui::Slider::new(&mut state.constants.grid_depth, 0..=10).text("grid depth"));
             ui.add(
                 egui::Slider::new(&mut state.constants.grid_max_iter, 0..=1000)
-                    .text("grid max iter"),
+                    .text(tr("grid max iter")),
             );
             ui.add(
                 egui::Slider::new(&mut state.constants.shadow_max_iter, 0..=1000)
-                    .text("shadow max iter"),
+                    .text(tr("shadow max iter")),
             );
             ui.add(
                 egui::Slider::new(&mut state.constants.shadow_cone_angle, 0..=180)
-                    .text("shadow cone angle"),
+                    .text(tr("shadow cone angle")),
             );
             ui.add(
                 egui::Slider::new(&mut state.constants.shadow_strength, 0..=20)
-                    .text("shadow strength"),
+                    .text(tr("shadow strength")),
             );
-            ui.add(egui::Slider::new(&mut state.constants.ao_strength, 0..=20).text("ao strength"));
             ui.add(
-                egui::Slider::new(&mut state.constants.debug_display, 0..=3).text("debug display"),
+                egui::Slider::new(&mut state.constants.ao_strength, 0..=20).text(tr("ao strength")),
+            );
+            ui.add(
+                egui::Slider::new(&mut state.constants.debug_display, 0..=4)
+                    .text(tr("debug display")),
+            )
+            .on_hover_text(tr(
+                "1: ray complexity, 2: depth, 3: normals, 4: volume aabb entry and exit",
+            ));
+            let palettes = ["channels", "viridis", "cividis"];
+            let mut palette = state.constants.debug_palette;
+            egui::ComboBox::from_label(tr("debug colors"))
+                .selected_text(tr(palettes[palette as usize % palettes.len()]))
+                .show_ui(ui, |ui| {
+                    for (i, name) in palettes.iter().enumerate() {
+                        ui.selectable_value(&mut palette, i as u32, tr(name));
+                    }
+                })
+                .response
+                .on_hover_text(tr("viridis and cividis stay readable with color blindness"));
+            if palette != state.constants.debug_palette {
+                state.constants.debug_palette = palette;
+                state
+                    .wgpu_state
+                    .reload_shaders(&state.device, &state.config, &state.constants);
+            }
+            let mut show_misses = state.constants.show_aabb_misses != 0;
+            if ui
+                .checkbox(&mut show_misses, tr("highlight rays missing the volume"))
+                .on_hover_text(tr(
+                    "rays that never enter the bounding box of the scene are tinted magenta",
+                ))
+                .changed()
+            {
+                state.constants.show_aabb_misses = show_misses as u32;
+                state
+                    .wgpu_state
+                    .reload_shaders(&state.device, &state.config, &state.constants);
+            }
+            ui.checkbox(&mut state.gizmos.show_bounds, tr("show bounds"))
+                .on_hover_text(tr(
+                    "outline the volume, the fog volumes and the local lights over the scene",
+                ));
+            ui.toggle_value(&mut state.slice_viewer.open, tr("slice viewer"));
+            export_dvo_requested = ui
+                .button(tr("export octree"))
+                .on_hover_text(tr(
+                    "save the octree built on the gpu and compare it with the cpu reference",
+                ))
+                .clicked();
+            ui.collapsing(tr("features"), |ui| {
+                for (name, feature) in FEATURES {
+                    let mut enabled = state.settings.enabled(feature);
+                    if ui.checkbox(&mut enabled, tr(name)).changed() {
+                        state.settings.set_enabled(feature, enabled);
+                    }
+                }
+            });
+            ray_levels(ui, &mut state.settings, state.constants.octree_depth);
+            ui.add(
+                egui::Slider::new(&mut state.constants.msaa_level, 0..=4).text(tr("MSAA level")),
             );
-            ui.add(egui::Slider::new(&mut state.constants.msaa_level, 0..=4).text("MSAA level"));
-            ui.add(egui::Slider::new(&mut state.lights.angle, 0.0..=360.0).text("angle"));
-            ui.add(egui::Slider::new(&mut state.lights.azimuth, 0.0..=90.0).text("azimuth"));
+            let mut footprint_lod = state.constants.footprint_lod != 0;
+            if ui
+                .checkbox(&mut footprint_lod, tr("mip anti-aliasing"))
+                .on_hover_text(tr(
+                    "average the colors of the distant voxels covered by each pixel",
+                ))
+                .changed()
+            {
+                state.constants.footprint_lod = footprint_lod as u32;
+            }
+            let filters = ["box", "gaussian", "occupancy-weighted", "dominant color"];
+            egui::ComboBox::from_label(tr("mipmap filter"))
+                .selected_text(tr(filters[state.constants.mip_filter as usize % filters.len()]))
+                .show_ui(ui, |ui| {
+                    for (i, name) in filters.iter().enumerate() {
+                        ui.selectable_value(&mut state.constants.mip_filter, i as u32, tr(name));
+                    }
+                })
+                .response
+                .on_hover_text(tr(
+                    "how the distant colors are downsampled. dominant color keeps the flat colors of stylized scenes",
+                ));
+            ui.add(egui::Slider::new(&mut state.lights.angle, 0.0..=360.0).text(tr("angle")));
+            ui.add(egui::Slider::new(&mut state.lights.azimuth, -20.0..=90.0).text(tr("azimuth")))
+                .on_hover_text(tr("below 0, the sun has set and the sky turns to night"));
+            ui.add(
+                egui::Slider::new(&mut state.lights.uniform.turbidity, 2.0..=10.0)
+                    .text(tr("turbidity")),
+            )
+            .on_hover_text(tr("haze of the sky, from clear to hazy"));
+            ui.add(
+                egui::Slider::new(&mut state.lights.sun_speed, -30.0..=30.0).text(tr("sun speed")),
+            )
+            .on_hover_text(tr("degrees per second of scene time"));
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::Slider::new(&mut state.clock.scale, 0.0..=clock::MAX_SCALE)
+                        .text(tr("time scale")),
+                )
+                .on_hover_text(tr("speed of the animations, - and = halve and double it"));
+                ui.checkbox(&mut state.clock.paused, tr("pause (P)"));
+            });
+            ui.toggle_value(&mut state.placing_sun, tr("place the sun"))
+                .on_hover_text(tr(
+                    "click in the viewport to point the sun towards the cursor",
+                ));
+            ui.collapsing(tr("light shadows"), |ui| {
+                let sun = &mut state.lights.uniform.sun;
+                let mut shadow = sun.shadow != 0;
+                if ui.checkbox(&mut shadow, tr("sun shadows")).changed() {
+                    sun.shadow = shadow as u32;
+                }
+                ui.add(
+                    egui::Slider::new(&mut sun.shadow_max_iter, 0..=1000)
+                        .text(tr("sun shadow max iter")),
+                );
+                ui.add(
+                    egui::Slider::new(&mut sun.shadow_softness, 0.0..=50.0)
+                        .text(tr("sun shadow softness")),
+                );
+                ui.add(
+                    egui::Slider::new(&mut state.lights.uniform.shadow_budget, 0..=2000)
+                        .text(tr("shadow budget")),
+                )
+                .on_hover_text(tr("iterations shared by all the lights that cast shadows"));
+            });
+        });
+
+        window("Compare").show(&ctx, |ui| {
+            let mut enabled = state.compare.enabled;
+            if ui
+                .checkbox(&mut enabled, tr("split-screen comparison"))
+                .on_hover_text(tr(
+                    "the right of the divider is rendered with the settings below",
+                ))
+                .changed()
+            {
+                match enabled {
+                    true => state.compare.start(&state.settings, &state.constants),
+                    false => state.compare.enabled = false,
+                }
+            }
+            if !state.compare.enabled {
+                return;
+            }
+            let b = &mut state.compare;
+            ui.add(egui::Slider::new(&mut b.split, 0.0..=1.0).text(tr("divider")));
+            ui.collapsing(tr("features"), |ui| {
+                for (name, feature) in FEATURES {
+                    let mut enabled = b.settings.enabled(feature);
+                    if ui.checkbox(&mut enabled, tr(name)).changed() {
+                        b.settings.set_enabled(feature, enabled);
+                    }
+                }
+            });
+            ray_levels(ui, &mut b.settings, b.constants.octree_depth);
+            let constants = &mut b.constants;
+            ui.add(
+                egui::Slider::new(&mut constants.octree_max_iter, 0..=1000)
+                    .text(tr("octree max iter")),
+            );
+            ui.add(
+                egui::Slider::new(&mut constants.shadow_max_iter, 0..=1000)
+                    .text(tr("shadow max iter")),
+            );
+            ui.add(
+                egui::Slider::new(&mut constants.shadow_cone_angle, 0..=180)
+                    .text(tr("shadow cone angle")),
+            );
+            ui.add(
+                egui::Slider::new(&mut constants.shadow_strength, 0..=20)
+                    .text(tr("shadow strength")),
+            );
+            ui.add(egui::Slider::new(&mut constants.ao_strength, 0..=20).text(tr("ao strength")));
+            ui.add(egui::Slider::new(&mut constants.msaa_level, 0..=4).text(tr("MSAA level")));
+            let modes = ["raymarched", "path traced"];
+            egui::ComboBox::from_label(tr("render mode"))
+                .selected_text(tr(modes[constants.render_mode as usize % modes.len()]))
+                .show_ui(ui, |ui| {
+                    for (i, name) in modes.iter().enumerate() {
+                        ui.selectable_value(&mut constants.render_mode, i as u32, tr(name));
+                    }
+                });
+            let mut footprint_lod = constants.footprint_lod != 0;
+            if ui
+                .checkbox(&mut footprint_lod, tr("mip anti-aliasing"))
+                .changed()
+            {
+                constants.footprint_lod = footprint_lod as u32;
+            }
+        });
+
+        if state.compare.enabled {
+            compare_divider(&ctx, &mut state.compare.split);
+        }
+
+        window("Baked lighting").show(&ctx, |ui| {
+            ui.label(if state.constants.baked_lighting == 1 {
+                tr("using baked lighting")
+            } else {
+                tr("using realtime lighting")
+            });
+            ui.label(tr("baking uses the current sun direction."));
+            ui.horizontal(|ui| {
+                if ui.button(tr("bake")).clicked() {
+                    bake_requested = Some(false);
+                }
+                if ui.button(tr("bake and write into .wvox")).clicked() {
+                    bake_requested = Some(true);
+                }
+                clear_bake_requested = ui.button(tr("clear")).clicked();
+            });
+        });
+
+        window("Reflection probes").show(&ctx, |ui| {
+            ui.label(format!(
+                "{}: {}/{} ({} {})",
+                tr("probes"),
+                state.probes.positions.len(),
+                MAX_PROBES,
+                state.probes.uniform.count,
+                tr("baked"),
+            ));
+            ui.horizontal(|ui| {
+                let full = state.probes.positions.len() >= MAX_PROBES;
+                if ui
+                    .add_enabled(!full, egui::Button::new(tr("place at camera")))
+                    .clicked()
+                {
+                    state.probes.add(state.camera.pos());
+                }
+                bake_probes_requested = ui.button(tr("bake")).clicked();
+                if ui.button(tr("clear")).clicked() {
+                    state.probes.clear();
+                }
+            });
+            ui.add(
+                egui::Slider::new(&mut state.probes.uniform.strength, 0.0..=1.0)
+                    .text(tr("reflection strength")),
+            );
+        });
+
+        window("Measure").show(&ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button(tr("mark A")).clicked() {
+                    state.measure.a = Some(state.camera.pos());
+                }
+                if ui.button(tr("mark B")).clicked() {
+                    state.measure.b = Some(state.camera.pos());
+                }
+            });
+            if let (Some(a), Some(b)) = (state.measure.a, state.measure.b) {
+                let dist = glm::distance(&a, &b);
+                ui.label(format!(
+                    "{}: {:.2} m ({:.1} {})",
+                    tr("distance"),
+                    state.meta.to_meters(dist),
+                    dist,
+                    tr("voxels"),
+                ));
+            }
+            ui.separator();
+            ui.add(
+                egui::Slider::new(&mut state.meta.voxels_per_meter, 0.1..=64.0)
+                    .logarithmic(true)
+                    .text(tr("voxels per meter")),
+            );
+            let seed =
+                ui.add(egui::DragValue::new(&mut state.meta.noise_seed).prefix(tr("noise seed: ")));
+            if seed.changed() {
+                state.constants.noise_seed = state.meta.noise_seed;
+            }
+            if state.constants.detail_textures != 0 {
+                let detail = ui.add(
+                    egui::Slider::new(&mut state.meta.detail_distance, 4..=256)
+                        .logarithmic(true)
+                        .text(tr("detail textures distance")),
+                );
+                if detail.changed() {
+                    state.constants.detail_distance = state.meta.detail_distance;
+                }
+            }
+            // the palette entry selected in the builder mode.
+            let material = state.editor.material as usize;
+            let mut amplitude = state
+                .meta
+                .detail_noise
+                .get(material - 1)
+                .copied()
+                .unwrap_or(0.0);
+            if ui
+                .add(
+                    egui::Slider::new(&mut amplitude, 0.0..=1.0)
+                        .text(tr("detail noise of the selected material")),
+                )
+                .changed()
+            {
+                let noise = &mut state.meta.detail_noise;
+                noise.resize(noise.len().max(material), 0.0);
+                noise[material - 1] = amplitude;
+            }
+            let mut water = state.meta.water.contains(&(material as u32));
+            if ui
+                .checkbox(&mut water, tr("the selected material is water"))
+                .on_hover_text(tr("foam along the shores and brighter shallow water"))
+                .changed()
+            {
+                state.meta.water.retain(|m| *m != material as u32);
+                if water {
+                    state.meta.water.push(material as u32);
+                }
+            }
+            let mut surface = state
+                .meta
+                .surfaces
+                .iter()
+                .find(|s| s.entry == material as u32)
+                .cloned()
+                .unwrap_or(Surface {
+                    entry: material as u32,
+                    ..Default::default()
+                });
+            let mut changed = false;
+            changed |= ui
+                .add(egui::Slider::new(&mut surface.roughness, 0.0..=1.0).text(tr("roughness")))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut surface.metalness, 0.0..=1.0).text(tr("metalness")))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut surface.emissive, 0.0..=8.0).text(tr("emission")))
+                .changed();
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut surface.ior, 1.0..=3.0)
+                        .text(tr("index of refraction")),
+                )
+                .changed();
+            if changed {
+                state.meta.surfaces.retain(|s| s.entry != surface.entry);
+                state.meta.surfaces.push(surface);
+            }
+            let mut contours = state.meta.contours;
+            if ui
+                .checkbox(&mut contours, tr("smooth contours"))
+                .on_hover_text(tr(
+                    "clip the surface voxels with a plane fitted to their neighbors",
+                ))
+                .changed()
+            {
+                contours_requested = Some(contours);
+            }
+            if ui.button(tr("save scene metadata")).clicked() {
+                if let Err(err) = state.meta.save(&state.scene_path) {
+                    eprintln!("failed to save scene metadata: {}", err);
+                }
+            }
+        });
+
+        window("Route").show(&ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut state.route.file);
+                if ui.button(tr("load")).clicked() {
+                    let path = PathBuf::from(&state.route.file);
+                    match state.route.load(&path) {
+                        Ok(()) => state.queue.write_buffer(
+                            &state.wgpu_state.route_points_buffer,
+                            0,
+                            state.route.points_bytes(),
+                        ),
+                        Err(err) => eprintln!("{}", err),
+                    }
+                }
+            });
+            ui.label(format!("{}: {}", tr("points"), state.route.points.len()));
+            ui.checkbox(&mut state.route.visible, tr("visible"));
+            ui.horizontal(|ui| {
+                let mut color: [f32; 3] = state.route.uniform.color.into();
+                ui.color_edit_button_rgb(&mut color);
+                state.route.uniform.color = color.into();
+                ui.label(tr("color"));
+            });
+            ui.add(
+                egui::Slider::new(&mut state.route.uniform.width, 0.05..=10.0).text(tr("width")),
+            );
+        });
+
+        window("Environment").show(&ctx, |ui| {
+            let selected = match state.meta.lighting {
+                Some(preset) => tr(&format!("{:?}", preset)).to_owned(),
+                None => tr("None").to_owned(),
+            };
+            egui::ComboBox::from_label(tr("lighting"))
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for preset in LightingPreset::ALL {
+                        let name = tr(&format!("{:?}", preset)).to_owned();
+                        let selected = state.meta.lighting == Some(preset);
+                        if ui.selectable_label(selected, name).clicked() {
+                            preset.apply(&mut state.lights, &mut state.environment);
+                            state.meta.lighting = Some(preset);
+                        }
+                    }
+                })
+                .response
+                .on_hover_text(tr("stored with the scene metadata, see the Measure window"));
+            ui.add(
+                egui::Slider::new(&mut state.lights.uniform.ambient, 0.0..=4.0).text(tr("ambient")),
+            );
+            ui.add_enabled(
+                !state.exposure.enabled,
+                egui::Slider::new(&mut state.lights.uniform.exposure, 0.1..=4.0)
+                    .logarithmic(true)
+                    .text(tr("exposure")),
+            );
+            if ui
+                .checkbox(&mut state.exposure.enabled, tr("auto exposure"))
+                .on_hover_text(tr("adapt the exposure to the brightness of the view"))
+                .changed()
+            {
+                state.constants.auto_exposure = state.exposure.enabled as u32;
+                state
+                    .wgpu_state
+                    .reload_shaders(&state.device, &state.config, &state.constants);
+            }
+            if state.exposure.enabled {
+                let exposure = &mut state.exposure;
+                ui.add(egui::Slider::new(&mut exposure.min_ev, -8.0..=8.0).text(tr("min EV")));
+                ui.add(egui::Slider::new(&mut exposure.max_ev, -8.0..=8.0).text(tr("max EV")));
+                ui.add(
+                    egui::Slider::new(&mut exposure.speed, 0.1..=10.0)
+                        .logarithmic(true)
+                        .text(tr("adaptation speed")),
+                );
+            }
+
+            ui.separator();
+            let env = &mut state.environment;
+            egui::ComboBox::from_label(tr("background"))
+                .selected_text(tr(&format!("{:?}", env.background_mode)).to_owned())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut env.background_mode,
+                        BackgroundMode::Solid,
+                        tr("Solid"),
+                    );
+                    ui.selectable_value(
+                        &mut env.background_mode,
+                        BackgroundMode::Gradient,
+                        tr("Gradient"),
+                    );
+                    ui.selectable_value(&mut env.background_mode, BackgroundMode::Sky, tr("Sky"));
+                });
+            ui.horizontal(|ui| {
+                let mut color: [f32; 3] = env.uniform.background_color.into();
+                ui.color_edit_button_rgb(&mut color);
+                env.uniform.background_color = color.into();
+                let mut color: [f32; 3] = env.uniform.background_horizon.into();
+                ui.color_edit_button_rgb(&mut color);
+                env.uniform.background_horizon = color.into();
+                ui.label(tr("background colors"));
+            });
+            ui.horizontal(|ui| {
+                for preset in BackgroundPreset::ALL {
+                    if ui.button(tr(&format!("{:?}", preset))).clicked() {
+                        env.apply_preset(preset);
+                    }
+                }
+            });
+
+            ui.separator();
+            egui::ComboBox::from_label(tr("ground"))
+                .selected_text(tr(&format!("{:?}", env.ground_mode)).to_owned())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut env.ground_mode, GroundMode::None, tr("None"));
+                    ui.selectable_value(&mut env.ground_mode, GroundMode::Flat, tr("Flat"));
+                    ui.selectable_value(&mut env.ground_mode, GroundMode::Checker, tr("Checker"));
+                });
+            ui.horizontal(|ui| {
+                let mut color: [f32; 3] = env.uniform.ground_color.into();
+                ui.color_edit_button_rgb(&mut color);
+                env.uniform.ground_color = color.into();
+                let mut color: [f32; 3] = env.uniform.checker_color.into();
+                ui.color_edit_button_rgb(&mut color);
+                env.uniform.checker_color = color.into();
+                ui.label(tr("ground colors"));
+            });
+            ui.add(
+                egui::Slider::new(&mut env.checker_size, 0.1..=100.0)
+                    .logarithmic(true)
+                    .text(tr("checker size (m)")),
+            );
+            ui.add(
+                egui::DragValue::new(&mut env.uniform.ground_height)
+                    .prefix(tr("ground height: "))
+                    .suffix(tr(" voxels")),
+            );
+
+            ui.separator();
+            ui.checkbox(&mut env.fog_enabled, tr("horizon fog"));
+            ui.horizontal(|ui| {
+                let mut color: [f32; 3] = env.uniform.fog_color.into();
+                ui.color_edit_button_rgb(&mut color);
+                env.uniform.fog_color = color.into();
+                ui.label(tr("fog color"));
+            });
+            ui.add(
+                egui::Slider::new(&mut env.fog_distance, 1.0..=10000.0)
+                    .logarithmic(true)
+                    .text(tr("fog distance (m)")),
+            );
+        });
+
+        window("Lights").show(&ctx, |ui| {
+            let mut removed = None;
+            for (i, light) in state.meta.lights.iter_mut().enumerate() {
+                ui.collapsing(format!("{} {}", tr("light"), i + 1), |ui| {
+                    egui::ComboBox::from_label(tr("kind"))
+                        .selected_text(tr(light.kind.name()))
+                        .show_ui(ui, |ui| {
+                            for kind in LightKind::ALL {
+                                ui.selectable_value(&mut light.kind, kind, tr(kind.name()));
+                            }
+                        });
+                    let mut vectors = vec![("direction", &mut light.dir)];
+                    if light.kind != LightKind::Directional {
+                        vectors.insert(0, ("position", &mut light.pos));
+                    }
+                    for (label, v) in vectors {
+                        ui.horizontal(|ui| {
+                            for c in v.iter_mut() {
+                                ui.add(egui::DragValue::new(c).speed(0.1));
+                            }
+                            ui.label(tr(label));
+                        });
+                    }
+                    if ui.button(tr("move to camera")).clicked() {
+                        light.pos = state.camera.pos().into();
+                        light.dir = state.camera.ray_dir(&glm::vec2(0.0, 0.0)).into();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.color_edit_button_rgb(&mut light.color);
+                        ui.label(tr("color"));
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut light.intensity, 0.01..=100.0)
+                            .logarithmic(true)
+                            .text(tr("intensity")),
+                    );
+                    if light.kind != LightKind::Directional {
+                        ui.add(
+                            egui::Slider::new(&mut light.radius, 0.5..=100.0)
+                                .logarithmic(true)
+                                .text(tr("radius (m)")),
+                        );
+                    }
+                    if light.kind == LightKind::Spot {
+                        ui.add(egui::Slider::new(&mut light.cone, 1.0..=89.0).text(tr("cone")));
+                    }
+                    ui.checkbox(&mut light.shadow, tr("shadows"));
+                    if light.shadow {
+                        ui.add(
+                            egui::Slider::new(&mut light.shadow_max_iter, 0..=1000)
+                                .text(tr("shadow max iter")),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut light.shadow_softness, 0.0..=50.0)
+                                .text(tr("shadow softness")),
+                        );
+                    }
+                    if ui.button(tr("remove")).clicked() {
+                        removed = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = removed {
+                state.meta.lights.remove(i);
+            }
+
+            let full = state.meta.lights.len() >= MAX_LOCAL_LIGHTS;
+            if ui
+                .add_enabled(!full, egui::Button::new(tr("add at camera")))
+                .clicked()
+            {
+                let light = LocalLight::at(&state.camera.pos());
+                state.meta.lights.push(light);
+            }
+            ui.weak(tr("stored with the scene metadata, see the Measure window"));
+        });
+
+        window("Fog volumes").show(&ctx, |ui| {
+            let mut removed = None;
+            for (i, volume) in state.meta.fog_volumes.iter_mut().enumerate() {
+                ui.collapsing(format!("{} {}", tr("volume"), i + 1), |ui| {
+                    for (label, corner) in [("min", &mut volume.min), ("max", &mut volume.max)] {
+                        ui.horizontal(|ui| {
+                            for v in corner.iter_mut() {
+                                ui.add(egui::DragValue::new(v).speed(0.5));
+                            }
+                            ui.label(tr(label));
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        ui.color_edit_button_rgb(&mut volume.color);
+                        ui.label(tr("color"));
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut volume.density, 0.001..=2.0)
+                            .logarithmic(true)
+                            .text(tr("density (per m)")),
+                    );
+                    if ui.button(tr("remove")).clicked() {
+                        removed = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = removed {
+                state.meta.fog_volumes.remove(i);
+            }
+
+            let full = state.meta.fog_volumes.len() >= MAX_FOG_VOLUMES;
+            if ui
+                .add_enabled(!full, egui::Button::new(tr("add around camera")))
+                .clicked()
+            {
+                let half_size = state.meta.to_voxels(8.0);
+                let volume = FogVolume::around(&state.camera.pos(), half_size);
+                state.meta.fog_volumes.push(volume);
+            }
+            ui.weak(tr("stored with the scene metadata, see the Measure window"));
+        });
+
+        window("Turntable").show(&ctx, |ui| {
+            let settings = &mut state.turntable;
+            ui.add(egui::Slider::new(&mut settings.seconds, 1.0..=60.0).text(tr("seconds")));
+            ui.add(egui::Slider::new(&mut settings.fps, 10..=60).text(tr("fps")));
+            ui.add(egui::Slider::new(&mut settings.width, 64..=3840).text(tr("width")));
+            ui.add(egui::Slider::new(&mut settings.height, 64..=2160).text(tr("height")));
+            ui.add(egui::Slider::new(&mut settings.elevation, -89.0..=89.0).text(tr("elevation")));
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut settings.output);
+                export_requested = ui.button(tr("export turntable")).clicked();
+            });
+        });
+
+        if let Some(inspector) = &mut state.dvo_inspector {
+            let mut open = true;
+            window("Octree inspector").open(&mut open).show(&ctx, |ui| {
+                let levels = inspector.dvo.levels.len();
+                let level = ui
+                    .add(egui::Slider::new(&mut inspector.level, 0..=levels - 1).text(tr("level")));
+                let dim = inspector.dvo.levels[inspector.level].dim().0;
+                inspector.z = inspector.z.min(dim - 1);
+                let z = ui.add(egui::Slider::new(&mut inspector.z, 0..=dim - 1).text("z"));
+                if level.changed() || z.changed() {
+                    inspector.texture = None;
+                }
+                ui.label(format!(
+                    "{}: {}",
+                    tr("mismatching nodes"),
+                    inspector.mismatches[inspector.level]
+                ));
+
+                if inspector.texture.is_none() {
+                    let image = inspector.slice_image();
+                    inspector.texture = Some(ctx.load_texture(
+                        "octree slice",
+                        image,
+                        egui::TextureOptions::NEAREST,
+                    ));
+                }
+                if let Some(texture) = &inspector.texture {
+                    ui.add(egui::Image::new(texture).fit_to_exact_size(egui::vec2(256.0, 256.0)));
+                }
+            });
+            if !open {
+                state.dvo_inspector = None;
+            }
+        }
+
+        let viewer = &mut state.slice_viewer;
+        if let (true, Some(texture)) = (viewer.open, viewer.texture) {
+            window("Slice viewer")
+                .open(&mut viewer.open)
+                .show(&ctx, |ui| {
+                    egui::ComboBox::from_label(tr("texture"))
+                        .selected_text(tr(&format!("{:?}", viewer.source)).to_owned())
+                        .show_ui(ui, |ui| {
+                            for source in SliceSource::ALL {
+                                ui.selectable_value(
+                                    &mut viewer.source,
+                                    source,
+                                    tr(&format!("{source:?}")),
+                                );
+                            }
+                        });
+                    let (levels, _) = state.wgpu_state.slice_extent(viewer.source, 0);
+                    viewer.level = viewer.level.min(levels - 1);
+                    ui.add(egui::Slider::new(&mut viewer.level, 0..=levels - 1).text(tr("mip")));
+                    let (_, depth) = state.wgpu_state.slice_extent(viewer.source, viewer.level);
+                    viewer.z = viewer.z.min(depth - 1);
+                    ui.add(egui::Slider::new(&mut viewer.z, 0..=depth - 1).text("z"));
+                    let size = egui::Vec2::splat(SLICE_SIZE as f32 / 2.0);
+                    ui.image(egui::load::SizedTexture::new(texture, size));
+                });
+        }
+
+        window("Timelapse").show(&ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut state.timelapse.dir);
+                if ui.button(tr("open")).clicked() {
+                    let dir = PathBuf::from(&state.timelapse.dir);
+                    if let Err(err) = state.timelapse.open(&dir) {
+                        eprintln!("failed to open `{}`: {}", dir.display(), err);
+                    }
+                }
+            });
+
+            let len = state.timelapse.frames.len();
+            if len > 0 {
+                let mut current = state.timelapse.current;
+                let slider =
+                    ui.add(egui::Slider::new(&mut current, 0..=len - 1).text(tr("snapshot")));
+                if slider.changed() {
+                    state.timelapse.seek(current);
+                }
+                ui.label(format!(
+                    "{}",
+                    state.timelapse.frames[state.timelapse.current].display()
+                ));
+                ui.horizontal(|ui| {
+                    let label = if state.timelapse.playing {
+                        tr("pause")
+                    } else {
+                        tr("play")
+                    };
+                    if ui.button(label).clicked() {
+                        state.timelapse.playing = !state.timelapse.playing;
+                    }
+                    ui.add(
+                        egui::Slider::new(&mut state.timelapse.interval, 0.1..=10.0)
+                            .text(tr("seconds per snapshot")),
+                    );
+                });
+            }
         });
     });
 
+    if let Some(action) = palette_action {
+        run_action(state, action);
+    }
+
+    if let Some(index) = tab_requested {
+        state.switch_tab(index);
+    }
+    if let Some(index) = close_tab_requested {
+        state.close_tab(index);
+    }
+    if open_tab_requested {
+        run_action(state, Action::OpenTab);
+    }
+
+    if continue_requested {
+        state.continue_session();
+    }
+
+    if cancel_loading_requested {
+        state.cancel_loading();
+    }
+
+    if save_config_requested {
+        state.save_config();
+    }
+
+    match hud_action {
+        Some(HudAction::ImportPalette) => {
+            if let Some(path) = pick_palette(false) {
+                if let Err(err) = state.import_palette(&path) {
+                    state.error = Some(err.into());
+                }
+            }
+        }
+        Some(HudAction::ExportPalette) => {
+            if let Some(path) = pick_palette(true) {
+                if let Err(err) = palette_file::save(&path, state.voxels.palette()) {
+                    eprintln!("failed to export the palette: {}", err);
+                }
+            }
+        }
+        Some(HudAction::SaveScene) => state.save_scene(),
+        None => {}
+    }
+
+    if gif_requested {
+        state.export_history();
+    }
+
+    if screenshot_requested {
+        state.screenshot();
+    }
+
+    if copy_frame_requested {
+        state.copy_frame();
+    }
+
+    if let Some(save) = bake_requested {
+        state.bake_lighting(save);
+    }
+
+    if clear_bake_requested {
+        state.clear_baked_lighting();
+    }
+
+    if state.slice_viewer.open {
+        let viewer = &state.slice_viewer;
+        state.wgpu_state.render_slice(
+            &state.device,
+            &state.queue,
+            viewer.source,
+            viewer.level,
+            viewer.z,
+        );
+    }
+
+    if export_dvo_requested {
+        state.export_dvo();
+    }
+
+    if let Some(enabled) = contours_requested {
+        state.set_contours(enabled);
+    }
+    if bake_probes_requested {
+        state.bake_probes();
+    }
+
+    if export_requested {
+        if let Err(err) = export_turntable(state) {
+            eprintln!("turntable export failed: {}", err);
+        }
+    }
+
     full_output
 }
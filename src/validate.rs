@@ -0,0 +1,290 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use ndarray::Array3;
+use thiserror::Error;
+
+use crate::{
+    bake::Lightmap,
+    cli::Repair,
+    features,
+    fog::FogVolume,
+    materials::Surface,
+    scene::SceneMeta,
+    voxels::{self, Voxels},
+};
+
+// `wender validate`: checks a .wvox scene and its metadata before the renderer trips on them, e.g.
+// after a broken conversion. the file is decoded as is, without the checks of `Voxels::from_path`,
+// and the problems are listed as errors (the scene cannot be rendered correctly) or warnings.
+// with `repair`, the out-of-range voxels are clamped or cleared, the mismatched baked lighting is
+// dropped and the invalid metadata is reset, then the scene is written back.
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to open `{0}`: {1}")]
+    IOError(PathBuf, std::io::Error),
+    #[error("failed to decode `{0}`: {1}")]
+    DecodeError(PathBuf, bincode::Error),
+    #[error("only .wvox scenes can be validated")]
+    FormatError,
+    #[error(transparent)]
+    SceneError(#[from] voxels::Error),
+    #[error("failed to write `{0}`: {1}")]
+    WriteError(PathBuf, bincode::Error),
+    #[error("failed to write the metadata of `{0}`: {1}")]
+    MetaError(PathBuf, std::io::Error),
+}
+
+/// out-of-range voxels listed in the report at most.
+const MAX_LISTED: usize = 5;
+
+#[derive(Default)]
+struct Report {
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+/// the contents of a .wvox file, see `Voxels::save`.
+struct RawScene {
+    voxels: Array3<u32>,
+    palette: Vec<[u8; 4]>,
+    lightmap: Option<Lightmap>,
+    meta: SceneMeta,
+}
+
+fn read(path: &Path) -> Result<RawScene, Error> {
+    if path.extension().is_some_and(|ext| ext != "wvox") {
+        return Err(Error::FormatError);
+    }
+    let file = File::open(path).map_err(|e| Error::IOError(path.to_owned(), e))?;
+    let mut file = BufReader::new(file);
+    let (voxels, palette) =
+        bincode::deserialize_from(&mut file).map_err(|e| Error::DecodeError(path.to_owned(), e))?;
+    Ok(RawScene {
+        voxels,
+        palette,
+        lightmap: bincode::deserialize_from(&mut file).ok(),
+        meta: SceneMeta::load(path),
+    })
+}
+
+fn check(scene: &RawScene) -> Report {
+    let mut report = Report::default();
+    let palette_len = scene.palette.len() as u32;
+
+    if scene.voxels.is_empty() {
+        report.errors.push("the volume has no voxels".to_owned());
+    } else if scene.voxels.iter().all(|v| *v == 0) {
+        report.warnings.push("the volume is empty".to_owned());
+    }
+    if let Err(err) = features::validate_palette(scene.palette.len()) {
+        report.errors.push(err.to_string());
+    }
+
+    // voxels per palette entry, and the out-of-range ones.
+    let mut used = vec![0u64; scene.palette.len() + 1];
+    let mut out_of_range = BTreeMap::<u32, u64>::new();
+    let mut listed = Vec::new();
+    for ((i, j, k), v) in scene.voxels.indexed_iter() {
+        match used.get_mut(*v as usize) {
+            Some(count) => *count += 1,
+            None => {
+                *out_of_range.entry(*v).or_default() += 1;
+                if listed.len() < MAX_LISTED {
+                    // world x and z are swapped relative to the array axes.
+                    listed.push(format!("({k}, {j}, {i}) = {v}"));
+                }
+            }
+        }
+    }
+    if !out_of_range.is_empty() {
+        let count = out_of_range.values().sum::<u64>();
+        report.errors.push(format!(
+            "{count} voxels use palette indices above the {palette_len} entries: {:?}, e.g. {}",
+            out_of_range.keys().collect::<Vec<_>>(),
+            listed.join(", ")
+        ));
+    }
+    for (i, color) in scene.palette.iter().enumerate() {
+        let count = used[i + 1];
+        if color[3] == 0 && count > 0 {
+            report.warnings.push(format!(
+                "palette entry {} is fully transparent but used by {count} voxels",
+                i + 1
+            ));
+        }
+    }
+    let unused = (1..used.len()).filter(|i| used[*i] == 0).count();
+    if unused > 0 {
+        report
+            .warnings
+            .push(format!("{unused} palette entries are not used"));
+    }
+
+    if let Some(lightmap) = &scene.lightmap {
+        if lightmap.dim() != scene.voxels.dim() {
+            report.errors.push(format!(
+                "the baked lighting is {:?} but the volume is {:?}, it is ignored",
+                lightmap.dim(),
+                scene.voxels.dim()
+            ));
+        }
+    }
+
+    let meta = &scene.meta;
+    if !(meta.voxels_per_meter.is_finite() && meta.voxels_per_meter > 0.0) {
+        report.errors.push(format!(
+            "invalid voxels_per_meter {}",
+            meta.voxels_per_meter
+        ));
+    }
+    if !meta.detail_noise.is_empty() && meta.detail_noise.len() != scene.palette.len() {
+        report.warnings.push(format!(
+            "detail_noise has {} values for {palette_len} palette entries",
+            meta.detail_noise.len()
+        ));
+    }
+    if meta.detail_noise.iter().any(|n| !(0.0..=1.0).contains(n)) {
+        report
+            .errors
+            .push("detail_noise values must be from 0 to 1".to_owned());
+    }
+    let water = meta
+        .water
+        .iter()
+        .filter(|i| **i == 0 || **i > palette_len)
+        .collect::<Vec<_>>();
+    if !water.is_empty() {
+        report.errors.push(format!(
+            "water entries {water:?} are not in the palette (1..={palette_len})"
+        ));
+    }
+    let surfaces = meta
+        .surfaces
+        .iter()
+        .filter(|s| !is_valid_surface(s, palette_len))
+        .count();
+    if surfaces > 0 {
+        report.errors.push(format!(
+            "{surfaces} surfaces are not in the palette (1..={palette_len}) or have non-finite values"
+        ));
+    }
+    let fog = meta.fog_volumes.iter().filter(|f| !is_valid_fog(f)).count();
+    if fog > 0 {
+        report.errors.push(format!(
+            "{fog} fog volumes have non-finite values or an inverted box"
+        ));
+    }
+
+    report
+}
+
+fn is_valid_fog(fog: &FogVolume) -> bool {
+    let values = fog.min.iter().chain(&fog.max).chain(&fog.color);
+    values.chain([&fog.density]).all(|v| v.is_finite())
+        && (0..3).all(|a| fog.min[a] <= fog.max[a])
+        && fog.density >= 0.0
+}
+
+fn is_valid_surface(surface: &Surface, palette_len: u32) -> bool {
+    let values = [
+        surface.roughness,
+        surface.metalness,
+        surface.emissive,
+        surface.ior,
+    ];
+    (1..=palette_len).contains(&surface.entry) && values.iter().all(|v| v.is_finite())
+}
+
+fn repair(scene: &mut RawScene, mode: Repair) {
+    let palette_len = scene.palette.len() as u32;
+    scene
+        .voxels
+        .mapv_inplace(|v| match (v > palette_len, mode) {
+            (false, _) => v,
+            (true, Repair::Clamp) => palette_len,
+            (true, Repair::Clear) => 0,
+        });
+    if scene
+        .lightmap
+        .as_ref()
+        .is_some_and(|lightmap| lightmap.dim() != scene.voxels.dim())
+    {
+        scene.lightmap = None;
+    }
+
+    let meta = &mut scene.meta;
+    if !(meta.voxels_per_meter.is_finite() && meta.voxels_per_meter > 0.0) {
+        meta.voxels_per_meter = 1.0;
+    }
+    if !meta.detail_noise.is_empty() {
+        meta.detail_noise.resize(scene.palette.len(), 0.0);
+        for n in &mut meta.detail_noise {
+            *n = if n.is_finite() {
+                n.clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+        }
+    }
+    meta.water.retain(|i| (1..=palette_len).contains(i));
+    meta.surfaces.retain(|s| is_valid_surface(s, palette_len));
+    meta.fog_volumes.retain(is_valid_fog);
+}
+
+fn run(scene: &Path, mode: Option<Repair>, output: Option<&Path>) -> Result<bool, Error> {
+    let mut raw = read(scene)?;
+    let report = check(&raw);
+    for warning in &report.warnings {
+        println!("warning: {warning}");
+    }
+    for error in &report.errors {
+        println!("error: {error}");
+    }
+    println!(
+        "{} errors, {} warnings",
+        report.errors.len(),
+        report.warnings.len()
+    );
+
+    let Some(mode) = mode else {
+        return Ok(report.errors.is_empty());
+    };
+    if report.errors.is_empty() {
+        println!("nothing to repair");
+        return Ok(true);
+    }
+    repair(&mut raw, mode);
+    let output = output.unwrap_or(scene);
+    let mut voxels = Voxels::from_parts(raw.voxels, raw.palette, raw.lightmap, output)?;
+    voxels.meta = raw.meta;
+    voxels
+        .save(output)
+        .map_err(|e| Error::WriteError(output.to_owned(), e))?;
+    voxels
+        .meta
+        .save(output)
+        .map_err(|e| Error::MetaError(output.to_owned(), e))?;
+
+    let remaining = check(&read(output)?).errors;
+    for error in &remaining {
+        println!("not repaired: {error}");
+    }
+    Ok(remaining.is_empty())
+}
+
+/// check `scene` and repair it into `output` with `mode`. returns whether the scene is valid.
+pub fn validate(scene: &Path, mode: Option<Repair>, output: Option<&Path>) -> bool {
+    match run(scene, mode, output) {
+        Ok(valid) => valid,
+        Err(err) => {
+            eprintln!("error: {err}");
+            false
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{camera::CameraMode, lights::LightingPreset, State};
+
+// view links: the camera, the lighting and the feature toggles packed in a short string, to
+// share an exact viewpoint of a scene like a map permalink. the scene itself is not part of the
+// link, it must be opened first. the string is `wv1.` followed by the bincode of `ViewLink` in
+// url-safe base64, the version is bumped when the fields change.
+
+const PREFIX: &str = "wv1.";
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("not a view link")]
+    PrefixError,
+    #[error("invalid view link: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+    #[error("invalid view link: {0}")]
+    FormatError(#[from] bincode::Error),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ViewLink {
+    pos: [f32; 3],
+    /// yaw and pitch, in radians.
+    view: (f32, f32),
+    lighting: Option<LightingPreset>,
+    /// sun angle and azimuth, in degrees.
+    sun: (f32, f32),
+    ambient: f32,
+    exposure: f32,
+    features: u32,
+}
+
+impl ViewLink {
+    pub fn capture(state: &State) -> Self {
+        let (yaw, pitch) = state.controller.view();
+        let sensitivity = state.controller.sensitivity;
+        Self {
+            pos: state.camera.pos().into(),
+            view: ((yaw * sensitivity) as f32, (pitch * sensitivity) as f32),
+            lighting: state.meta.lighting,
+            sun: (state.lights.angle, state.lights.azimuth),
+            ambient: state.lights.uniform.ambient,
+            exposure: state.lights.uniform.exposure,
+            features: state.settings.uniform.features,
+        }
+    }
+
+    pub fn apply(&self, state: &mut State) {
+        // the orbit camera would move away from the position.
+        state.set_camera_mode(CameraMode::Fly);
+        state.camera.set_pos(&glm::Vec3::from(self.pos));
+        let sensitivity = state.controller.sensitivity;
+        state.controller.set_view((
+            self.view.0 as f64 / sensitivity,
+            self.view.1 as f64 / sensitivity,
+        ));
+        // the preset sets the background and the fog, the rest is overridden below.
+        if let Some(preset) = self.lighting {
+            preset.apply(&mut state.lights, &mut state.environment);
+        }
+        state.meta.lighting = self.lighting;
+        (state.lights.angle, state.lights.azimuth) = self.sun;
+        state.lights.uniform.ambient = self.ambient;
+        state.lights.uniform.exposure = self.exposure;
+        state.lights.update();
+        state.settings.uniform.features = self.features;
+    }
+
+    pub fn encode(&self) -> String {
+        let bytes = bincode::serialize(self).unwrap();
+        format!("{PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    pub fn decode(link: &str) -> Result<Self, Error> {
+        let data = link.trim().strip_prefix(PREFIX).ok_or(Error::PrefixError)?;
+        let bytes = URL_SAFE_NO_PAD.decode(data)?;
+        Ok(bincode::deserialize(&bytes)?)
+    }
+}
@@ -0,0 +1,100 @@
+#import "gbuffer.wgsl"::{ gbuffer_pos, load_gsample, GSample, SURFACE_VOXEL, SURFACE_GROUND }
+#import "shading.wgsl"::{ occlusion, voxel_occlusion }
+#import "settings.wgsl"::{ feature_enabled, FEATURE_CONTACT_SHADOWS }
+#import "lights.wgsl"::{ lights }
+#import "ray.wgsl"::{ cam, cam_pos, view_pos, DEPTH_NEAR }
+
+// the visibility pass of the deferred renderer: the sun shadow and the ao of the surfaces of the
+// g-buffer, the rays towards the sun and the ao cones being the costly part of the shading. the
+// lighting pass reads them back, see `shader.wgsl`. the contact shadows march the g-buffer in
+// screen space.
+//
+// this module "requires":
+// const DEBUG_DISPLAY: u32;
+// const RENDER_MODE: u32;
+// (and the constants required by the imported modules)
+
+// steps and length in voxels of the screen space march of the contact shadows.
+const CONTACT_SHADOW_STEPS: u32 = 16u;
+const CONTACT_SHADOW_DIST: f32 = 6.0;
+// depth in voxels behind the g-buffer surfaces that still occludes, thin details cast shadows
+// without the silhouettes of distant objects shadowing everything behind them.
+const CONTACT_SHADOW_THICKNESS: f32 = 1.5;
+
+struct VertexInput {
+    @location(0) pos: vec2f,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_pos: vec4f,
+    @location(0) pos: vec2f,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.pos = in.pos;
+    out.clip_pos = vec4f(out.pos, 0.0, 1.0);
+
+    return out;
+}
+
+// pixel of the render space position `pos` in the g-buffer, the inverse of `cam_ray_dir`.
+// negative when `pos` is behind the near plane.
+fn screen_pixel(pos: vec3f) -> vec2f {
+    let v = view_pos(pos);
+    if v.z < DEPTH_NEAR {
+        return vec2f(-1.0);
+    }
+    let tan_y = tan(cam.fov_y / 2.0);
+    let screen = v.xy / (v.z * vec2f(tan_y * cam.aspect, tan_y));
+    let size = vec2f(textureDimensions(gbuffer_pos));
+    return vec2f(0.5 + 0.5 * screen.x, 0.5 - 0.5 * screen.y) * size;
+}
+
+// contact shadows: a short march towards the light in screen space, against the distances of
+// the g-buffer, darkening the creases and the feet of small details that the cone traced shadows
+// blur away. fades out with the distance to the occluder, between 0 (lit) and 1 (shadowed).
+fn contact_shadow(s: GSample, light_dir: vec3f) -> f32 {
+    if !feature_enabled(FEATURE_CONTACT_SHADOWS) || dot(s.normal, light_dir) <= 0.0 {
+        return 0.0;
+    }
+    let size = vec2f(textureDimensions(gbuffer_pos));
+    let start = s.pos + s.normal * 0.05;
+    // the g-buffer distances are along the camera rays, hence the bias with the distance.
+    let bias = 0.02 + s.t * 2e-3;
+
+    for (var i = 1u; i <= CONTACT_SHADOW_STEPS; i++) {
+        let pos = start + light_dir * (CONTACT_SHADOW_DIST * f32(i) / f32(CONTACT_SHADOW_STEPS));
+        let pixel = screen_pixel(pos);
+        if any(pixel < vec2f(0.0)) || any(pixel >= size) {
+            return 0.0;
+        }
+        let scene_t = textureLoad(gbuffer_pos, vec2u(pixel), 0).w;
+        let behind = distance(pos, cam_pos()) - scene_t;
+        if behind > bias && behind < CONTACT_SHADOW_THICKNESS {
+            return 1.0 - f32(i - 1u) / f32(CONTACT_SHADOW_STEPS);
+        }
+    }
+    return 0.0;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    // the debug displays and the path traced mode do not use the shading.
+    if #DEBUG_DISPLAY != 0u || #RENDER_MODE == 1u {
+        return vec4f(0.0);
+    }
+    let s = load_gsample(vec2u(in.clip_pos.xy));
+    var occluded = vec2f(0.0);
+    if s.kind == SURFACE_VOXEL {
+        occluded = voxel_occlusion(s.voxel, s.pos, s.normal);
+    } else if s.kind == SURFACE_GROUND {
+        occluded = occlusion(s.pos, s.normal);
+    } else {
+        return vec4f(0.0);
+    }
+    occluded.x = max(occluded.x, contact_shadow(s, lights.sun.dir));
+    return vec4f(occluded, 0.0, 0.0);
+}
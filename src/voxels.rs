@@ -1,62 +1,520 @@
-use std::{fs::File, io::BufReader};
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read},
+    path::{Path, PathBuf},
+};
 
+use dot_vox::{DotVoxData, SceneNode};
 use nalgebra_glm as glm;
-use ndarray::{s, Array3, Zip};
+use ndarray::{s, Array3, Axis, Zip};
+use thiserror::Error;
 
-#[cfg(byte_voxels)]
+use crate::{
+    bake::Lightmap,
+    chunks, features,
+    loading::{LoadProgress, Stage},
+    remap::PaletteRemap,
+    scene::SceneMeta,
+};
+
+#[cfg(feature = "byte_voxels")]
 pub type VoxelsFormat = u8;
-#[cfg(not(byte_voxels))]
+#[cfg(not(feature = "byte_voxels"))]
 pub type VoxelsFormat = u32;
 
+#[cfg(feature = "f16_colors")]
+pub type ColorsFormat = [half::f16; 4];
+#[cfg(not(feature = "f16_colors"))]
+pub type ColorsFormat = glm::U8Vec4;
+
+#[cfg(feature = "f16_colors")]
+fn to_colors_format(rgba: [u8; 4]) -> ColorsFormat {
+    rgba.map(|c| half::f16::from_f32(c as f32 / 255.0))
+}
+#[cfg(not(feature = "f16_colors"))]
+fn to_colors_format(rgba: [u8; 4]) -> ColorsFormat {
+    glm::U8Vec4::from(rgba)
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to open `{0}`: {1}")]
+    IOError(PathBuf, std::io::Error),
+    #[error("failed to decode `{0}`: {1}")]
+    DecodeError(PathBuf, bincode::Error),
+    #[error("failed to decode `{0}`: {1}")]
+    VoxError(PathBuf, &'static str),
+    #[error("`{0}` contains no voxels")]
+    EmptyScene(PathBuf),
+    #[error("failed to read `{0}`: {1}")]
+    ChunksError(PathBuf, chunks::Error),
+    #[error(transparent)]
+    FeatureError(#[from] features::Error),
+    #[error("loading `{0}` was cancelled")]
+    Cancelled(PathBuf),
+}
+
+/// color of a palette index, 0 being empty.
+fn color_of(palette: &[[u8; 4]], i: u32) -> ColorsFormat {
+    if i == 0 {
+        Default::default()
+    } else {
+        to_colors_format(palette[i as usize - 1])
+    }
+}
+
+/// colors of palette indices, 0 being empty.
+pub fn colorize(palette: &[[u8; 4]], voxels: &Array3<VoxelsFormat>) -> Array3<ColorsFormat> {
+    colorize_with(palette, voxels, &LoadProgress::new())
+}
+
+/// `colorize` in slabs along the first axis, reporting the progress of each.
+fn colorize_with(
+    palette: &[[u8; 4]],
+    voxels: &Array3<VoxelsFormat>,
+    progress: &LoadProgress,
+) -> Array3<ColorsFormat> {
+    progress.begin(Stage::Colors, voxels.len_of(Axis(0)));
+    let mut colors = Array3::default(voxels.raw_dim());
+    Zip::from(colors.axis_iter_mut(Axis(0)))
+        .and(voxels.axis_iter(Axis(0)))
+        .par_for_each(|mut colors, voxels| {
+            if progress.is_cancelled() {
+                return;
+            }
+            Zip::from(&mut colors)
+                .and(&voxels)
+                .for_each(|c, i| *c = color_of(palette, *i as u32));
+            progress.step();
+        });
+    colors
+}
+
 #[derive(Debug)]
 pub struct Voxels {
     voxels: Array3<VoxelsFormat>,
-    colors: Array3<glm::U8Vec4>,
+    colors: Array3<ColorsFormat>,
+    palette: Vec<[u8; 4]>,
+    shape: (usize, usize, usize), // before padding to a power of 2 cube
+    pub lightmap: Option<Lightmap>,
+    pub path: PathBuf,
+    pub meta: SceneMeta,
+    /// a palette remap was applied on load, see `remap.rs`.
+    remapped: bool,
+}
+
+/// apply the palette remap of the scene at `path`, if any. returns whether there was one.
+fn remap(path: &Path, vox: &mut Array3<u32>, palette: &mut [[u8; 4]]) -> bool {
+    let Some(remap) = PaletteRemap::load(path) else {
+        return false;
+    };
+    remap.apply(vox, palette);
+    true
 }
 
 impl Voxels {
-    pub fn new() -> Self {
-        let file = std::env::args()
-            .nth(1)
-            .unwrap_or("assets/minecraft_511.wvox".to_owned());
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        Self::load(path, &LoadProgress::new())
+    }
+
+    /// load the scene at `path` through the stages of `loading.rs`, reporting to `progress`.
+    #[tracing::instrument(skip_all, fields(path = %path.display()))]
+    pub fn load(path: &Path, progress: &LoadProgress) -> Result<Self, Error> {
+        let cancelled = || Error::Cancelled(path.to_owned());
+        let (mut vox, mut palette, baked) = Self::read(path, progress)?;
+        if progress.is_cancelled() {
+            return Err(cancelled());
+        }
+
+        progress.begin(Stage::Remap, 1);
+        let remapped = remap(path, &mut vox, &mut palette);
+        progress.step();
+        if progress.is_cancelled() {
+            return Err(cancelled());
+        }
 
-        let asset_file = File::open(&file).expect("missing asset file");
-        let asset_file = BufReader::new(asset_file);
-        let (vox, palette): (Array3<u32>, Vec<[u8; 4]>) =
-            bincode::deserialize_from(asset_file).expect("failed to load asset");
+        Self::from_parts_with(vox, palette, baked, path, progress)
+            .map(|v| v.with_remapped(remapped))
+    }
+
+    /// the unpadded palette indices, the palette and the baked lighting of a scene file.
+    fn read(
+        path: &Path,
+        progress: &LoadProgress,
+    ) -> Result<(Array3<u32>, Vec<[u8; 4]>, Option<Lightmap>), Error> {
+        if path.extension().is_some_and(|ext| ext == "wchunks") {
+            let (vox, palette) =
+                chunks::read(path, progress).map_err(|e| Error::ChunksError(path.to_owned(), e))?;
+            return Ok((vox, palette, None));
+        }
+        progress.begin(Stage::Decompress, 1);
+        if path.extension().is_some_and(|ext| ext == "vox") {
+            let (vox, palette) = Self::read_vox(path)?;
+            progress.step();
+            return Ok((vox, palette, None));
+        }
+
+        let asset_file = File::open(path).map_err(|e| Error::IOError(path.to_owned(), e))?;
+        let res = Self::decode(BufReader::new(asset_file), path);
+        progress.step();
+        res
+    }
+
+    /// decode a .wvox scene read from `reader`, `path` is only used in the errors.
+    fn decode(
+        mut reader: impl Read,
+        path: &Path,
+    ) -> Result<(Array3<u32>, Vec<[u8; 4]>, Option<Lightmap>), Error> {
+        let (vox, palette): (Array3<u32>, Vec<[u8; 4]>) = bincode::deserialize_from(&mut reader)
+            .map_err(|e| Error::DecodeError(path.to_owned(), e))?;
+        // baked lighting is optionally appended after the voxels and palette, see `save`.
+        let baked: Option<Lightmap> = bincode::deserialize_from(&mut reader)
+            .ok()
+            .filter(|baked: &Lightmap| baked.dim() == vox.dim());
+        Ok((vox, palette, baked))
+    }
+
+    /// load a .wvox scene from memory, e.g. handed over by an application embedding the
+    /// renderer. `path` names the scene, its metadata is read next to it if it exists.
+    #[cfg(feature = "ffi")]
+    pub fn from_bytes(bytes: &[u8], path: &Path) -> Result<Self, Error> {
+        let (vox, palette, baked) = Self::decode(bytes, path)?;
+        Self::from_parts(vox, palette, baked, path)
+    }
+
+    /// read a MagicaVoxel .vox file. the models of the scene graph are flattened into a single
+    /// volume, their rotations are ignored.
+    fn read_vox(path: &Path) -> Result<(Array3<u32>, Vec<[u8; 4]>), Error> {
+        let bytes = fs::read(path).map_err(|e| Error::IOError(path.to_owned(), e))?;
+        let data = dot_vox::load_bytes(&bytes).map_err(|e| Error::VoxError(path.to_owned(), e))?;
+
+        let mut instances = Vec::new();
+        if data.scenes.is_empty() {
+            // files older than the scene graph hold a single model.
+            instances.extend((0..data.models.len() as u32).map(|id| (id, glm::IVec3::zeros())));
+        } else {
+            vox_instances(&data, 0, glm::IVec3::zeros(), &mut instances);
+        }
+
+        // model voxels relative to the model center, in vox coordinates (z up).
+        let placed = instances
+            .iter()
+            .filter_map(|(id, offset)| Some((data.models.get(*id as usize)?, offset)))
+            .flat_map(|(model, offset)| {
+                let size = glm::vec3(model.size.x, model.size.y, model.size.z).map(|s| s as i32);
+                let origin = offset - size / 2;
+                model.voxels.iter().map(move |v| {
+                    let pos = origin + glm::vec3(v.x, v.y, v.z).map(|c| c as i32);
+                    (pos, v.i as u32 + 1)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if placed.is_empty() {
+            return Err(Error::EmptyScene(path.to_owned()));
+        }
+        let (min, max) = placed.iter().fold(
+            (glm::IVec3::repeat(i32::MAX), glm::IVec3::repeat(i32::MIN)),
+            |(min, max), (pos, _)| (min.inf(pos), max.sup(pos)),
+        );
+        let size = (max - min).map(|c| c as usize + 1);
+
+        // vox z is up, so it goes on the vertical array axis.
+        let mut vox = Array3::zeros((size.x, size.z, size.y));
+        for (pos, i) in &placed {
+            let pos = (pos - min).map(|c| c as usize);
+            vox[[pos.x, pos.z, pos.y]] = *i;
+        }
+
+        // only keep the palette entries up to the last one used, the 256 entries of a .vox do
+        // not fit in byte voxels otherwise.
+        let used = placed.iter().map(|(_, i)| *i as usize).max().unwrap_or(0);
+        let palette: Vec<_> = data.palette[..used.min(data.palette.len())]
+            .iter()
+            .map(|c| [c.r, c.g, c.b, c.a])
+            .collect();
+
+        println!(
+            "loaded {} models ({} voxels) from `{}`",
+            instances.len(),
+            placed.len(),
+            path.display()
+        );
+        Ok((vox, palette))
+    }
+
+    /// build the scene from unpadded palette indices, padding it to a power of 2 cube.
+    pub fn from_parts(
+        vox: Array3<u32>,
+        palette: Vec<[u8; 4]>,
+        baked: Option<Lightmap>,
+        path: &Path,
+    ) -> Result<Self, Error> {
+        Self::from_parts_with(vox, palette, baked, path, &LoadProgress::new())
+    }
+
+    /// `from_parts`, padding and deriving the colors in slabs along the first axis.
+    fn from_parts_with(
+        vox: Array3<u32>,
+        palette: Vec<[u8; 4]>,
+        baked: Option<Lightmap>,
+        path: &Path,
+        progress: &LoadProgress,
+    ) -> Result<Self, Error> {
+        features::validate_palette(palette.len())?;
 
         // round up to pow of 2
-        let dim = vox.shape().iter().max().unwrap();
+        let dim = match vox.shape().iter().max() {
+            Some(dim) if *dim > 0 => dim,
+            _ => return Err(Error::EmptyScene(path.to_owned())),
+        };
         let max_dim: usize = 2 << (dim - 1).ilog2();
         println!(
             "dim: {dim:?} ({max_dim}) -> dvo_depth = {}",
             max_dim.ilog2() - 1
         );
+        progress.begin(Stage::Pad, vox.len_of(Axis(0)));
         let mut voxels = Array3::zeros((max_dim, max_dim, max_dim));
-        voxels
-            .slice_mut(s![..vox.dim().0, ..vox.dim().1, ..vox.dim().2])
-            .assign(&vox.mapv(|x| x as VoxelsFormat));
-        println!(
-            "mem: {}B = {}MiB",
-            voxels.len() * 4,
-            voxels.len() * 4 / 1024 / 1024
-        );
-
-        let colors = Zip::from(&voxels).par_map_collect(|i| {
-            if *i == 0 {
-                Default::default()
-            } else {
-                glm::U8Vec4::from(palette[*i as usize - 1])
+        Zip::from(
+            voxels
+                .slice_mut(s![..vox.dim().0, ..vox.dim().1, ..vox.dim().2])
+                .axis_iter_mut(Axis(0)),
+        )
+        .and(vox.axis_iter(Axis(0)))
+        .par_for_each(|mut padded, vox| {
+            if progress.is_cancelled() {
+                return;
             }
+            Zip::from(&mut padded)
+                .and(&vox)
+                .for_each(|p, v| *p = *v as VoxelsFormat);
+            progress.step();
         });
+        if progress.is_cancelled() {
+            return Err(Error::Cancelled(path.to_owned()));
+        }
+        let mem = voxels.len() * std::mem::size_of::<VoxelsFormat>();
+        println!("mem: {}B = {}MiB", mem, mem / 1024 / 1024);
+
+        let lightmap = baked.map(|baked| {
+            println!("loaded baked lighting");
+            let mut lightmap = Array3::from_elem((max_dim, max_dim, max_dim), [0u8; 2]);
+            lightmap
+                .slice_mut(s![..vox.dim().0, ..vox.dim().1, ..vox.dim().2])
+                .assign(&baked);
+            lightmap
+        });
+
+        let colors = colorize_with(&palette, &voxels, progress);
+        if progress.is_cancelled() {
+            return Err(Error::Cancelled(path.to_owned()));
+        }
+
+        Ok(Self {
+            voxels,
+            colors,
+            palette,
+            shape: vox.dim(),
+            lightmap,
+            path: path.to_owned(),
+            meta: SceneMeta::load(path),
+            remapped: false,
+        })
+    }
+
+    fn with_remapped(mut self, remapped: bool) -> Self {
+        self.remapped = remapped;
+        self
+    }
+
+    /// write the scene back in the .wvox format. the baked lighting, if any, is appended after
+    /// the `(voxels, palette)` tuple, so readers unaware of it still load the file.
+    #[tracing::instrument(skip_all, fields(path = %path.display()))]
+    pub fn save(&self, path: &Path) -> bincode::Result<()> {
+        let (x, y, z) = self.shape;
+        let vox = self.voxels.slice(s![..x, ..y, ..z]).mapv(|v| v as u32);
+
+        // write to a temporary file first, so a failure never corrupts the scene.
+        let tmp_path = path.with_extension("wvox.tmp");
+        {
+            let mut file = BufWriter::new(File::create(&tmp_path)?);
+            bincode::serialize_into(&mut file, &(&vox, &self.palette))?;
+            if let Some(lightmap) = &self.lightmap {
+                bincode::serialize_into(&mut file, &lightmap.slice(s![..x, ..y, ..z]))?;
+            }
+        }
+        fs::rename(&tmp_path, path)?;
+
+        println!("wrote `{}`", path.display());
+        if self.remapped && path == self.path {
+            PaletteRemap::retire(path);
+        }
+        Ok(())
+    }
+
+    /// set the voxel at world coordinates (x, y, z) to the palette entry `material`, 0 to clear
+    /// it. returns false if the voxel is outside the volume or the entry does not exist.
+    pub fn set(&mut self, x: u32, y: u32, z: u32, material: u32) -> bool {
+        self.fill(glm::vec3(x, y, z), glm::vec3(x + 1, y + 1, z + 1), material)
+    }
+
+    /// set the box `min..max` of world coordinates to the palette entry `material`, 0 to clear
+    /// it. the box is clamped to the volume. returns false if nothing was written.
+    pub fn fill(&mut self, min: glm::UVec3, max: glm::UVec3, material: u32) -> bool {
+        let max = max.map(|c| c.min(self.dim()));
+        if material as usize > self.palette.len() || (0..3).any(|a| min[a] >= max[a]) {
+            return false;
+        }
+        // world x and z are swapped relative to the array axes.
+        let range = |a: usize| min[a] as usize..max[a] as usize;
+        self.voxels
+            .slice_mut(s![range(2), range(1), range(0)])
+            .fill(material as VoxelsFormat);
+        self.colors
+            .slice_mut(s![range(2), range(1), range(0)])
+            .fill(color_of(&self.palette, material));
+
+        // the voxels outside of the unpadded shape are not saved.
+        if material != 0 {
+            let (i, j, k) = self.shape;
+            self.shape = (
+                i.max(max.z as usize),
+                j.max(max.y as usize),
+                k.max(max.x as usize),
+            );
+        }
+        true
+    }
+
+    /// recolor the scene with a palette of the same number of entries.
+    pub fn set_palette(&mut self, palette: Vec<[u8; 4]>) {
+        assert_eq!(palette.len(), self.palette.len());
+        self.colors = colorize(&palette, &self.voxels);
+        self.palette = palette;
+    }
+
+    /// overwrite a box of the volume, e.g. a streamed chunk. `origin` is in array axes.
+    pub fn write_region(
+        &mut self,
+        origin: [usize; 3],
+        voxels: &Array3<VoxelsFormat>,
+        colors: &Array3<ColorsFormat>,
+    ) {
+        let [i, j, k] = origin;
+        let (di, dj, dk) = voxels.dim();
+        let region = s![i..i + di, j..j + dj, k..k + dk];
+        self.voxels.slice_mut(region).assign(voxels);
+        self.colors.slice_mut(region).assign(colors);
+    }
+
+    /// whether the voxel at world coordinates `cell` is solid. out of bounds voxels are empty.
+    pub fn is_solid(&self, cell: glm::IVec3) -> bool {
+        let dim = self.dim() as i32;
+        if cell.iter().any(|c| *c < 0 || *c >= dim) {
+            return false;
+        }
+        // world x and z are swapped relative to the array axes, like in the gpu textures.
+        self.voxels[[cell.z as usize, cell.y as usize, cell.x as usize]] != 0
+    }
+
+    /// cast a ray in world coordinates through the voxel grid (3d dda). returns the first solid
+    /// voxel hit within `max_dist` and the distance to it.
+    pub fn raycast(
+        &self,
+        pos: &glm::Vec3,
+        dir: &glm::Vec3,
+        max_dist: f32,
+    ) -> Option<(glm::IVec3, f32)> {
+        raycast_grid(self.dim(), pos, dir, max_dist, |cell| self.is_solid(cell))
+    }
+
+    /// the scene downsampled by 2^`levels`. each voxel takes the first solid voxel of its block.
+    /// the baked lighting is dropped.
+    pub fn downsample(&self, levels: u32) -> Self {
+        let block = 1 << levels;
+        let voxels = Zip::from(self.voxels.exact_chunks((block, block, block)))
+            .par_map_collect(|chunk| chunk.iter().copied().find(|v| *v != 0).unwrap_or(0));
+        let colors = colorize(&self.palette, &voxels);
+        let (x, y, z) = self.shape;
+        println!("downsampled the scene {block}x: {}^3", voxels.dim().0);
+
+        Self {
+            voxels,
+            colors,
+            palette: self.palette.clone(),
+            shape: (x.div_ceil(block), y.div_ceil(block), z.div_ceil(block)),
+            lightmap: None,
+            path: self.path.clone(),
+            meta: self.meta.downsampled(block as f32),
+            remapped: self.remapped,
+        }
+    }
+
+    /// number of solid voxels in each `block`^3 block of the volume, like a coarse mip level.
+    pub fn occupancy(&self, block: usize) -> Array3<usize> {
+        Zip::from(self.voxels.exact_chunks((block, block, block)))
+            .par_map_collect(|chunk| chunk.iter().filter(|v| **v != 0).count())
+    }
+
+    /// a camera position above the highest voxel near the center of the scene, and a point to
+    /// look at, the center of the densest region.
+    pub fn spawn(&self, eye_height: f32) -> (glm::Vec3, glm::Vec3) {
+        let (sz, sy, sx) = self.shape;
+        let (cx, cz) = (sx / 2, sz / 2);
+        let radius = (sx.max(sz) / 16).max(1);
+
+        // highest solid voxel in the columns around the center. array axes are (z, y, x).
+        let top = (cz.saturating_sub(radius)..(cz + radius).min(sz))
+            .flat_map(|z| (cx.saturating_sub(radius)..(cx + radius).min(sx)).map(move |x| (z, x)))
+            .filter_map(|(z, x)| {
+                let column = self.voxels.slice(s![z, ..sy, x]);
+                column.iter().rposition(|v| *v != 0)
+            })
+            .max();
+        let floor = top.map(|y| y as f32 + 1.0).unwrap_or(sy as f32);
+        let pos = glm::vec3(cx as f32 + 0.5, floor + eye_height, cz as f32 + 0.5);
+
+        let block = (self.dim() as usize / 16).max(1);
+        let occupancy = self.occupancy(block);
+        let densest = occupancy
+            .indexed_iter()
+            .max_by_key(|(_, count)| **count)
+            .filter(|(_, count)| **count > 0)
+            .map(|((z, y, x), _)| {
+                (glm::vec3(x as f32, y as f32, z as f32) + glm::vec3(0.5, 0.5, 0.5)) * block as f32
+            });
+        let target = densest.unwrap_or(glm::vec3(cx as f32, sy as f32 * 0.5, cz as f32));
 
-        Self { voxels, colors }
+        (pos, target)
+    }
+
+    pub fn voxels(&self) -> &Array3<VoxelsFormat> {
+        &self.voxels
+    }
+
+    pub fn colors(&self) -> &Array3<ColorsFormat> {
+        &self.colors
+    }
+
+    /// shape of the array before padding, array axes.
+    pub fn shape(&self) -> (usize, usize, usize) {
+        self.shape
+    }
+
+    pub fn palette(&self) -> &[[u8; 4]] {
+        &self.palette
     }
 
     pub fn dim(&self) -> u32 {
         self.voxels.dim().0 as u32
     }
 
+    /// size of the scene before padding, in world coordinates.
+    pub fn bounds(&self) -> glm::Vec3 {
+        let (z, y, x) = self.shape;
+        glm::vec3(x as f32, y as f32, z as f32)
+    }
+
     pub fn voxels_bytes(&self) -> &[u8] {
         bytemuck::cast_slice(self.voxels.as_slice().unwrap())
     }
@@ -64,4 +522,103 @@ impl Voxels {
     pub fn colors_bytes(&self) -> &[u8] {
         bytemuck::cast_slice(self.colors.as_slice().unwrap())
     }
+
+    pub fn lightmap_bytes(&self) -> Option<&[u8]> {
+        self.lightmap
+            .as_ref()
+            .map(|lightmap| bytemuck::cast_slice(lightmap.as_slice().unwrap()))
+    }
+}
+
+/// models of the scene graph below `node`, with their translation in vox coordinates.
+fn vox_instances(
+    data: &DotVoxData,
+    node: u32,
+    offset: glm::IVec3,
+    out: &mut Vec<(u32, glm::IVec3)>,
+) {
+    match data.scenes.get(node as usize) {
+        Some(SceneNode::Transform { frames, child, .. }) => {
+            // translations are stored as a "x y z" string attribute.
+            let translation = frames
+                .first()
+                .and_then(|frame| frame.attributes.get("_t"))
+                .map(|t| {
+                    let mut c = t.split_whitespace().map(|c| c.parse().unwrap_or(0));
+                    glm::vec3(
+                        c.next().unwrap_or(0),
+                        c.next().unwrap_or(0),
+                        c.next().unwrap_or(0),
+                    )
+                })
+                .unwrap_or(glm::IVec3::zeros());
+            vox_instances(data, *child, offset + translation, out);
+        }
+        Some(SceneNode::Group { children, .. }) => {
+            for child in children {
+                vox_instances(data, *child, offset, out);
+            }
+        }
+        Some(SceneNode::Shape { models, .. }) => {
+            out.extend(models.iter().map(|model| (model.model_id, offset)));
+        }
+        None => {}
+    }
+}
+
+/// 3d dda through a `dim`^3 grid, in world coordinates. returns the first cell for which
+/// `is_solid` holds within `max_dist`, and the distance to it.
+pub fn raycast_grid(
+    dim: u32,
+    pos: &glm::Vec3,
+    dir: &glm::Vec3,
+    max_dist: f32,
+    is_solid: impl Fn(glm::IVec3) -> bool,
+) -> Option<(glm::IVec3, f32)> {
+    let dim = dim as i32;
+    let mut cell = glm::IVec3::new(
+        pos.x.floor() as i32,
+        pos.y.floor() as i32,
+        pos.z.floor() as i32,
+    );
+    let mut step = glm::IVec3::zeros();
+    let mut t_max = glm::Vec3::zeros();
+    let mut t_delta = glm::Vec3::zeros();
+
+    for a in 0..3 {
+        step[a] = if dir[a] >= 0.0 { 1 } else { -1 };
+        if dir[a] == 0.0 {
+            t_max[a] = f32::INFINITY;
+            t_delta[a] = f32::INFINITY;
+        } else {
+            t_delta[a] = (1.0 / dir[a]).abs();
+            let next = if dir[a] > 0.0 {
+                cell[a] as f32 + 1.0 - pos[a]
+            } else {
+                pos[a] - cell[a] as f32
+            };
+            t_max[a] = next * t_delta[a];
+        }
+    }
+
+    let mut t = 0.0;
+    while t <= max_dist {
+        if is_solid(cell) {
+            return Some((cell, t));
+        }
+
+        // the ray left the volume for good.
+        let leaving =
+            (0..3).any(|a| (cell[a] < 0 && step[a] < 0) || (cell[a] >= dim && step[a] > 0));
+        if leaving {
+            return None;
+        }
+
+        let a = t_max.imin();
+        t = t_max[a];
+        t_max[a] += t_delta[a];
+        cell[a] += step[a];
+    }
+
+    None
 }
@@ -0,0 +1,51 @@
+// watchdog for long frames: when several frames in a row take longer than `THRESHOLD`, e.g. after
+// raising the iterations on a weak gpu, the quality is stepped down so that the app stays
+// responsive (see `State::step_down_quality`). the frame time is the real time between two
+// updates, which includes waiting for the gpu. a single long frame, like a shader rebuild or a
+// scene load, is not enough.
+
+/// frame time above which a frame is slow, in seconds.
+pub const THRESHOLD: f32 = 0.2;
+/// slow frames in a row before stepping down.
+const SLOW_FRAMES: u32 = 3;
+/// frames ignored after a step-down, while the shaders are rebuilt.
+const COOLDOWN: u32 = 10;
+
+pub struct Watchdog {
+    pub enabled: bool,
+    /// step-downs since the start.
+    pub steps: u32,
+    slow_frames: u32,
+    cooldown: u32,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            steps: 0,
+            slow_frames: 0,
+            cooldown: 0,
+        }
+    }
+
+    /// count a frame of `frame_time` seconds, returns whether the quality should be stepped down.
+    pub fn tick(&mut self, frame_time: f32) -> bool {
+        if !self.enabled || self.cooldown > 0 {
+            self.cooldown = self.cooldown.saturating_sub(1);
+            self.slow_frames = 0;
+            return false;
+        }
+        match frame_time > THRESHOLD {
+            true => self.slow_frames += 1,
+            false => self.slow_frames = 0,
+        }
+        if self.slow_frames < SLOW_FRAMES {
+            return false;
+        }
+        self.slow_frames = 0;
+        self.cooldown = COOLDOWN;
+        self.steps += 1;
+        true
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{channel, Receiver};
+
+#[cfg(not(target_arch = "wasm32"))]
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+// hot reload: watches the shader sources, so that saving a .wgsl file reloads the shaders like
+// the R key. the events of a frame are coalesced, editors often write a file in several steps.
+// the web viewer embeds its shaders, there is nothing to watch.
+
+#[cfg(target_arch = "wasm32")]
+pub struct ShaderWatcher;
+
+#[cfg(target_arch = "wasm32")]
+impl ShaderWatcher {
+    pub fn new(_dir: &Path) -> Option<Self> {
+        None
+    }
+
+    pub fn changed(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ShaderWatcher {
+    // dropping the watcher stops it.
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ShaderWatcher {
+    /// watch the .wgsl files of `dir`. `None` if it cannot be watched, e.g. when running from an
+    /// installed binary without the sources.
+    pub fn new(dir: &Path) -> Option<Self> {
+        let (sender, events) = channel();
+        let watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            let _ = sender.send(res);
+        })
+        .and_then(|mut watcher| {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        });
+        match watcher {
+            Ok(watcher) => {
+                println!("watching the shaders in `{}`", dir.display());
+                Some(Self {
+                    _watcher: watcher,
+                    events,
+                })
+            }
+            Err(err) => {
+                eprintln!("shader hot reload disabled: {err}");
+                None
+            }
+        }
+    }
+
+    /// whether a shader was written since the last call.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.events.try_iter().flatten() {
+            changed |= matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| path.extension().is_some_and(|ext| ext == "wgsl"));
+        }
+        changed
+    }
+}
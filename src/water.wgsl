@@ -0,0 +1,65 @@
+#import "bindings.wgsl"::{ voxel_ids }
+#import "materials.wgsl"::{ material_of, is_water }
+#import "noise.wgsl"::{ value_noise }
+#import "ray.wgsl"::{ cam }
+
+// this shader is a "module" supposed to be included.
+// the shoreline of the water voxels: a foam band along the solid voxels next to them, and a
+// brighter color where the bottom is close, found with a few neighbor lookups around the hit.
+// the edge of the foam moves with the scene time.
+//
+// this module "exports":
+// fn shade_water(albedo: vec4f, voxel: vec3u, hit_pos: vec3f) -> vec4f
+//
+// this module "requires":
+// const NOISE_SEED: u32;
+
+// width of the foam band, in voxels.
+const FOAM_WIDTH: f32 = 0.4;
+const FOAM_COLOR: vec3f = vec3f(0.9, 0.95, 1.0);
+// brightening of the water right above the bottom.
+const SHALLOW_BRIGHTNESS: f32 = 0.5;
+// speed of the foam edge, in noise cells per second.
+const FOAM_SPEED: f32 = 0.3;
+
+// whether a voxel is solid and not water. false outside of the volume.
+fn is_shore(voxel: vec3i) -> bool {
+    let dim = vec3i(textureDimensions(voxel_ids));
+    if any(voxel < vec3i(0)) || any(voxel >= dim) {
+        return false;
+    }
+    let id = textureLoad(voxel_ids, vec3u(voxel), 0).r;
+    return id != 0u && !is_water(material_of(vec3u(voxel)));
+}
+
+// the albedo of a water voxel with its foam and shallow color.
+fn shade_water(albedo: vec4f, voxel: vec3u, hit_pos: vec3f) -> vec4f {
+    let cell = vec3i(voxel);
+    let p = hit_pos.xz - vec2f(voxel.xz);
+
+    // distance from the hit to the nearest shore cell around, in the horizontal plane.
+    var shore = FOAM_WIDTH;
+    for (var dz = -1; dz <= 1; dz++) {
+        for (var dx = -1; dx <= 1; dx++) {
+            if (dx != 0 || dz != 0) && is_shore(cell + vec3i(dx, 0, dz)) {
+                let lo = vec2f(f32(dx), f32(dz));
+                let d = length(max(max(lo - p, p - lo - 1.0), vec2f(0.0)));
+                shore = min(shore, d);
+            }
+        }
+    }
+    // a ragged edge rather than a clean offset of the shore.
+    let drift = vec3f(0.0, cam.time * FOAM_SPEED, 0.0);
+    let width = FOAM_WIDTH * (0.5 + value_noise(hit_pos * 3.0 + drift, #NOISE_SEED + 5u));
+    let foam = 1.0 - smoothstep(width * 0.5, width, shore);
+
+    var shallow = 0.0;
+    if is_shore(cell - vec3i(0, 1, 0)) {
+        shallow = 1.0;
+    } else if is_shore(cell - vec3i(0, 2, 0)) {
+        shallow = 0.5;
+    }
+
+    let color = albedo.rgb * (1.0 + SHALLOW_BRIGHTNESS * shallow);
+    return vec4f(mix(color, FOAM_COLOR, foam * 0.8), albedo.a);
+}
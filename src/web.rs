@@ -0,0 +1,93 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{chunks, scene::SceneMeta, voxels};
+
+// `wender export-web`: a self-contained web bundle of a scene, to be served by any static http
+// server. the bundle holds the web viewer (built separately, see `web/index.html`), the scene in
+// the chunked format and an html shell with the canvas element.
+
+/// files of the wasm-bindgen output needed by the html shell.
+const PKG_FILES: [&str; 2] = ["wender.js", "wender_bg.wasm"];
+/// name of the scene in the bundle.
+const SCENE_FILE: &str = "scene.wchunks";
+
+const HTML_SHELL: &str = include_str!("../web/index.html");
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("failed to write `{0}`: {1}")]
+    IOError(PathBuf, io::Error),
+    #[error("missing `{0}`, build the web viewer first, see `web/index.html`")]
+    MissingPackage(PathBuf),
+    #[error(transparent)]
+    SceneError(#[from] voxels::Error),
+    #[error("failed to write the chunked scene: {0}")]
+    ChunksError(#[from] chunks::Error),
+}
+
+/// write the bundle of `scene` into `out_dir`. returns whether it succeeded.
+pub fn export_web(scene: &Path, out_dir: &Path, pkg: &Path, max_dim: u32) -> bool {
+    match export(scene, out_dir, pkg, max_dim) {
+        Ok(()) => {
+            println!(
+                "wrote `{}`, serve it with any static http server",
+                out_dir.display()
+            );
+            true
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            false
+        }
+    }
+}
+
+fn export(scene: &Path, out_dir: &Path, pkg: &Path, max_dim: u32) -> Result<(), Error> {
+    // fail early, before the long scene conversion.
+    for file in PKG_FILES {
+        let path = pkg.join(file);
+        if !path.is_file() {
+            return Err(Error::MissingPackage(path));
+        }
+    }
+
+    fs::create_dir_all(out_dir).map_err(|e| Error::IOError(out_dir.to_owned(), e))?;
+
+    for file in PKG_FILES {
+        let dst = out_dir.join(file);
+        fs::copy(pkg.join(file), &dst).map_err(|e| Error::IOError(dst, e))?;
+    }
+
+    let mut voxels = voxels::Voxels::from_path(scene)?;
+    // the streamed chunks cannot be downsampled by the viewer, it is done here.
+    let levels = (0..).find(|l| voxels.dim() >> l <= max_dim.max(1)).unwrap();
+    if levels > 0 {
+        voxels = voxels.downsample(levels);
+    }
+    let scene_path = out_dir.join(SCENE_FILE);
+    let count = chunks::write(&voxels, &scene_path)?;
+    println!("wrote {count} chunks to `{}`", scene_path.display());
+
+    let meta_src = SceneMeta::path(scene);
+    if meta_src.is_file() {
+        let meta_dst = SceneMeta::path(&scene_path);
+        fs::copy(&meta_src, &meta_dst).map_err(|e| Error::IOError(meta_dst, e))?;
+    }
+
+    let title = scene
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let html = HTML_SHELL
+        .replace("{{title}}", &title)
+        .replace("{{scene}}", SCENE_FILE);
+    let html_path = out_dir.join("index.html");
+    fs::write(&html_path, html).map_err(|e| Error::IOError(html_path, e))?;
+
+    Ok(())
+}
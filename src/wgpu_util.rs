@@ -1,39 +1,437 @@
 use dot_vox::Size;
+use image::RgbaImage;
 use nalgebra_glm as glm;
+use ndarray::{s, Array3};
 use pollster::FutureExt;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::str::FromStr;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::*;
 
+use crate::accumulation::AccumulationUniform;
+use crate::brickmap::{BrickUpdate, ATLAS_BRICKS, BRICK};
+use crate::camera::CameraUniform;
+use crate::dvo::Dvo;
+use crate::error::Error;
+use crate::exposure::LUMA_HISTOGRAM_BINS;
+use crate::feedback::ITER_HISTOGRAM_BINS;
+use crate::gizmos::{GizmoVertex, Gizmos, MAX_GIZMO_VERTICES};
 use crate::preproc::{self, preprocess_shader};
-use crate::voxels::VoxelsFormat;
+use crate::probes::{MAX_PROBES, PROBE_SIZE};
+use crate::route::MAX_ROUTE_POINTS;
+use crate::voxels::{ColorsFormat, Voxels, VoxelsFormat};
 
-const OCTREE_FORMAT: TextureFormat = if cfg!(byte_voxels) {
+pub(crate) const OCTREE_FORMAT: TextureFormat = if cfg!(feature = "byte_voxels") {
     TextureFormat::R8Uint
 } else {
     TextureFormat::R32Uint
 };
 // const OCTREE_FORMAT = TextureFormat::R8Uint;
 
+pub(crate) const COLORS_FORMAT: TextureFormat = if cfg!(feature = "f16_colors") {
+    TextureFormat::Rgba16Float
+} else {
+    TextureFormat::Rgba8Unorm
+};
+
+/// how the color mipmaps are computed, negotiated with the adapter by `features::mip_path`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum MipPath {
+    /// the levels are read and written as storage textures of `COLORS_FORMAT`.
+    Storage,
+    /// the levels are read as sampled textures and computed into a buffer, which is copied into
+    /// the texture. for the adapters without read-only storage textures of `COLORS_FORMAT`, e.g.
+    /// in browsers.
+    Copy,
+}
+
+/// top-level bricks of the dvo per axis, culled against the camera frustum. must match
+/// `culling.wgsl`.
+pub(crate) const CULL_DIM: u32 = 16;
+
+/// tiles per axis of the screen, slices of the view depth and lights per cluster of the light
+/// culling. must match `clusters.wgsl`.
+pub(crate) const CLUSTER_TILES: u32 = 16;
+pub(crate) const CLUSTER_SLICES: u32 = 24;
+pub(crate) const MAX_CLUSTER_LIGHTS: u32 = 32;
+
+/// number of spherical harmonics coefficients of the sky, see `sh.wgsl`.
+pub(crate) const SKY_SH_COEFFS: usize = 9;
+
+/// size and format of the image of the slice viewer, see `slice.wgsl`.
+pub(crate) const SLICE_SIZE: u32 = 512;
+pub(crate) const SLICE_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+/// the 3d textures the slice viewer can display. must match `slice.wgsl`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub(crate) enum SliceSource {
+    Voxels = 0,
+    Dvo = 1,
+    Colors = 2,
+}
+
+impl SliceSource {
+    pub const ALL: [Self; 3] = [Self::Voxels, Self::Dvo, Self::Colors];
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SliceParams {
+    source: u32,
+    level: u32,
+    z: u32,
+    _pad: u32,
+}
+
+/// side of the bricks of the volume re-uploaded after an edit, in voxels.
+pub(crate) const EDIT_BRICK: u32 = 8;
+
+/// size of a node of the 64-tree, see `contree.wgsl`.
+const CONTREE_NODE_SIZE: BufferAddress = 16;
+
+/// size of a pixel of the path traced history, rgb as f16, see `pathtrace.wgsl`.
+const HISTORY_PIXEL_SIZE: BufferAddress = 8;
+
+/// formats of the g-buffer, see `gbuffer.wgsl`: the hit position and distance, then the voxel,
+/// material and normal packed in integers.
+const GBUFFER_POS_FORMAT: TextureFormat = TextureFormat::Rgba32Float;
+const GBUFFER_SURFACE_FORMAT: TextureFormat = TextureFormat::Rgba32Uint;
+/// format of the sun shadow and ao written by the visibility pass.
+const VISIBILITY_FORMAT: TextureFormat = TextureFormat::Rg16Float;
+/// format of the depth of the primary hits, reversed so the far distances keep their precision.
+const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+/// format of the distances of the beam pre-pass.
+const BEAM_FORMAT: TextureFormat = TextureFormat::R32Float;
+/// pixels per side of the tiles of the beam pre-pass, must match `primary.wgsl`.
+const BEAM_TILE: u32 = 8;
+
+/// number of rays cast by `WgpuState::pick`.
+pub(crate) const PICK_SAMPLES: usize = 5;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct PickResult {
+    pub pos: glm::Vec3,
+    pub t: f32,
+    pub voxel: glm::UVec3,
+    pub hit: u32,
+    /// normal of the face hit.
+    pub normal: glm::Vec3,
+    _pad: u32,
+}
+
 pub(crate) struct WgpuState {
     pub camera_buffer: Buffer,
+    /// the camera of the view-dependent passes, see `Frustum::render_camera`.
+    pub render_camera_buffer: Buffer,
     pub lights_buffer: Buffer,
+    pub local_lights_buffer: Buffer,
+    pub route_buffer: Buffer,
+    pub route_points_buffer: Buffer,
+    pub environment_buffer: Buffer,
+    pub frustum_buffer: Buffer,
+    pub settings_buffer: Buffer,
+    pub probes_buffer: Buffer,
+    pub fog_volumes_buffer: Buffer,
+    pub materials_buffer: Buffer,
+    pub accumulation_buffer: Buffer,
+    sky_sh_buffer: Buffer,
+    iter_histogram_buffer: Buffer,
+    luma_histogram_buffer: Buffer,
+    /// the average of the path traced frames, one pixel unless a pipeline path traces.
+    history_buffer: Buffer,
     octree_texture: Texture,
     voxels_texture: Texture,
     colors_texture: Texture,
+    lightmap_texture: Texture,
+    probes_texture: Texture,
+    sdf_texture: Texture,
+    contours_texture: Texture,
+    brick_index_texture: Texture,
+    brick_atlas_texture: Texture,
+    contree_buffer: Buffer,
+    contree_count_buffer: Buffer,
+    /// the bricks in the camera frustum, written by the culling pass, see `culling.wgsl`.
+    visible_bricks_buffer: Buffer,
+    /// the local lights of each cluster of the view frustum, written by the light culling pass,
+    /// see `clusters.wgsl`.
+    cluster_lights_buffer: Buffer,
+    detail_texture: Texture,
     vertex_buffer: Buffer,
+    gizmos_buffer: Buffer,
+    /// vertices of `gizmos_buffer` drawn, see `write_gizmos`.
+    gizmo_vertices: u32,
 
     uniforms_bind_group: BindGroup,
     octree_bind_group: BindGroup,
+    sky_sh_bind_group: BindGroup,
+    gizmos_bind_group: BindGroup,
+    culling_bind_group: BindGroup,
+    light_culling_bind_group: BindGroup,
 
-    render_pipeline: RenderPipeline,
+    scene_pipelines: ScenePipelines,
+    gbuffer: GBuffer,
+    gizmos_pipeline: RenderPipeline,
+    blit_pipeline: RenderPipeline,
+    slice_pipeline: RenderPipeline,
+    slice_texture: Texture,
     octree_pipeline: ComputePipeline,
     mipmap_pipeline: ComputePipeline,
+    mip_path: MipPath,
+    pick_pipeline: ComputePipeline,
+    sky_sh_pipeline: ComputePipeline,
+    sdf_pipelines: SdfPipelines,
+    contours_pipeline: ComputePipeline,
+    contree_pipeline: ComputePipeline,
+    culling_pipeline: ComputePipeline,
+    light_culling_pipeline: ComputePipeline,
+
+    /// the scene is rendered here instead of the window when the render resolution differs.
+    scene_target: Option<SceneTarget>,
+    /// the right of the divider is rendered with another profile.
+    compare: Option<ComparePass>,
+    /// bricks of the volume edited since the last upload, in bricks of `EDIT_BRICK` voxels.
+    dirty_bricks: HashSet<glm::UVec3>,
+    /// counts the writes to the volume, the path traced average restarts when it changes.
+    volume_version: Cell<u64>,
+    pub(crate) profiler: GpuProfiler,
+    /// errors of the last shader reload, shown in the ui until dismissed.
+    pub(crate) shader_errors: Vec<String>,
+    /// the constants of the last shader build.
+    constants: ShaderConstants,
+}
+
+/// the passes of the distance field jump flooding, see `compute_sdf.wgsl`.
+struct SdfPipelines {
+    seed: ComputePipeline,
+    jump: ComputePipeline,
+    distance: ComputePipeline,
+}
+
+struct SceneTarget {
+    view: TextureView,
+    bind_group: BindGroup,
+    size: (u32, u32),
+}
+
+/// the passes of the deferred renderer, built with the same constants. they share the bind group
+/// layouts, the lighting pass uses all of them.
+struct ScenePipelines {
+    /// one ray per corner of the tiles of the primary pass, see `beam.wgsl`.
+    beam: RenderPipeline,
+    /// traces the camera rays into the g-buffer, see `primary.wgsl`.
+    primary: RenderPipeline,
+    /// the sun shadows and the ao of the g-buffer, see `visibility.wgsl`.
+    visibility: RenderPipeline,
+    /// shades the g-buffer, see `shader.wgsl`.
+    lighting: RenderPipeline,
+}
+
+/// the g-buffer and the visibility target, at least as large as the targets the scene is drawn
+/// to. the passes read them at the pixel they shade.
+struct GBuffer {
+    pos_view: TextureView,
+    surface_view: TextureView,
+    /// the depth of the primary hits, for the gizmos.
+    depth_view: TextureView,
+    visibility_view: TextureView,
+    /// the distances of the beam pre-pass, one texel per corner of the tiles.
+    beam_view: TextureView,
+    /// the g-buffer, read by the visibility and lighting passes.
+    bind_group: BindGroup,
+    /// the visibility target, read by the lighting pass.
+    visibility_bind_group: BindGroup,
+    /// the beam target, read by the primary pass.
+    beam_bind_group: BindGroup,
+    size: (u32, u32),
+}
+
+/// a part of the target drawn with its own pipelines, see `WgpuState::draw_sides`.
+struct Side<'a> {
+    pipelines: &'a ScenePipelines,
+    uniforms_bind_group: &'a BindGroup,
+    /// x and width of the scissor rect, and the height of the target. the whole target if None.
+    scissor: Option<(u32, u32, u32)>,
+}
+
+/// the profile b of the split-screen comparison, see `compare.rs`. it shares every buffer of the
+/// render pipeline but the settings.
+struct ComparePass {
+    pipelines: ScenePipelines,
+    settings_buffer: Buffer,
+    uniforms_bind_group: BindGroup,
+    constants: ShaderConstants,
+    /// x of the divider and size of the target, in pixels.
+    split: (u32, u32, u32),
+}
+
+/// gpu time of the passes, measured with timestamp queries when the device supports them.
+/// the passes ask for the timestamp writes of a named scope, the queries are resolved at the end
+/// of the frame and the scopes of the same name are added up, e.g. the levels of the octree.
+pub(crate) struct GpuProfiler {
+    pub enabled: bool,
+    /// measure the passes while the profiler is disabled, for the dynamic resolution.
+    pub frame_timing: bool,
+    /// the query set, the resolve buffer and the readback buffer.
+    queries: Option<(QuerySet, Buffer, Buffer)>,
+    /// nanoseconds per timestamp tick.
+    period: f32,
+    scopes: RefCell<Vec<&'static str>>,
+    /// scopes resolved by the last `resolve`, the later ones are dropped.
+    resolved: Cell<usize>,
+    /// last measured time of each scope, in milliseconds.
+    pub timings: Vec<(&'static str, f32)>,
+    /// gpu time of all the passes of the last measured frame, in milliseconds.
+    pub frame_ms: f32,
+}
+
+impl GpuProfiler {
+    const MAX_SCOPES: u32 = 64;
+
+    pub(crate) fn new(device: &Device, queue: &Queue) -> Self {
+        let queries = device
+            .features()
+            .contains(Features::TIMESTAMP_QUERY)
+            .then(|| {
+                let count = Self::MAX_SCOPES * 2;
+                let size = count as BufferAddress * QUERY_SIZE as BufferAddress;
+                let query_set = device.create_query_set(&QuerySetDescriptor {
+                    label: Some("profiler query set"),
+                    ty: QueryType::Timestamp,
+                    count,
+                });
+                let resolve_buffer = device.create_buffer(&BufferDescriptor {
+                    label: Some("profiler resolve buffer"),
+                    size,
+                    usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                });
+                let readback_buffer = device.create_buffer(&BufferDescriptor {
+                    label: Some("profiler readback buffer"),
+                    size,
+                    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                });
+                (query_set, resolve_buffer, readback_buffer)
+            });
+        Self {
+            enabled: false,
+            frame_timing: false,
+            queries,
+            period: queue.get_timestamp_period(),
+            scopes: RefCell::new(Vec::new()),
+            resolved: Cell::new(0),
+            timings: Vec::new(),
+            frame_ms: 0.0,
+        }
+    }
+
+    /// whether the device supports timestamp queries.
+    pub(crate) fn supported(&self) -> bool {
+        self.queries.is_some()
+    }
+
+    /// the query set and the index of the first of the two queries of a new scope, or None when
+    /// not profiling.
+    fn begin_scope(&self, label: &'static str) -> Option<(&QuerySet, u32)> {
+        let (query_set, ..) = self
+            .queries
+            .as_ref()
+            .filter(|_| self.enabled || self.frame_timing)?;
+        let mut scopes = self.scopes.borrow_mut();
+        if scopes.len() as u32 == Self::MAX_SCOPES {
+            return None;
+        }
+        scopes.push(label);
+        Some((query_set, (scopes.len() as u32 - 1) * 2))
+    }
+
+    pub(crate) fn compute_writes(&self, label: &'static str) -> Option<ComputePassTimestampWrites> {
+        let (query_set, index) = self.begin_scope(label)?;
+        Some(ComputePassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    pub(crate) fn render_writes(&self, label: &'static str) -> Option<RenderPassTimestampWrites> {
+        let (query_set, index) = self.begin_scope(label)?;
+        Some(RenderPassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// resolve the queries of the scopes recorded so far, after the last pass of the frame.
+    pub(crate) fn resolve(&self, encoder: &mut CommandEncoder) {
+        let Some((query_set, resolve_buffer, readback_buffer)) = &self.queries else {
+            return;
+        };
+        let scopes = self.scopes.borrow().len();
+        self.resolved.set(scopes);
+        if scopes == 0 {
+            return;
+        }
+        let count = scopes as u32 * 2;
+        let size = count as BufferAddress * QUERY_SIZE as BufferAddress;
+        encoder.resolve_query_set(query_set, 0..count, resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(resolve_buffer, 0, readback_buffer, 0, size);
+    }
+
+    /// read back the resolved queries once the frame is submitted, and update `timings`.
+    pub(crate) fn read(&mut self, device: &Device) {
+        let mut scopes = std::mem::take(self.scopes.get_mut());
+        scopes.truncate(self.resolved.take());
+        let Some((_, _, readback_buffer)) = &self.queries else {
+            return;
+        };
+        if scopes.is_empty() {
+            return;
+        }
+
+        let size = scopes.len() as BufferAddress * 2 * QUERY_SIZE as BufferAddress;
+        let slice = readback_buffer.slice(..size);
+        slice.map_async(MapMode::Read, |res| {
+            res.expect("failed to map profiler buffer")
+        });
+        device.poll(Maintain::Wait);
+        let ticks: Vec<u64> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        readback_buffer.unmap();
+
+        let mut frame: Vec<(&'static str, f32)> = Vec::new();
+        for (label, pair) in scopes.iter().zip(ticks.chunks_exact(2)) {
+            let ms = pair[1].saturating_sub(pair[0]) as f32 * self.period / 1e6;
+            match frame.iter_mut().find(|(l, _)| l == label) {
+                Some((_, total)) => *total += ms,
+                None => frame.push((label, ms)),
+            }
+        }
+        self.frame_ms = frame.iter().map(|(_, ms)| ms).sum();
+        for (label, ms) in frame {
+            match self.timings.iter_mut().find(|(l, _)| *l == label) {
+                Some((_, last)) => *last = ms,
+                None => self.timings.push((label, ms)),
+            }
+        }
+    }
 }
 
+// the values of `ShaderConstants::traversal`, the `TRAVERSAL` shader def. must match
+// traversal.wgsl.
+pub(crate) const TRAVERSAL_DVO: u32 = 0;
+pub(crate) const TRAVERSAL_SDF: u32 = 1;
+pub(crate) const TRAVERSAL_BRICKS: u32 = 2;
+pub(crate) const TRAVERSAL_CONTREE: u32 = 3;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub(crate) struct ShaderConstants {
     pub octree_depth: u32,
     pub octree_max_iter: u32,
@@ -45,16 +443,96 @@ pub(crate) struct ShaderConstants {
     pub ao_strength: u32,
     pub msaa_level: u32,
     pub debug_display: u32,
+    /// color map of the debug displays, 0: one channel per quantity, 1: viridis, 2: cividis.
+    pub debug_palette: u32,
+    pub baked_lighting: u32,
+    pub noise_seed: u32,
+    /// primary rays traversal: dvo descent, distance field, brick map or 64-tree, see
+    /// `TRAVERSAL_DVO` and the next constants.
+    pub traversal: u32,
+    pub sdf_max_iter: u32,
+    /// clip the voxels hit by primary rays with their contour, see `SceneMeta::contours`.
+    pub contours: u32,
+    /// record the iterations histogram, see `feedback.rs`.
+    pub iter_feedback: u32,
+    pub show_aabb_misses: u32,
+    /// record the luminance histogram, see `exposure.rs`.
+    pub auto_exposure: u32,
+    /// map the block textures on the voxels near the camera, see `detail.rs`.
+    pub detail_textures: u32,
+    pub detail_distance: u32,
+    /// filter the albedo of distant voxels with the color mip of the pixel footprint, see
+    /// `footprint.wgsl`.
+    pub footprint_lod: u32,
+    /// kernel of the color mipmaps, 0: box, 1: gaussian, 2: occupancy-weighted, 3: dominant
+    /// color. see `mipmap.wgsl`.
+    pub mip_filter: u32,
+    /// 0: raymarched, 1: path traced and accumulated over the frames, see `accumulation.rs`.
+    pub render_mode: u32,
 }
 
 pub(crate) struct Buffers<'a> {
     pub camera: &'a [u8],
     pub lights: &'a [u8],
+    pub local_lights: &'a [u8],
+    pub route: &'a [u8],
+    pub environment: &'a [u8],
+    pub frustum: &'a [u8],
+    pub settings: &'a [u8],
+    pub probes: &'a [u8],
+    pub fog_volumes: &'a [u8],
+    pub materials: &'a [u8],
     pub voxels: &'a [u8],
     pub colors: &'a [u8],
+    pub lightmap: Option<&'a [u8]>,
+}
+
+impl Default for ShaderConstants {
+    fn default() -> Self {
+        let grid_depth = 2;
+        Self {
+            octree_depth: 8,
+            octree_max_iter: 200,
+            grid_depth,
+            grid_max_iter: 2u32.pow(grid_depth) * 4,
+            shadow_max_iter: 100,
+            shadow_cone_angle: 1,
+            shadow_strength: 10,
+            ao_strength: 10,
+            msaa_level: 1,
+            debug_display: 0,
+            debug_palette: 0,
+            baked_lighting: 0,
+            noise_seed: 0,
+            traversal: TRAVERSAL_DVO,
+            sdf_max_iter: 512,
+            contours: 0,
+            iter_feedback: 0,
+            show_aabb_misses: 0,
+            auto_exposure: 0,
+            detail_textures: 0,
+            detail_distance: 32,
+            footprint_lod: 0,
+            mip_filter: 0,
+            render_mode: 0,
+        }
+    }
 }
 
 impl ShaderConstants {
+    /// these constants, with the ones that describe the scene taken from `scene`.
+    pub fn with_scene(&self, scene: &Self) -> Self {
+        Self {
+            octree_depth: scene.octree_depth,
+            baked_lighting: scene.baked_lighting,
+            noise_seed: scene.noise_seed,
+            contours: scene.contours,
+            detail_textures: scene.detail_textures,
+            detail_distance: scene.detail_distance,
+            ..self.clone()
+        }
+    }
+
     pub fn to_hashmap(&self) -> HashMap<String, f64> {
         HashMap::from([
             ("OCTREE_DEPTH".to_owned(), self.octree_depth as f64),
@@ -70,6 +548,25 @@ impl ShaderConstants {
             ("AO_STRENGTH".to_owned(), self.ao_strength as f64),
             ("MSAA_LEVEL".to_owned(), self.msaa_level as f64),
             ("DEBUG_DISPLAY".to_owned(), self.debug_display as f64),
+            ("DEBUG_PALETTE".to_owned(), self.debug_palette as f64),
+            ("BAKED_LIGHTING".to_owned(), self.baked_lighting as f64),
+            ("NOISE_SEED".to_owned(), self.noise_seed as f64),
+            ("TRAVERSAL".to_owned(), self.traversal as f64),
+            ("SDF_MAX_ITER".to_owned(), self.sdf_max_iter as f64),
+            ("CONTOURS".to_owned(), self.contours as f64),
+            ("ITER_FEEDBACK".to_owned(), self.iter_feedback as f64),
+            ("SHOW_AABB_MISSES".to_owned(), self.show_aabb_misses as f64),
+            ("AUTO_EXPOSURE".to_owned(), self.auto_exposure as f64),
+            ("DETAIL_TEXTURES".to_owned(), self.detail_textures as f64),
+            ("DETAIL_DISTANCE".to_owned(), self.detail_distance as f64),
+            ("FOOTPRINT_LOD".to_owned(), self.footprint_lod as f64),
+            ("MIP_FILTER".to_owned(), self.mip_filter as f64),
+            ("RENDER_MODE".to_owned(), self.render_mode as f64),
+            ("PICK_SAMPLES".to_owned(), PICK_SAMPLES as f64),
+            (
+                "COLORS_F16".to_owned(),
+                (COLORS_FORMAT == TextureFormat::Rgba16Float) as u32 as f64,
+            ),
             (
                 "OCTREE_FORMAT".to_owned(),
                 (OCTREE_FORMAT.target_pixel_byte_cost().unwrap() * 8) as f64,
@@ -85,779 +582,4214 @@ impl WgpuState {
         surface_config: &SurfaceConfiguration,
         buffers: &Buffers,
         constants: &ShaderConstants,
-    ) -> Self {
+        color_mips: bool,
+        mip_path: MipPath,
+    ) -> Result<Self, Error> {
         let dim = 2u32.pow(constants.octree_depth + 1);
-        let render_pipeline = create_shader_pipeline(device, surface_config, constants).unwrap();
-        let octree_pipeline = create_octree_pipeline(device, constants).unwrap();
-        let mipmap_pipeline = create_mipmap_pipeline(device, constants).unwrap();
+        let scene_pipelines = create_scene_pipelines(device, surface_config, constants)
+            .map_err(|_| Error::ShaderError)?;
+        let octree_pipeline =
+            create_octree_pipeline(device, constants).map_err(|_| Error::ShaderError)?;
+        let mipmap_pipeline =
+            create_mipmap_pipeline(device, constants, mip_path).map_err(|_| Error::ShaderError)?;
+        let pick_pipeline =
+            create_pick_pipeline(device, constants).map_err(|_| Error::ShaderError)?;
+        let sky_sh_pipeline =
+            create_sky_sh_pipeline(device, constants).map_err(|_| Error::ShaderError)?;
+        let sdf_pipelines =
+            create_sdf_pipelines(device, constants).map_err(|_| Error::ShaderError)?;
+        let contours_pipeline =
+            create_contours_pipeline(device, constants).map_err(|_| Error::ShaderError)?;
+        let contree_pipeline =
+            create_contree_pipeline(device, constants).map_err(|_| Error::ShaderError)?;
+        let culling_pipeline =
+            create_culling_pipeline(device, constants).map_err(|_| Error::ShaderError)?;
+        let light_culling_pipeline =
+            create_light_culling_pipeline(device, constants).map_err(|_| Error::ShaderError)?;
+        let gizmos_pipeline =
+            create_gizmos_pipeline(device, surface_config).map_err(|_| Error::ShaderError)?;
+        let blit_pipeline =
+            create_blit_pipeline(device, surface_config).map_err(|_| Error::ShaderError)?;
+        let slice_pipeline = create_slice_pipeline(device).map_err(|_| Error::ShaderError)?;
+        let slice_texture = create_scene_texture(device, SLICE_FORMAT, SLICE_SIZE, SLICE_SIZE);
 
         let camera_buffer = create_camera_buffer(device, buffers.camera);
+        let render_camera_buffer = create_camera_buffer(device, buffers.camera);
         let lights_buffer = create_lights_buffer(device, buffers.lights);
+        let local_lights_buffer = create_local_lights_buffer(device, buffers.local_lights);
+        let route_buffer = create_route_buffer(device, buffers.route);
+        let route_points_buffer = create_route_points_buffer(device);
+        let environment_buffer = create_environment_buffer(device, buffers.environment);
+        let frustum_buffer = create_frustum_buffer(device, buffers.frustum);
+        let settings_buffer = create_settings_buffer(device, buffers.settings);
+        let probes_buffer = create_probes_buffer(device, buffers.probes);
+        let fog_volumes_buffer = create_fog_volumes_buffer(device, buffers.fog_volumes);
+        let sky_sh_buffer = create_sky_sh_buffer(device);
+        let iter_histogram_buffer = create_iter_histogram_buffer(device);
+        let luma_histogram_buffer = create_luma_histogram_buffer(device);
+        let accumulation_buffer = create_accumulation_buffer(device);
+        let history_buffer = create_history_buffer(device, 1);
         let octree_texture = create_octree_texture(device, dim);
-        let colors_texture = create_colors_texture(device, queue, dim, buffers.colors);
+        let colors_texture =
+            create_colors_texture(device, queue, dim, buffers.colors, color_mips, mip_path);
         let vertex_buffer = create_vertex_buffer(device);
+        let gizmos_buffer = create_gizmos_buffer(device);
         let voxels_texture = create_voxels_texture(device, queue, dim, buffers.voxels);
+        let lightmap_texture = create_lightmap_texture(device, queue, dim, buffers.lightmap);
+        let probes_texture = create_probes_texture(device, surface_config.format);
+        let sdf_texture = create_sdf_texture(device, dim);
+        let contours_texture = create_contours_texture(device, dim, constants.contours != 0);
+        let (brick_index_texture, brick_atlas_texture) =
+            create_brick_textures(device, dim, constants.traversal == TRAVERSAL_BRICKS);
+        let contree_buffer =
+            create_contree_buffer(device, dim, constants.traversal == TRAVERSAL_CONTREE);
+        let contree_count_buffer = create_contree_count_buffer(device);
+        let visible_bricks_buffer = create_visible_bricks_buffer(device);
+        let cluster_lights_buffer = create_cluster_lights_buffer(device);
+        let detail_texture = create_detail_texture(device, queue, None);
+        let materials_buffer = create_materials_buffer(device, buffers.materials);
 
+        let gbuffer = create_gbuffer(
+            device,
+            &scene_pipelines,
+            surface_config.width,
+            surface_config.height,
+        );
         let uniforms_bind_group = create_uniforms_bind_group(
             device,
-            &render_pipeline.get_bind_group_layout(0),
+            &scene_pipelines.lighting.get_bind_group_layout(0),
+            &camera_buffer,
+            &render_camera_buffer,
+            &lights_buffer,
+            &route_buffer,
+            &route_points_buffer,
+            &environment_buffer,
+            &frustum_buffer,
+            &settings_buffer,
+            &sky_sh_buffer,
+            &probes_buffer,
+            &fog_volumes_buffer,
+            &iter_histogram_buffer,
+            &luma_histogram_buffer,
+            &local_lights_buffer,
+            &accumulation_buffer,
+            &history_buffer,
+        );
+        let sky_sh_bind_group = create_sky_sh_bind_group(
+            device,
+            &sky_sh_pipeline.get_bind_group_layout(0),
+            &lights_buffer,
+            &environment_buffer,
+            &settings_buffer,
+            &sky_sh_buffer,
+        );
+        let gizmos_bind_group = create_gizmos_bind_group(
+            device,
+            &gizmos_pipeline.get_bind_group_layout(0),
             &camera_buffer,
+        );
+        let culling_bind_group = create_culling_bind_group(
+            device,
+            &culling_pipeline.get_bind_group_layout(0),
+            &render_camera_buffer,
+            &visible_bricks_buffer,
+        );
+        let light_culling_bind_group = create_light_culling_bind_group(
+            device,
+            &light_culling_pipeline.get_bind_group_layout(0),
+            &render_camera_buffer,
             &lights_buffer,
+            &local_lights_buffer,
+            &cluster_lights_buffer,
         );
         let octree_bind_group = create_octree_bind_group(
             device,
-            &render_pipeline.get_bind_group_layout(1),
+            &scene_pipelines.lighting.get_bind_group_layout(1),
             &octree_texture,
             &colors_texture,
+            &lightmap_texture,
+            &probes_texture,
+            &sdf_texture,
+            &contours_texture,
+            &brick_index_texture,
+            &brick_atlas_texture,
+            &contree_buffer,
+            &detail_texture,
+            &voxels_texture,
+            &materials_buffer,
+            &visible_bricks_buffer,
+            &cluster_lights_buffer,
         );
-        Self {
+        let state = Self {
             camera_buffer,
+            render_camera_buffer,
             lights_buffer,
+            local_lights_buffer,
+            route_buffer,
+            route_points_buffer,
+            environment_buffer,
+            frustum_buffer,
+            settings_buffer,
+            probes_buffer,
+            fog_volumes_buffer,
+            materials_buffer,
+            accumulation_buffer,
+            sky_sh_buffer,
+            iter_histogram_buffer,
+            luma_histogram_buffer,
+            history_buffer,
             octree_texture,
             voxels_texture,
             colors_texture,
+            lightmap_texture,
+            probes_texture,
+            sdf_texture,
+            contours_texture,
+            brick_index_texture,
+            brick_atlas_texture,
+            contree_buffer,
+            contree_count_buffer,
+            visible_bricks_buffer,
+            cluster_lights_buffer,
+            detail_texture,
             vertex_buffer,
+            gizmos_buffer,
+            gizmo_vertices: 0,
 
             uniforms_bind_group,
             octree_bind_group,
+            sky_sh_bind_group,
+            gizmos_bind_group,
+            culling_bind_group,
+            light_culling_bind_group,
 
-            render_pipeline,
+            scene_pipelines,
+            gbuffer,
+            gizmos_pipeline,
+            blit_pipeline,
+            slice_pipeline,
+            slice_texture,
             octree_pipeline,
             mipmap_pipeline,
+            mip_path,
+            pick_pipeline,
+            sky_sh_pipeline,
+            sdf_pipelines,
+            contours_pipeline,
+            contree_pipeline,
+            culling_pipeline,
+            light_culling_pipeline,
+
+            scene_target: None,
+            compare: None,
+            dirty_bricks: HashSet::new(),
+            volume_version: Cell::new(0),
+            profiler: GpuProfiler::new(device, queue),
+            shader_errors: Vec::new(),
+            constants: constants.clone(),
+        };
+        state.project_sky(device, queue);
+        Ok(state)
+    }
+
+    /// draw the scene into `view`, a target of `size` pixels. the g-buffer must be at least as
+    /// large, see `reserve_gbuffer`.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn draw(&self, view: &TextureView, size: (u32, u32), encoder: &mut CommandEncoder) {
+        let side = Side {
+            pipelines: &self.scene_pipelines,
+            uniforms_bind_group: &self.uniforms_bind_group,
+            scissor: None,
+        };
+        self.draw_sides(view, size, encoder, &[side]);
+    }
+
+    /// draw the scene, the right of the divider with the profile b when comparing.
+    fn draw_split(&self, view: &TextureView, size: (u32, u32), encoder: &mut CommandEncoder) {
+        let Some(compare) = &self.compare else {
+            return self.draw(view, size, encoder);
+        };
+        let (split, width, height) = compare.split;
+        let sides = [
+            Side {
+                pipelines: &self.scene_pipelines,
+                uniforms_bind_group: &self.uniforms_bind_group,
+                scissor: Some((0, split, height)),
+            },
+            Side {
+                pipelines: &compare.pipelines,
+                uniforms_bind_group: &compare.uniforms_bind_group,
+                scissor: Some((split, width - split, height)),
+            },
+        ];
+        self.draw_sides(view, size, encoder, &sides);
+    }
+
+    /// draw the three passes of the deferred renderer, each side with its own pipelines. the
+    /// g-buffer passes are restricted to the `size` of the target.
+    fn draw_sides(
+        &self,
+        view: &TextureView,
+        size: (u32, u32),
+        encoder: &mut CommandEncoder,
+        sides: &[Side],
+    ) {
+        self.cull_bricks(encoder);
+        self.cull_lights(encoder);
+        self.draw_beams(size, encoder, sides);
+
+        let gbuffer = &self.gbuffer;
+        debug_assert!(size.0 <= gbuffer.size.0 && size.1 <= gbuffer.size.1);
+        let attachment = |view| {
+            Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: StoreOp::Store,
+                },
+            })
+        };
+        let passes: [(&str, &'static str, &[Option<RenderPassColorAttachment>]); 3] = [
+            (
+                "primary pass",
+                "primary rays",
+                &[
+                    attachment(&gbuffer.pos_view),
+                    attachment(&gbuffer.surface_view),
+                ],
+            ),
+            (
+                "visibility pass",
+                "shadows and ao",
+                &[attachment(&gbuffer.visibility_view)],
+            ),
+            ("lighting pass", "lighting", &[attachment(view)]),
+        ];
+
+        for (i, (label, scope, color_attachments)) in passes.into_iter().enumerate() {
+            // the primary pass writes the depth of its hits, the sky is at 0.
+            let depth_stencil_attachment = (i == 0).then(|| RenderPassDepthStencilAttachment {
+                view: &gbuffer.depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(0.0),
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            });
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some(label),
+                color_attachments,
+                depth_stencil_attachment,
+                timestamp_writes: self.profiler.render_writes(scope),
+                ..Default::default()
+            });
+            render_pass.set_viewport(0.0, 0.0, size.0 as f32, size.1 as f32, 0.0, 1.0);
+            render_pass.set_bind_group(1, &self.octree_bind_group, &[]);
+            if i == 0 {
+                render_pass.set_bind_group(2, &gbuffer.beam_bind_group, &[]);
+            } else {
+                render_pass.set_bind_group(2, &gbuffer.bind_group, &[]);
+            }
+            if i > 1 {
+                render_pass.set_bind_group(3, &gbuffer.visibility_bind_group, &[]);
+            }
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            for side in sides {
+                if let Some((x, w, height)) = side.scissor {
+                    if w == 0 {
+                        continue;
+                    }
+                    render_pass.set_scissor_rect(x, 0, w, height);
+                }
+                let pipelines = side.pipelines;
+                let pipeline = [
+                    &pipelines.primary,
+                    &pipelines.visibility,
+                    &pipelines.lighting,
+                ][i];
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(0, side.uniforms_bind_group, &[]);
+                render_pass.draw(0..6, 0..1);
+            }
         }
     }
 
-    pub(crate) fn draw(&self, view: &TextureView, encoder: &mut CommandEncoder) {
+    /// trace the corners of the tiles of the primary pass for a target of `size` pixels into the
+    /// beam target. the viewport is offset by half a texel, so the texel (x, y) is at the corner
+    /// of the pixel (8x, 8y) and not at its center.
+    fn draw_beams(&self, size: (u32, u32), encoder: &mut CommandEncoder, sides: &[Side]) {
+        let gbuffer = &self.gbuffer;
         let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-            label: Some("render Pass"),
+            label: Some("beam pass"),
             color_attachments: &[Some(RenderPassColorAttachment {
-                view: &view,
+                view: &gbuffer.beam_view,
                 resolve_target: None,
                 ops: Operations {
-                    load: LoadOp::Clear(Color::BLACK),
+                    // the texels outside the viewport start the rays at the camera.
+                    load: LoadOp::Clear(Color::TRANSPARENT),
                     store: StoreOp::Store,
                 },
             })],
+            timestamp_writes: self.profiler.render_writes("beam pre-pass"),
             ..Default::default()
         });
-
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.uniforms_bind_group, &[]);
+        let tile = BEAM_TILE as f32;
+        render_pass.set_viewport(
+            0.5,
+            0.5,
+            size.0 as f32 / tile,
+            size.1 as f32 / tile,
+            0.0,
+            1.0,
+        );
         render_pass.set_bind_group(1, &self.octree_bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.draw(0..6, 0..1);
-    }
-
-    pub(crate) fn compute_octree(
-        &self,
-        device: &Device,
-        encoder: &mut CommandEncoder,
-        mut dim: u32,
-    ) {
-        let mut depth = 0;
-
-        fn compute_single_pass(
-            pipeline: &ComputePipeline,
-            device: &Device,
-            encoder: &mut CommandEncoder,
-            input_view: &TextureView,
-            output_view: &TextureView,
-            dim: u32,
-        ) {
-            let bind_group = device.create_bind_group(&BindGroupDescriptor {
-                label: Some("compute bind group"),
-                layout: &pipeline.get_bind_group_layout(0),
-                entries: &[
-                    BindGroupEntry {
-                        binding: 0,
-                        resource: BindingResource::TextureView(&input_view),
-                    },
-                    BindGroupEntry {
-                        binding: 1,
-                        resource: BindingResource::TextureView(&output_view),
-                    },
-                ],
-            });
-
-            {
-                let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-                    label: Some("compute pass"),
-                    timestamp_writes: None,
-                });
-                compute_pass.set_pipeline(pipeline);
-                compute_pass.set_bind_group(0, &bind_group, &[]);
-                compute_pass.dispatch_workgroups(dim / 2, dim / 2, dim / 2);
+        let (width, height) = beam_size(gbuffer.size);
+        for side in sides {
+            if let Some((x, w, _)) = side.scissor {
+                if w == 0 {
+                    continue;
+                }
+                // the corners of the tiles the scissor rect overlaps.
+                let start = x / BEAM_TILE;
+                let end = ((x + w).div_ceil(BEAM_TILE) + 1).min(width);
+                render_pass.set_scissor_rect(start, 0, end - start, height);
             }
+            render_pass.set_pipeline(&side.pipelines.beam);
+            render_pass.set_bind_group(0, side.uniforms_bind_group, &[]);
+            render_pass.draw(0..6, 0..1);
         }
+    }
 
-        // first pass
-        {
-            let input_view = self.voxels_texture.create_view(&TextureViewDescriptor {
-                label: Some("input texture view"),
-                ..Default::default()
-            });
-
-            let output_view = self.octree_texture.create_view(&TextureViewDescriptor {
-                label: Some("output texture view"),
-                base_mip_level: 0,
-                mip_level_count: Some(1),
-                ..Default::default()
-            });
-
-            println!("compute octree, depth={depth}, dim={dim}");
-            compute_single_pass(
-                &self.octree_pipeline,
-                device,
-                encoder,
-                &input_view,
-                &output_view,
-                dim,
-            );
-            dim /= 2;
-            depth += 1;
-        }
-
-        // next passes
-        while dim > 1 {
-            let input_view = self.octree_texture.create_view(&TextureViewDescriptor {
-                label: Some("input texture view"),
-                base_mip_level: depth - 1,
-                mip_level_count: Some(1),
-                ..Default::default()
-            });
+    /// mark the top-level bricks in the frustum of the camera in `render_camera_buffer`, for the
+    /// primary rays of the next passes.
+    fn cull_bricks(&self, encoder: &mut CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("culling pass"),
+            timestamp_writes: self.profiler.compute_writes("brick culling"),
+        });
+        compute_pass.set_pipeline(&self.culling_pipeline);
+        compute_pass.set_bind_group(0, &self.culling_bind_group, &[]);
+        let workgroups = CULL_DIM.div_ceil(4);
+        compute_pass.dispatch_workgroups(workgroups, workgroups, workgroups);
+    }
 
-            let output_view = self.octree_texture.create_view(&TextureViewDescriptor {
-                label: Some("output texture view"),
-                base_mip_level: depth,
-                mip_level_count: Some(1),
-                ..Default::default()
-            });
+    /// list the local lights reaching each cluster of the frustum of the camera in
+    /// `render_camera_buffer`, for the lighting pass.
+    fn cull_lights(&self, encoder: &mut CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("light culling pass"),
+            timestamp_writes: self.profiler.compute_writes("light culling"),
+        });
+        compute_pass.set_pipeline(&self.light_culling_pipeline);
+        compute_pass.set_bind_group(0, &self.light_culling_bind_group, &[]);
+        let tiles = CLUSTER_TILES.div_ceil(4);
+        compute_pass.dispatch_workgroups(tiles, tiles, CLUSTER_SLICES.div_ceil(4));
+    }
 
-            println!("compute octree, depth={depth}, dim={dim}");
-            compute_single_pass(
-                &self.octree_pipeline,
-                device,
-                encoder,
-                &input_view,
-                &output_view,
-                dim,
-            );
-            dim /= 2;
-            depth += 1;
+    /// render the right of the divider with the shaders built with `constants`, or stop comparing
+    /// if None. the shaders are rebuilt only when the constants change. returns false if they
+    /// failed to compile, the errors are added to `shader_errors`.
+    pub(crate) fn set_compare(
+        &mut self,
+        device: &Device,
+        surface_config: &SurfaceConfiguration,
+        constants: Option<&ShaderConstants>,
+    ) -> bool {
+        let Some(constants) = constants else {
+            self.compare = None;
+            return true;
+        };
+        if self
+            .compare
+            .as_ref()
+            .is_some_and(|compare| compare.constants == *constants)
+        {
+            return true;
         }
+        let pipelines = match create_scene_pipelines(device, surface_config, constants) {
+            Ok(pipelines) => pipelines,
+            Err(err) => {
+                self.shader_errors.push(err);
+                self.compare = None;
+                return false;
+            }
+        };
+        let settings_buffer = create_settings_buffer(device, &[0; 16]);
+        let uniforms_bind_group = self.uniforms_bind_group_of(device, &pipelines, &settings_buffer);
+        self.compare = Some(ComparePass {
+            pipelines,
+            settings_buffer,
+            uniforms_bind_group,
+            constants: constants.clone(),
+            split: (0, 0, 0),
+        });
+        true
     }
 
-    pub(crate) fn compute_mipmap(
+    /// the uniforms bind group of `pipelines`, with the buffers of this state but
+    /// `settings_buffer`.
+    fn uniforms_bind_group_of(
         &self,
         device: &Device,
-        encoder: &mut CommandEncoder,
-        mut dim: u32,
-    ) {
-        let mut depth = 0;
-
-        while dim > 2 {
-            let input_view = self.colors_texture.create_view(&TextureViewDescriptor {
-                label: Some("input texture view"),
-                base_mip_level: depth,
-                mip_level_count: Some(1),
-                ..Default::default()
-            });
-
-            let output_view = self.colors_texture.create_view(&TextureViewDescriptor {
-                label: Some("output texture view"),
-                base_mip_level: depth + 1,
-                mip_level_count: Some(1),
-                ..Default::default()
-            });
-
-            println!("compute mipmap, depth={depth}, dim={dim}");
-            let bind_group = device.create_bind_group(&BindGroupDescriptor {
-                label: Some("mipmap bind group"),
-                layout: &self.mipmap_pipeline.get_bind_group_layout(0),
-                entries: &[
-                    BindGroupEntry {
-                        binding: 0,
-                        resource: BindingResource::TextureView(&input_view),
-                    },
-                    BindGroupEntry {
-                        binding: 1,
-                        resource: BindingResource::TextureView(&output_view),
-                    },
-                ],
-            });
-
-            {
-                let mut render_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-                    label: Some("mipmap pass"),
-                    timestamp_writes: None,
-                });
+        pipelines: &ScenePipelines,
+        settings_buffer: &Buffer,
+    ) -> BindGroup {
+        create_uniforms_bind_group(
+            device,
+            &pipelines.lighting.get_bind_group_layout(0),
+            &self.camera_buffer,
+            &self.render_camera_buffer,
+            &self.lights_buffer,
+            &self.route_buffer,
+            &self.route_points_buffer,
+            &self.environment_buffer,
+            &self.frustum_buffer,
+            settings_buffer,
+            &self.sky_sh_buffer,
+            &self.probes_buffer,
+            &self.fog_volumes_buffer,
+            &self.iter_histogram_buffer,
+            &self.luma_histogram_buffer,
+            &self.local_lights_buffer,
+            &self.accumulation_buffer,
+            &self.history_buffer,
+        )
+    }
 
-                render_pass.set_pipeline(&self.mipmap_pipeline);
-                render_pass.set_bind_group(0, &bind_group, &[]);
-                render_pass.dispatch_workgroups(dim / 2, dim / 2, dim / 2)
-            }
+    /// size the history of the path traced mode for a render target of `size` pixels. it is
+    /// only allocated while the pipeline or the compared one path traces. returns whether it was
+    /// reallocated, its contents are lost.
+    pub(crate) fn update_history(&mut self, device: &Device, size: (u32, u32)) -> bool {
+        let path_traced = self.constants.render_mode == 1
+            || self
+                .compare
+                .as_ref()
+                .is_some_and(|compare| compare.constants.render_mode == 1);
+        let pixels = match path_traced {
+            true => size.0 as BufferAddress * size.1 as BufferAddress,
+            false => 1,
+        };
+        if self.history_buffer.size() == pixels * HISTORY_PIXEL_SIZE {
+            return false;
+        }
+        self.history_buffer = create_history_buffer(device, pixels);
+        self.uniforms_bind_group =
+            self.uniforms_bind_group_of(device, &self.scene_pipelines, &self.settings_buffer);
+        if let Some(compare) = &self.compare {
+            let bind_group =
+                self.uniforms_bind_group_of(device, &compare.pipelines, &compare.settings_buffer);
+            self.compare.as_mut().unwrap().uniforms_bind_group = bind_group;
+        }
+        true
+    }
 
-            dim /= 2;
-            depth += 1;
+    /// write the settings of the profile b, and place the divider at `split` pixels of a target
+    /// of `size` pixels.
+    pub(crate) fn update_compare(
+        &mut self,
+        queue: &Queue,
+        settings: &[u8],
+        split: u32,
+        size: (u32, u32),
+    ) {
+        if let Some(compare) = &mut self.compare {
+            queue.write_buffer(&compare.settings_buffer, 0, settings);
+            compare.split = (split.min(size.0), size.0, size.1);
         }
     }
 
-    pub(crate) fn reload_shaders(
+    /// render the scene at `size` and upscale it to the window, or directly to the window if
+    /// `size` is None.
+    pub(crate) fn set_render_size(
         &mut self,
         device: &Device,
         surface_config: &SurfaceConfiguration,
-        constants: &ShaderConstants,
+        size: Option<(u32, u32)>,
     ) {
-        if let Some(render_pipeline) = create_shader_pipeline(device, surface_config, constants) {
-            self.render_pipeline = render_pipeline;
-        }
-        if let Some(octree_pipeline) = create_octree_pipeline(device, constants) {
-            self.octree_pipeline = octree_pipeline;
+        self.scene_target = size.map(|(width, height)| {
+            let texture = create_scene_texture(device, surface_config.format, width, height);
+            let view = texture.create_view(&Default::default());
+            let bind_group =
+                create_blit_bind_group(device, &self.blit_pipeline.get_bind_group_layout(0), &view);
+            SceneTarget {
+                view,
+                bind_group,
+                size: (width, height),
+            }
+        });
+        let (width, height) = size.unwrap_or((surface_config.width, surface_config.height));
+        if self.gbuffer.size != (width, height) {
+            self.gbuffer = create_gbuffer(device, &self.scene_pipelines, width, height);
         }
-        if let Some(mipmap_pipeline) = create_mipmap_pipeline(device, constants) {
-            self.mipmap_pipeline = mipmap_pipeline;
+    }
+
+    /// grow the g-buffer to draw into a target of `size` pixels, e.g. an export larger than the
+    /// window. it shrinks back with the next `set_render_size`, the gizmos are hidden until then.
+    pub(crate) fn reserve_gbuffer(&mut self, device: &Device, size: (u32, u32)) {
+        let (width, height) = self.gbuffer.size;
+        if size.0 > width || size.1 > height {
+            let (width, height) = (size.0.max(width), size.1.max(height));
+            self.gbuffer = create_gbuffer(device, &self.scene_pipelines, width, height);
         }
     }
-}
 
-pub(crate) fn create_colors_texture(
-    device: &Device,
-    queue: &Queue,
-    dim: u32,
-    colors_data: &[u8],
+    /// upload the camera of the rays, and the camera of the culling passes and the light
+    /// clusters. they differ while the render camera is frozen, see `Frustum::render_camera`.
+    pub(crate) fn write_cameras(
+        &self,
+        queue: &Queue,
+        camera: &CameraUniform,
+        render_camera: &CameraUniform,
+    ) {
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(camera));
+        queue.write_buffer(
+            &self.render_camera_buffer,
+            0,
+            bytemuck::bytes_of(render_camera),
+        );
+    }
+
+    /// upload the gizmos drawn over the scene in the window.
+    pub(crate) fn write_gizmos(&mut self, queue: &Queue, gizmos: &Gizmos) {
+        let vertices = gizmos.vertices();
+        if !vertices.is_empty() {
+            queue.write_buffer(&self.gizmos_buffer, 0, bytemuck::cast_slice(vertices));
+        }
+        self.gizmo_vertices = vertices.len() as u32;
+    }
+
+    /// draw the gizmos over the scene drawn to `view`, depth tested against the primary hits.
+    /// the depth target must be the size of `view`, they are skipped while the g-buffer is
+    /// reserved for a larger target.
+    fn draw_gizmos(&self, view: &TextureView, size: (u32, u32), encoder: &mut CommandEncoder) {
+        if self.gizmo_vertices == 0 || self.gbuffer.size != size {
+            return;
+        }
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("gizmos pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &self.gbuffer.depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: self.profiler.render_writes("gizmos"),
+            ..Default::default()
+        });
+
+        render_pass.set_pipeline(&self.gizmos_pipeline);
+        render_pass.set_bind_group(0, &self.gizmos_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.gizmos_buffer.slice(..));
+        render_pass.draw(0..self.gizmo_vertices, 0..1);
+    }
+
+    /// draw the scene and the gizmos to the window of `size` pixels, through the scene target if
+    /// there is one.
+    pub(crate) fn draw_scaled(
+        &self,
+        view: &TextureView,
+        size: (u32, u32),
+        encoder: &mut CommandEncoder,
+    ) {
+        let Some(target) = &self.scene_target else {
+            self.draw_split(view, size, encoder);
+            return self.draw_gizmos(view, size, encoder);
+        };
+        self.draw_split(&target.view, target.size, encoder);
+        self.draw_gizmos(&target.view, target.size, encoder);
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("blit pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+
+        render_pass.set_pipeline(&self.blit_pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..6, 0..1);
+    }
+
+    /// the image of the slice viewer, updated by `render_slice`.
+    pub(crate) fn slice_view(&self) -> TextureView {
+        self.slice_texture.create_view(&Default::default())
+    }
+
+    fn slice_texture_of(&self, source: SliceSource) -> &Texture {
+        match source {
+            SliceSource::Voxels => &self.voxels_texture,
+            SliceSource::Dvo => &self.octree_texture,
+            SliceSource::Colors => &self.colors_texture,
+        }
+    }
+
+    /// (mip levels, depth of `level`) of a slice viewer source.
+    pub(crate) fn slice_extent(&self, source: SliceSource, level: u32) -> (u32, u32) {
+        let texture = self.slice_texture_of(source);
+        let levels = texture.mip_level_count();
+        (
+            levels,
+            texture.depth_or_array_layers() >> level.min(levels - 1),
+        )
+    }
+
+    /// draw the slice `z` of the mip `level` of `source` into the slice viewer image.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn render_slice(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        source: SliceSource,
+        level: u32,
+        z: u32,
+    ) {
+        let params = SliceParams {
+            source: source as u32,
+            level,
+            z,
+            _pad: 0,
+        };
+        let params_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("slice params buffer"),
+            contents: bytemuck::bytes_of(&params),
+            usage: BufferUsages::UNIFORM,
+        });
+        let views = [
+            self.voxels_texture.create_view(&Default::default()),
+            self.octree_texture.create_view(&Default::default()),
+            self.colors_texture.create_view(&Default::default()),
+        ];
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("slice bind group"),
+            layout: &self.slice_pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&views[0]),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&views[1]),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&views[2]),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let view = self.slice_view();
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("slice encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("slice pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            render_pass.set_pipeline(&self.slice_pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(0..6, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn compute_octree(&self, device: &Device, encoder: &mut CommandEncoder, dim: u32) {
+        println!("compute octree, dim={dim}");
+        self.compute_octree_region(device, encoder, glm::zero(), glm::UVec3::repeat(dim));
+    }
+
+    /// recompute the octree levels over the box `min..max` of the volume, in voxels.
+    pub(crate) fn compute_octree_region(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        min: glm::UVec3,
+        max: glm::UVec3,
+    ) {
+        self.volume_version.set(self.volume_version.get() + 1);
+        for depth in 0..self.octree_texture.mip_level_count() {
+            let input_view = if depth == 0 {
+                self.voxels_texture.create_view(&TextureViewDescriptor {
+                    label: Some("input texture view"),
+                    ..Default::default()
+                })
+            } else {
+                self.octree_texture.create_view(&TextureViewDescriptor {
+                    label: Some("input texture view"),
+                    base_mip_level: depth - 1,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            };
+
+            let output_view = self.octree_texture.create_view(&TextureViewDescriptor {
+                label: Some("output texture view"),
+                base_mip_level: depth,
+                mip_level_count: Some(1),
+                ..Default::default()
+            });
+
+            let scale = 2 << depth;
+            dispatch_region(
+                device,
+                encoder,
+                self.profiler.compute_writes("octree build"),
+                &self.octree_pipeline,
+                &input_view,
+                &output_view,
+                min / scale,
+                max.map(|c| c.div_ceil(scale)),
+            );
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn compute_mipmap(&self, device: &Device, encoder: &mut CommandEncoder, dim: u32) {
+        println!("compute mipmap, dim={dim}");
+        self.compute_mipmap_region(device, encoder, glm::zero(), glm::UVec3::repeat(dim));
+    }
+
+    /// recompute the color mipmaps over the box `min..max` of the volume, in voxels.
+    pub(crate) fn compute_mipmap_region(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        min: glm::UVec3,
+        max: glm::UVec3,
+    ) {
+        for depth in 0..self.colors_texture.mip_level_count() - 1 {
+            let input_view = self.colors_texture.create_view(&TextureViewDescriptor {
+                label: Some("input texture view"),
+                base_mip_level: depth,
+                mip_level_count: Some(1),
+                ..Default::default()
+            });
+
+            let scale = 2 << depth;
+            let (lo, hi) = (min / scale, max.map(|c| c.div_ceil(scale)));
+            if self.mip_path == MipPath::Copy {
+                self.copy_mip_region(device, encoder, &input_view, depth + 1, lo, hi);
+                continue;
+            }
+
+            let output_view = self.colors_texture.create_view(&TextureViewDescriptor {
+                label: Some("output texture view"),
+                base_mip_level: depth + 1,
+                mip_level_count: Some(1),
+                ..Default::default()
+            });
+
+            dispatch_region(
+                device,
+                encoder,
+                self.profiler.compute_writes("mipmap"),
+                &self.mipmap_pipeline,
+                &input_view,
+                &output_view,
+                lo,
+                hi,
+            );
+        }
+    }
+
+    /// compute the box `min..max` of the color mip level `level` into a buffer and copy it into
+    /// the texture, see `MipPath::Copy`. the box is split in slabs that fit in a storage buffer.
+    fn copy_mip_region(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        input_view: &TextureView,
+        level: u32,
+        min: glm::UVec3,
+        max: glm::UVec3,
+    ) {
+        let size = max - min;
+        if size.iter().any(|c| *c == 0) {
+            return;
+        }
+        let texel = COLORS_FORMAT.block_copy_size(None).unwrap();
+        // the rows of a buffer copy are aligned, the texels past the box are left unused.
+        let row = (size.x * texel).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT) / texel;
+        let slice_bytes = (row * size.y * texel) as BufferAddress;
+        let limits = device.limits();
+        let max_bytes =
+            (limits.max_storage_buffer_binding_size as BufferAddress).min(limits.max_buffer_size);
+        let slab = ((max_bytes / slice_bytes) as u32).clamp(1, size.z);
+
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("mipmap copy buffer"),
+            size: slice_bytes * slab as BufferAddress,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        for z in (min.z..max.z).step_by(slab as usize) {
+            let depth = slab.min(max.z - z);
+            let region_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("mipmap copy region buffer"),
+                contents: bytemuck::cast_slice(&[min.x, min.y, z, 0, row, size.y, 0, 0]),
+                usage: BufferUsages::UNIFORM,
+            });
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("mipmap copy bind group"),
+                layout: &self.mipmap_pipeline.get_bind_group_layout(0),
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(input_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: region_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+            {
+                let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("mipmap copy pass"),
+                    timestamp_writes: self.profiler.compute_writes("mipmap"),
+                });
+                compute_pass.set_pipeline(&self.mipmap_pipeline);
+                compute_pass.set_bind_group(0, &bind_group, &[]);
+                compute_pass.dispatch_workgroups(size.x, size.y, depth);
+            }
+
+            encoder.copy_buffer_to_texture(
+                ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(row * texel),
+                        rows_per_image: Some(size.y),
+                    },
+                },
+                ImageCopyTexture {
+                    texture: &self.colors_texture,
+                    mip_level: level,
+                    origin: Origin3d {
+                        x: min.x,
+                        y: min.y,
+                        z,
+                    },
+                    aspect: TextureAspect::All,
+                },
+                Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: depth,
+                },
+            );
+        }
+    }
+
+    /// mark the box `min..max` of the volume as edited, in voxels. it is uploaded by
+    /// `upload_edits`.
+    pub(crate) fn mark_dirty(&mut self, min: glm::UVec3, max: glm::UVec3) {
+        let (lo, hi) = (min / EDIT_BRICK, max.map(|c| c.div_ceil(EDIT_BRICK)));
+        for z in lo.z..hi.z {
+            for y in lo.y..hi.y {
+                for x in lo.x..hi.x {
+                    self.dirty_bricks.insert(glm::vec3(x, y, z));
+                }
+            }
+        }
+    }
+
+    /// upload the bricks edited since the last call, and recompute the octree and the color
+    /// mipmaps over their bounding box. the distance field and contours are recomputed in full.
+    #[tracing::instrument(skip_all, fields(bricks = self.dirty_bricks.len()))]
+    pub(crate) fn upload_edits(&mut self, device: &Device, queue: &Queue, voxels: &Voxels) {
+        if self.dirty_bricks.is_empty() {
+            return;
+        }
+        let dim = self.voxels_texture.width();
+        let mut min = glm::UVec3::repeat(dim);
+        let mut max = glm::UVec3::zeros();
+
+        for brick in std::mem::take(&mut self.dirty_bricks) {
+            let lo = brick * EDIT_BRICK;
+            let hi = lo.map(|c| (c + EDIT_BRICK).min(dim));
+            // texture x, y, z are the array axes 2, 1, 0.
+            let region = s![
+                lo.z as usize..hi.z as usize,
+                lo.y as usize..hi.y as usize,
+                lo.x as usize..hi.x as usize
+            ];
+            self.write_region(
+                queue,
+                [lo.z as usize, lo.y as usize, lo.x as usize],
+                &voxels.voxels().slice(region).to_owned(),
+                &voxels.colors().slice(region).to_owned(),
+            );
+            min = min.inf(&lo);
+            max = max.sup(&hi);
+        }
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("edit encoder"),
+        });
+        self.compute_octree_region(device, &mut encoder, min, max);
+        // the nodes are allocated top-down, the tree is not updated in place.
+        self.compute_contree(device, &mut encoder);
+        self.compute_sdf(device, &mut encoder);
+        self.compute_contours(device, &mut encoder);
+        self.compute_mipmap_region(device, &mut encoder, min, max);
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// replace the scene volume. textures are reallocated only when the dimension or the color
+    /// mipmaps change, then the octree and mipmaps are recomputed.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn set_voxels(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        voxels: &Voxels,
+        color_mips: bool,
+    ) {
+        let dim = voxels.dim();
+        let mips_changed = color_mips != self.has_color_mips();
+
+        if dim != self.voxels_texture.width() || mips_changed {
+            self.voxels_texture = create_voxels_texture(device, queue, dim, voxels.voxels_bytes());
+            self.colors_texture = create_colors_texture(
+                device,
+                queue,
+                dim,
+                voxels.colors_bytes(),
+                color_mips,
+                self.mip_path,
+            );
+            self.octree_texture = create_octree_texture(device, dim);
+            self.sdf_texture = create_sdf_texture(device, dim);
+        } else {
+            write_texture_3d(queue, &self.voxels_texture, voxels.voxels_bytes());
+            write_texture_3d(queue, &self.colors_texture, voxels.colors_bytes());
+        }
+        self.contours_texture = create_contours_texture(device, dim, voxels.meta.contours);
+        // the brick map of the new volume is streamed from scratch.
+        let bricks_enabled = self.brick_atlas_texture.width() > 1;
+        (self.brick_index_texture, self.brick_atlas_texture) =
+            create_brick_textures(device, dim, bricks_enabled);
+        self.contree_buffer = create_contree_buffer(device, dim, self.has_contree());
+        self.set_lightmap(device, queue, voxels.lightmap_bytes());
+        // edits of the previous volume are obsolete.
+        self.dirty_bricks.clear();
+        self.rebuild(device, queue);
+    }
+
+    /// overwrite a box of the scene volume, e.g. a streamed chunk. `origin` and the arrays are in
+    /// array axes. the octree and mipmaps must be recomputed afterwards.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn write_region(
+        &self,
+        queue: &Queue,
+        origin: [usize; 3],
+        voxels: &Array3<VoxelsFormat>,
+        colors: &Array3<ColorsFormat>,
+    ) {
+        // texture x, y, z are the array axes 2, 1, 0.
+        let [i, j, k] = origin;
+        let origin = Origin3d {
+            x: k as u32,
+            y: j as u32,
+            z: i as u32,
+        };
+        let (di, dj, dk) = voxels.dim();
+        let size = Extent3d {
+            width: dk as u32,
+            height: dj as u32,
+            depth_or_array_layers: di as u32,
+        };
+        let voxels = voxels.as_standard_layout();
+        let colors = colors.as_standard_layout();
+        write_region_3d(
+            queue,
+            &self.voxels_texture,
+            origin,
+            size,
+            bytemuck::cast_slice(voxels.as_slice().unwrap()),
+        );
+        write_region_3d(
+            queue,
+            &self.colors_texture,
+            origin,
+            size,
+            bytemuck::cast_slice(colors.as_slice().unwrap()),
+        );
+    }
+
+    /// compute the brick distance field from the octree by jump flooding. the octree must be
+    /// computed first.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn compute_sdf(&self, device: &Device, encoder: &mut CommandEncoder) {
+        let bricks = self.sdf_texture.width();
+        let workgroups = bricks.div_ceil(4);
+        let dvo_view = self.octree_texture.create_view(&Default::default());
+        let sdf_view = self.sdf_texture.create_view(&Default::default());
+        let seeds = [
+            create_seeds_texture(device, bricks),
+            create_seeds_texture(device, bricks),
+        ]
+        .map(|texture| texture.create_view(&Default::default()));
+
+        let mut dispatch = |pipeline: &ComputePipeline, entries: &[BindGroupEntry]| {
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("sdf bind group"),
+                layout: &pipeline.get_bind_group_layout(0),
+                entries,
+            });
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("sdf pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(workgroups, workgroups, workgroups);
+        };
+
+        dispatch(
+            &self.sdf_pipelines.seed,
+            &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&dvo_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&seeds[0]),
+                },
+            ],
+        );
+
+        let mut current = 0;
+        let mut step = bricks / 2;
+        while step >= 1 {
+            let params_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("sdf jump params buffer"),
+                contents: bytemuck::cast_slice(&[step, 0, 0, 0]),
+                usage: BufferUsages::UNIFORM,
+            });
+            dispatch(
+                &self.sdf_pipelines.jump,
+                &[
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&seeds[1 - current]),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::TextureView(&seeds[current]),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                ],
+            );
+            current = 1 - current;
+            step /= 2;
+        }
+
+        dispatch(
+            &self.sdf_pipelines.distance,
+            &[
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&seeds[current]),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: BindingResource::TextureView(&sdf_view),
+                },
+            ],
+        );
+    }
+
+    /// fit the contours of the surface voxels, if the scene has contours.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn compute_contours(&self, device: &Device, encoder: &mut CommandEncoder) {
+        let dim = self.contours_texture.width();
+        if dim != self.colors_texture.width() {
+            return;
+        }
+
+        let colors_view = self.colors_texture.create_view(&TextureViewDescriptor {
+            base_mip_level: 0,
+            mip_level_count: Some(1),
+            ..Default::default()
+        });
+        let contours_view = self.contours_texture.create_view(&Default::default());
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("contours bind group"),
+            layout: &self.contours_pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&colors_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&contours_view),
+                },
+            ],
+        });
+
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("contours pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.contours_pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        let workgroups = dim.div_ceil(4);
+        compute_pass.dispatch_workgroups(workgroups, workgroups, workgroups);
+    }
+
+    /// enable or disable the contours of the scene. the shaders must be reloaded with the
+    /// matching `ShaderConstants::contours`.
+    pub(crate) fn set_contours(&mut self, device: &Device, queue: &Queue, enabled: bool) {
+        let dim = self.voxels_texture.width();
+        self.contours_texture = create_contours_texture(device, dim, enabled);
+        self.rebind_octree(device);
+        self.rebuild(device, queue);
+    }
+
+    /// whether the 64-tree is allocated, see `contree.wgsl`.
+    pub(crate) fn has_contree(&self) -> bool {
+        self.contree_buffer.size() > CONTREE_NODE_SIZE
+    }
+
+    /// allocate and build the 64-tree, or free it. the shaders must be reloaded with the matching
+    /// `ShaderConstants::traversal`.
+    pub(crate) fn set_contree(&mut self, device: &Device, queue: &Queue, enabled: bool) {
+        let dim = self.voxels_texture.width();
+        self.contree_buffer = create_contree_buffer(device, dim, enabled);
+        self.rebind_octree(device);
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("contree encoder"),
+        });
+        self.compute_contree(device, &mut encoder);
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// build the 64-tree from the octree, one pass per level from the root. the octree must be
+    /// up to date.
+    pub(crate) fn compute_contree(&self, device: &Device, encoder: &mut CommandEncoder) {
+        if !self.has_contree() {
+            return;
+        }
+        let dim = self.voxels_texture.width();
+        let levels = dim.ilog2().div_ceil(2);
+
+        let voxels_view = self.voxels_texture.create_view(&Default::default());
+        let octree_view = self.octree_texture.create_view(&Default::default());
+        encoder.clear_buffer(&self.contree_count_buffer, 0, None);
+
+        for level in 0..levels {
+            let level_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("contree level buffer"),
+                contents: bytemuck::cast_slice(&[level, 0, 0, 0]),
+                usage: BufferUsages::UNIFORM,
+            });
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("contree bind group"),
+                layout: &self.contree_pipeline.get_bind_group_layout(0),
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(&voxels_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&octree_view),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: self.contree_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: self.contree_count_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 4,
+                        resource: level_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("contree pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.contree_pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            // nodes of the level per axis, the root may extend past the volume.
+            let nodes = dim.div_ceil(4u32.pow(levels - level));
+            let workgroups = nodes.div_ceil(4);
+            compute_pass.dispatch_workgroups(workgroups, workgroups, workgroups);
+        }
+    }
+
+    /// allocate or free the brick map textures. the shaders must be reloaded with the matching
+    /// `ShaderConstants::traversal`, and the bricks streamed with `write_bricks`.
+    pub(crate) fn set_brick_map(&mut self, device: &Device, enabled: bool) {
+        let dim = self.voxels_texture.width();
+        (self.brick_index_texture, self.brick_atlas_texture) =
+            create_brick_textures(device, dim, enabled);
+        self.rebind_octree(device);
+    }
+
+    /// apply the residency changes of the brick map: evicted bricks are cleared from the index,
+    /// uploaded bricks are copied to their atlas slot.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn write_bricks(&self, queue: &Queue, update: &BrickUpdate) {
+        let write_entry = |brick: &glm::UVec3, entry: u32| {
+            let origin = Origin3d {
+                x: brick.x,
+                y: brick.y,
+                z: brick.z,
+            };
+            let size = Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            };
+            write_region_3d(
+                queue,
+                &self.brick_index_texture,
+                origin,
+                size,
+                bytemuck::bytes_of(&entry),
+            );
+        };
+
+        for brick in &update.evicted {
+            write_entry(brick, 0);
+        }
+        for (brick, slot, voxels) in &update.uploads {
+            let origin = Origin3d {
+                x: slot % ATLAS_BRICKS * BRICK,
+                y: slot / ATLAS_BRICKS % ATLAS_BRICKS * BRICK,
+                z: slot / (ATLAS_BRICKS * ATLAS_BRICKS) * BRICK,
+            };
+            let size = Extent3d {
+                width: BRICK,
+                height: BRICK,
+                depth_or_array_layers: BRICK,
+            };
+            write_region_3d(
+                queue,
+                &self.brick_atlas_texture,
+                origin,
+                size,
+                bytemuck::cast_slice(voxels),
+            );
+            write_entry(brick, slot + 1);
+        }
+    }
+
+    /// recompute the octree and the color mipmaps from the volume.
+    pub(crate) fn rebuild(&self, device: &Device, queue: &Queue) {
+        let dim = self.voxels_texture.width();
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("compute encoder"),
+        });
+        self.compute_octree(device, &mut encoder, dim);
+        self.compute_contree(device, &mut encoder);
+        self.compute_sdf(device, &mut encoder);
+        self.compute_contours(device, &mut encoder);
+        self.compute_mipmap(device, &mut encoder, dim);
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// project the sky into spherical harmonics for the ambient light. reads the lights,
+    /// environment and settings buffers, so call it after they were written.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn project_sky(&self, device: &Device, queue: &Queue) {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("sky sh encoder"),
+        });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("sky sh pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.sky_sh_pipeline);
+            compute_pass.set_bind_group(0, &self.sky_sh_bind_group, &[]);
+            compute_pass.dispatch_workgroups(1, 1, 1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// cast rays from `pos` through the octree on the gpu. blocks until the results are read back.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn pick(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        pos: &glm::Vec3,
+        dirs: &[glm::Vec3; PICK_SAMPLES],
+    ) -> [PickResult; PICK_SAMPLES] {
+        let rays = std::iter::once(pos)
+            .chain(dirs.iter())
+            .map(|v| glm::vec4(v.x, v.y, v.z, 0.0))
+            .collect::<Vec<_>>();
+        let results_size = (PICK_SAMPLES * std::mem::size_of::<PickResult>()) as BufferAddress;
+
+        let rays_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("pick rays buffer"),
+            contents: bytemuck::cast_slice(&rays),
+            usage: BufferUsages::UNIFORM,
+        });
+        let results_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("pick results buffer"),
+            size: results_size,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("pick readback buffer"),
+            size: results_size,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let rays_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("pick bind group"),
+            layout: &self.pick_pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: rays_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: results_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let octree_view = self.octree_texture.create_view(&Default::default());
+        let colors_view = self.colors_texture.create_view(&TextureViewDescriptor {
+            base_mip_level: 0,
+            mip_level_count: Some(1),
+            ..Default::default()
+        });
+        let octree_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("pick octree bind group"),
+            layout: &self.pick_pipeline.get_bind_group_layout(1),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&octree_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&colors_view),
+                },
+                BindGroupEntry {
+                    binding: 14,
+                    resource: self.visible_bricks_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("pick encoder"),
+        });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("pick pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pick_pipeline);
+            compute_pass.set_bind_group(0, &rays_bind_group, &[]);
+            compute_pass.set_bind_group(1, &octree_bind_group, &[]);
+            compute_pass.dispatch_workgroups(PICK_SAMPLES as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&results_buffer, 0, &readback_buffer, 0, results_size);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        slice.map_async(MapMode::Read, |res| res.expect("failed to map pick buffer"));
+        device.poll(Maintain::Wait);
+        let results = bytemuck::pod_read_unaligned(&slice.get_mapped_range());
+        readback_buffer.unmap();
+
+        results
+    }
+
+    /// read back every level of the octree built on the gpu.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn read_octree(&self, device: &Device, queue: &Queue) -> Dvo {
+        let levels = 0..self.octree_texture.mip_level_count();
+        let levels = read_texture_mips(device, queue, &self.octree_texture, levels)
+            .into_iter()
+            .enumerate()
+            .map(|(level, bytes)| {
+                let dim = (self.octree_texture.width() >> level) as usize;
+                let nodes = bytes
+                    .chunks_exact(std::mem::size_of::<VoxelsFormat>())
+                    .map(bytemuck::pod_read_unaligned)
+                    .collect();
+                Array3::from_shape_vec((dim, dim, dim), nodes).unwrap()
+            })
+            .collect();
+
+        Dvo { levels }
+    }
+
+    /// upload a saved octree instead of computing it. it must have the dim of the scene.
+    pub(crate) fn write_octree(&self, queue: &Queue, dvo: &Dvo) {
+        self.volume_version.set(self.volume_version.get() + 1);
+        for (level, nodes) in dvo.levels.iter().enumerate() {
+            let bytes = bytemuck::cast_slice(nodes.as_slice().unwrap());
+            write_texture_mip(queue, &self.octree_texture, level as u32, bytes);
+        }
+    }
+
+    /// read back the color mipmaps, without the base level.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn read_color_mips(&self, device: &Device, queue: &Queue) -> Vec<Vec<u8>> {
+        let levels = 1..self.colors_texture.mip_level_count();
+        read_texture_mips(device, queue, &self.colors_texture, levels)
+    }
+
+    /// upload saved color mipmaps instead of computing them.
+    pub(crate) fn write_color_mips(&self, queue: &Queue, mips: &[Vec<u8>]) {
+        for (level, bytes) in mips.iter().enumerate() {
+            write_texture_mip(queue, &self.colors_texture, level as u32 + 1, bytes);
+        }
+    }
+
+    /// changes whenever the volume is written, see `volume_version`.
+    pub(crate) fn volume_version(&self) -> u64 {
+        self.volume_version.get()
+    }
+
+    pub(crate) fn mip_path(&self) -> MipPath {
+        self.mip_path
+    }
+
+    /// whether the colors have mipmaps, see `budget.rs`.
+    pub(crate) fn has_color_mips(&self) -> bool {
+        self.colors_texture.mip_level_count() > 1
+    }
+
+    /// read back the iterations histogram accumulated since the last call, and reset it.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn read_iter_histogram(&self, device: &Device, queue: &Queue) -> Vec<u32> {
+        read_histogram(device, queue, &self.iter_histogram_buffer)
+    }
+
+    /// read back the luminance histogram accumulated since the last call, and reset it.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn read_luma_histogram(&self, device: &Device, queue: &Queue) -> Vec<u32> {
+        read_histogram(device, queue, &self.luma_histogram_buffer)
+    }
+
+    /// upload new colors of the whole volume, e.g. after a palette change, and recompute the
+    /// color mipmaps and the contours.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn set_colors(&self, device: &Device, queue: &Queue, voxels: &Voxels) {
+        self.volume_version.set(self.volume_version.get() + 1);
+        write_texture_3d(queue, &self.colors_texture, voxels.colors_bytes());
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("colors encoder"),
+        });
+        self.compute_contours(device, &mut encoder);
+        self.compute_mipmap(device, &mut encoder, voxels.dim());
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// replace the detail textures atlas, or remove it with `None`. see `detail.rs`.
+    pub(crate) fn set_detail(&mut self, device: &Device, queue: &Queue, atlas: Option<&RgbaImage>) {
+        self.detail_texture = create_detail_texture(device, queue, atlas);
+        self.rebind_octree(device);
+    }
+
+    /// replace the baked lighting texture, or remove it with `None`.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn set_lightmap(&mut self, device: &Device, queue: &Queue, data: Option<&[u8]>) {
+        let dim = self.voxels_texture.width();
+        self.lightmap_texture = create_lightmap_texture(device, queue, dim, data);
+        self.rebind_octree(device);
+    }
+
+    fn rebind_octree(&mut self, device: &Device) {
+        self.octree_bind_group = create_octree_bind_group(
+            device,
+            &self.scene_pipelines.lighting.get_bind_group_layout(1),
+            &self.octree_texture,
+            &self.colors_texture,
+            &self.lightmap_texture,
+            &self.probes_texture,
+            &self.sdf_texture,
+            &self.contours_texture,
+            &self.brick_index_texture,
+            &self.brick_atlas_texture,
+            &self.contree_buffer,
+            &self.detail_texture,
+            &self.voxels_texture,
+            &self.materials_buffer,
+            &self.visible_bricks_buffer,
+            &self.cluster_lights_buffer,
+        );
+    }
+
+    /// render the scene with the current camera into `target`, a `PROBE_SIZE` square texture,
+    /// and copy it to the layer `layer` of the probes cubemap array (probe * 6 + face). the
+    /// g-buffer must be reserved for it.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn render_probe_face(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        target: &Texture,
+        layer: u32,
+    ) {
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("probe encoder"),
+        });
+        self.draw(
+            &target.create_view(&Default::default()),
+            (PROBE_SIZE, PROBE_SIZE),
+            &mut encoder,
+        );
+        encoder.copy_texture_to_texture(
+            target.as_image_copy(),
+            ImageCopyTexture {
+                texture: &self.probes_texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+                aspect: TextureAspect::All,
+            },
+            Extent3d {
+                width: PROBE_SIZE,
+                height: PROBE_SIZE,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Device,
+        surface_config: &SurfaceConfiguration,
+        constants: &ShaderConstants,
+    ) {
+        // the pipelines that fail to compile keep their previous version.
+        let mut errors = Vec::new();
+        match create_scene_pipelines(device, surface_config, constants) {
+            Ok(scene_pipelines) => self.scene_pipelines = scene_pipelines,
+            Err(err) => errors.push(err),
+        }
+        match create_octree_pipeline(device, constants) {
+            Ok(octree_pipeline) => self.octree_pipeline = octree_pipeline,
+            Err(err) => errors.push(err),
+        }
+        match create_mipmap_pipeline(device, constants, self.mip_path) {
+            Ok(mipmap_pipeline) => self.mipmap_pipeline = mipmap_pipeline,
+            Err(err) => errors.push(err),
+        }
+        match create_pick_pipeline(device, constants) {
+            Ok(pick_pipeline) => self.pick_pipeline = pick_pipeline,
+            Err(err) => errors.push(err),
+        }
+        match create_sky_sh_pipeline(device, constants) {
+            Ok(sky_sh_pipeline) => {
+                self.sky_sh_bind_group = create_sky_sh_bind_group(
+                    device,
+                    &sky_sh_pipeline.get_bind_group_layout(0),
+                    &self.lights_buffer,
+                    &self.environment_buffer,
+                    &self.settings_buffer,
+                    &self.sky_sh_buffer,
+                );
+                self.sky_sh_pipeline = sky_sh_pipeline;
+            }
+            Err(err) => errors.push(err),
+        }
+        match create_sdf_pipelines(device, constants) {
+            Ok(sdf_pipelines) => self.sdf_pipelines = sdf_pipelines,
+            Err(err) => errors.push(err),
+        }
+        match create_contours_pipeline(device, constants) {
+            Ok(contours_pipeline) => self.contours_pipeline = contours_pipeline,
+            Err(err) => errors.push(err),
+        }
+        match create_contree_pipeline(device, constants) {
+            Ok(contree_pipeline) => self.contree_pipeline = contree_pipeline,
+            Err(err) => errors.push(err),
+        }
+        match create_culling_pipeline(device, constants) {
+            Ok(culling_pipeline) => {
+                self.culling_bind_group = create_culling_bind_group(
+                    device,
+                    &culling_pipeline.get_bind_group_layout(0),
+                    &self.render_camera_buffer,
+                    &self.visible_bricks_buffer,
+                );
+                self.culling_pipeline = culling_pipeline;
+            }
+            Err(err) => errors.push(err),
+        }
+        match create_light_culling_pipeline(device, constants) {
+            Ok(light_culling_pipeline) => {
+                self.light_culling_bind_group = create_light_culling_bind_group(
+                    device,
+                    &light_culling_pipeline.get_bind_group_layout(0),
+                    &self.render_camera_buffer,
+                    &self.lights_buffer,
+                    &self.local_lights_buffer,
+                    &self.cluster_lights_buffer,
+                );
+                self.light_culling_pipeline = light_culling_pipeline;
+            }
+            Err(err) => errors.push(err),
+        }
+        self.shader_errors = errors;
+        self.constants = constants.clone();
+    }
+
+    /// the constants the shaders were last built with, even if some failed to compile.
+    pub(crate) fn constants(&self) -> &ShaderConstants {
+        &self.constants
+    }
+}
+
+pub(crate) fn create_colors_texture(
+    device: &Device,
+    queue: &Queue,
+    dim: u32,
+    colors_data: &[u8],
+    mips: bool,
+    mip_path: MipPath,
+) -> Texture {
+    // let colors_texture = device.create_texture_with_data(
+    //     queue,
+    //     &TextureDescriptor {
+    //         label: Some("colors texture"),
+    //         size: Extent3d {
+    //             width: dim,
+    //             height: dim,
+    //             depth_or_array_layers: dim,
+    //         },
+    //         mip_level_count: dim.ilog2(),
+    //         sample_count: 1,
+    //         dimension: TextureDimension::D3,
+    //         format: TextureFormat::Rgba8Unorm,
+    //         usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
+    //         view_formats: &[],
+    //     },
+    //     util::TextureDataOrder::LayerMajor,
+    //     colors_data,
+    // );
+    let size = Extent3d {
+        width: dim,
+        height: dim,
+        depth_or_array_layers: dim,
+    };
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("colors texture"),
+        size,
+        mip_level_count: if mips { dim.ilog2() } else { 1 },
+        sample_count: 1,
+        dimension: TextureDimension::D3,
+        format: COLORS_FORMAT,
+        usage: match mip_path {
+            MipPath::Storage => TextureUsages::STORAGE_BINDING,
+            MipPath::Copy => TextureUsages::empty(),
+        } | TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_SRC
+            | TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    let copy = ImageCopyTexture {
+        texture: &texture,
+        mip_level: 0,
+        origin: Origin3d::ZERO,
+        aspect: TextureAspect::All,
+    };
+    let layout = ImageDataLayout {
+        offset: 0,
+        bytes_per_row: Some(dim * COLORS_FORMAT.block_copy_size(None).unwrap()),
+        rows_per_image: Some(dim),
+    };
+    queue.write_texture(copy, colors_data, layout, size);
+
+    texture
+}
+
+/// the baked sun visibility and ambient occlusion. without baked lighting, a 1x1x1 placeholder
+/// is created because the bind group always needs a texture.
+/// the detail textures atlas, see `detail.rs`. a single texel when the scene has none.
+pub(crate) fn create_detail_texture(
+    device: &Device,
+    queue: &Queue,
+    atlas: Option<&RgbaImage>,
+) -> Texture {
+    let (width, height, data) = match atlas {
+        Some(atlas) => (atlas.width(), atlas.height(), atlas.as_raw().as_slice()),
+        None => (1, 1, [0u8; 4].as_slice()),
+    };
+
+    device.create_texture_with_data(
+        queue,
+        &TextureDescriptor {
+            label: Some("detail texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        util::TextureDataOrder::LayerMajor,
+        data,
+    )
+}
+
+pub(crate) fn create_lightmap_texture(
+    device: &Device,
+    queue: &Queue,
+    dim: u32,
+    lightmap_data: Option<&[u8]>,
+) -> Texture {
+    let (dim, data) = match lightmap_data {
+        Some(data) => (dim, Cow::Borrowed(data)),
+        None => (1, Cow::Owned(vec![0u8; 2])),
+    };
+
+    let lightmap_texture = device.create_texture_with_data(
+        queue,
+        &TextureDescriptor {
+            label: Some("lightmap texture"),
+            size: Extent3d {
+                width: dim,
+                height: dim,
+                depth_or_array_layers: dim,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format: TextureFormat::Rg8Unorm,
+            usage: TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        util::TextureDataOrder::LayerMajor,
+        &data,
+    );
+
+    lightmap_texture
+}
+
+/// cubemap array of the reflection probes, 6 layers per probe. the faces are rendered with the
+/// render pipeline, so it has the format of the surface.
+pub(crate) fn create_probes_texture(device: &Device, format: TextureFormat) -> Texture {
+    let probes_texture = device.create_texture(&TextureDescriptor {
+        label: Some("probes texture"),
+        size: Extent3d {
+            width: PROBE_SIZE,
+            height: PROBE_SIZE,
+            depth_or_array_layers: 6 * MAX_PROBES as u32,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    probes_texture
+}
+
+/// overwrite the first mip level of a cube 3d texture.
+pub(crate) fn write_texture_3d(queue: &Queue, texture: &Texture, data: &[u8]) {
+    let dim = texture.width();
+    let size = Extent3d {
+        width: dim,
+        height: dim,
+        depth_or_array_layers: dim,
+    };
+    write_region_3d(queue, texture, Origin3d::ZERO, size, data);
+}
+
+/// overwrite a whole mip level of a cubic 3d texture. `data` is tightly packed.
+pub(crate) fn write_texture_mip(queue: &Queue, texture: &Texture, level: u32, data: &[u8]) {
+    let dim = texture.width() >> level;
+    let texel_size = texture.format().block_copy_size(None).unwrap();
+    let copy = ImageCopyTexture {
+        texture,
+        mip_level: level,
+        origin: Origin3d::ZERO,
+        aspect: TextureAspect::All,
+    };
+    let layout = ImageDataLayout {
+        offset: 0,
+        bytes_per_row: Some(dim * texel_size),
+        rows_per_image: Some(dim),
+    };
+    let size = Extent3d {
+        width: dim,
+        height: dim,
+        depth_or_array_layers: dim,
+    };
+    queue.write_texture(copy, data, layout, size);
+}
+
+/// read back mip levels of a cubic 3d texture, tightly packed. the texture must allow copies.
+pub(crate) fn read_texture_mips(
+    device: &Device,
+    queue: &Queue,
+    texture: &Texture,
+    levels: std::ops::Range<u32>,
+) -> Vec<Vec<u8>> {
+    let texel_size = texture.format().block_copy_size(None).unwrap();
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("texture readback encoder"),
+    });
+
+    let readbacks = levels
+        .map(|level| {
+            let dim = texture.width() >> level;
+            let padded_bytes_per_row = (dim * texel_size).div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT)
+                * COPY_BYTES_PER_ROW_ALIGNMENT;
+            let buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("texture readback buffer"),
+                size: (padded_bytes_per_row * dim * dim) as BufferAddress,
+                usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            encoder.copy_texture_to_buffer(
+                ImageCopyTexture {
+                    texture,
+                    mip_level: level,
+                    origin: Origin3d::ZERO,
+                    aspect: TextureAspect::All,
+                },
+                ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_bytes_per_row),
+                        rows_per_image: Some(dim),
+                    },
+                },
+                Extent3d {
+                    width: dim,
+                    height: dim,
+                    depth_or_array_layers: dim,
+                },
+            );
+            (buffer, dim, padded_bytes_per_row)
+        })
+        .collect::<Vec<_>>();
+    queue.submit(std::iter::once(encoder.finish()));
+
+    readbacks
+        .into_iter()
+        .map(|(buffer, dim, padded_bytes_per_row)| {
+            let slice = buffer.slice(..);
+            slice.map_async(MapMode::Read, |res| {
+                res.expect("failed to map texture readback buffer")
+            });
+            device.poll(Maintain::Wait);
+            let row_size = (dim * texel_size) as usize;
+            let mut bytes = Vec::with_capacity(row_size * (dim * dim) as usize);
+            for row in slice
+                .get_mapped_range()
+                .chunks(padded_bytes_per_row as usize)
+            {
+                bytes.extend_from_slice(&row[..row_size]);
+            }
+            bytes
+        })
+        .collect()
+}
+
+/// run a `cs_main(origin + id)` pass of the octree or mipmap shader over the box `min..max` of
+/// the output level.
+fn dispatch_region(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    timestamp_writes: Option<ComputePassTimestampWrites>,
+    pipeline: &ComputePipeline,
+    input_view: &TextureView,
+    output_view: &TextureView,
+    min: glm::UVec3,
+    max: glm::UVec3,
+) {
+    let origin_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("compute origin buffer"),
+        contents: bytemuck::cast_slice(&[min.x, min.y, min.z, 0]),
+        usage: BufferUsages::UNIFORM,
+    });
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("compute bind group"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(input_view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(output_view),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: origin_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    let size = max - min;
+    let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+        label: Some("compute pass"),
+        timestamp_writes,
+    });
+    compute_pass.set_pipeline(pipeline);
+    compute_pass.set_bind_group(0, &bind_group, &[]);
+    compute_pass.dispatch_workgroups(size.x, size.y, size.z);
+}
+
+/// overwrite a box of the first mip level of a 3d texture. `data` is tightly packed.
+pub(crate) fn write_region_3d(
+    queue: &Queue,
+    texture: &Texture,
+    origin: Origin3d,
+    size: Extent3d,
+    data: &[u8],
+) {
+    let texel_size = texture.format().block_copy_size(None).unwrap();
+    let copy = ImageCopyTexture {
+        texture,
+        mip_level: 0,
+        origin,
+        aspect: TextureAspect::All,
+    };
+    let layout = ImageDataLayout {
+        offset: 0,
+        bytes_per_row: Some(size.width * texel_size),
+        rows_per_image: Some(size.height),
+    };
+    queue.write_texture(copy, data, layout, size);
+}
+
+/// offscreen target of the scene when it is rendered at a lower resolution than the window.
+pub(crate) fn create_scene_texture(
+    device: &Device,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: Some("scene texture"),
+        size: Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
+
+pub(crate) fn create_octree_texture(device: &Device, dim: u32) -> Texture {
+    let depth = dim.ilog2();
+
+    let octree_texture = device.create_texture(&TextureDescriptor {
+        label: Some("octree texture"),
+        // copies are used to save the octree and load it back, see `dvo.rs`.
+        usage: TextureUsages::TEXTURE_BINDING
+            | TextureUsages::STORAGE_BINDING
+            | TextureUsages::COPY_SRC
+            | TextureUsages::COPY_DST,
+        size: Extent3d {
+            width: dim / 2,
+            height: dim / 2,
+            depth_or_array_layers: dim / 2,
+        },
+        mip_level_count: depth,
+        sample_count: 1,
+        dimension: TextureDimension::D3,
+        format: OCTREE_FORMAT,
+        view_formats: &[],
+    });
+
+    octree_texture
+}
+
+/// distance to the nearest occupied brick of 4^3 voxels, in bricks. see `compute_sdf.wgsl`.
+pub(crate) fn create_sdf_texture(device: &Device, dim: u32) -> Texture {
+    let sdf_texture = device.create_texture(&TextureDescriptor {
+        label: Some("sdf texture"),
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
+        size: Extent3d {
+            width: dim / 4,
+            height: dim / 4,
+            depth_or_array_layers: dim / 4,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D3,
+        format: TextureFormat::R32Uint,
+        view_formats: &[],
+    });
+
+    sdf_texture
+}
+
+/// contour plane of each voxel, see `compute_contours.wgsl`. scenes without contours get a single
+/// empty texel.
+pub(crate) fn create_contours_texture(device: &Device, dim: u32, enabled: bool) -> Texture {
+    let dim = if enabled { dim } else { 1 };
+    device.create_texture(&TextureDescriptor {
+        label: Some("contours texture"),
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
+        size: Extent3d {
+            width: dim,
+            height: dim,
+            depth_or_array_layers: dim,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D3,
+        format: TextureFormat::Rgba8Snorm,
+        view_formats: &[],
+    })
+}
+
+/// the brick map: atlas slot + 1 of each brick of `BRICK`^3 voxels, 0 being empty or not resident,
+/// and the atlas of the resident bricks. see `brickmap.rs`. they are 1^3 when the brick map is
+/// not the traversal.
+pub(crate) fn create_brick_textures(
+    device: &Device,
+    dim: u32,
+    enabled: bool,
+) -> (Texture, Texture) {
+    let (bricks, atlas) = if enabled {
+        ((dim / BRICK).max(1), ATLAS_BRICKS * BRICK)
+    } else {
+        (1, 1)
+    };
+    let create = |label, size, format| {
+        device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            size: Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: size,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format,
+            view_formats: &[],
+        })
+    };
+    (
+        create("brick index texture", bricks, TextureFormat::R32Uint),
+        create("brick atlas texture", atlas, OCTREE_FORMAT),
+    )
+}
+
+/// nodes of the 64-tree, see `contree.wgsl`. the buffer holds as many nodes as a full tree of the
+/// volume, up to the largest storage binding of the device. a single node when the 64-tree is not
+/// the traversal.
+pub(crate) fn create_contree_buffer(device: &Device, dim: u32, enabled: bool) -> Buffer {
+    let nodes = if enabled {
+        let levels = dim.ilog2().div_ceil(2);
+        let full = (0..levels)
+            .map(|level| (dim.div_ceil(4u32.pow(levels - level)) as u64).pow(3))
+            .sum::<u64>();
+        let max_nodes = device.limits().max_storage_buffer_binding_size as u64 / CONTREE_NODE_SIZE;
+        full.min(max_nodes)
+    } else {
+        1
+    };
+    device.create_buffer(&BufferDescriptor {
+        label: Some("contree buffer"),
+        size: nodes * CONTREE_NODE_SIZE,
+        usage: BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    })
+}
+
+pub(crate) fn create_contree_count_buffer(device: &Device) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("contree count buffer"),
+        size: std::mem::size_of::<u32>() as BufferAddress,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// nearest occupied brick of each brick, only used while computing the distance field.
+fn create_seeds_texture(device: &Device, bricks: u32) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: Some("sdf seeds texture"),
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
+        size: Extent3d {
+            width: bricks,
+            height: bricks,
+            depth_or_array_layers: bricks,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D3,
+        format: TextureFormat::Rgba16Uint,
+        view_formats: &[],
+    })
+}
+
+pub(crate) fn create_vertex_buffer(device: &Device) -> Buffer {
+    const BUF_DATA: &[glm::Vec2] = &[
+        glm::Vec2::new(-1.0, -1.0),
+        glm::Vec2::new(1.0, -1.0),
+        glm::Vec2::new(1.0, 1.0),
+        glm::Vec2::new(-1.0, -1.0),
+        glm::Vec2::new(1.0, 1.0),
+        glm::Vec2::new(-1.0, 1.0),
+    ];
+
+    let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("vertex buffer"),
+        contents: bytemuck::cast_slice(BUF_DATA),
+        usage: BufferUsages::VERTEX,
+    });
+
+    vertex_buffer
+}
+
+/// the vertices of the gizmos, see `gizmos.rs`.
+pub(crate) fn create_gizmos_buffer(device: &Device) -> Buffer {
+    let gizmos_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("gizmos buffer"),
+        size: (MAX_GIZMO_VERTICES * std::mem::size_of::<GizmoVertex>()) as BufferAddress,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    gizmos_buffer
+}
+
+pub(crate) fn create_camera_buffer(device: &Device, camera_data: &[u8]) -> Buffer {
+    let camera_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("camera buffer"),
+        contents: camera_data,
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+
+    camera_buffer
+}
+
+pub(crate) fn create_lights_buffer(device: &Device, lights_data: &[u8]) -> Buffer {
+    let lights_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("lights buffer"),
+        contents: lights_data,
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+
+    lights_buffer
+}
+
+/// the lights placed in the scene, see `lights.rs`.
+pub(crate) fn create_local_lights_buffer(device: &Device, local_lights_data: &[u8]) -> Buffer {
+    let local_lights_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("local lights buffer"),
+        contents: local_lights_data,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+    });
+
+    local_lights_buffer
+}
+
+pub(crate) fn create_route_buffer(device: &Device, route_data: &[u8]) -> Buffer {
+    let route_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("route buffer"),
+        contents: route_data,
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+
+    route_buffer
+}
+
+pub(crate) fn create_route_points_buffer(device: &Device) -> Buffer {
+    let route_points_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("route points buffer"),
+        size: (MAX_ROUTE_POINTS * std::mem::size_of::<glm::Vec4>()) as BufferAddress,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    route_points_buffer
+}
+
+pub(crate) fn create_environment_buffer(device: &Device, environment_data: &[u8]) -> Buffer {
+    let environment_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("environment buffer"),
+        contents: environment_data,
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+
+    environment_buffer
+}
+
+pub(crate) fn create_frustum_buffer(device: &Device, frustum_data: &[u8]) -> Buffer {
+    let frustum_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("frustum buffer"),
+        contents: frustum_data,
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+
+    frustum_buffer
+}
+
+pub(crate) fn create_settings_buffer(device: &Device, settings_data: &[u8]) -> Buffer {
+    let settings_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("settings buffer"),
+        contents: settings_data,
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+
+    settings_buffer
+}
+
+pub(crate) fn create_probes_buffer(device: &Device, probes_data: &[u8]) -> Buffer {
+    let probes_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("probes buffer"),
+        contents: probes_data,
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+
+    probes_buffer
+}
+
+pub(crate) fn create_fog_volumes_buffer(device: &Device, fog_volumes_data: &[u8]) -> Buffer {
+    let fog_volumes_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("fog volumes buffer"),
+        contents: fog_volumes_data,
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+
+    fog_volumes_buffer
+}
+
+/// the shading properties of each voxel value, see `materials.rs`.
+pub(crate) fn create_materials_buffer(device: &Device, materials_data: &[u8]) -> Buffer {
+    let materials_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("materials buffer"),
+        contents: materials_data,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+    });
+
+    materials_buffer
+}
+
+pub(crate) fn create_sky_sh_buffer(device: &Device) -> Buffer {
+    let sky_sh_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("sky sh buffer"),
+        size: (SKY_SH_COEFFS * std::mem::size_of::<glm::Vec4>()) as BufferAddress,
+        usage: BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    });
+
+    sky_sh_buffer
+}
+
+/// copy a histogram of u32 counters accumulated by the shaders to the cpu, and reset it.
+fn read_histogram(device: &Device, queue: &Queue, buffer: &Buffer) -> Vec<u32> {
+    let size = buffer.size();
+    let readback_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("histogram readback buffer"),
+        size,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("histogram encoder"),
+    });
+    encoder.copy_buffer_to_buffer(buffer, 0, &readback_buffer, 0, size);
+    encoder.clear_buffer(buffer, 0, None);
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = readback_buffer.slice(..);
+    slice.map_async(MapMode::Read, |res| {
+        res.expect("failed to map histogram buffer")
+    });
+    device.poll(Maintain::Wait);
+    let histogram = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+    readback_buffer.unmap();
+
+    histogram
+}
+
+pub(crate) fn create_luma_histogram_buffer(device: &Device) -> Buffer {
+    let luma_histogram_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("luma histogram buffer"),
+        size: (LUMA_HISTOGRAM_BINS * std::mem::size_of::<u32>()) as BufferAddress,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    luma_histogram_buffer
+}
+
+pub(crate) fn create_iter_histogram_buffer(device: &Device) -> Buffer {
+    let iter_histogram_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("iter histogram buffer"),
+        size: (ITER_HISTOGRAM_BINS * std::mem::size_of::<u32>()) as BufferAddress,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    iter_histogram_buffer
+}
+
+pub(crate) fn create_accumulation_buffer(device: &Device) -> Buffer {
+    let accumulation_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("accumulation buffer"),
+        size: std::mem::size_of::<AccumulationUniform>() as BufferAddress,
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    accumulation_buffer
+}
+
+pub(crate) fn create_visible_bricks_buffer(device: &Device) -> Buffer {
+    let visible_bricks_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("visible bricks buffer"),
+        size: CULL_DIM.pow(3) as BufferAddress * std::mem::size_of::<u32>() as BufferAddress,
+        usage: BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    });
+
+    visible_bricks_buffer
+}
+
+/// the light lists of the clusters, see `clusters.wgsl`: the number of lights, then their
+/// indices.
+pub(crate) fn create_cluster_lights_buffer(device: &Device) -> Buffer {
+    let clusters = CLUSTER_TILES * CLUSTER_TILES * CLUSTER_SLICES;
+    let cluster_lights_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("cluster lights buffer"),
+        size: (clusters * (1 + MAX_CLUSTER_LIGHTS)) as BufferAddress
+            * std::mem::size_of::<u32>() as BufferAddress,
+        usage: BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    });
+
+    cluster_lights_buffer
+}
+
+pub(crate) fn create_history_buffer(device: &Device, pixels: BufferAddress) -> Buffer {
+    let history_buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("history buffer"),
+        size: pixels * HISTORY_PIXEL_SIZE,
+        usage: BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    });
+
+    history_buffer
+}
+
+pub(crate) fn create_voxels_texture(
+    device: &Device,
+    queue: &Queue,
+    dim: u32,
+    voxels_data: &[u8],
 ) -> Texture {
-    // let colors_texture = device.create_texture_with_data(
-    //     queue,
-    //     &TextureDescriptor {
-    //         label: Some("colors texture"),
-    //         size: Extent3d {
-    //             width: dim,
-    //             height: dim,
-    //             depth_or_array_layers: dim,
-    //         },
-    //         mip_level_count: dim.ilog2(),
-    //         sample_count: 1,
-    //         dimension: TextureDimension::D3,
-    //         format: TextureFormat::Rgba8Unorm,
-    //         usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
-    //         view_formats: &[],
-    //     },
-    //     util::TextureDataOrder::LayerMajor,
-    //     colors_data,
-    // );
-    let size = Extent3d {
-        width: dim,
-        height: dim,
-        depth_or_array_layers: dim,
+    let voxels_texture = device.create_texture_with_data(
+        queue,
+        &TextureDescriptor {
+            label: Some("voxels texture"),
+            size: Extent3d {
+                width: dim,
+                height: dim,
+                depth_or_array_layers: dim,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format: OCTREE_FORMAT,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::STORAGE_BINDING
+                | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        util::TextureDataOrder::LayerMajor,
+        voxels_data,
+    );
+
+    voxels_texture
+}
+
+pub(crate) fn create_uniforms_bind_group(
+    device: &Device,
+    bind_group_layout: &BindGroupLayout,
+    camera_buffer: &Buffer,
+    render_camera_buffer: &Buffer,
+    lights_buffer: &Buffer,
+    route_buffer: &Buffer,
+    route_points_buffer: &Buffer,
+    environment_buffer: &Buffer,
+    frustum_buffer: &Buffer,
+    settings_buffer: &Buffer,
+    sky_sh_buffer: &Buffer,
+    probes_buffer: &Buffer,
+    fog_volumes_buffer: &Buffer,
+    iter_histogram_buffer: &Buffer,
+    luma_histogram_buffer: &Buffer,
+    local_lights_buffer: &Buffer,
+    accumulation_buffer: &Buffer,
+    history_buffer: &Buffer,
+) -> BindGroup {
+    let uniforms_bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("uniforms bind group"),
+        layout: &bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: lights_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: route_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: route_points_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 4,
+                resource: environment_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 5,
+                resource: frustum_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 6,
+                resource: settings_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 7,
+                resource: sky_sh_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 8,
+                resource: probes_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 9,
+                resource: fog_volumes_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 10,
+                resource: iter_histogram_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 11,
+                resource: luma_histogram_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 12,
+                resource: local_lights_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 13,
+                resource: accumulation_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 14,
+                resource: history_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 15,
+                resource: render_camera_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    uniforms_bind_group
+}
+
+/// the lights, environment and settings are shared with the render pipeline, the
+/// spherical harmonics are written instead of read.
+pub(crate) fn create_sky_sh_bind_group(
+    device: &Device,
+    bind_group_layout: &BindGroupLayout,
+    lights_buffer: &Buffer,
+    environment_buffer: &Buffer,
+    settings_buffer: &Buffer,
+    sky_sh_buffer: &Buffer,
+) -> BindGroup {
+    let sky_sh_bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("sky sh bind group"),
+        layout: &bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 1,
+                resource: lights_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 4,
+                resource: environment_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 6,
+                resource: settings_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 7,
+                resource: sky_sh_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    sky_sh_bind_group
+}
+
+pub(crate) fn create_octree_bind_group(
+    device: &Device,
+    bind_group_layout: &BindGroupLayout,
+    octree_texture: &Texture,
+    colors_texture: &Texture,
+    lightmap_texture: &Texture,
+    probes_texture: &Texture,
+    sdf_texture: &Texture,
+    contours_texture: &Texture,
+    brick_index_texture: &Texture,
+    brick_atlas_texture: &Texture,
+    contree_buffer: &Buffer,
+    detail_texture: &Texture,
+    voxels_texture: &Texture,
+    materials_buffer: &Buffer,
+    visible_bricks_buffer: &Buffer,
+    cluster_lights_buffer: &Buffer,
+) -> BindGroup {
+    let octree_view = octree_texture.create_view(&TextureViewDescriptor {
+        label: Some("octree texture view"),
+        ..Default::default()
+    });
+
+    let colors_view = colors_texture.create_view(&TextureViewDescriptor {
+        label: Some("colors texture view"),
+        base_mip_level: 0,
+        mip_level_count: Some(1),
+        ..Default::default()
+    });
+
+    let lightmap_view = lightmap_texture.create_view(&TextureViewDescriptor {
+        label: Some("lightmap texture view"),
+        ..Default::default()
+    });
+
+    let probes_view = probes_texture.create_view(&TextureViewDescriptor {
+        label: Some("probes texture view"),
+        dimension: Some(TextureViewDimension::CubeArray),
+        ..Default::default()
+    });
+
+    let sdf_view = sdf_texture.create_view(&TextureViewDescriptor {
+        label: Some("sdf texture view"),
+        ..Default::default()
+    });
+
+    let contours_view = contours_texture.create_view(&TextureViewDescriptor {
+        label: Some("contours texture view"),
+        ..Default::default()
+    });
+
+    let brick_index_view = brick_index_texture.create_view(&TextureViewDescriptor {
+        label: Some("brick index texture view"),
+        ..Default::default()
+    });
+
+    let brick_atlas_view = brick_atlas_texture.create_view(&TextureViewDescriptor {
+        label: Some("brick atlas texture view"),
+        ..Default::default()
+    });
+
+    let detail_view = detail_texture.create_view(&TextureViewDescriptor {
+        label: Some("detail texture view"),
+        ..Default::default()
+    });
+
+    let voxels_view = voxels_texture.create_view(&TextureViewDescriptor {
+        label: Some("voxels texture view"),
+        ..Default::default()
+    });
+
+    let linear_sampler = device.create_sampler(&SamplerDescriptor {
+        label: Some("linear sampler"),
+        mag_filter: FilterMode::Linear,
+        min_filter: FilterMode::Linear,
+        mipmap_filter: FilterMode::Linear,
+        ..Default::default()
+    });
+
+    let nearest_sampler = device.create_sampler(&SamplerDescriptor {
+        label: Some("nearest sampler"),
+        mag_filter: FilterMode::Nearest,
+        min_filter: FilterMode::Linear,
+        mipmap_filter: FilterMode::Linear,
+        ..Default::default()
+    });
+
+    let octree_bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("octree bind group"),
+        layout: &bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&octree_view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(&colors_view),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: BindingResource::Sampler(&linear_sampler),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: BindingResource::Sampler(&nearest_sampler),
+            },
+            BindGroupEntry {
+                binding: 4,
+                resource: BindingResource::TextureView(&lightmap_view),
+            },
+            BindGroupEntry {
+                binding: 5,
+                resource: BindingResource::TextureView(&probes_view),
+            },
+            BindGroupEntry {
+                binding: 6,
+                resource: BindingResource::TextureView(&sdf_view),
+            },
+            BindGroupEntry {
+                binding: 7,
+                resource: BindingResource::TextureView(&contours_view),
+            },
+            BindGroupEntry {
+                binding: 8,
+                resource: BindingResource::TextureView(&brick_index_view),
+            },
+            BindGroupEntry {
+                binding: 9,
+                resource: BindingResource::TextureView(&brick_atlas_view),
+            },
+            BindGroupEntry {
+                binding: 10,
+                resource: contree_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 11,
+                resource: BindingResource::TextureView(&detail_view),
+            },
+            BindGroupEntry {
+                binding: 12,
+                resource: BindingResource::TextureView(&voxels_view),
+            },
+            BindGroupEntry {
+                binding: 13,
+                resource: materials_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 14,
+                resource: visible_bricks_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 15,
+                resource: cluster_lights_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    octree_bind_group
+}
+
+/// log a shader compilation error, and return it for the ui.
+fn shader_error(err: String) -> String {
+    eprintln!("{err}");
+    err
+}
+
+#[tracing::instrument(skip_all)]
+fn compile_render_shader(
+    device: &Device,
+    path: &str,
+    constants: &HashMap<String, f64>,
+) -> Result<ShaderModule, String> {
+    let preproc_ctx = preproc::Context {
+        main: &PathBuf::from_str(path).unwrap(),
+        constants,
     };
-    let texture = device.create_texture(&TextureDescriptor {
-        label: Some("colors texture"),
-        size,
-        mip_level_count: dim.ilog2(),
-        sample_count: 1,
-        dimension: TextureDimension::D3,
-        format: TextureFormat::Rgba8Unorm,
-        usage: TextureUsages::TEXTURE_BINDING
-            | TextureUsages::STORAGE_BINDING
-            | TextureUsages::COPY_DST,
-        view_formats: &[],
+    let shader_module = match preprocess_shader(&preproc_ctx) {
+        Ok(module) => module,
+        Err(err) => {
+            return Err(shader_error(format!("{err}")));
+        }
+    };
+
+    device.push_error_scope(ErrorFilter::Validation);
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(path),
+        // source: ShaderSource::Naga(Cow::Owned(shader_module)),
+        source: ShaderSource::Naga(Cow::Owned(shader_module)),
+        // source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("compiled_shader_opt.wgsl"))),
+    });
+
+    let err = device.pop_error_scope().block_on();
+    match err {
+        Some(err) => Err(shader_error(format!("shader error: {err}"))),
+        None => {
+            println!("compiled `{path}`");
+            Ok(shader)
+        }
+    }
+}
+
+/// the fullscreen quad pipeline of a pass of the deferred renderer.
+fn create_pass_pipeline(
+    device: &Device,
+    label: &str,
+    shader: &ShaderModule,
+    layout: &PipelineLayout,
+    targets: &[Option<ColorTargetState>],
+    depth_stencil: Option<DepthStencilState>,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[VertexBufferLayout {
+                array_stride: std::mem::size_of::<glm::Vec2>() as BufferAddress,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &[VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: VertexFormat::Float32x2,
+                }],
+            }],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets,
+            compilation_options: Default::default(),
+        }),
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            ..Default::default()
+        },
+        depth_stencil,
+        multisample: Default::default(),
+        multiview: None,
+        // cache: None,
+    })
+}
+
+/// the g-buffer and the visibility target of `width`x`height` pixels, bound for `pipelines`.
+fn create_gbuffer(device: &Device, pipelines: &ScenePipelines, width: u32, height: u32) -> GBuffer {
+    let view = |format| {
+        create_scene_texture(device, format, width, height).create_view(&Default::default())
+    };
+    let pos_view = view(GBUFFER_POS_FORMAT);
+    let surface_view = view(GBUFFER_SURFACE_FORMAT);
+    let depth_view = view(DEPTH_FORMAT);
+    let visibility_view = view(VISIBILITY_FORMAT);
+    let (beam_width, beam_height) = beam_size((width, height));
+    let beam_view = create_scene_texture(device, BEAM_FORMAT, beam_width, beam_height)
+        .create_view(&Default::default());
+
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("gbuffer bind group"),
+        layout: &pipelines.lighting.get_bind_group_layout(2),
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&pos_view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(&surface_view),
+            },
+        ],
+    });
+    let visibility_bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("visibility bind group"),
+        layout: &pipelines.lighting.get_bind_group_layout(3),
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::TextureView(&visibility_view),
+        }],
+    });
+    let beam_bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("beam bind group"),
+        layout: &pipelines.primary.get_bind_group_layout(2),
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::TextureView(&beam_view),
+        }],
+    });
+    GBuffer {
+        pos_view,
+        surface_view,
+        depth_view,
+        visibility_view,
+        beam_view,
+        bind_group,
+        visibility_bind_group,
+        beam_bind_group,
+        size: (width, height),
+    }
+}
+
+/// the size of the beam target for a g-buffer of `size` pixels: one texel per corner of the tiles,
+/// the last ones included.
+fn beam_size(size: (u32, u32)) -> (u32, u32) {
+    (
+        size.0.div_ceil(BEAM_TILE) + 1,
+        size.1.div_ceil(BEAM_TILE) + 1,
+    )
+}
+
+/// the passes of the deferred renderer, see `ScenePipelines`.
+#[tracing::instrument(skip_all)]
+fn create_scene_pipelines(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+    constants: &ShaderConstants,
+) -> Result<ScenePipelines, String> {
+    let constants = constants.to_hashmap();
+    let beam_shader = compile_render_shader(device, "src/beam.wgsl", &constants)?;
+    let primary_shader = compile_render_shader(device, "src/primary.wgsl", &constants)?;
+    let visibility_shader = compile_render_shader(device, "src/visibility.wgsl", &constants)?;
+    let lighting_shader = compile_render_shader(device, "src/shader.wgsl", &constants)?;
+
+    let octree_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("octree bind group layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                // octree
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Uint,
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // colors
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // linear_sampler
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // nearest_sampler
+                binding: 3,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // lightmap
+                binding: 4,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // probes_tex
+                binding: 5,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::CubeArray,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // sdf
+                binding: 6,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Uint,
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // contours
+                binding: 7,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // brick_index
+                binding: 8,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Uint,
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // brick_atlas
+                binding: 9,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Uint,
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // contree
+                binding: 10,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // detail_atlas
+                binding: 11,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // voxel_ids
+                binding: 12,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Uint,
+                    view_dimension: TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // materials
+                binding: 13,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // visible_bricks
+                binding: 14,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // cluster_lights
+                binding: 15,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+
+    let uniforms_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("uniforms bind group layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                // camera
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // lights
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // route
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // route_points
+                binding: 3,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // environment
+                binding: 4,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // frustum
+                binding: 5,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // settings
+                binding: 6,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // sky_sh
+                binding: 7,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // probes
+                binding: 8,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // fog_volumes
+                binding: 9,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // iter_histogram
+                binding: 10,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // luma_histogram
+                binding: 11,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // local_lights
+                binding: 12,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // accumulation
+                binding: 13,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // history
+                binding: 14,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // render_cam
+                binding: 15,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
     });
-    let copy = ImageCopyTexture {
-        texture: &texture,
-        mip_level: 0,
-        origin: Origin3d::ZERO,
-        aspect: TextureAspect::All,
+
+    let gbuffer_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("gbuffer bind group layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                // gbuffer_pos
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // gbuffer_surface
+                binding: 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Uint,
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+        ],
+    });
+
+    let visibility_bind_group_layout =
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("visibility bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                // visibility
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+    let beam_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("beam bind group layout"),
+        entries: &[BindGroupLayoutEntry {
+            // beam_depth
+            binding: 0,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: false },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        }],
+    });
+
+    // each pass uses the layouts of the previous one, and the targets it reads.
+    let bind_group_layouts = [
+        &uniforms_bind_group_layout,
+        &octree_bind_group_layout,
+        &gbuffer_bind_group_layout,
+        &visibility_bind_group_layout,
+    ];
+    let pipeline_layout = |label, groups| {
+        device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &bind_group_layouts[..groups],
+            push_constant_ranges: &[],
+        })
     };
-    let layout = ImageDataLayout {
-        offset: 0,
-        bytes_per_row: Some(dim * 4),
-        rows_per_image: Some(dim),
+    let target = |format| {
+        Some(ColorTargetState {
+            format,
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        })
     };
-    queue.write_texture(copy, colors_data, layout, size);
 
-    texture
+    let beam = create_pass_pipeline(
+        device,
+        "beam pipeline",
+        &beam_shader,
+        &pipeline_layout("beam pipeline layout", 2),
+        &[target(BEAM_FORMAT)],
+        None,
+    );
+    // the primary pass reads the beam target in place of the g-buffer.
+    let primary_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("primary pipeline layout"),
+        bind_group_layouts: &[
+            &uniforms_bind_group_layout,
+            &octree_bind_group_layout,
+            &beam_bind_group_layout,
+        ],
+        push_constant_ranges: &[],
+    });
+    let primary = create_pass_pipeline(
+        device,
+        "primary pipeline",
+        &primary_shader,
+        &primary_layout,
+        &[target(GBUFFER_POS_FORMAT), target(GBUFFER_SURFACE_FORMAT)],
+        // every pixel writes its depth, the quad is not tested against it.
+        Some(DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::Always,
+            stencil: Default::default(),
+            bias: Default::default(),
+        }),
+    );
+    let visibility = create_pass_pipeline(
+        device,
+        "visibility pipeline",
+        &visibility_shader,
+        &pipeline_layout("visibility pipeline layout", 3),
+        &[target(VISIBILITY_FORMAT)],
+        None,
+    );
+    let lighting = create_pass_pipeline(
+        device,
+        "lighting pipeline",
+        &lighting_shader,
+        &pipeline_layout("lighting pipeline layout", 4),
+        &[Some(ColorTargetState {
+            format: surface_config.format,
+            blend: Some(BlendState {
+                color: BlendComponent::REPLACE,
+                alpha: BlendComponent::REPLACE,
+            }),
+            write_mask: ColorWrites::ALL,
+        })],
+        None,
+    );
+
+    Ok(ScenePipelines {
+        beam,
+        primary,
+        visibility,
+        lighting,
+    })
 }
 
-pub(crate) fn create_octree_texture(device: &Device, dim: u32) -> Texture {
-    let depth = dim.ilog2();
+pub(crate) fn create_blit_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    scene_view: &TextureView,
+) -> BindGroup {
+    let sampler = device.create_sampler(&SamplerDescriptor {
+        label: Some("blit sampler"),
+        mag_filter: FilterMode::Linear,
+        min_filter: FilterMode::Linear,
+        ..Default::default()
+    });
 
-    let octree_texture = device.create_texture(&TextureDescriptor {
-        label: Some("octree texture"),
-        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
-        size: Extent3d {
-            width: dim / 2,
-            height: dim / 2,
-            depth_or_array_layers: dim / 2,
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("blit bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(scene_view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Sampler(&sampler),
+            },
+        ],
+    })
+}
+
+/// the lines of the gizmos over the scene, see `gizmos.wgsl`. the depth is only read, and it is
+/// reversed: the lines nearer than the voxels have a greater depth.
+#[tracing::instrument(skip_all)]
+fn create_gizmos_pipeline(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+) -> Result<RenderPipeline, String> {
+    let constants = ShaderConstants::default().to_hashmap();
+    let shader = compile_render_shader(device, "src/gizmos.wgsl", &constants)?;
+
+    Ok(device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("gizmos pipeline"),
+        layout: None,
+        vertex: VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[VertexBufferLayout {
+                array_stride: std::mem::size_of::<GizmoVertex>() as BufferAddress,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &[
+                    VertexAttribute {
+                        offset: 0,
+                        shader_location: 0,
+                        format: VertexFormat::Float32x3,
+                    },
+                    VertexAttribute {
+                        offset: std::mem::size_of::<glm::Vec3>() as BufferAddress,
+                        shader_location: 1,
+                        format: VertexFormat::Unorm8x4,
+                    },
+                ],
+            }],
+            compilation_options: Default::default(),
         },
-        mip_level_count: depth,
-        sample_count: 1,
-        dimension: TextureDimension::D3,
-        format: OCTREE_FORMAT,
-        view_formats: &[],
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(ColorTargetState {
+                format: surface_config.format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::LineList,
+            ..Default::default()
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: CompareFunction::GreaterEqual,
+            stencil: Default::default(),
+            bias: Default::default(),
+        }),
+        multisample: Default::default(),
+        multiview: None,
+    }))
+}
+
+fn create_gizmos_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    camera_buffer: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("gizmos bind group"),
+        layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: camera_buffer.as_entire_binding(),
+        }],
+    })
+}
+
+fn create_culling_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    camera_buffer: &Buffer,
+    visible_bricks_buffer: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("culling bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: visible_bricks_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+fn create_light_culling_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    camera_buffer: &Buffer,
+    lights_buffer: &Buffer,
+    local_lights_buffer: &Buffer,
+    cluster_lights_buffer: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("light culling bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: lights_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: cluster_lights_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 12,
+                resource: local_lights_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+#[tracing::instrument(skip_all)]
+fn create_blit_pipeline(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+) -> Result<RenderPipeline, String> {
+    create_quad_pipeline(device, "blit", "src/blit.wgsl", surface_config.format)
+}
+
+#[tracing::instrument(skip_all)]
+fn create_slice_pipeline(device: &Device) -> Result<RenderPipeline, String> {
+    create_quad_pipeline(device, "slice", "src/slice.wgsl", SLICE_FORMAT)
+}
+
+/// a pipeline drawing the fullscreen quad of the vertex buffer with the shader at `path`.
+fn create_quad_pipeline(
+    device: &Device,
+    label: &str,
+    path: &str,
+    format: TextureFormat,
+) -> Result<RenderPipeline, String> {
+    let constants = ShaderConstants::default().to_hashmap();
+    let preproc_ctx = preproc::Context {
+        main: &PathBuf::from_str(path).unwrap(),
+        constants: &constants,
+    };
+    let shader_module = match preprocess_shader(&preproc_ctx) {
+        Ok(module) => module,
+        Err(err) => {
+            return Err(shader_error(format!("preproc error: {err}")));
+        }
+    };
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(label),
+        source: ShaderSource::Naga(Cow::Owned(shader_module)),
+    });
+
+    let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(&format!("{label} pipeline")),
+        layout: None,
+        vertex: VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[VertexBufferLayout {
+                array_stride: std::mem::size_of::<glm::Vec2>() as BufferAddress,
+                step_mode: VertexStepMode::Vertex,
+                attributes: &[VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: VertexFormat::Float32x2,
+                }],
+            }],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: Default::default(),
+        multiview: None,
+    });
+
+    Ok(pipeline)
+}
+
+#[tracing::instrument(skip_all)]
+fn create_octree_pipeline(
+    device: &Device,
+    constants: &ShaderConstants,
+) -> Result<ComputePipeline, String> {
+    let constants = constants.to_hashmap();
+    let preproc_ctx = preproc::Context {
+        main: &PathBuf::from_str("src/compute_octree.wgsl").unwrap(),
+        constants: &constants,
+    };
+
+    let shader_module = match preprocess_shader(&preproc_ctx) {
+        Ok(module) => module,
+        Err(err) => {
+            return Err(shader_error(format!("preproc error: {err}")));
+        }
+    };
+
+    device.push_error_scope(ErrorFilter::Validation);
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("compute"),
+        source: ShaderSource::Naga(Cow::Owned(shader_module)),
+    });
+
+    let err = device.pop_error_scope().block_on();
+    match err {
+        Some(err) => {
+            return Err(shader_error(format!("shader error: {err}")));
+        }
+        None => println!("compiled compute shader"),
+    }
+
+    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("compute bind group layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                // voxels
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::StorageTexture {
+                    access: StorageTextureAccess::ReadOnly,
+                    format: OCTREE_FORMAT,
+                    view_dimension: TextureViewDimension::D3,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // octree
+                binding: 1,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::StorageTexture {
+                    access: StorageTextureAccess::WriteOnly,
+                    format: OCTREE_FORMAT,
+                    view_dimension: TextureViewDimension::D3,
+                },
+                count: None,
+            },
+        ],
     });
 
-    octree_texture
-}
-
-pub(crate) fn create_vertex_buffer(device: &Device) -> Buffer {
-    const BUF_DATA: &[glm::Vec2] = &[
-        glm::Vec2::new(-1.0, -1.0),
-        glm::Vec2::new(1.0, -1.0),
-        glm::Vec2::new(1.0, 1.0),
-        glm::Vec2::new(-1.0, -1.0),
-        glm::Vec2::new(1.0, 1.0),
-        glm::Vec2::new(-1.0, 1.0),
-    ];
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("compute pipeline layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
 
-    let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("vertex buffer"),
-        contents: bytemuck::cast_slice(BUF_DATA),
-        usage: BufferUsages::VERTEX,
+    let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("compute pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "cs_main",
+        compilation_options: Default::default(),
+        // cache: None,
     });
 
-    vertex_buffer
+    Ok(compute_pipeline)
 }
 
-pub(crate) fn create_camera_buffer(device: &Device, camera_data: &[u8]) -> Buffer {
-    let camera_buffer = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("camera buffer"),
-        contents: camera_data,
-        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-    });
+#[tracing::instrument(skip_all)]
+fn create_mipmap_pipeline(
+    device: &Device,
+    constants: &ShaderConstants,
+    mip_path: MipPath,
+) -> Result<ComputePipeline, String> {
+    let mut constants = constants.to_hashmap();
+    constants.insert(
+        "MIPMAP_COPY".to_owned(),
+        (mip_path == MipPath::Copy) as u32 as f64,
+    );
+    let preproc_ctx = preproc::Context {
+        main: &PathBuf::from_str("src/mipmap.wgsl").unwrap(),
+        constants: &constants,
+    };
 
-    camera_buffer
-}
+    let shader_module = match preprocess_shader(&preproc_ctx) {
+        Ok(module) => module,
+        Err(err) => {
+            return Err(shader_error(format!("preproc error: {err}")));
+        }
+    };
 
-pub(crate) fn create_lights_buffer(device: &Device, lights_data: &[u8]) -> Buffer {
-    let lights_buffer = device.create_buffer_init(&BufferInitDescriptor {
-        label: Some("lights buffer"),
-        contents: lights_data,
-        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    device.push_error_scope(ErrorFilter::Validation);
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("mipmap"),
+        source: ShaderSource::Naga(Cow::Owned(shader_module)),
     });
 
-    lights_buffer
-}
+    let err = device.pop_error_scope().block_on();
+    match err {
+        Some(err) => {
+            return Err(shader_error(format!("shader error: {err}")));
+        }
+        None => println!("compiled compute shader"),
+    }
 
-pub(crate) fn create_voxels_texture(
-    device: &Device,
-    queue: &Queue,
-    dim: u32,
-    voxels_data: &[u8],
-) -> Texture {
-    let voxels_texture = device.create_texture_with_data(
-        queue,
-        &TextureDescriptor {
-            label: Some("voxels texture"),
-            size: Extent3d {
-                width: dim,
-                height: dim,
-                depth_or_array_layers: dim,
+    let (input, output) = match mip_path {
+        MipPath::Storage => (
+            BindingType::StorageTexture {
+                access: StorageTextureAccess::ReadOnly,
+                format: COLORS_FORMAT,
+                view_dimension: TextureViewDimension::D3,
             },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D3,
-            format: OCTREE_FORMAT,
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
-            view_formats: &[],
-        },
-        util::TextureDataOrder::LayerMajor,
-        voxels_data,
-    );
-
-    voxels_texture
-}
-
-pub(crate) fn create_uniforms_bind_group(
-    device: &Device,
-    bind_group_layout: &BindGroupLayout,
-    camera_buffer: &Buffer,
-    lights_buffer: &Buffer,
-) -> BindGroup {
-    let uniforms_bind_group = device.create_bind_group(&BindGroupDescriptor {
-        label: Some("uniforms bind group"),
-        layout: &bind_group_layout,
+            BindingType::StorageTexture {
+                access: StorageTextureAccess::WriteOnly,
+                format: COLORS_FORMAT,
+                view_dimension: TextureViewDimension::D3,
+            },
+        ),
+        MipPath::Copy => (
+            BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D3,
+                multisampled: false,
+            },
+            BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        ),
+    };
+    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("mipmap bind group layout"),
         entries: &[
-            BindGroupEntry {
+            BindGroupLayoutEntry {
+                // in_tex
                 binding: 0,
-                resource: camera_buffer.as_entire_binding(),
+                visibility: ShaderStages::COMPUTE,
+                ty: input,
+                count: None,
             },
-            BindGroupEntry {
+            BindGroupLayoutEntry {
+                // out_tex, or out_buf
                 binding: 1,
-                resource: lights_buffer.as_entire_binding(),
+                visibility: ShaderStages::COMPUTE,
+                ty: output,
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                // origin, or region
+                binding: 2,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
             },
         ],
     });
 
-    uniforms_bind_group
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("compute pipeline layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("mipmap pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "cs_main",
+        compilation_options: Default::default(),
+        // cache: None,
+    });
+
+    Ok(pipeline)
 }
 
-pub(crate) fn create_octree_bind_group(
+#[tracing::instrument(skip_all)]
+fn create_pick_pipeline(
     device: &Device,
-    bind_group_layout: &BindGroupLayout,
-    octree_texture: &Texture,
-    colors_texture: &Texture,
-) -> BindGroup {
-    let octree_view = octree_texture.create_view(&TextureViewDescriptor {
-        label: Some("octree texture view"),
-        ..Default::default()
-    });
+    constants: &ShaderConstants,
+) -> Result<ComputePipeline, String> {
+    let constants = constants.to_hashmap();
+    let preproc_ctx = preproc::Context {
+        main: &PathBuf::from_str("src/pick.wgsl").unwrap(),
+        constants: &constants,
+    };
 
-    let colors_view = colors_texture.create_view(&TextureViewDescriptor {
-        label: Some("colors texture view"),
-        base_mip_level: 0,
-        mip_level_count: Some(1),
-        ..Default::default()
+    let shader_module = match preprocess_shader(&preproc_ctx) {
+        Ok(module) => module,
+        Err(err) => {
+            return Err(shader_error(format!("preproc error: {err}")));
+        }
+    };
+
+    device.push_error_scope(ErrorFilter::Validation);
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("pick"),
+        source: ShaderSource::Naga(Cow::Owned(shader_module)),
     });
 
-    let linear_sampler = device.create_sampler(&SamplerDescriptor {
-        label: Some("linear sampler"),
-        mag_filter: FilterMode::Linear,
-        min_filter: FilterMode::Linear,
-        mipmap_filter: FilterMode::Linear,
-        ..Default::default()
+    let err = device.pop_error_scope().block_on();
+    match err {
+        Some(err) => {
+            return Err(shader_error(format!("shader error: {err}")));
+        }
+        None => println!("compiled pick shader"),
+    }
+
+    // the layout is derived from the shader: the rays in group 0, and the octree and colors
+    // textures in group 1, like in the render pipeline.
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("pick pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: "cs_main",
+        compilation_options: Default::default(),
+        // cache: None,
     });
 
-    let nearest_sampler = device.create_sampler(&SamplerDescriptor {
-        label: Some("nearest sampler"),
-        mag_filter: FilterMode::Nearest,
-        min_filter: FilterMode::Linear,
-        mipmap_filter: FilterMode::Linear,
-        ..Default::default()
+    Ok(pipeline)
+}
+
+#[tracing::instrument(skip_all)]
+fn create_sky_sh_pipeline(
+    device: &Device,
+    constants: &ShaderConstants,
+) -> Result<ComputePipeline, String> {
+    let constants = constants.to_hashmap();
+    let preproc_ctx = preproc::Context {
+        main: &PathBuf::from_str("src/sky_sh.wgsl").unwrap(),
+        constants: &constants,
+    };
+
+    let shader_module = match preprocess_shader(&preproc_ctx) {
+        Ok(module) => module,
+        Err(err) => {
+            return Err(shader_error(format!("preproc error: {err}")));
+        }
+    };
+
+    device.push_error_scope(ErrorFilter::Validation);
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("sky sh"),
+        source: ShaderSource::Naga(Cow::Owned(shader_module)),
     });
 
-    let octree_bind_group = device.create_bind_group(&BindGroupDescriptor {
-        label: Some("octree bind group"),
-        layout: &bind_group_layout,
-        entries: &[
-            BindGroupEntry {
-                binding: 0,
-                resource: BindingResource::TextureView(&octree_view),
-            },
-            BindGroupEntry {
-                binding: 1,
-                resource: BindingResource::TextureView(&colors_view),
-            },
-            BindGroupEntry {
-                binding: 2,
-                resource: BindingResource::Sampler(&linear_sampler),
-            },
-            BindGroupEntry {
-                binding: 3,
-                resource: BindingResource::Sampler(&nearest_sampler),
-            },
-        ],
+    let err = device.pop_error_scope().block_on();
+    match err {
+        Some(err) => {
+            return Err(shader_error(format!("shader error: {err}")));
+        }
+        None => println!("compiled sky sh shader"),
+    }
+
+    // the layout is derived from the shader: the lights, environment, settings and sky_sh
+    // bindings of group 0, at the same indices as in the render pipeline.
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("sky sh pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: "cs_main",
+        compilation_options: Default::default(),
+        // cache: None,
     });
 
-    octree_bind_group
+    Ok(pipeline)
 }
 
-pub(crate) fn create_shader_pipeline(
+#[tracing::instrument(skip_all)]
+fn create_sdf_pipelines(
     device: &Device,
-    surface_config: &SurfaceConfiguration,
     constants: &ShaderConstants,
-) -> Option<RenderPipeline> {
+) -> Result<SdfPipelines, String> {
     let constants = constants.to_hashmap();
     let preproc_ctx = preproc::Context {
-        main: &PathBuf::from_str("src/shader.wgsl").unwrap(),
+        main: &PathBuf::from_str("src/compute_sdf.wgsl").unwrap(),
         constants: &constants,
     };
+
     let shader_module = match preprocess_shader(&preproc_ctx) {
         Ok(module) => module,
         Err(err) => {
-            eprintln!("{}", err);
-            return None;
+            return Err(shader_error(format!("preproc error: {err}")));
         }
     };
 
     device.push_error_scope(ErrorFilter::Validation);
 
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("shader"),
-        // source: ShaderSource::Naga(Cow::Owned(shader_module)),
+        label: Some("sdf"),
         source: ShaderSource::Naga(Cow::Owned(shader_module)),
-        // source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("compiled_shader_opt.wgsl"))),
     });
 
     let err = device.pop_error_scope().block_on();
     match err {
         Some(err) => {
-            eprintln!("shader error: {}", err);
-            return None;
+            return Err(shader_error(format!("shader error: {err}")));
         }
-        None => println!("compiled render shader"),
+        None => println!("compiled sdf shader"),
     }
 
-    let octree_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        label: Some("octree bind group layout"),
-        entries: &[
-            BindGroupLayoutEntry {
-                // octree
-                binding: 0,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Texture {
-                    sample_type: TextureSampleType::Uint,
-                    view_dimension: TextureViewDimension::D3,
-                    multisampled: false,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                // colors
-                binding: 1,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Texture {
-                    sample_type: TextureSampleType::Float { filterable: true },
-                    view_dimension: TextureViewDimension::D3,
-                    multisampled: false,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                // linear_sampler
-                binding: 2,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                // nearest_sampler
-                binding: 3,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Sampler(SamplerBindingType::Filtering),
-                count: None,
-            },
-        ],
-    });
-
-    let uniforms_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        label: Some("uniforms bind group layout"),
-        entries: &[
-            BindGroupLayoutEntry {
-                // camera
-                binding: 0,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                // lights
-                binding: 1,
-                visibility: ShaderStages::FRAGMENT,
-                ty: BindingType::Buffer {
-                    ty: BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-        ],
-    });
-
-    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("render pipeline layout"),
-        bind_group_layouts: &[&uniforms_bind_group_layout, &octree_bind_group_layout],
-        push_constant_ranges: &[],
-    });
-
-    let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: Some("render pipeline"),
-        layout: Some(&pipeline_layout),
-        vertex: VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: &[VertexBufferLayout {
-                array_stride: std::mem::size_of::<glm::Vec2>() as BufferAddress,
-                step_mode: VertexStepMode::Vertex,
-                attributes: &[VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: VertexFormat::Float32x2,
-                }],
-            }],
-            compilation_options: Default::default(),
-        },
-        fragment: Some(FragmentState {
+    // the layouts are derived from the shader, each pass only uses some of the bindings.
+    let pipeline = |entry_point| {
+        device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("sdf pipeline"),
+            layout: None,
             module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(ColorTargetState {
-                format: surface_config.format,
-                blend: Some(BlendState {
-                    color: BlendComponent::REPLACE,
-                    alpha: BlendComponent::REPLACE,
-                }),
-                write_mask: ColorWrites::ALL,
-            })],
+            entry_point,
             compilation_options: Default::default(),
-        }),
-        primitive: PrimitiveState {
-            topology: PrimitiveTopology::TriangleList,
-            front_face: FrontFace::Ccw,
-            cull_mode: Some(Face::Back),
-            ..Default::default()
-        },
-        depth_stencil: None,
-        multisample: Default::default(),
-        multiview: None,
-        // cache: None,
-    });
+            // cache: None,
+        })
+    };
 
-    Some(pipeline)
+    Ok(SdfPipelines {
+        seed: pipeline("cs_seed"),
+        jump: pipeline("cs_jump"),
+        distance: pipeline("cs_distance"),
+    })
 }
 
-fn create_octree_pipeline(device: &Device, constants: &ShaderConstants) -> Option<ComputePipeline> {
+#[tracing::instrument(skip_all)]
+fn create_contours_pipeline(
+    device: &Device,
+    constants: &ShaderConstants,
+) -> Result<ComputePipeline, String> {
     let constants = constants.to_hashmap();
     let preproc_ctx = preproc::Context {
-        main: &PathBuf::from_str("src/compute_octree.wgsl").unwrap(),
+        main: &PathBuf::from_str("src/compute_contours.wgsl").unwrap(),
         constants: &constants,
     };
 
     let shader_module = match preprocess_shader(&preproc_ctx) {
         Ok(module) => module,
         Err(err) => {
-            eprintln!("preproc error: {}", err);
-            return None;
+            return Err(shader_error(format!("preproc error: {err}")));
         }
     };
 
     device.push_error_scope(ErrorFilter::Validation);
 
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("compute"),
+        label: Some("contours"),
         source: ShaderSource::Naga(Cow::Owned(shader_module)),
     });
 
     let err = device.pop_error_scope().block_on();
     match err {
         Some(err) => {
-            eprintln!("shader error: {}", err);
-            return None;
+            return Err(shader_error(format!("shader error: {err}")));
         }
-        None => println!("compiled compute shader"),
+        None => println!("compiled contours shader"),
     }
 
-    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        label: Some("compute bind group layout"),
-        entries: &[
-            BindGroupLayoutEntry {
-                // voxels
-                binding: 0,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::StorageTexture {
-                    access: StorageTextureAccess::ReadOnly,
-                    format: OCTREE_FORMAT,
-                    view_dimension: TextureViewDimension::D3,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                // octree
-                binding: 1,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::StorageTexture {
-                    access: StorageTextureAccess::WriteOnly,
-                    format: OCTREE_FORMAT,
-                    view_dimension: TextureViewDimension::D3,
-                },
-                count: None,
-            },
-        ],
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("contours pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: "cs_contours",
+        compilation_options: Default::default(),
+        // cache: None,
     });
 
-    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("compute pipeline layout"),
-        bind_group_layouts: &[&bind_group_layout],
-        push_constant_ranges: &[],
+    Ok(pipeline)
+}
+
+fn create_contree_pipeline(
+    device: &Device,
+    constants: &ShaderConstants,
+) -> Result<ComputePipeline, String> {
+    let constants = constants.to_hashmap();
+    let preproc_ctx = preproc::Context {
+        main: &PathBuf::from_str("src/compute_contree.wgsl").unwrap(),
+        constants: &constants,
+    };
+
+    let shader_module = match preprocess_shader(&preproc_ctx) {
+        Ok(module) => module,
+        Err(err) => {
+            return Err(shader_error(format!("preproc error: {err}")));
+        }
+    };
+
+    device.push_error_scope(ErrorFilter::Validation);
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("contree"),
+        source: ShaderSource::Naga(Cow::Owned(shader_module)),
     });
 
-    let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        label: Some("compute pipeline"),
-        layout: Some(&pipeline_layout),
+    let err = device.pop_error_scope().block_on();
+    match err {
+        Some(err) => {
+            return Err(shader_error(format!("shader error: {err}")));
+        }
+        None => println!("compiled contree shader"),
+    }
+
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("contree pipeline"),
+        layout: None,
         module: &shader,
-        entry_point: "cs_main",
+        entry_point: "cs_contree",
         compilation_options: Default::default(),
         // cache: None,
     });
 
-    Some(compute_pipeline)
+    Ok(pipeline)
 }
 
-fn create_mipmap_pipeline(device: &Device, constants: &ShaderConstants) -> Option<ComputePipeline> {
+fn create_culling_pipeline(
+    device: &Device,
+    constants: &ShaderConstants,
+) -> Result<ComputePipeline, String> {
     let constants = constants.to_hashmap();
     let preproc_ctx = preproc::Context {
-        main: &PathBuf::from_str("src/mipmap.wgsl").unwrap(),
+        main: &PathBuf::from_str("src/compute_culling.wgsl").unwrap(),
         constants: &constants,
     };
 
     let shader_module = match preprocess_shader(&preproc_ctx) {
         Ok(module) => module,
         Err(err) => {
-            eprintln!("preproc error: {}", err);
-            return None;
+            return Err(shader_error(format!("preproc error: {err}")));
         }
     };
 
     device.push_error_scope(ErrorFilter::Validation);
 
     let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("mipmap"),
+        label: Some("culling"),
         source: ShaderSource::Naga(Cow::Owned(shader_module)),
     });
 
     let err = device.pop_error_scope().block_on();
     match err {
         Some(err) => {
-            eprintln!("shader error: {}", err);
-            return None;
+            return Err(shader_error(format!("shader error: {err}")));
         }
-        None => println!("compiled compute shader"),
+        None => println!("compiled culling shader"),
     }
 
-    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-        label: Some("mipmap bind group layout"),
-        entries: &[
-            BindGroupLayoutEntry {
-                // in_tex
-                binding: 0,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::StorageTexture {
-                    access: StorageTextureAccess::ReadOnly,
-                    format: TextureFormat::Rgba8Unorm,
-                    view_dimension: TextureViewDimension::D3,
-                },
-                count: None,
-            },
-            BindGroupLayoutEntry {
-                // out_tex
-                binding: 1,
-                visibility: ShaderStages::COMPUTE,
-                ty: BindingType::StorageTexture {
-                    access: StorageTextureAccess::WriteOnly,
-                    format: TextureFormat::Rgba8Unorm,
-                    view_dimension: TextureViewDimension::D3,
-                },
-                count: None,
-            },
-        ],
+    // the layout is derived from the shader: the camera and the visible bricks in group 0.
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("culling pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: "cs_culling",
+        compilation_options: Default::default(),
+        // cache: None,
     });
 
-    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("compute pipeline layout"),
-        bind_group_layouts: &[&bind_group_layout],
-        push_constant_ranges: &[],
+    Ok(pipeline)
+}
+
+fn create_light_culling_pipeline(
+    device: &Device,
+    constants: &ShaderConstants,
+) -> Result<ComputePipeline, String> {
+    let constants = constants.to_hashmap();
+    let preproc_ctx = preproc::Context {
+        main: &PathBuf::from_str("src/compute_light_culling.wgsl").unwrap(),
+        constants: &constants,
+    };
+
+    let shader_module = match preprocess_shader(&preproc_ctx) {
+        Ok(module) => module,
+        Err(err) => {
+            return Err(shader_error(format!("preproc error: {err}")));
+        }
+    };
+
+    device.push_error_scope(ErrorFilter::Validation);
+
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("light culling"),
+        source: ShaderSource::Naga(Cow::Owned(shader_module)),
     });
 
+    let err = device.pop_error_scope().block_on();
+    match err {
+        Some(err) => {
+            return Err(shader_error(format!("shader error: {err}")));
+        }
+        None => println!("compiled light culling shader"),
+    }
+
+    // the layout is derived from the shader: the camera, the lights and the cluster lists in
+    // group 0.
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        label: Some("mipmap pipeline"),
-        layout: Some(&pipeline_layout),
+        label: Some("light culling pipeline"),
+        layout: None,
         module: &shader,
-        entry_point: "cs_main",
+        entry_point: "cs_light_culling",
         compilation_options: Default::default(),
         // cache: None,
     });
 
-    Some(pipeline)
+    Ok(pipeline)
 }